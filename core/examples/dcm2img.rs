@@ -0,0 +1,53 @@
+//! Convert a frame of a DICOM image file into a PNG, PGM or PPM image.
+//!
+//! Usage: dcm2img <input.dcm> <output.(png|pgm|ppm|pnm)> [frame] [--16bit]
+//!
+//! Grayscale images are rendered with the VOI window of the file (or the
+//! full range of values if there is none). `--16bit` renders grayscale
+//! images with 16 bits per sample instead of 8.
+extern crate dicom_core;
+
+use dicom_core::object::pixeldata::export::DisplayImage;
+use dicom_core::open_file;
+
+use std::fs::File;
+use std::io::BufWriter;
+
+type DynResult<T> = Result<T, Box<::std::error::Error>>;
+
+fn main() -> DynResult<()> {
+    let mut args: Vec<String> = ::std::env::args().skip(1).collect();
+    let sixteen_bit = match args.iter().position(|a| a == "--16bit") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let input = args.first().expect("Missing path to DICOM file");
+    let output = args.get(1).expect("Missing path to output image");
+    let frame: u32 = match args.get(2) {
+        Some(frame) => frame.parse()?,
+        None => 0,
+    };
+
+    let obj = open_file(input)?;
+    let (module, pixels) = obj.decode_pixel_data()?;
+    let image = DisplayImage::render(&obj, &module, &pixels, sixteen_bit)?;
+    if frame >= image.number_of_frames() {
+        return Err(format!(
+            "Frame {} is out of bounds, the image has {} frame(s)",
+            frame,
+            image.number_of_frames()
+        ).into());
+    }
+
+    let to = BufWriter::new(File::create(output)?);
+    if output.ends_with(".png") {
+        image.write_png(to, frame)?;
+    } else {
+        image.write_pnm(to, frame)?;
+    }
+
+    Ok(())
+}
@@ -1,22 +1,27 @@
-//! This module contains a mid-level abstraction for reading DICOM content sequentially.
+//! This module contains a mid-level abstraction for reading and writing DICOM
+//! content sequentially.
 //!
 //! The `parser` module is used to obtain DICOM element headers and values. At this level,
 //! headers and values are treated as tokens which can be used to form a syntax tree of
-//! a full data set.
+//! a full data set. Likewise, the `printer` module is used to write these tokens back.
+use data::decode::basic::LittleEndianBasicDecoder;
+use data::decode::Decode;
 use data::parser::{DicomParser, DynamicDicomParser, Parse};
-use data::text::SpecificCharacterSet;
-use data::value::{DicomValueType, PrimitiveValue};
+use data::printer::{DynamicDicomPrinter, Print};
+use data::text::{DynamicTextCodec, SpecificCharacterSet};
+use data::value::{DicomValueType, PrimitiveValue, Value};
 use data::Tag;
 use data::VR;
 use data::{DataElement, DataElementHeader, Header, Length, SequenceItemHeader};
 use dictionary::{DataDictionary, StandardDataDictionary};
-use error::{Error, InvalidValueReadError, Result};
+use error::{DataSetSyntaxError, Error, InvalidValueReadError, Result};
 use object::mem::InMemDicomObject;
 use std::fmt;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::iter::Iterator;
 use std::marker::PhantomData;
 use std::ops::DerefMut;
+use transfer_syntax::implicit_le::StandardImplicitVRLittleEndianDecoder;
 use transfer_syntax::TransferSyntax;
 use util::{ReadSeek, SeekInterval};
 
@@ -30,14 +35,32 @@ pub struct DataSetReader<S, P, D> {
     depth: u32,
     /// whether the reader is expecting an item next (or a sequence delimiter)
     in_sequence: bool,
+    /// whether the reader is inside an encapsulated pixel data element
+    in_pixel_sequence: bool,
+    /// whether the first item of the current pixel data element was read
+    offset_table_read: bool,
     /// fuse the iteration process if true
     hard_break: bool,
     /// last decoded header
     last_header: Option<DataElementHeader>,
+    /// the specific character set of the text values read so far
+    charset: SpecificCharacterSet,
+    /// the parser of a sequence of unknown type (UN) with undefined length,
+    /// and the depth at which it was opened
+    un_sequence: Option<(UnSequenceParser<S>, u32)>,
 }
 
 type InMemElement<D> = DataElement<InMemDicomObject<D>>;
 
+/// The contents of a sequence of unknown type (UN) with undefined length
+/// are always encoded in implicit VR little endian (PS3.5 6.2.2).
+type UnSequenceParser<S> = DicomParser<
+    StandardImplicitVRLittleEndianDecoder<S>,
+    LittleEndianBasicDecoder,
+    S,
+    DynamicTextCodec,
+>;
+
 fn is_parse<S: ?Sized, P>(_: &P)
 where
     S: Read,
@@ -59,8 +82,12 @@ impl<'s, S: 's> DataSetReader<S, DynamicDicomParser, StandardDataDictionary> {
             dict: StandardDataDictionary,
             depth: 0,
             in_sequence: false,
+            in_pixel_sequence: false,
+            offset_table_read: false,
             hard_break: false,
             last_header: None,
+            charset: cs,
+            un_sequence: None,
         })
     }
 }
@@ -84,8 +111,12 @@ impl<'s, S: 's, D> DataSetReader<S, DynamicDicomParser, D> {
             dict,
             depth: 0,
             in_sequence: false,
+            in_pixel_sequence: false,
+            offset_table_read: false,
             hard_break: false,
             last_header: None,
+            charset: cs,
+            un_sequence: None,
         })
     }
 }
@@ -103,8 +134,12 @@ where
             dict: StandardDataDictionary,
             depth: 0,
             in_sequence: false,
+            in_pixel_sequence: false,
+            offset_table_read: false,
             hard_break: false,
             last_header: None,
+            charset: SpecificCharacterSet::Default,
+            un_sequence: None,
         }
     }
 }
@@ -115,9 +150,66 @@ where
     P: Parse<Read + 's>,
 {
     fn read_primitive_element(&mut self, header: DataElementHeader) -> Result<InMemElement<D>> {
-        let value = self.parser.read_value(&mut self.source, &header)?.into();
+        let value = self.read_value(&header)?.into();
         Ok(DataElement { header, value })
     }
+
+    fn decode_header(&mut self) -> Result<DataElementHeader> {
+        match self.un_sequence {
+            Some((ref parser, _)) => parser.decode_header(&mut self.source),
+            None => self.parser.decode_header(&mut self.source),
+        }
+    }
+
+    fn decode_item_header(&mut self) -> Result<SequenceItemHeader> {
+        match self.un_sequence {
+            Some((ref parser, _)) => parser.decode_item_header(&mut self.source),
+            None => self.parser.decode_item_header(&mut self.source),
+        }
+    }
+
+    fn read_value(&mut self, header: &DataElementHeader) -> Result<PrimitiveValue> {
+        match self.un_sequence {
+            Some((ref parser, _)) => parser.read_value(&mut self.source, header),
+            None => self.parser.read_value(&mut self.source, header),
+        }
+    }
+
+    fn set_character_set(&mut self, charset: SpecificCharacterSet) -> Result<()> {
+        self.charset = charset;
+        if let Some((ref mut parser, _)) = self.un_sequence {
+            parser.set_character_set(charset)?;
+        }
+        self.parser.set_character_set(charset)
+    }
+
+    /// Enter a sequence of unknown type (UN) with undefined length.
+    fn start_un_sequence(&mut self) -> Result<()> {
+        self.in_sequence = true;
+        self.depth += 1;
+        if self.un_sequence.is_none() {
+            let text = self.charset
+                .get_codec()
+                .ok_or(Error::UnsupportedCharacterSet)?;
+            let parser = DicomParser::new(
+                StandardImplicitVRLittleEndianDecoder::with_std_dict(),
+                LittleEndianBasicDecoder,
+                text,
+            );
+            self.un_sequence = Some((parser, self.depth));
+        }
+        Ok(())
+    }
+}
+
+/// Resolve the character set from the value of Specific Character Set
+/// (0008,0005). An empty value stands for the default repertoire, which is
+/// also used when the defined terms are not recognized.
+pub(crate) fn character_set_of(value: &PrimitiveValue) -> SpecificCharacterSet {
+    value
+        .strings()
+        .and_then(|codes| SpecificCharacterSet::from_codes(&codes))
+        .unwrap_or_default()
 }
 
 /// A token of a DICOM data set stream. This is part of the interpretation of a
@@ -137,6 +229,16 @@ pub enum DicomDataToken {
     ItemEnd,
     /// A primitive data element value.
     PrimitiveValue(PrimitiveValue),
+    /// The beginning of an encapsulated pixel data element, in place of the
+    /// primitive value after its header. It is followed by the offset table,
+    /// the fragments, and the end of the sequence.
+    PixelSequenceStart,
+    /// The Basic Offset Table of an encapsulated pixel data element,
+    /// which is the value of its first item.
+    OffsetTable(Vec<u32>),
+    /// The value of an item in an encapsulated pixel data element:
+    /// a fragment of the compressed pixel data.
+    ItemValue(Vec<u8>),
 }
 
 impl fmt::Display for DicomDataToken {
@@ -145,6 +247,7 @@ impl fmt::Display for DicomDataToken {
             &DicomDataToken::PrimitiveValue(ref v) => {
                 write!(f, "PrimitiveValue({:?})", v.value_type())
             }
+            &DicomDataToken::ItemValue(ref v) => write!(f, "ItemValue({} bytes)", v.len()),
             other => write!(f, "{:?}", other),
         }
     }
@@ -162,8 +265,49 @@ where
         if self.hard_break {
             return None;
         }
-        if self.in_sequence {
-            match self.parser.decode_item_header(&mut self.source) {
+        if self.in_pixel_sequence {
+            match self.decode_item_header() {
+                Ok(SequenceItemHeader::Item { len }) => {
+                    // the first item is the offset table, the others are fragments
+                    let (vr, first) = if self.offset_table_read {
+                        (VR::OB, false)
+                    } else {
+                        (VR::UL, true)
+                    };
+                    self.offset_table_read = true;
+                    let header = DataElementHeader::new(Tag(0xFFFE, 0xE000), vr, len);
+                    match self.read_value(&header) {
+                        Ok(v) => Some(Ok(match (first, v) {
+                            (true, PrimitiveValue::U32(table)) => {
+                                DicomDataToken::OffsetTable(table)
+                            }
+                            (true, _) => DicomDataToken::OffsetTable(Vec::new()),
+                            (false, PrimitiveValue::U8(data)) => DicomDataToken::ItemValue(data),
+                            (false, _) => DicomDataToken::ItemValue(Vec::new()),
+                        })),
+                        Err(e) => {
+                            self.hard_break = true;
+                            Some(Err(e))
+                        }
+                    }
+                }
+                Ok(SequenceItemHeader::SequenceDelimiter) => {
+                    self.in_pixel_sequence = false;
+                    Some(Ok(DicomDataToken::SequenceEnd))
+                }
+                Ok(SequenceItemHeader::ItemDelimiter) => {
+                    self.hard_break = true;
+                    Some(Err(
+                        DataSetSyntaxError::UnexpectedToken(DicomDataToken::ItemEnd).into(),
+                    ))
+                }
+                Err(e) => {
+                    self.hard_break = true;
+                    Some(Err(e))
+                }
+            }
+        } else if self.in_sequence {
+            match self.decode_item_header() {
                 Ok(header) => match header {
                     SequenceItemHeader::Item { len } => {
                         // entered a new item
@@ -177,6 +321,10 @@ where
                     }
                     SequenceItemHeader::SequenceDelimiter => {
                         // closed a sequence
+                        let un_depth = self.un_sequence.as_ref().map(|&(_, depth)| depth);
+                        if un_depth == Some(self.depth) {
+                            self.un_sequence = None;
+                        }
                         self.depth -= 1;
                         self.in_sequence = false;
                         Some(Ok(DicomDataToken::SequenceEnd))
@@ -190,7 +338,18 @@ where
         } else if self.last_header.is_some() {
             // a plain element header was read, so a value is expected
             let header = self.last_header.unwrap();
-            let v = match self.parser.read_value(&mut self.source, &header) {
+            if header.len().is_undefined() {
+                self.last_header = None;
+                if header.tag() != Tag(0x7FE0, 0x0010) {
+                    self.hard_break = true;
+                    return Some(Err(InvalidValueReadError::UnresolvedValueLength.into()));
+                }
+                // encapsulated pixel data
+                self.in_pixel_sequence = true;
+                self.offset_table_read = false;
+                return Some(Ok(DicomDataToken::PixelSequenceStart));
+            }
+            let v = match self.read_value(&header) {
                 Ok(v) => v,
                 Err(e) => {
                     self.hard_break = true;
@@ -205,21 +364,17 @@ where
                 ..
             }) = self.last_header
             {
-                // TODO trigger an error or warning on unsupported specific character sets.
-                // Edge case handling strategies should be considered in the future.
-                if let Some(charset) = v.string().and_then(SpecificCharacterSet::from_code) {
-                    if let Err(e) = self.parser.set_character_set(charset) {
-                        self.hard_break = true;
-                        self.last_header = None;
-                        return Some(Err(Error::from(e)));
-                    }
+                if let Err(e) = self.set_character_set(character_set_of(&v)) {
+                    self.hard_break = true;
+                    self.last_header = None;
+                    return Some(Err(e));
                 }
             }
             self.last_header = None;
             Some(Ok(DicomDataToken::PrimitiveValue(v)))
         } else {
             // a data element header or item delimiter is expected
-            match self.parser.decode_header(&mut self.source) {
+            match self.decode_header() {
                 Ok(DataElementHeader {
                     tag,
                     vr: VR::SQ,
@@ -229,6 +384,21 @@ where
                     self.depth += 1;
                     Some(Ok(DicomDataToken::SequenceStart { tag, len }))
                 }
+                Ok(DataElementHeader {
+                    tag,
+                    vr: VR::UN,
+                    len,
+                }) if len.is_undefined() =>
+                {
+                    // a sequence of unknown type, read as an SQ element
+                    match self.start_un_sequence() {
+                        Ok(()) => Some(Ok(DicomDataToken::SequenceStart { tag, len })),
+                        Err(e) => {
+                            self.hard_break = true;
+                            Some(Err(e))
+                        }
+                    }
+                }
                 Ok(DataElementHeader {
                     tag: Tag(0xFFFE, 0xE00D),
                     ..
@@ -257,8 +427,320 @@ where
     }
 }
 
+/// The strategy for recording the length of sequences and items when
+/// writing a data set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SequenceLength {
+    /// Sequences and items have an undefined length and are closed
+    /// with the respective delimiters.
+    Undefined,
+    /// Sequences and items have an explicit length, calculated from their
+    /// contents. No delimiters are written.
+    Defined,
+}
+
+impl Default for SequenceLength {
+    fn default() -> SequenceLength {
+        SequenceLength::Undefined
+    }
+}
+
+/// A pending sequence or item, kept while its length is unknown.
+#[derive(Debug)]
+enum WriterFrame {
+    Sequence { tag: Tag, data: Vec<u8> },
+    Item { data: Vec<u8> },
+}
+
+/// A higher-level writer for producing a DICOM data set to an arbitrary
+/// destination, either from a sequence of data set tokens or from a
+/// full DICOM object. This is the writing counterpart of `DataSetReader`.
+///
+/// In `SequenceLength::Defined` mode, the contents of each sequence are kept
+/// in memory until the sequence is closed, so that its length is known.
+#[derive(Debug)]
+pub struct DataSetWriter<W, P> {
+    to: W,
+    printer: P,
+    seq_length: SequenceLength,
+    /// the sequences and items currently open
+    frames: Vec<WriterFrame>,
+    /// last written header, waiting for a value
+    last_header: Option<DataElementHeader>,
+    /// whether an encapsulated pixel data element is being written
+    in_pixel_sequence: bool,
+}
+
+impl<W> DataSetWriter<W, DynamicDicomPrinter<'static>>
+where
+    W: Write,
+{
+    /// Create a new data set writer to the given destination,
+    /// while considering the given transfer syntax and specific character set.
+    pub fn new_with(to: W, ts: &TransferSyntax, cs: SpecificCharacterSet) -> Result<Self> {
+        let printer = DynamicDicomPrinter::new_with(ts, cs)?;
+        Ok(DataSetWriter::new(to, printer))
+    }
+}
+
+impl<W, P> DataSetWriter<W, P>
+where
+    W: Write,
+    P: Print<Write>,
+{
+    /// Create a new data set writer with the given printer.
+    pub fn new(to: W, printer: P) -> Self {
+        DataSetWriter {
+            to,
+            printer,
+            seq_length: SequenceLength::default(),
+            frames: Vec::new(),
+            last_header: None,
+            in_pixel_sequence: false,
+        }
+    }
+
+    /// Define how the lengths of sequences and items are written
+    /// (undefined by default).
+    pub fn sequence_length(mut self, seq_length: SequenceLength) -> Self {
+        self.seq_length = seq_length;
+        self
+    }
+
+    /// Retrieve the inner destination.
+    pub fn into_inner(self) -> W {
+        self.to
+    }
+
+    /// Write all tokens from the given iterator.
+    pub fn write_sequence<I>(&mut self, tokens: I) -> Result<()>
+    where
+        I: IntoIterator<Item = DicomDataToken>,
+    {
+        for token in tokens {
+            self.write(token)?;
+        }
+        Ok(())
+    }
+
+    /// Write a single data set token. An element header must be followed by
+    /// its primitive value.
+    pub fn write(&mut self, token: DicomDataToken) -> Result<()> {
+        match (self.last_header.take(), token) {
+            (Some(header), DicomDataToken::PrimitiveValue(ref v)) => {
+                self.write_element(header.tag(), header.vr(), v)
+            }
+            (Some(header), DicomDataToken::PixelSequenceStart)
+                if header.tag() == Tag(0x7FE0, 0x0010) =>
+            {
+                self.start_pixel_sequence(header.tag(), header.vr())
+            }
+            (None, DicomDataToken::OffsetTable(ref table)) if self.in_pixel_sequence => {
+                self.write_offset_table(table)
+            }
+            (None, DicomDataToken::ItemValue(ref data)) if self.in_pixel_sequence => {
+                self.write_fragment(data)
+            }
+            (None, DicomDataToken::SequenceEnd) if self.in_pixel_sequence => {
+                self.end_pixel_sequence()
+            }
+            (None, DicomDataToken::ElementHeader(header)) => {
+                self.last_header = Some(header);
+                Ok(())
+            }
+            (None, DicomDataToken::SequenceStart { tag, .. }) => self.start_sequence(tag),
+            (None, DicomDataToken::ItemStart { .. }) => self.start_item(),
+            (None, DicomDataToken::ItemEnd) => self.end_item(),
+            (None, DicomDataToken::SequenceEnd) => self.end_sequence(),
+            (_, token) => Err(DataSetSyntaxError::UnexpectedToken(token).into()),
+        }
+    }
+
+    /// Write all elements of the given DICOM object. File meta group
+    /// elements (0002,xxxx) do not belong in a data set and are skipped.
+    pub fn write_object<D>(&mut self, obj: &InMemDicomObject<D>) -> Result<()> {
+        for elem in obj {
+            if elem.tag().group() == 0x0002 {
+                continue;
+            }
+            match *elem.value() {
+                Value::Primitive(ref v) => self.write_element(elem.tag(), elem.vr(), v)?,
+                Value::Sequence { ref items, .. } => {
+                    self.start_sequence(elem.tag())?;
+                    for item in items {
+                        self.start_item()?;
+                        self.write_object(item)?;
+                        self.end_item()?;
+                    }
+                    self.end_sequence()?;
+                }
+                Value::PixelSequence {
+                    ref offset_table,
+                    ref fragments,
+                } => {
+                    self.start_pixel_sequence(elem.tag(), elem.vr())?;
+                    self.write_offset_table(offset_table)?;
+                    for fragment in fragments {
+                        self.write_fragment(fragment)?;
+                    }
+                    self.end_pixel_sequence()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ensure that the data set is complete and flush the destination.
+    pub fn finish(&mut self) -> Result<()> {
+        if self.last_header.is_some() || !self.frames.is_empty() || self.in_pixel_sequence {
+            return Err(DataSetSyntaxError::PrematureEnd.into());
+        }
+        self.to.flush()?;
+        Ok(())
+    }
+
+    fn write_element(&mut self, tag: Tag, vr: VR, value: &PrimitiveValue) -> Result<()> {
+        let data = self.printer.encode_value(vr, value)?;
+        self.write_header(DataElementHeader::new(tag, vr, Length(data.len() as u32)))?;
+        self.write_bytes(&data)?;
+
+        // if it's a Specific Character Set, update the printer immediately.
+        if tag == Tag(0x0008, 0x0005) {
+            self.printer.set_character_set(character_set_of(value))?;
+        }
+        Ok(())
+    }
+
+    fn start_sequence(&mut self, tag: Tag) -> Result<()> {
+        match self.seq_length {
+            SequenceLength::Undefined => {
+                self.write_header(DataElementHeader::new(tag, VR::SQ, Length::undefined()))
+            }
+            SequenceLength::Defined => {
+                self.frames.push(WriterFrame::Sequence {
+                    tag,
+                    data: Vec::new(),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn start_item(&mut self) -> Result<()> {
+        match self.seq_length {
+            SequenceLength::Undefined => {
+                let mut buf = Vec::with_capacity(8);
+                self.printer
+                    .encode_item_header(&mut buf, Length::undefined().0)?;
+                self.write_bytes(&buf)
+            }
+            SequenceLength::Defined => {
+                self.frames.push(WriterFrame::Item { data: Vec::new() });
+                Ok(())
+            }
+        }
+    }
+
+    fn end_item(&mut self) -> Result<()> {
+        match self.seq_length {
+            SequenceLength::Undefined => {
+                let mut buf = Vec::with_capacity(8);
+                self.printer.encode_item_delimiter(&mut buf)?;
+                self.write_bytes(&buf)
+            }
+            SequenceLength::Defined => match self.frames.pop() {
+                Some(WriterFrame::Item { data }) => {
+                    let mut buf = Vec::with_capacity(8);
+                    self.printer.encode_item_header(&mut buf, data.len() as u32)?;
+                    self.write_bytes(&buf)?;
+                    self.write_bytes(&data)
+                }
+                _ => Err(DataSetSyntaxError::UnexpectedToken(DicomDataToken::ItemEnd).into()),
+            },
+        }
+    }
+
+    fn end_sequence(&mut self) -> Result<()> {
+        match self.seq_length {
+            SequenceLength::Undefined => {
+                let mut buf = Vec::with_capacity(8);
+                self.printer.encode_sequence_delimiter(&mut buf)?;
+                self.write_bytes(&buf)
+            }
+            SequenceLength::Defined => match self.frames.pop() {
+                Some(WriterFrame::Sequence { tag, data }) => {
+                    let len = Length(data.len() as u32);
+                    self.write_header(DataElementHeader::new(tag, VR::SQ, len))?;
+                    self.write_bytes(&data)
+                }
+                _ => Err(DataSetSyntaxError::UnexpectedToken(DicomDataToken::SequenceEnd).into()),
+            },
+        }
+    }
+
+    /// Start an encapsulated pixel data element. Unlike sequences,
+    /// these always have an undefined length.
+    fn start_pixel_sequence(&mut self, tag: Tag, vr: VR) -> Result<()> {
+        self.in_pixel_sequence = true;
+        self.write_header(DataElementHeader::new(tag, vr, Length::undefined()))
+    }
+
+    fn write_offset_table(&mut self, table: &[u32]) -> Result<()> {
+        let data = self.printer
+            .encode_value(VR::UL, &PrimitiveValue::U32(table.to_vec()))?;
+        let mut buf = Vec::with_capacity(8);
+        self.printer.encode_item_header(&mut buf, data.len() as u32)?;
+        self.write_bytes(&buf)?;
+        self.write_bytes(&data)
+    }
+
+    fn write_fragment(&mut self, data: &[u8]) -> Result<()> {
+        // fragments are padded to an even length
+        let padded_len = (data.len() as u32 + 1) & !1;
+        let mut buf = Vec::with_capacity(8);
+        self.printer.encode_item_header(&mut buf, padded_len)?;
+        self.write_bytes(&buf)?;
+        self.write_bytes(data)?;
+        if data.len() % 2 == 1 {
+            self.write_bytes(&[0])?;
+        }
+        Ok(())
+    }
+
+    fn end_pixel_sequence(&mut self) -> Result<()> {
+        self.in_pixel_sequence = false;
+        let mut buf = Vec::with_capacity(8);
+        self.printer.encode_sequence_delimiter(&mut buf)?;
+        self.write_bytes(&buf)
+    }
+
+    fn write_header(&mut self, header: DataElementHeader) -> Result<()> {
+        let mut buf = Vec::with_capacity(12);
+        self.printer.encode_element_header(&mut buf, header)?;
+        self.write_bytes(&buf)
+    }
+
+    /// Write raw data, either to the innermost pending sequence or item,
+    /// or directly to the destination.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        match self.frames.last_mut() {
+            Some(&mut WriterFrame::Sequence { ref mut data, .. })
+            | Some(&mut WriterFrame::Item { ref mut data }) => {
+                data.extend_from_slice(bytes);
+            }
+            None => self.to.write_all(bytes)?,
+        }
+        Ok(())
+    }
+}
+
 /// An iterator for retrieving DICOM object element markers from a random
 /// access data source.
+///
+/// Element values are skipped over rather than read, so that only the
+/// element headers are visited. Sequences and items of defined length do not
+/// have delimiters in the source: markers for the respective delimiters are
+/// still produced when their end is reached, positioned at that end.
 #[derive(Debug)]
 pub struct LazyDataSetReader<S, DS, P> {
     source: S,
@@ -266,27 +748,44 @@ pub struct LazyDataSetReader<S, DS, P> {
     depth: u32,
     in_sequence: bool,
     hard_break: bool,
+    /// the sequences and items currently open
+    open: Vec<OpenContainer>,
     phantom: PhantomData<DS>,
 }
 
-impl<'s> LazyDataSetReader<&'s mut ReadSeek, &'s mut Read, DynamicDicomParser> {
+/// A sequence or item currently open in a lazy data set reader.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OpenContainer {
+    /// a sequence, ending at the given position if its length is defined
+    Sequence(Option<u64>),
+    /// an item, ending at the given position if its length is defined
+    Item(Option<u64>),
+    /// an encapsulated pixel data element, with fragments instead of items
+    PixelSequence,
+    /// a sequence of unknown type (UN) with undefined length, whose contents
+    /// are encoded in implicit VR little endian
+    UnSequence,
+}
+
+impl OpenContainer {
+    fn end(&self) -> Option<u64> {
+        match *self {
+            OpenContainer::Sequence(end) | OpenContainer::Item(end) => end,
+            OpenContainer::PixelSequence | OpenContainer::UnSequence => None,
+        }
+    }
+}
+
+impl<S> LazyDataSetReader<S, (), DynamicDicomParser>
+where
+    S: ReadSeek,
+{
     /// Create a new iterator with the given random access source,
     /// while considering the given transfer syntax and specific character set.
-    pub fn new_with(
-        source: &'s mut ReadSeek,
-        ts: &TransferSyntax,
-        cs: SpecificCharacterSet,
-    ) -> Result<Self> {
+    pub fn new_with(source: S, ts: &TransferSyntax, cs: SpecificCharacterSet) -> Result<Self> {
         let parser = DicomParser::new_with(ts, cs)?;
 
-        Ok(LazyDataSetReader {
-            source: source,
-            parser: parser,
-            depth: 0,
-            in_sequence: false,
-            hard_break: false,
-            phantom: PhantomData,
-        })
+        Ok(LazyDataSetReader::new(source, parser))
     }
 }
 
@@ -297,15 +796,21 @@ where
     /// Create a new iterator with the given parser.
     pub fn new(source: S, parser: P) -> LazyDataSetReader<S, DS, P> {
         LazyDataSetReader {
-            source: source,
-            parser: parser,
+            source,
+            parser,
             depth: 0,
             in_sequence: false,
             hard_break: false,
+            open: Vec::new(),
             phantom: PhantomData,
         }
     }
 
+    /// Retrieve the inner source and parser.
+    pub fn into_parts(self) -> (S, P) {
+        (self.source, self.parser)
+    }
+
     /// Get the inner source's position in the stream using `seek()`.
     fn get_position(&mut self) -> Result<u64>
     where
@@ -315,90 +820,155 @@ where
     }
 
     fn create_element_marker(&mut self, header: DataElementHeader) -> Result<DicomElementMarker> {
-        match self.get_position() {
-            Ok(pos) => Ok(DicomElementMarker {
-                header: header,
-                pos: pos,
-            }),
-            Err(e) => {
-                self.hard_break = true;
-                Err(Error::from(e))
-            }
-        }
+        let pos = self.get_position()?;
+        Ok(DicomElementMarker { header, pos })
     }
 
     fn create_item_marker(&mut self, header: SequenceItemHeader) -> Result<DicomElementMarker> {
-        match self.get_position() {
-            Ok(pos) => Ok(DicomElementMarker {
-                header: From::from(header),
-                pos: pos,
-            }),
-            Err(e) => {
-                self.hard_break = true;
-                Err(Error::from(e))
+        let pos = self.get_position()?;
+        Ok(DicomElementMarker {
+            header: From::from(header),
+            pos,
+        })
+    }
+
+    /// Skip the value of an element or fragment with a defined length.
+    fn skip_value(&mut self, len: Length) -> Result<()> {
+        let len = len.get()
+            .ok_or(InvalidValueReadError::UnresolvedValueLength)?;
+        self.source.seek(SeekFrom::Current(i64::from(len)))?;
+        Ok(())
+    }
+
+    /// Close the innermost sequence or item, producing a delimiter marker.
+    fn close_container(&mut self) -> Result<DicomElementMarker> {
+        match self.open.pop() {
+            Some(OpenContainer::Item(_)) => {
+                self.in_sequence = true;
+                self.create_item_marker(SequenceItemHeader::ItemDelimiter)
             }
+            Some(_) => {
+                self.depth -= 1;
+                self.in_sequence = false;
+                self.create_item_marker(SequenceItemHeader::SequenceDelimiter)
+            }
+            None => Err(Error::UnexpectedElement),
         }
     }
 }
 
-impl<S, P> Iterator for LazyDataSetReader<S, (), P>
+impl<'s, S: 's, DS, P> LazyDataSetReader<S, DS, P>
 where
     S: ReadSeek,
-    P: Parse<S>,
+    P: Parse<Read + 's>,
 {
-    type Item = Result<DicomElementMarker>;
+    fn decode_header(&mut self) -> Result<DataElementHeader> {
+        if self.in_un_sequence() {
+            StandardImplicitVRLittleEndianDecoder::with_std_dict().decode_header(&mut self.source)
+        } else {
+            self.parser.decode_header(&mut self.source)
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.hard_break {
-            return None;
+    fn decode_item_header(&mut self) -> Result<SequenceItemHeader> {
+        if self.in_un_sequence() {
+            StandardImplicitVRLittleEndianDecoder::with_std_dict()
+                .decode_item_header(&mut self.source)
+        } else {
+            self.parser.decode_item_header(&mut self.source)
         }
+    }
+
+    fn in_un_sequence(&self) -> bool {
+        self.open.contains(&OpenContainer::UnSequence)
+    }
+
+    fn next_marker(&mut self) -> Result<Option<DicomElementMarker>> {
+        // sequences and items of defined length end without a delimiter
+        if let Some(end) = self.open.last().and_then(OpenContainer::end) {
+            if self.get_position()? >= end {
+                return self.close_container().map(Some);
+            }
+        }
+
         if self.in_sequence {
-            match self.parser.decode_item_header(&mut self.source) {
-                Ok(header) => match header {
-                    header @ SequenceItemHeader::Item { .. } => {
-                        self.in_sequence = false;
-                        Some(self.create_item_marker(header))
-                    }
-                    SequenceItemHeader::ItemDelimiter => {
-                        self.in_sequence = true;
-                        Some(self.create_item_marker(header))
-                    }
-                    SequenceItemHeader::SequenceDelimiter => {
-                        self.depth -= 1;
+            let header = self.decode_item_header()?;
+            match header {
+                SequenceItemHeader::Item { len } => {
+                    let marker = self.create_item_marker(header)?;
+                    if let Some(&OpenContainer::PixelSequence) = self.open.last() {
+                        // fragments are not data sets, skip them altogether
+                        self.skip_value(len)?;
+                    } else {
                         self.in_sequence = false;
-                        Some(self.create_item_marker(header))
+                        let end = len.get().map(|len| marker.pos + u64::from(len));
+                        self.open.push(OpenContainer::Item(end));
                     }
-                },
-                Err(e) => {
-                    self.hard_break = true;
-                    Some(Err(Error::from(e)))
+                    Ok(Some(marker))
                 }
+                SequenceItemHeader::ItemDelimiter => Err(Error::UnexpectedElement),
+                SequenceItemHeader::SequenceDelimiter => self.close_container().map(Some),
             }
         } else {
-            match self.parser.decode_header(&mut self.source) {
-                Ok(
-                    header @ DataElementHeader {
-                        tag: Tag(0x0008, 0x0005),
-                        vr: _,
-                        len: _,
-                    },
-                ) => {
-                    let marker = self.create_element_marker(header);
-
-                    Some(marker)
-                }
-                Ok(header) => {
-                    // check if SQ
-                    if header.vr() == VR::SQ {
-                        self.in_sequence = true;
-                        self.depth += 1;
-                    }
-                    Some(self.create_element_marker(header))
-                }
-                Err(e) => {
-                    self.hard_break = true;
-                    Some(Err(Error::from(e)))
+            let header = match self.decode_header() {
+                Ok(header) => header,
+                Err(Error::Io(ref e))
+                    if e.kind() == ::std::io::ErrorKind::UnexpectedEof && self.open.is_empty() =>
+                {
+                    // end of the data set
+                    return Ok(None);
                 }
+                Err(e) => return Err(e),
+            };
+
+            if header.tag() == Tag(0xFFFE, 0xE00D) {
+                // item delimiter
+                return self.close_container().map(Some);
+            }
+
+            let marker = self.create_element_marker(header)?;
+            if header.vr() == VR::SQ {
+                self.in_sequence = true;
+                self.depth += 1;
+                let end = header.len().get().map(|len| marker.pos + u64::from(len));
+                self.open.push(OpenContainer::Sequence(end));
+            } else if header.len().is_undefined() {
+                let container = match (header.tag(), header.vr()) {
+                    (_, VR::UN) => OpenContainer::UnSequence,
+                    (Tag(0x7FE0, 0x0010), _) => OpenContainer::PixelSequence,
+                    _ => return Err(InvalidValueReadError::UnresolvedValueLength.into()),
+                };
+                self.in_sequence = true;
+                self.depth += 1;
+                self.open.push(container);
+            } else {
+                self.skip_value(header.len())?;
+            }
+            Ok(Some(marker))
+        }
+    }
+}
+
+impl<'s, S: 's, DS, P> Iterator for LazyDataSetReader<S, DS, P>
+where
+    S: ReadSeek,
+    P: Parse<Read + 's>,
+{
+    type Item = Result<DicomElementMarker>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.hard_break {
+            return None;
+        }
+        match self.next_marker() {
+            Ok(Some(marker)) => Some(Ok(marker)),
+            Ok(None) => {
+                self.hard_break = true;
+                None
+            }
+            Err(e) => {
+                self.hard_break = true;
+                Some(Err(e))
             }
         }
     }
@@ -431,7 +1001,7 @@ impl DicomElementMarker {
             .len()
             .get()
             .ok_or(InvalidValueReadError::UnresolvedValueLength)? as u64;
-        let interval = SeekInterval::new_at(source, self.pos..len)?;
+        let interval = SeekInterval::new_at(source, self.pos..self.pos + len)?;
         Ok(interval)
     }
 
@@ -460,3 +1030,384 @@ impl Header for DicomElementMarker {
         self.header.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DataSetReader, DataSetWriter, DicomDataToken, LazyDataSetReader, SequenceLength};
+    use data::value::PrimitiveValue;
+    use data::{DataElementHeader, Length, Tag, VR};
+    use data::text::SpecificCharacterSet;
+    use std::io::Cursor;
+    use transfer_syntax::ExplicitVRLittleEndian;
+
+    fn sample_tokens() -> Vec<DicomDataToken> {
+        vec![
+            DicomDataToken::SequenceStart {
+                tag: Tag(0x0018, 0x6011),
+                len: Length::undefined(),
+            },
+            DicomDataToken::ItemStart {
+                len: Length::undefined(),
+            },
+            DicomDataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0018, 0x6012),
+                VR::US,
+                Length(2),
+            )),
+            DicomDataToken::PrimitiveValue(PrimitiveValue::U16(vec![1])),
+            DicomDataToken::ItemEnd,
+            DicomDataToken::SequenceEnd,
+            DicomDataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0020, 0x4000),
+                VR::LT,
+                Length(3),
+            )),
+            DicomDataToken::PrimitiveValue(PrimitiveValue::Str("TEST".to_owned())),
+        ]
+    }
+
+    // the same tokens, encoded in explicit VR little endian:
+    //  (0018,6011) SQ, undefined length
+    //    item, undefined length
+    //      (0018,6012) US 1
+    //    item delimiter
+    //  sequence delimiter
+    //  (0020,4000) LT "TEST"
+    const UNDEFINED_LENGTH_DATA: &'static [u8] = &[
+        0x18, 0x00, 0x11, 0x60, b'S', b'Q', 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFE, 0xFF, 0x00, 0xE0, 0xFF, 0xFF, 0xFF, 0xFF,
+        0x18, 0x00, 0x12, 0x60, b'U', b'S', 0x02, 0x00, 0x01, 0x00,
+        0xFE, 0xFF, 0x0D, 0xE0, 0x00, 0x00, 0x00, 0x00,
+        0xFE, 0xFF, 0xDD, 0xE0, 0x00, 0x00, 0x00, 0x00,
+        0x20, 0x00, 0x00, 0x40, b'L', b'T', 0x04, 0x00, b'T', b'E', b'S', b'T',
+    ];
+
+    #[test]
+    fn write_tokens_undefined_length() {
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        writer.write_sequence(sample_tokens()).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(&writer.into_inner()[..], UNDEFINED_LENGTH_DATA);
+    }
+
+    #[test]
+    fn write_tokens_defined_length() {
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap()
+            .sequence_length(SequenceLength::Defined);
+        writer.write_sequence(sample_tokens()).unwrap();
+        writer.finish().unwrap();
+
+        // no delimiters, sequence length 18 and item length 10
+        let expected: &[u8] = &[
+            0x18, 0x00, 0x11, 0x60, b'S', b'Q', 0x00, 0x00, 0x12, 0x00, 0x00, 0x00,
+            0xFE, 0xFF, 0x00, 0xE0, 0x0A, 0x00, 0x00, 0x00,
+            0x18, 0x00, 0x12, 0x60, b'U', b'S', 0x02, 0x00, 0x01, 0x00,
+            0x20, 0x00, 0x00, 0x40, b'L', b'T', 0x04, 0x00, b'T', b'E', b'S', b'T',
+        ];
+        assert_eq!(&writer.into_inner()[..], expected);
+    }
+
+    #[test]
+    fn write_and_read_back_tokens() {
+        let mut reader = DataSetReader::new_with(
+            UNDEFINED_LENGTH_DATA,
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let tokens: Vec<_> = reader.by_ref().map(|t| t.unwrap()).collect();
+        assert_eq!(tokens.len(), 8);
+
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        writer.write_sequence(tokens).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(&writer.into_inner()[..], UNDEFINED_LENGTH_DATA);
+    }
+
+    #[test]
+    fn specific_character_set_switches_text_codec() {
+        let name = "Hong^Gildong=洪^吉洞=홍^길동";
+        let tokens = vec![
+            DicomDataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0008, 0x0005),
+                VR::CS,
+                Length(16),
+            )),
+            DicomDataToken::PrimitiveValue(PrimitiveValue::Strs(vec![
+                "".to_owned(),
+                "ISO 2022 IR 149".to_owned(),
+            ])),
+            DicomDataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0010, 0x0010),
+                VR::PN,
+                Length::undefined(),
+            )),
+            DicomDataToken::PrimitiveValue(PrimitiveValue::Str(name.to_owned())),
+        ];
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        writer.write_sequence(tokens).unwrap();
+        writer.finish().unwrap();
+        let data = writer.into_inner();
+        let korean: &[u8] = b"\x1b$)C\xc8\xab^\x1b$)C\xb1\xe6\xb5\xbf";
+        assert!(data.windows(korean.len()).any(|w| w == korean));
+
+        let reader = DataSetReader::new_with(
+            Cursor::new(data.clone()),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let tokens: Vec<_> = reader.map(|t| t.unwrap()).collect();
+        match tokens[3] {
+            DicomDataToken::PrimitiveValue(ref v) => {
+                assert_eq!(v.string().map(|v| v.trim_end()), Some(name))
+            }
+            ref token => panic!("unexpected token {:?}", token),
+        }
+
+        // unknown character sets fall back to the default repertoire
+        let mut data = data;
+        data[8..24].copy_from_slice(b"ISO_IR 999      ");
+        let reader = DataSetReader::new_with(
+            Cursor::new(data),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        assert!(reader.map(|t| t.map(|_| ())).collect::<Result<Vec<_>, _>>().is_ok());
+    }
+
+    #[test]
+    fn split_multi_byte_values_after_decoding() {
+        // 乗 is encoded in GBK as 81 5C, where the second byte is a backslash
+        let raw: &[u8] = &[
+            0x08, 0x00, 0x05, 0x00, b'C', b'S', 4, 0, b'G', b'B', b'K', b' ',
+            0x10, 0x00, 0x10, 0x00, b'P', b'N', 8, 0,
+            0x81, 0x5C, b'^', b'A', b'\\', 0x81, 0x5C, b' ',
+        ];
+        let reader = DataSetReader::new_with(
+            Cursor::new(raw),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let tokens: Vec<_> = reader.map(|t| t.unwrap()).collect();
+        match tokens[3] {
+            DicomDataToken::PrimitiveValue(ref v) => {
+                let names: Vec<_> = v.strings().unwrap().iter().map(|s| s.trim_end()).collect();
+                assert_eq!(names, vec!["乗^A", "乗"]);
+            }
+            ref token => panic!("unexpected token {:?}", token),
+        }
+    }
+
+    #[test]
+    fn empty_specific_character_set_is_the_default() {
+        let tokens = vec![
+            DicomDataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0008, 0x0005),
+                VR::CS,
+                Length(0),
+            )),
+            DicomDataToken::PrimitiveValue(PrimitiveValue::Empty),
+            DicomDataToken::ElementHeader(DataElementHeader::new(
+                Tag(0x0010, 0x0010),
+                VR::PN,
+                Length::undefined(),
+            )),
+            DicomDataToken::PrimitiveValue(PrimitiveValue::Str("Doe^John".to_owned())),
+        ];
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        writer.write_sequence(tokens).unwrap();
+        writer.finish().unwrap();
+
+        let reader = DataSetReader::new_with(
+            Cursor::new(writer.into_inner()),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let tokens: Vec<_> = reader.map(|t| t.unwrap()).collect();
+        assert_eq!(tokens.len(), 4);
+        match tokens[3] {
+            DicomDataToken::PrimitiveValue(ref v) => assert_eq!(v.string(), Some("Doe^John")),
+            ref token => panic!("unexpected token {:?}", token),
+        }
+    }
+
+    #[test]
+    fn unfinished_data_set_is_an_error() {
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let mut tokens = sample_tokens();
+        tokens.pop();
+        writer.write_sequence(tokens).unwrap();
+        assert!(writer.finish().is_err());
+
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        assert!(writer.write(DicomDataToken::PrimitiveValue(PrimitiveValue::Empty)).is_err());
+    }
+
+    #[test]
+    fn read_and_write_encapsulated_pixel_data() {
+        // (7FE0,0010) OB, undefined length
+        //   item: empty offset table
+        //   item: fragment [1, 2, 3, 4]
+        // sequence delimiter
+        let raw: &[u8] = &[
+            0xE0, 0x7F, 0x10, 0x00, b'O', b'B', 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+            0xFE, 0xFF, 0x00, 0xE0, 0x00, 0x00, 0x00, 0x00,
+            0xFE, 0xFF, 0x00, 0xE0, 0x04, 0x00, 0x00, 0x00, 1, 2, 3, 4,
+            0xFE, 0xFF, 0xDD, 0xE0, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let reader =
+            DataSetReader::new_with(raw, &ExplicitVRLittleEndian, SpecificCharacterSet::Default)
+                .unwrap();
+        let tokens: Vec<_> = reader.map(|t| t.unwrap()).collect();
+        assert_eq!(tokens.len(), 5);
+        match tokens[0] {
+            DicomDataToken::ElementHeader(header) => {
+                assert_eq!(header.tag, Tag(0x7FE0, 0x0010));
+                assert!(header.len.is_undefined());
+            }
+            ref token => panic!("unexpected token {}", token),
+        }
+        match (&tokens[1], &tokens[2], &tokens[3], &tokens[4]) {
+            (
+                &DicomDataToken::PixelSequenceStart,
+                &DicomDataToken::OffsetTable(ref table),
+                &DicomDataToken::ItemValue(ref fragment),
+                &DicomDataToken::SequenceEnd,
+            ) => {
+                assert!(table.is_empty());
+                assert_eq!(fragment, &vec![1, 2, 3, 4]);
+            }
+            _ => panic!("unexpected tokens {:?}", tokens),
+        }
+
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap()
+            .sequence_length(SequenceLength::Defined);
+        writer.write_sequence(tokens).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(&writer.into_inner()[..], raw);
+    }
+    /// (0008,1115) UN, undefined length
+    ///   item, undefined length
+    ///     (0010,0010) PN "Doe^J ", in implicit VR little endian
+    ///   item delimiter
+    /// sequence delimiter
+    /// (0010,0020) LO "42"
+    const UN_SEQUENCE_DATA: &[u8] = &[
+        0x08, 0x00, 0x15, 0x11, b'U', b'N', 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFE, 0xFF, 0x00, 0xE0, 0xFF, 0xFF, 0xFF, 0xFF,
+        0x10, 0x00, 0x10, 0x00, 0x06, 0x00, 0x00, 0x00, b'D', b'o', b'e', b'^', b'J', b' ',
+        0xFE, 0xFF, 0x0D, 0xE0, 0x00, 0x00, 0x00, 0x00,
+        0xFE, 0xFF, 0xDD, 0xE0, 0x00, 0x00, 0x00, 0x00,
+        0x10, 0x00, 0x20, 0x00, b'L', b'O', 0x02, 0x00, b'4', b'2',
+    ];
+
+    #[test]
+    fn read_un_sequence_in_implicit_vr() {
+        let reader = DataSetReader::new_with(
+            UN_SEQUENCE_DATA,
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let tokens: Vec<_> = reader.map(|t| t.unwrap()).collect();
+        assert_eq!(tokens.len(), 8);
+        match (&tokens[0], &tokens[1], &tokens[2], &tokens[3]) {
+            (
+                &DicomDataToken::SequenceStart { tag, len },
+                &DicomDataToken::ItemStart { .. },
+                &DicomDataToken::ElementHeader(header),
+                &DicomDataToken::PrimitiveValue(ref value),
+            ) => {
+                assert_eq!(tag, Tag(0x0008, 0x1115));
+                assert!(len.is_undefined());
+                assert_eq!(header, DataElementHeader::new(Tag(0x0010, 0x0010), VR::PN, Length(6)));
+                assert_eq!(value.string().map(str::trim_end), Some("Doe^J"));
+            }
+            _ => panic!("unexpected tokens {:?}", tokens),
+        }
+        match (&tokens[4], &tokens[5], &tokens[6]) {
+            (
+                &DicomDataToken::ItemEnd,
+                &DicomDataToken::SequenceEnd,
+                &DicomDataToken::ElementHeader(header),
+            ) => {
+                assert_eq!(header, DataElementHeader::new(Tag(0x0010, 0x0020), VR::LO, Length(2)));
+            }
+            _ => panic!("unexpected tokens {:?}", tokens),
+        }
+
+        let reader = LazyDataSetReader::new_with(
+            Cursor::new(UN_SEQUENCE_DATA),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let tags: Vec<_> = reader.map(|m| m.unwrap().header.tag).collect();
+        assert_eq!(
+            tags,
+            vec![
+                Tag(0x0008, 0x1115),
+                Tag(0xFFFE, 0xE000),
+                Tag(0x0010, 0x0010),
+                Tag(0xFFFE, 0xE00D),
+                Tag(0xFFFE, 0xE0DD),
+                Tag(0x0010, 0x0020),
+            ]
+        );
+    }
+
+    #[test]
+    fn undefined_length_outside_of_pixel_data_is_an_error() {
+        // (0009,0010) OB, undefined length
+        let raw: &[u8] = &[0x09, 0x00, 0x10, 0x00, b'O', b'B', 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF];
+        let reader =
+            DataSetReader::new_with(raw, &ExplicitVRLittleEndian, SpecificCharacterSet::Default)
+                .unwrap();
+        assert!(reader.map(|t| t.map(|_| ())).collect::<Result<Vec<_>, _>>().is_err());
+
+        let reader = LazyDataSetReader::new_with(
+            Cursor::new(raw),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        assert!(reader.map(|m| m.map(|_| ())).collect::<Result<Vec<_>, _>>().is_err());
+
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let header = DataElementHeader::new(Tag(0x0009, 0x0010), VR::OB, Length::undefined());
+        writer.write(DicomDataToken::ElementHeader(header)).unwrap();
+        assert!(writer.write(DicomDataToken::PixelSequenceStart).is_err());
+    }
+}
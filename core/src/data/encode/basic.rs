@@ -124,6 +124,15 @@ pub enum BasicEncoder {
 
 use self::BasicEncoder::{BE, LE};
 
+impl From<Endianness> for BasicEncoder {
+    fn from(endianness: Endianness) -> BasicEncoder {
+        match endianness {
+            Endianness::LE => LE(LittleEndianBasicEncoder::default()),
+            Endianness::BE => BE(BigEndianBasicEncoder::default()),
+        }
+    }
+}
+
 /// Handle multiple encoding tasks with the expected endianness. The parameter `$e`
 /// will either yield a `LittleEndianBasicEncoder` or a `BigEndianBasicEncoder`. When
 /// the specific basic encoder is still unknown in compile-time, this macro can be used
@@ -89,3 +89,57 @@ pub trait Encode {
         Ok(())
     }
 }
+
+impl<T: ?Sized> Encode for Box<T>
+where
+    T: Encode,
+{
+    type Writer = <T as Encode>::Writer;
+
+    fn encode_tag(&self, to: &mut Self::Writer, tag: Tag) -> Result<()> {
+        (**self).encode_tag(to, tag)
+    }
+
+    fn encode_element_header(&self, to: &mut Self::Writer, de: DataElementHeader) -> Result<usize> {
+        (**self).encode_element_header(to, de)
+    }
+
+    fn encode_item_header(&self, to: &mut Self::Writer, len: u32) -> Result<()> {
+        (**self).encode_item_header(to, len)
+    }
+
+    fn encode_item_delimiter(&self, to: &mut Self::Writer) -> Result<()> {
+        (**self).encode_item_delimiter(to)
+    }
+
+    fn encode_sequence_delimiter(&self, to: &mut Self::Writer) -> Result<()> {
+        (**self).encode_sequence_delimiter(to)
+    }
+}
+
+impl<'a, T: ?Sized> Encode for &'a T
+where
+    T: Encode,
+{
+    type Writer = <T as Encode>::Writer;
+
+    fn encode_tag(&self, to: &mut Self::Writer, tag: Tag) -> Result<()> {
+        (**self).encode_tag(to, tag)
+    }
+
+    fn encode_element_header(&self, to: &mut Self::Writer, de: DataElementHeader) -> Result<usize> {
+        (**self).encode_element_header(to, de)
+    }
+
+    fn encode_item_header(&self, to: &mut Self::Writer, len: u32) -> Result<()> {
+        (**self).encode_item_header(to, len)
+    }
+
+    fn encode_item_delimiter(&self, to: &mut Self::Writer) -> Result<()> {
+        (**self).encode_item_delimiter(to)
+    }
+
+    fn encode_sequence_delimiter(&self, to: &mut Self::Writer) -> Result<()> {
+        (**self).encode_sequence_delimiter(to)
+    }
+}
@@ -3,14 +3,22 @@
 //! element header, and element composite types.
 
 pub mod dataset;
+pub mod datetime;
 pub mod decode;
 pub mod encode;
 pub mod parser;
+pub mod person_name;
+pub mod printer;
+pub mod range;
 pub mod text;
 pub mod value;
 
 use data::value::{DicomValueType, PrimitiveValue, Value};
 use error::{Error, Result};
+#[cfg(feature = "serde")]
+use serde::de::Error as DeError;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::fmt;
@@ -44,6 +52,7 @@ pub trait Header {
 
 /// A data type that represents and owns a DICOM data element.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DataElement<I> {
     header: DataElementHeader,
     value: Value<I>,
@@ -225,6 +234,7 @@ where
 /// A data structure for a data element header, containing
 /// a tag, value representation and specified length.
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DataElementHeader {
     /// DICOM tag
     pub tag: Tag,
@@ -336,6 +346,7 @@ impl Header for SequenceItemHeader {
 
 /// An enum type for a DICOM value representation.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum VR {
     /// Application Entity
     AE,
@@ -533,6 +544,62 @@ impl fmt::Display for Tag {
     }
 }
 
+/// Tags are serialized in their textual form `(GGGG,EEEE)` in human-readable
+/// formats, and as a `(group, element)` tuple otherwise.
+#[cfg(feature = "serde")]
+impl Serialize for Tag {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            (self.0, self.1).serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let text = String::deserialize(deserializer)?;
+            parse_tag(&text).ok_or_else(|| {
+                D::Error::custom(format!("invalid tag \"{}\", expected (GGGG,EEEE)", text))
+            })
+        } else {
+            let (group, element) = <(u16, u16)>::deserialize(deserializer)?;
+            Ok(Tag(group, element))
+        }
+    }
+}
+
+/// Parse a tag in the form `(GGGG,EEEE)`.
+#[cfg(feature = "serde")]
+fn parse_tag(text: &str) -> Option<Tag> {
+    let text = text.trim();
+    if !text.starts_with('(') || !text.ends_with(')') {
+        return None;
+    }
+    let mut parts = text[1..text.len() - 1].split(',');
+    let mut next = || {
+        let part = parts.next()?.trim();
+        if part.len() != 4 {
+            return None;
+        }
+        u16::from_str_radix(part, 16).ok()
+    };
+    let tag = Tag(next()?, next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(tag)
+}
+
 impl PartialEq<(u16, u16)> for Tag {
     fn eq(&self, other: &(u16, u16)) -> bool {
         self.0 == other.0 && self.1 == other.1
@@ -598,6 +665,7 @@ impl From<[u16; 2]> for Tag {
 /// ```
 ///
 #[derive(Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Length(pub u32);
 
 const UNDEFINED_LEN: u32 = 0xFFFF_FFFF;
@@ -785,4 +853,170 @@ mod tests {
         assert_eq!(0x0010u16, t.group());
         assert_eq!(0x0020u16, t.element());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tag_serde_as_text() {
+        let t = Tag(0x7FE0, 0x0010);
+        let json = ::serde_json::to_string(&t).unwrap();
+        assert_eq!(json, "\"(7FE0,0010)\"");
+        assert_eq!(::serde_json::from_str::<Tag>(&json).unwrap(), t);
+        assert_eq!(::serde_json::from_str::<Tag>("\"(7fe0, 0010)\"").unwrap(), t);
+        assert!(::serde_json::from_str::<Tag>("\"7FE00010\"").is_err());
+        assert!(::serde_json::from_str::<Tag>("\"(7FE0,0010,0000)\"").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tag_serde_as_tuple() {
+        use self::compact::{from_compact, to_compact};
+
+        let t = Tag(0x7FE0, 0x0010);
+        assert_eq!(to_compact(&t).unwrap(), vec![0x7FE0, 0x0010]);
+        assert_eq!(from_compact::<Tag>(vec![0x7FE0, 0x0010]).unwrap(), t);
+        assert!(from_compact::<Tag>(vec![0x7FE0]).is_err());
+    }
+
+    /// A format which is not human-readable, holding nothing but a
+    /// sequence of `u16` numbers.
+    #[cfg(feature = "serde")]
+    mod compact {
+        use serde::de::value::{Error, SeqDeserializer};
+        use serde::de::{Deserialize, Deserializer, Visitor};
+        use serde::ser::{self, Error as SerError, Impossible, Serialize, Serializer};
+
+        pub fn to_compact<T: Serialize>(value: &T) -> Result<Vec<u16>, Error> {
+            value.serialize(Compact)
+        }
+
+        pub fn from_compact<'de, T: Deserialize<'de>>(values: Vec<u16>) -> Result<T, Error> {
+            T::deserialize(CompactValues(values))
+        }
+
+        struct Compact;
+
+        struct CompactTuple(Vec<u16>);
+
+        type Unsupported = Impossible<Vec<u16>, Error>;
+
+        macro_rules! unsupported {
+            ($($method: ident ($($arg: ty),*) -> $ok: ty;)*) => {
+                $(
+                    fn $method(self, $(_: $arg),*) -> Result<$ok, Error> {
+                        Err(Error::custom("unsupported by the compact format"))
+                    }
+                )*
+            };
+        }
+
+        impl Serializer for Compact {
+            type Ok = Vec<u16>;
+            type Error = Error;
+            type SerializeSeq = Unsupported;
+            type SerializeTuple = CompactTuple;
+            type SerializeTupleStruct = Unsupported;
+            type SerializeTupleVariant = Unsupported;
+            type SerializeMap = Unsupported;
+            type SerializeStruct = Unsupported;
+            type SerializeStructVariant = Unsupported;
+
+            fn is_human_readable(&self) -> bool {
+                false
+            }
+
+            fn serialize_u16(self, v: u16) -> Result<Vec<u16>, Error> {
+                Ok(vec![v])
+            }
+
+            fn serialize_tuple(self, len: usize) -> Result<CompactTuple, Error> {
+                Ok(CompactTuple(Vec::with_capacity(len)))
+            }
+
+            fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<Vec<u16>, Error> {
+                Err(Error::custom("unsupported by the compact format"))
+            }
+
+            fn serialize_newtype_struct<T: ?Sized + Serialize>(
+                self,
+                _: &'static str,
+                _: &T,
+            ) -> Result<Vec<u16>, Error> {
+                Err(Error::custom("unsupported by the compact format"))
+            }
+
+            fn serialize_newtype_variant<T: ?Sized + Serialize>(
+                self,
+                _: &'static str,
+                _: u32,
+                _: &'static str,
+                _: &T,
+            ) -> Result<Vec<u16>, Error> {
+                Err(Error::custom("unsupported by the compact format"))
+            }
+
+            unsupported! {
+                serialize_bool(bool) -> Vec<u16>;
+                serialize_i8(i8) -> Vec<u16>;
+                serialize_i16(i16) -> Vec<u16>;
+                serialize_i32(i32) -> Vec<u16>;
+                serialize_i64(i64) -> Vec<u16>;
+                serialize_u8(u8) -> Vec<u16>;
+                serialize_u32(u32) -> Vec<u16>;
+                serialize_u64(u64) -> Vec<u16>;
+                serialize_f32(f32) -> Vec<u16>;
+                serialize_f64(f64) -> Vec<u16>;
+                serialize_char(char) -> Vec<u16>;
+                serialize_str(&str) -> Vec<u16>;
+                serialize_bytes(&[u8]) -> Vec<u16>;
+                serialize_none() -> Vec<u16>;
+                serialize_unit() -> Vec<u16>;
+                serialize_unit_struct(&'static str) -> Vec<u16>;
+                serialize_unit_variant(&'static str, u32, &'static str) -> Vec<u16>;
+                serialize_seq(Option<usize>) -> Unsupported;
+                serialize_tuple_struct(&'static str, usize) -> Unsupported;
+                serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Unsupported;
+                serialize_map(Option<usize>) -> Unsupported;
+                serialize_struct(&'static str, usize) -> Unsupported;
+                serialize_struct_variant(&'static str, u32, &'static str, usize)
+                    -> Unsupported;
+            }
+        }
+
+        impl ser::SerializeTuple for CompactTuple {
+            type Ok = Vec<u16>;
+            type Error = Error;
+
+            fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+                self.0.extend(value.serialize(Compact)?);
+                Ok(())
+            }
+
+            fn end(self) -> Result<Vec<u16>, Error> {
+                Ok(self.0)
+            }
+        }
+
+        struct CompactValues(Vec<u16>);
+
+        impl<'de> Deserializer<'de> for CompactValues {
+            type Error = Error;
+
+            fn is_human_readable(&self) -> bool {
+                false
+            }
+
+            fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let mut values = SeqDeserializer::new(self.0.into_iter());
+                let value = visitor.visit_seq(&mut values)?;
+                values.end()?;
+                Ok(value)
+            }
+
+            forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map struct enum identifier ignored_any
+            }
+        }
+    }
 }
@@ -0,0 +1,233 @@
+//! This module contains a structured representation of Person Name (PN)
+//! values.
+//!
+//! A person name is made of up to three component groups: alphabetic,
+//! ideographic and phonetic, separated by `=`. Each group holds up to five
+//! components, separated by `^`: the family name, given name, middle name,
+//! name prefix and name suffix.
+
+use std::fmt;
+
+/// The five components of a person name in one representation.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct PersonNameComponents {
+    /// The family name complex.
+    pub family: String,
+    /// The given name complex.
+    pub given: String,
+    /// The middle name.
+    pub middle: String,
+    /// The name prefix, such as a title.
+    pub prefix: String,
+    /// The name suffix.
+    pub suffix: String,
+}
+
+impl PersonNameComponents {
+    /// Parse a component group, without the `=` delimiters. Missing
+    /// components are left empty, and surrounding spaces are removed.
+    pub fn parse(text: &str) -> Self {
+        let mut components = text.splitn(5, '^').map(|c| c.trim().to_owned());
+        let mut next = || components.next().unwrap_or_default();
+        PersonNameComponents {
+            family: next(),
+            given: next(),
+            middle: next(),
+            prefix: next(),
+            suffix: next(),
+        }
+    }
+
+    /// Check whether all components are empty.
+    pub fn is_empty(&self) -> bool {
+        self.components().iter().all(|c| c.is_empty())
+    }
+
+    /// The components in their order of encoding.
+    fn components(&self) -> [&str; 5] {
+        [&self.family, &self.given, &self.middle, &self.prefix, &self.suffix]
+    }
+}
+
+impl fmt::Display for PersonNameComponents {
+    /// Format the component group in its encoded form, without trailing
+    /// `^` delimiters.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let components = self.components();
+        let len = components.iter().rposition(|c| !c.is_empty()).map_or(0, |i| i + 1);
+        write!(f, "{}", components[..len].join("^"))
+    }
+}
+
+/// A person name, with its alphabetic, ideographic and phonetic
+/// representations.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct PersonName {
+    /// The alphabetic representation of the name.
+    pub alphabetic: PersonNameComponents,
+    /// The ideographic representation of the name, such as kanji.
+    pub ideographic: PersonNameComponents,
+    /// The phonetic representation of the name, such as hiragana.
+    pub phonetic: PersonNameComponents,
+}
+
+impl PersonName {
+    /// Parse a single person name as encoded in a PN value, such as
+    /// `Yamada^Tarou=山田^太郎=やまだ^たろう`. Padding is ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut groups = text.trim_end_matches(&[' ', '\0'][..])
+            .splitn(3, '=')
+            .map(PersonNameComponents::parse);
+        let mut next = || groups.next().unwrap_or_default();
+        PersonName {
+            alphabetic: next(),
+            ideographic: next(),
+            phonetic: next(),
+        }
+    }
+
+    /// Parse all person names of a multi-valued PN value.
+    pub fn parse_all(text: &str) -> Vec<Self> {
+        text.split('\\').map(PersonName::parse).collect()
+    }
+
+    /// Create a person name with only an alphabetic representation.
+    pub fn new<F, G>(family: F, given: G) -> Self
+    where
+        F: Into<String>,
+        G: Into<String>,
+    {
+        PersonName {
+            alphabetic: PersonNameComponents {
+                family: family.into(),
+                given: given.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// The alphabetic family name.
+    pub fn family_name(&self) -> &str {
+        &self.alphabetic.family
+    }
+
+    /// The alphabetic given name.
+    pub fn given_name(&self) -> &str {
+        &self.alphabetic.given
+    }
+
+    /// The alphabetic middle name.
+    pub fn middle_name(&self) -> &str {
+        &self.alphabetic.middle
+    }
+
+    /// The alphabetic name prefix.
+    pub fn prefix(&self) -> &str {
+        &self.alphabetic.prefix
+    }
+
+    /// The alphabetic name suffix.
+    pub fn suffix(&self) -> &str {
+        &self.alphabetic.suffix
+    }
+
+    /// Check whether the name has no components in any representation.
+    pub fn is_empty(&self) -> bool {
+        self.alphabetic.is_empty() && self.ideographic.is_empty() && self.phonetic.is_empty()
+    }
+}
+
+impl fmt::Display for PersonName {
+    /// Format the person name in its encoded form, without trailing
+    /// delimiters.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let groups = [
+            self.alphabetic.to_string(),
+            self.ideographic.to_string(),
+            self.phonetic.to_string(),
+        ];
+        let len = groups.iter().rposition(|g| !g.is_empty()).map_or(0, |i| i + 1);
+        write!(f, "{}", groups[..len].join("="))
+    }
+}
+
+impl<'a> From<&'a str> for PersonName {
+    fn from(text: &'a str) -> Self {
+        PersonName::parse(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PersonName, PersonNameComponents};
+
+    #[test]
+    fn parse_alphabetic_name() {
+        let name = PersonName::parse("Adams^John Robert Quincy^^Rev.^B.A. M.Div. ");
+        assert_eq!(name.family_name(), "Adams");
+        assert_eq!(name.given_name(), "John Robert Quincy");
+        assert_eq!(name.middle_name(), "");
+        assert_eq!(name.prefix(), "Rev.");
+        assert_eq!(name.suffix(), "B.A. M.Div.");
+        assert!(name.ideographic.is_empty());
+        assert_eq!(name.to_string(), "Adams^John Robert Quincy^^Rev.^B.A. M.Div.");
+
+        let name = PersonName::parse("Morrison-Jones^Susan^^^Ph.D., Chief Executive Officer");
+        assert_eq!(name.family_name(), "Morrison-Jones");
+        assert_eq!(name.suffix(), "Ph.D., Chief Executive Officer");
+
+        let name = PersonName::parse("Doe");
+        assert_eq!(name, PersonName::new("Doe", ""));
+        assert_eq!(name.to_string(), "Doe");
+        assert!(PersonName::parse("").is_empty());
+    }
+
+    #[test]
+    fn parse_component_groups() {
+        let text = "Yamada^Tarou=山田^太郎=やまだ^たろう";
+        let name = PersonName::parse(text);
+        assert_eq!(name.alphabetic, PersonNameComponents::parse("Yamada^Tarou"));
+        assert_eq!(name.ideographic.family, "山田");
+        assert_eq!(name.ideographic.given, "太郎");
+        assert_eq!(name.phonetic.family, "やまだ");
+        assert_eq!(name.phonetic.given, "たろう");
+        assert_eq!(name.to_string(), text);
+
+        // empty groups are kept only when followed by other groups
+        let name = PersonName::parse("=金^홍길=");
+        assert!(name.alphabetic.is_empty());
+        assert_eq!(name.ideographic.given, "홍길");
+        assert_eq!(name.to_string(), "=金^홍길");
+    }
+
+    #[test]
+    fn parse_multiple_names() {
+        let names = PersonName::parse_all("Smith^John\\Doe^Jane^^Dr.");
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[0], PersonName::new("Smith", "John"));
+        assert_eq!(names[1].prefix(), "Dr.");
+        assert_eq!(names[1].to_string(), "Doe^Jane^^Dr.");
+    }
+
+    #[test]
+    fn value_accessors() {
+        use data::value::{PrimitiveValue, Value};
+
+        let value = PrimitiveValue::Strs(vec!["Smith^John".to_owned(), "Doe^Jane".to_owned()]);
+        assert_eq!(value.person_name(), Some(PersonName::new("Smith", "John")));
+        assert_eq!(value.person_names().map(|v| v.len()), Some(2));
+        assert_eq!(PrimitiveValue::U16(vec![1]).person_name(), None);
+
+        let value: Value<PrimitiveValue> = Value::Primitive(PersonName::new("Doe", "Jane").into());
+        assert_eq!(value.to_person_name().unwrap().given_name(), "Jane");
+        assert_eq!(value.to_person_names().unwrap().len(), 1);
+
+        let empty: Value<PrimitiveValue> = Value::Primitive(PrimitiveValue::Empty);
+        assert_eq!(empty.to_person_name().unwrap(), PersonName::default());
+        assert!(empty.to_person_name().unwrap().is_empty());
+        assert!(empty.to_person_names().unwrap().is_empty());
+        let empty = PrimitiveValue::Strs(vec![]);
+        assert_eq!(empty.person_name(), Some(PersonName::default()));
+    }
+}
@@ -0,0 +1,413 @@
+//! This module provides a higher level abstraction for writing DICOM data.
+//! The structures provided here translate data element headers and
+//! primitive values into their binary form, according to a transfer syntax
+//! and a specific character set. It is the writing counterpart of the
+//! `parser` module.
+
+use data::datetime::{format_date, format_datetime, format_time};
+use data::encode::basic::BasicEncoder;
+use data::encode::{BasicEncode, Encode};
+use data::text::{DefaultCharacterSetCodec, DynamicTextCodec, SpecificCharacterSet, TextCodec};
+use data::value::{DicomValueType, PrimitiveValue};
+use data::{DataElementHeader, Tag, VR};
+use error::{CastValueError, Error, InvalidValueReadError, Result};
+use itertools::Itertools;
+use std::fmt;
+use std::fmt::Debug;
+use std::io::Write;
+use std::marker::PhantomData;
+use transfer_syntax::{DynamicEncoder, TransferSyntax};
+
+/// A trait for DICOM data printers, which abstracts the necessary parts
+/// of a full DICOM content writing process.
+pub trait Print<W: ?Sized>
+where
+    W: Write,
+{
+    /// Same as `Encode::encode_element_header` over the bound destination.
+    fn encode_element_header(&self, to: &mut W, header: DataElementHeader) -> Result<usize>;
+
+    /// Same as `Encode::encode_item_header` over the bound destination.
+    fn encode_item_header(&self, to: &mut W, len: u32) -> Result<()>;
+
+    /// Same as `Encode::encode_item_delimiter` over the bound destination.
+    fn encode_item_delimiter(&self, to: &mut W) -> Result<()>;
+
+    /// Same as `Encode::encode_sequence_delimiter` over the bound destination.
+    fn encode_sequence_delimiter(&self, to: &mut W) -> Result<()>;
+
+    /// Encode the given primitive value into a byte vector, as it would be
+    /// written in a data element with the given value representation. The
+    /// resulting data is always padded to an even length, so that its size
+    /// can be used as the element's value length.
+    ///
+    /// Numbers, dates and times are converted to text when the value
+    /// representation requires it (e.g. IS, DS, DA, TM and DT).
+    fn encode_value(&self, vr: VR, value: &PrimitiveValue) -> Result<Vec<u8>>;
+
+    /// Define the specific character set of subsequent text elements.
+    fn set_character_set(&mut self, charset: SpecificCharacterSet) -> Result<()>;
+}
+
+/// Alias for a dynamically resolved DICOM printer. Although the data destination may be
+/// known in compile time, the required encoder may vary according to an object's
+/// transfer syntax.
+pub type DynamicDicomPrinter<'w> =
+    DicomPrinter<DynamicEncoder<'w>, BasicEncoder, Write + 'w, DynamicTextCodec>;
+
+/// A data structure for printing DICOM data.
+/// This type encapsulates the necessary codecs in order
+/// to be as autonomous as possible in the DICOM content writing
+/// process.
+/// `W` is the generic parameter type for the destination's type,
+/// `E` is the type of the data element encoder,
+/// whereas `BE` is the type of the basic encoder.
+/// `TC` defines the text codec used underneath.
+pub struct DicomPrinter<E, BE, W: ?Sized, TC> {
+    phantom: PhantomData<W>,
+    encoder: E,
+    basic: BE,
+    text: TC,
+}
+
+impl<W: ?Sized, E, BE, TC> Debug for DicomPrinter<E, BE, W, TC>
+where
+    BE: Debug,
+    TC: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DicomPrinter")
+            .field("encoder", &"«omitted»")
+            .field("basic", &self.basic)
+            .field("text", &self.text)
+            .finish()
+    }
+}
+
+impl<'w> DynamicDicomPrinter<'w> {
+    /// Create a new DICOM printer for the given transfer syntax and character set.
+    pub fn new_with(ts: &TransferSyntax, cs: SpecificCharacterSet) -> Result<Self> {
+        let basic = ts.get_basic_encoder();
+        let encoder = ts.get_encoder()
+            .ok_or_else(|| Error::UnsupportedTransferSyntax)?;
+        let text = cs.get_codec()
+            .ok_or_else(|| Error::UnsupportedCharacterSet)?;
+
+        Ok(DicomPrinter {
+            phantom: PhantomData,
+            basic,
+            encoder,
+            text,
+        })
+    }
+}
+
+impl<E, BE, W: ?Sized, TC> DicomPrinter<E, BE, W, TC>
+where
+    E: Encode<Writer = W>,
+    BE: BasicEncode,
+    W: Write,
+    TC: TextCodec,
+{
+    /// Create a new DICOM printer from its parts.
+    pub fn new(encoder: E, basic: BE, text: TC) -> DicomPrinter<E, BE, W, TC> {
+        DicomPrinter {
+            phantom: PhantomData,
+            basic,
+            encoder,
+            text,
+        }
+    }
+
+    // ---------------- private methods ---------------------
+
+    fn encode_text(&self, vr: VR, text: &str) -> Result<Vec<u8>> {
+        let mut buf = match vr {
+            VR::AE | VR::AS | VR::CS | VR::DA | VR::DS | VR::DT | VR::IS | VR::TM | VR::UI => {
+                DefaultCharacterSetCodec.encode(text)?
+            }
+            _ => self.text.encode(text)?,
+        };
+        if buf.len() % 2 == 1 {
+            buf.push(text_padding(vr));
+        }
+        Ok(buf)
+    }
+
+    fn encode_numbers<T, F>(&self, values: &[T], size: usize, f: F) -> Result<Vec<u8>>
+    where
+        F: Fn(&mut Vec<u8>, &T) -> Result<()>,
+    {
+        let mut buf = Vec::with_capacity(values.len() * size);
+        for v in values {
+            f(&mut buf, v)?;
+        }
+        Ok(buf)
+    }
+}
+
+/// Obtain the byte used for padding text values of the given
+/// value representation to an even length.
+fn text_padding(vr: VR) -> u8 {
+    match vr {
+        VR::UI | VR::OB | VR::UN => 0,
+        _ => b' ',
+    }
+}
+
+/// Check whether values of the given representation are encoded as text.
+fn is_textual(vr: VR) -> bool {
+    match vr {
+        VR::AE
+        | VR::AS
+        | VR::CS
+        | VR::DA
+        | VR::DS
+        | VR::DT
+        | VR::IS
+        | VR::LO
+        | VR::LT
+        | VR::PN
+        | VR::SH
+        | VR::ST
+        | VR::TM
+        | VR::UC
+        | VR::UI
+        | VR::UR
+        | VR::UT => true,
+        _ => false,
+    }
+}
+
+impl<E, BE, W: ?Sized> Print<W> for DicomPrinter<E, BE, W, Box<TextCodec>>
+where
+    E: Encode<Writer = W>,
+    BE: BasicEncode,
+    W: Write,
+{
+    fn encode_element_header(&self, to: &mut W, header: DataElementHeader) -> Result<usize> {
+        self.encoder.encode_element_header(to, header)
+    }
+
+    fn encode_item_header(&self, to: &mut W, len: u32) -> Result<()> {
+        self.encoder.encode_item_header(to, len)
+    }
+
+    fn encode_item_delimiter(&self, to: &mut W) -> Result<()> {
+        self.encoder.encode_item_delimiter(to)
+    }
+
+    fn encode_sequence_delimiter(&self, to: &mut W) -> Result<()> {
+        self.encoder.encode_sequence_delimiter(to)
+    }
+
+    fn encode_value(&self, vr: VR, value: &PrimitiveValue) -> Result<Vec<u8>> {
+        if vr == VR::SQ {
+            // sequence objects are handled at a higher level
+            return Err(Error::from(InvalidValueReadError::NonPrimitiveType));
+        }
+
+        let mut buf = match *value {
+            PrimitiveValue::Empty => vec![],
+            PrimitiveValue::Str(ref s) => self.encode_text(vr, s)?,
+            PrimitiveValue::Strs(ref c) => self.encode_text(vr, &c.iter().join("\\"))?,
+            PrimitiveValue::Date(ref c) => {
+                self.encode_text(vr, &c.iter().map(format_date).join("\\"))?
+            }
+            PrimitiveValue::Time(ref c) => {
+                self.encode_text(vr, &c.iter().map(format_time).join("\\"))?
+            }
+            PrimitiveValue::DateTime(ref c) => {
+                self.encode_text(vr, &c.iter().map(format_datetime).join("\\"))?
+            }
+            PrimitiveValue::I16(ref c) if is_textual(vr) => {
+                self.encode_text(vr, &c.iter().join("\\"))?
+            }
+            PrimitiveValue::U16(ref c) if is_textual(vr) => {
+                self.encode_text(vr, &c.iter().join("\\"))?
+            }
+            PrimitiveValue::I32(ref c) if is_textual(vr) => {
+                self.encode_text(vr, &c.iter().join("\\"))?
+            }
+            PrimitiveValue::U32(ref c) if is_textual(vr) => {
+                self.encode_text(vr, &c.iter().join("\\"))?
+            }
+            PrimitiveValue::F32(ref c) if is_textual(vr) => {
+                self.encode_text(vr, &c.iter().map(|v| format_ds(*v)).join("\\"))?
+            }
+            PrimitiveValue::F64(ref c) if is_textual(vr) => {
+                self.encode_text(vr, &c.iter().map(|v| format_ds(*v)).join("\\"))?
+            }
+            PrimitiveValue::Tags(_) | PrimitiveValue::U8(_) if is_textual(vr) => {
+                return Err(CastValueError {
+                    requested: "string",
+                    got: value.value_type(),
+                }.into());
+            }
+            PrimitiveValue::Tags(ref c) => self.encode_numbers(c, 4, |buf, tag: &Tag| {
+                self.basic.encode_us(&mut *buf, tag.group())?;
+                self.basic.encode_us(buf, tag.element())
+            })?,
+            PrimitiveValue::U8(ref c) => c.clone(),
+            PrimitiveValue::I16(ref c) => {
+                self.encode_numbers(c, 2, |buf, v| self.basic.encode_ss(buf, *v))?
+            }
+            PrimitiveValue::U16(ref c) => {
+                self.encode_numbers(c, 2, |buf, v| self.basic.encode_us(buf, *v))?
+            }
+            PrimitiveValue::I32(ref c) => {
+                self.encode_numbers(c, 4, |buf, v| self.basic.encode_sl(buf, *v))?
+            }
+            PrimitiveValue::U32(ref c) => {
+                self.encode_numbers(c, 4, |buf, v| self.basic.encode_ul(buf, *v))?
+            }
+            PrimitiveValue::F32(ref c) => {
+                self.encode_numbers(c, 4, |buf, v| self.basic.encode_fl(buf, *v))?
+            }
+            PrimitiveValue::F64(ref c) => {
+                self.encode_numbers(c, 8, |buf, v| self.basic.encode_fd(buf, *v))?
+            }
+        };
+
+        if buf.len() % 2 == 1 {
+            buf.push(text_padding(vr));
+        }
+        Ok(buf)
+    }
+
+    fn set_character_set(&mut self, charset: SpecificCharacterSet) -> Result<()> {
+        self.text = charset
+            .get_codec()
+            .ok_or_else(|| Error::UnsupportedCharacterSet)?;
+        Ok(())
+    }
+}
+
+/// Format a number in the DS representation, which allows at most
+/// 16 characters. Numbers which would not fit in plain decimal notation
+/// are written in scientific notation instead, with as many significant
+/// digits as fit.
+pub(crate) fn format_ds<T>(value: T) -> String
+where
+    T: fmt::Display + fmt::LowerExp,
+{
+    let txt = value.to_string();
+    if txt.len() <= 16 {
+        return txt;
+    }
+    let txt = format!("{:e}", value);
+    if txt.len() <= 16 {
+        return txt;
+    }
+    (0..16)
+        .rev()
+        .map(|precision| format!("{:.*e}", precision, value))
+        .find(|txt| txt.len() <= 16)
+        .unwrap_or(txt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_ds, DynamicDicomPrinter, Print};
+    use chrono::{NaiveDate, NaiveTime};
+    use data::text::SpecificCharacterSet;
+    use data::value::PrimitiveValue;
+    use data::{DataElementHeader, Length, Tag, VR};
+    use transfer_syntax::{ExplicitVRBigEndian, ExplicitVRLittleEndian};
+
+    #[test]
+    fn encode_text_values_with_padding() {
+        let printer =
+            DynamicDicomPrinter::new_with(&ExplicitVRLittleEndian, SpecificCharacterSet::Default)
+                .unwrap();
+
+        let v = PrimitiveValue::Strs(vec!["1.2.840.10008.1.2.1".to_string()]);
+        assert_eq!(
+            printer.encode_value(VR::UI, &v).unwrap(),
+            b"1.2.840.10008.1.2.1\0".to_vec()
+        );
+
+        let v = PrimitiveValue::Strs(vec!["DERIVED".to_string(), "PRIMARY".to_string()]);
+        assert_eq!(
+            printer.encode_value(VR::CS, &v).unwrap(),
+            b"DERIVED\\PRIMARY ".to_vec()
+        );
+
+        let v = PrimitiveValue::I32(vec![512, -1]);
+        assert_eq!(printer.encode_value(VR::IS, &v).unwrap(), b"512\\-1".to_vec());
+
+        let v = PrimitiveValue::F64(vec![1.5, 0.25, -2.0]);
+        assert_eq!(
+            printer.encode_value(VR::DS, &v).unwrap(),
+            b"1.5\\0.25\\-2 ".to_vec()
+        );
+
+        let v = PrimitiveValue::Date(vec![NaiveDate::from_ymd(2018, 4, 26)]);
+        assert_eq!(printer.encode_value(VR::DA, &v).unwrap(), b"20180426".to_vec());
+
+        let v = PrimitiveValue::Time(vec![
+            NaiveTime::from_hms(7, 55, 1),
+            NaiveTime::from_hms_micro(10, 10, 10, 204_000),
+        ]);
+        assert_eq!(
+            printer.encode_value(VR::TM, &v).unwrap(),
+            b"075501\\101010.204000".to_vec()
+        );
+    }
+
+    #[test]
+    fn format_ds_in_16_characters() {
+        assert_eq!(format_ds(0.25), "0.25");
+        assert_eq!(format_ds(1e20), "1e20");
+        // the shortest exact form would take 21 characters
+        assert_eq!(format_ds(0.1 + 0.2), "3.00000000000e-1");
+        assert_eq!(format_ds(-1.234_567_890_123_456_7e-300), "-1.23456789e-300");
+    }
+
+    #[test]
+    fn encode_binary_values() {
+        let printer =
+            DynamicDicomPrinter::new_with(&ExplicitVRLittleEndian, SpecificCharacterSet::Default)
+                .unwrap();
+
+        let v = PrimitiveValue::U16(vec![0x0102, 0x0304]);
+        assert_eq!(
+            printer.encode_value(VR::US, &v).unwrap(),
+            vec![0x02, 0x01, 0x04, 0x03]
+        );
+        let v = PrimitiveValue::Tags(vec![Tag(0x0028, 0x0010)]);
+        assert_eq!(
+            printer.encode_value(VR::AT, &v).unwrap(),
+            vec![0x28, 0x00, 0x10, 0x00]
+        );
+        let v = PrimitiveValue::U8(vec![1, 2, 3]);
+        assert_eq!(printer.encode_value(VR::OB, &v).unwrap(), vec![1, 2, 3, 0]);
+        assert!(printer.encode_value(VR::SQ, &v).is_err());
+        assert!(printer.encode_value(VR::LO, &v).is_err());
+
+        let printer =
+            DynamicDicomPrinter::new_with(&ExplicitVRBigEndian, SpecificCharacterSet::Default)
+                .unwrap();
+        let v = PrimitiveValue::U32(vec![0x0102_0304]);
+        assert_eq!(
+            printer.encode_value(VR::UL, &v).unwrap(),
+            vec![0x01, 0x02, 0x03, 0x04]
+        );
+    }
+
+    #[test]
+    fn print_element_header() {
+        let printer =
+            DynamicDicomPrinter::new_with(&ExplicitVRLittleEndian, SpecificCharacterSet::Default)
+                .unwrap();
+        let mut out = Vec::new();
+        let len = printer
+            .encode_element_header(
+                &mut out,
+                DataElementHeader::new(Tag(0x0010, 0x0010), VR::PN, Length(8)),
+            )
+            .unwrap();
+        assert_eq!(len, 8);
+        assert_eq!(out, vec![0x10, 0x00, 0x10, 0x00, b'P', b'N', 0x08, 0x00]);
+    }
+}
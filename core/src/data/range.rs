@@ -0,0 +1,350 @@
+//! This module contains range types for the DA, TM and DT value
+//! representations, as used in range matching of query keys.
+//!
+//! A range is written as two values separated by a hyphen, either of which
+//! may be missing: `20200101-20201231`, `-1200` or `0800-`. A single value
+//! stands for every instant within its precision, so that `2020` covers the
+//! whole year and `1030` covers every second of that minute.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeZone};
+use error::{Error, InvalidValueReadError, Result};
+
+fn invalid() -> Error {
+    InvalidValueReadError::InvalidFormat.into()
+}
+
+/// Read a run of decimal digits as a number.
+fn number(text: &str) -> Result<u32> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    text.parse().map_err(|_| invalid())
+}
+
+/// An inclusive range of values, where a missing bound leaves the range
+/// open on that side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ValueRange<T> {
+    start: Option<T>,
+    end: Option<T>,
+}
+
+/// A range of dates, as in DA range matching.
+pub type DateRange = ValueRange<NaiveDate>;
+
+/// A range of times, as in TM range matching.
+pub type TimeRange = ValueRange<NaiveTime>;
+
+/// A range of date-times, as in DT range matching.
+pub type DateTimeRange = ValueRange<DateTime<FixedOffset>>;
+
+impl<T: PartialOrd> ValueRange<T> {
+    /// Create a range from its inclusive bounds.
+    pub fn new(start: Option<T>, end: Option<T>) -> Self {
+        ValueRange { start, end }
+    }
+
+    /// The earliest value of the range, if bounded.
+    pub fn start(&self) -> Option<&T> {
+        self.start.as_ref()
+    }
+
+    /// The latest value of the range, if bounded.
+    pub fn end(&self) -> Option<&T> {
+        self.end.as_ref()
+    }
+
+    /// Check whether the value lies within the range.
+    pub fn contains(&self, value: &T) -> bool {
+        self.start.iter().all(|s| s <= value)
+            && self.end.iter().all(|e| value <= e)
+    }
+
+    /// Parse a range with the function giving the earliest and latest
+    /// values covered by a single value. Every hyphen is tried as the
+    /// separator, since it may also be the sign of a time zone offset.
+    fn parse_with<F>(text: &str, bounds: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Result<(T, T)>,
+    {
+        let text = text.trim_matches(&[' ', '\0'][..]);
+        for (i, _) in text.match_indices('-') {
+            let start = match &text[..i] {
+                "" => None,
+                start => match bounds(start) {
+                    Ok((start, _)) => Some(start),
+                    Err(_) => continue,
+                },
+            };
+            let end = match &text[i + 1..] {
+                "" => None,
+                end => match bounds(end) {
+                    Ok((_, end)) => Some(end),
+                    Err(_) => continue,
+                },
+            };
+            match (start, end) {
+                (None, None) => continue,
+                (Some(ref start), Some(ref end)) if start > end => continue,
+                (start, end) => return Ok(ValueRange { start, end }),
+            }
+        }
+        let (start, end) = bounds(text)?;
+        Ok(ValueRange {
+            start: Some(start),
+            end: Some(end),
+        })
+    }
+}
+
+impl DateRange {
+    /// Parse a date range, such as `20200101-20201231` or `202003-`.
+    pub fn parse(text: &str) -> Result<Self> {
+        ValueRange::parse_with(text, date_bounds)
+    }
+}
+
+impl TimeRange {
+    /// Parse a time range, such as `0800-120000.5` or `-12`. A fraction of
+    /// a second is only allowed after the seconds.
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate dicom_core;
+    /// # use chrono::NaiveTime;
+    /// # use dicom_core::data::range::TimeRange;
+    /// # fn main() {
+    /// let range = TimeRange::parse("0800-120000.5").unwrap();
+    /// assert_eq!(range.start(), Some(&NaiveTime::from_hms_opt(8, 0, 0).unwrap()));
+    /// assert!(range.contains(&NaiveTime::from_hms_milli_opt(12, 0, 0, 500).unwrap()));
+    /// assert!(!range.contains(&NaiveTime::from_hms_milli_opt(12, 0, 0, 600).unwrap()));
+    ///
+    /// let range = TimeRange::parse("-12").unwrap();
+    /// assert_eq!(range.start(), None);
+    /// assert!(range.contains(&NaiveTime::from_hms_opt(12, 59, 59).unwrap()));
+    ///
+    /// assert!(TimeRange::parse("0800-1200.5").is_err());
+    /// # }
+    /// ```
+    pub fn parse(text: &str) -> Result<Self> {
+        ValueRange::parse_with(text, time_bounds)
+    }
+}
+
+impl DateTimeRange {
+    /// Parse a date-time range, such as `20200101-202001021200+0100`.
+    /// Values without a time zone offset are taken to be in UTC.
+    pub fn parse(text: &str) -> Result<Self> {
+        DateTimeRange::parse_with_offset(text, FixedOffset::east_opt(0).unwrap())
+    }
+
+    /// Parse a date-time range, where values without a time zone offset
+    /// are in the given offset, such as that of Timezone Offset From UTC
+    /// (0008,0201).
+    pub fn parse_with_offset(text: &str, offset: FixedOffset) -> Result<Self> {
+        ValueRange::parse_with(text, |v| datetime_bounds(v, offset))
+    }
+}
+
+/// The earliest and latest dates of a date value of any precision.
+fn date_bounds(text: &str) -> Result<(NaiveDate, NaiveDate)> {
+    if !text.is_ascii() || text.len() < 4 {
+        return Err(invalid());
+    }
+    let year = number(&text[..4])? as i32;
+    let ymd = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).ok_or_else(invalid);
+    match text.len() {
+        4 => Ok((ymd(year, 1, 1)?, ymd(year, 12, 31)?)),
+        6 => {
+            let month = number(&text[4..6])?;
+            let first = ymd(year, month, 1)?;
+            let next = if month == 12 {
+                ymd(year + 1, 1, 1)?
+            } else {
+                ymd(year, month + 1, 1)?
+            };
+            Ok((first, next.pred_opt().ok_or_else(invalid)?))
+        }
+        8 => {
+            let date = ymd(year, number(&text[4..6])?, number(&text[6..8])?)?;
+            Ok((date, date))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// The earliest and latest times of a time value of any precision.
+fn time_bounds(text: &str) -> Result<(NaiveTime, NaiveTime)> {
+    if !text.is_ascii() {
+        return Err(invalid());
+    }
+    let (main, fraction) = match text.find('.') {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    };
+    let hms = |h, m, s, micro| NaiveTime::from_hms_micro_opt(h, m, s, micro).ok_or_else(invalid);
+    let hour = number(main.get(..2).ok_or_else(invalid)?)?;
+    match (main.len(), fraction) {
+        (2, None) => Ok((hms(hour, 0, 0, 0)?, hms(hour, 59, 59, 999_999)?)),
+        (4, None) => {
+            let minute = number(&main[2..4])?;
+            Ok((hms(hour, minute, 0, 0)?, hms(hour, minute, 59, 999_999)?))
+        }
+        (6, fraction) => {
+            let minute = number(&main[2..4])?;
+            let second = number(&main[4..6])?;
+            let (micro, span) = match fraction {
+                Some(f) if f.len() > 6 => return Err(invalid()),
+                Some(f) => {
+                    let scale = 10u32.pow(6 - f.len() as u32);
+                    (number(f)? * scale, scale - 1)
+                }
+                None => (0, 999_999),
+            };
+            Ok((hms(hour, minute, second, micro)?, hms(hour, minute, second, micro + span)?))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// The earliest and latest instants of a date-time value of any precision,
+/// where values without a time zone offset are in `offset`.
+fn datetime_bounds(
+    text: &str,
+    offset: FixedOffset,
+) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    if !text.is_ascii() || text.len() < 4 {
+        return Err(invalid());
+    }
+    let (value, offset) = match text[4..].find(&['+', '-'][..]) {
+        Some(i) => {
+            let suffix = &text[4 + i..];
+            if suffix.len() != 5 {
+                return Err(invalid());
+            }
+            let seconds = (number(&suffix[1..3])? * 60 + number(&suffix[3..5])?) as i32 * 60;
+            let seconds = if suffix.starts_with('-') { -seconds } else { seconds };
+            (&text[..4 + i], FixedOffset::east_opt(seconds).ok_or_else(invalid)?)
+        }
+        None => (text, offset),
+    };
+    let split = usize::min(8, value.len());
+    let (first_day, last_day) = date_bounds(&value[..split])?;
+    let (start_time, end_time) = if value.len() > 8 {
+        time_bounds(&value[8..])?
+    } else {
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).ok_or_else(invalid)?;
+        (midnight, NaiveTime::from_hms_micro_opt(23, 59, 59, 999_999).ok_or_else(invalid)?)
+    };
+    let instant = |date: NaiveDate, time| {
+        offset
+            .from_local_datetime(&date.and_time(time))
+            .single()
+            .ok_or_else(invalid)
+    };
+    Ok((instant(first_day, start_time)?, instant(last_day, end_time)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DateRange, DateTimeRange, TimeRange};
+    use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeZone};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn time(h: u32, m: u32, s: u32, micro: u32) -> NaiveTime {
+        NaiveTime::from_hms_micro_opt(h, m, s, micro).unwrap()
+    }
+
+    fn at(
+        offset: FixedOffset,
+        (y, m, d): (i32, u32, u32),
+        (h, mi, s, micro): (u32, u32, u32, u32),
+    ) -> DateTime<FixedOffset> {
+        offset
+            .from_local_datetime(&date(y, m, d).and_time(time(h, mi, s, micro)))
+            .unwrap()
+    }
+
+    #[test]
+    fn date_ranges() {
+        let range = DateRange::parse("20200101-20201231").unwrap();
+        assert_eq!(range.start(), Some(&date(2020, 1, 1)));
+        assert_eq!(range.end(), Some(&date(2020, 12, 31)));
+        assert!(range.contains(&date(2020, 6, 15)));
+        assert!(!range.contains(&date(2021, 1, 1)));
+
+        // partial precision covers the whole year or month
+        let range = DateRange::parse("2020").unwrap();
+        assert_eq!(range, DateRange::new(Some(date(2020, 1, 1)), Some(date(2020, 12, 31))));
+        let range = DateRange::parse("202002 ").unwrap();
+        assert_eq!(range.end(), Some(&date(2020, 2, 29)));
+        let range = DateRange::parse("-201912").unwrap();
+        assert_eq!(range.start(), None);
+        assert!(range.contains(&date(1900, 1, 1)));
+        assert!(range.contains(&date(2019, 12, 31)));
+        assert!(!range.contains(&date(2020, 1, 1)));
+        let range = DateRange::parse("20200301-").unwrap();
+        assert_eq!(range.end(), None);
+        assert!(range.contains(&date(2100, 1, 1)));
+
+        for bad in &["", "-", "2020-2019", "20201301", "2020a", "202001011"] {
+            assert!(DateRange::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn time_ranges() {
+        let range = TimeRange::parse("0800-").unwrap();
+        assert_eq!(range.start(), Some(&time(8, 0, 0, 0)));
+        assert!(range.contains(&time(23, 59, 59, 0)));
+        assert!(!range.contains(&time(7, 59, 59, 999_999)));
+
+        let range = TimeRange::parse("-1200").unwrap();
+        assert_eq!(range.end(), Some(&time(12, 0, 59, 999_999)));
+
+        let range = TimeRange::parse("10").unwrap();
+        assert_eq!(range.start(), Some(&time(10, 0, 0, 0)));
+        assert_eq!(range.end(), Some(&time(10, 59, 59, 999_999)));
+
+        let range = TimeRange::parse("101530.5").unwrap();
+        assert_eq!(range.start(), Some(&time(10, 15, 30, 500_000)));
+        assert_eq!(range.end(), Some(&time(10, 15, 30, 599_999)));
+
+        for bad in &["1", "2500", "1200-0800", "10:15", "101530.1234567"] {
+            assert!(TimeRange::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn datetime_ranges() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let range = DateTimeRange::parse("2020-202006").unwrap();
+        assert_eq!(range.start(), Some(&at(utc, (2020, 1, 1), (0, 0, 0, 0))));
+        assert_eq!(range.end(), Some(&at(utc, (2020, 6, 30), (23, 59, 59, 999_999))));
+
+        // the hyphen of a time zone offset is not a range separator
+        let range = DateTimeRange::parse("202001011030-0500").unwrap();
+        let est = FixedOffset::west_opt(5 * 3600).unwrap();
+        assert_eq!(range.start(), Some(&at(est, (2020, 1, 1), (10, 30, 0, 0))));
+        assert!(range.contains(&at(utc, (2020, 1, 1), (15, 30, 30, 0))));
+        assert!(!range.contains(&at(utc, (2020, 1, 1), (10, 30, 30, 0))));
+
+        let range = DateTimeRange::parse("20200101120000+0100-").unwrap();
+        assert_eq!(range.start(), Some(&at(utc, (2020, 1, 1), (11, 0, 0, 0))));
+        assert_eq!(range.end(), None);
+
+        let range = DateTimeRange::parse("-20200101120000.25").unwrap();
+        assert_eq!(range.end(), Some(&at(utc, (2020, 1, 1), (12, 0, 0, 259_999))));
+
+        let local = FixedOffset::east_opt(3600).unwrap();
+        let range = DateTimeRange::parse_with_offset("20200101", local).unwrap();
+        assert_eq!(range.start(), Some(&at(utc, (2019, 12, 31), (23, 0, 0, 0))));
+
+        for bad in &["20", "2020+01", "20200101-20191231", "20200101T1200"] {
+            assert!(DateTimeRange::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+}
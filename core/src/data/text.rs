@@ -12,10 +12,14 @@
 //! - GB 18030
 //! - GB2312
 //!
-//! At the moment, this library supports only IR-6 and IR-192.
+//! All defined terms of Specific Character Set are supported, including
+//! ISO 2022 code extensions. JIS X 0201 romaji are read as ASCII, and
+//! characters of JIS X 0212 can be read but not written.
 
-use encoding::{DecoderTrap, EncoderTrap, Encoding, RawDecoder, StringWriter};
-use encoding::all::{ISO_8859_1, UTF_8};
+use encoding::{DecoderTrap, EncoderTrap, Encoding, EncodingRef, RawDecoder, StringWriter};
+use encoding::all::{ASCII, EUC_JP, GB18030, GBK, ISO_8859_1, ISO_8859_15, ISO_8859_2,
+                    ISO_8859_3, ISO_8859_4, ISO_8859_5, ISO_8859_6, ISO_8859_7, ISO_8859_8,
+                    UTF_8, WINDOWS_1254, WINDOWS_31J, WINDOWS_874, WINDOWS_949};
 use error::{Result, TextEncodingError};
 use std::fmt::Debug;
 
@@ -62,30 +66,456 @@ where
 /// Type alias for a type erased text codec.
 pub type DynamicTextCodec = Box<TextCodec>;
 
-/// An enum type for the the supported character sets.
-#[derive(Debug, Clone, Copy)]
+/// A character set which may be invoked through ISO 2022 code extensions,
+/// as identified by the defined terms of Specific Character Set (0008,0005)
+/// starting with `ISO 2022`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CodeElement {
+    /// ISO 2022 IR 6: the default repertoire (ASCII).
+    IsoIr6,
+    /// ISO 2022 IR 100: Latin alphabet No. 1.
+    IsoIr100,
+    /// ISO 2022 IR 101: Latin alphabet No. 2.
+    IsoIr101,
+    /// ISO 2022 IR 109: Latin alphabet No. 3.
+    IsoIr109,
+    /// ISO 2022 IR 110: Latin alphabet No. 4.
+    IsoIr110,
+    /// ISO 2022 IR 144: Cyrillic.
+    IsoIr144,
+    /// ISO 2022 IR 127: Arabic.
+    IsoIr127,
+    /// ISO 2022 IR 126: Greek.
+    IsoIr126,
+    /// ISO 2022 IR 138: Hebrew.
+    IsoIr138,
+    /// ISO 2022 IR 148: Latin alphabet No. 5.
+    IsoIr148,
+    /// ISO 2022 IR 203: Latin alphabet No. 9.
+    IsoIr203,
+    /// ISO 2022 IR 13: Japanese katakana (JIS X 0201).
+    IsoIr13,
+    /// ISO 2022 IR 166: Thai (TIS 620-2533).
+    IsoIr166,
+    /// ISO 2022 IR 87: Japanese kanji (JIS X 0208).
+    IsoIr87,
+    /// ISO 2022 IR 159: supplementary Japanese kanji (JIS X 0212).
+    IsoIr159,
+    /// ISO 2022 IR 149: Korean (KS X 1001).
+    IsoIr149,
+    /// ISO 2022 IR 58: simplified Chinese (GB 2312).
+    IsoIr58,
+}
+
+impl CodeElement {
+    /// Obtain the code element from its registration number in the ISO-IR
+    /// registry.
+    fn from_number(number: &str) -> Option<Self> {
+        use self::CodeElement::*;
+        match number {
+            "6" => Some(IsoIr6),
+            "100" => Some(IsoIr100),
+            "101" => Some(IsoIr101),
+            "109" => Some(IsoIr109),
+            "110" => Some(IsoIr110),
+            "144" => Some(IsoIr144),
+            "127" => Some(IsoIr127),
+            "126" => Some(IsoIr126),
+            "138" => Some(IsoIr138),
+            "148" => Some(IsoIr148),
+            "203" => Some(IsoIr203),
+            "13" => Some(IsoIr13),
+            "166" => Some(IsoIr166),
+            "87" => Some(IsoIr87),
+            "159" => Some(IsoIr159),
+            "149" => Some(IsoIr149),
+            "58" => Some(IsoIr58),
+            _ => None,
+        }
+    }
+
+    /// The defined term of this code element.
+    pub fn term(self) -> &'static str {
+        use self::CodeElement::*;
+        match self {
+            IsoIr6 => "ISO 2022 IR 6",
+            IsoIr100 => "ISO 2022 IR 100",
+            IsoIr101 => "ISO 2022 IR 101",
+            IsoIr109 => "ISO 2022 IR 109",
+            IsoIr110 => "ISO 2022 IR 110",
+            IsoIr144 => "ISO 2022 IR 144",
+            IsoIr127 => "ISO 2022 IR 127",
+            IsoIr126 => "ISO 2022 IR 126",
+            IsoIr138 => "ISO 2022 IR 138",
+            IsoIr148 => "ISO 2022 IR 148",
+            IsoIr203 => "ISO 2022 IR 203",
+            IsoIr13 => "ISO 2022 IR 13",
+            IsoIr166 => "ISO 2022 IR 166",
+            IsoIr87 => "ISO 2022 IR 87",
+            IsoIr159 => "ISO 2022 IR 159",
+            IsoIr149 => "ISO 2022 IR 149",
+            IsoIr58 => "ISO 2022 IR 58",
+        }
+    }
+
+    /// The graphic set designated to G0 by this code element.
+    fn g0(self) -> Graphic {
+        match self {
+            CodeElement::IsoIr87 => Graphic::Jis0208,
+            CodeElement::IsoIr159 => Graphic::Jis0212,
+            _ => Graphic::Ascii,
+        }
+    }
+
+    /// The graphic set designated to G1 by this code element, if any.
+    fn g1(self) -> Option<Graphic> {
+        use self::CodeElement::*;
+        match self {
+            IsoIr6 | IsoIr87 | IsoIr159 => None,
+            IsoIr149 => Some(Graphic::Ksx1001),
+            IsoIr58 => Some(Graphic::Gb2312),
+            single_byte => Some(Graphic::Upper(single_byte)),
+        }
+    }
+
+    /// The encoding of the upper half of a single-byte code element.
+    fn encoding(self) -> EncodingRef {
+        use self::CodeElement::*;
+        match self {
+            IsoIr101 => ISO_8859_2,
+            IsoIr109 => ISO_8859_3,
+            IsoIr110 => ISO_8859_4,
+            IsoIr144 => ISO_8859_5,
+            IsoIr127 => ISO_8859_6,
+            IsoIr126 => ISO_8859_7,
+            IsoIr138 => ISO_8859_8,
+            // the upper halves of ISO 8859-9 and Windows-1254 are the same
+            IsoIr148 => WINDOWS_1254,
+            IsoIr203 => ISO_8859_15,
+            // Shift_JIS single bytes in 0xA1..0xDF are the JIS X 0201 katakana
+            IsoIr13 => WINDOWS_31J,
+            // as are the upper halves of TIS 620 and Windows-874
+            IsoIr166 => WINDOWS_874,
+            _ => ISO_8859_1,
+        }
+    }
+}
+
+/// The number of code elements which can be invoked through ISO 2022 code
+/// extensions.
+const CODE_ELEMENT_COUNT: usize = 17;
+
+/// The code elements of a character set with ISO 2022 code extensions, in
+/// the order of the values of Specific Character Set. Repeated code
+/// elements are kept only once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CodeElements {
+    elements: [CodeElement; CODE_ELEMENT_COUNT],
+    len: usize,
+}
+
+impl CodeElements {
+    /// Collect the given code elements, in order.
+    pub fn from_slice(elements: &[CodeElement]) -> Self {
+        let mut out = CodeElements {
+            elements: [CodeElement::IsoIr6; CODE_ELEMENT_COUNT],
+            len: 0,
+        };
+        for &element in elements {
+            if !out.as_slice().contains(&element) {
+                out.elements[out.len] = element;
+                out.len += 1;
+            }
+        }
+        out
+    }
+
+    /// Obtain the code elements as a slice.
+    pub fn as_slice(&self) -> &[CodeElement] {
+        &self.elements[..self.len]
+    }
+}
+
+/// A graphic character set which can be designated to G0 or G1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Graphic {
+    /// ASCII, or the JIS X 0201 romaji, which are decoded as ASCII.
+    Ascii,
+    Jis0208,
+    Jis0212,
+    /// A single-byte set of 96 characters.
+    Upper(CodeElement),
+    Ksx1001,
+    Gb2312,
+}
+
+impl Graphic {
+    /// Whether this set is designated to G0 (invoked by bytes below 0x80).
+    fn is_g0(self) -> bool {
+        matches!(self, Graphic::Ascii | Graphic::Jis0208 | Graphic::Jis0212)
+    }
+
+    /// The escape sequence designating this set, without the leading ESC.
+    fn escape(self) -> &'static [u8] {
+        use self::CodeElement::*;
+        match self {
+            Graphic::Ascii => b"(B",
+            Graphic::Jis0208 => b"$B",
+            Graphic::Jis0212 => b"$(D",
+            Graphic::Ksx1001 => b"$)C",
+            Graphic::Gb2312 => b"$)A",
+            Graphic::Upper(IsoIr13) => b")I",
+            Graphic::Upper(element) => match element {
+                IsoIr101 => b"-B",
+                IsoIr109 => b"-C",
+                IsoIr110 => b"-D",
+                IsoIr144 => b"-L",
+                IsoIr127 => b"-G",
+                IsoIr126 => b"-F",
+                IsoIr138 => b"-H",
+                IsoIr148 => b"-M",
+                IsoIr203 => b"-b",
+                IsoIr166 => b"-T",
+                _ => b"-A",
+            },
+        }
+    }
+
+    /// Identify the set designated by the escape sequence at the start of
+    /// `text` (after ESC), along with the length of the sequence.
+    fn from_escape(text: &[u8]) -> Option<(Graphic, usize)> {
+        use self::CodeElement::*;
+        let upper = [
+            IsoIr100, IsoIr101, IsoIr109, IsoIr110, IsoIr144, IsoIr127, IsoIr126, IsoIr138,
+            IsoIr148, IsoIr203, IsoIr13, IsoIr166,
+        ];
+        let sets = [
+            Graphic::Ascii,
+            Graphic::Jis0208,
+            Graphic::Jis0212,
+            Graphic::Ksx1001,
+            Graphic::Gb2312,
+        ];
+        match text {
+            // JIS X 0201 romaji and the older JIS X 0208 designation
+            _ if text.starts_with(b"(J") => Some((Graphic::Ascii, 2)),
+            _ if text.starts_with(b"$@") => Some((Graphic::Jis0208, 2)),
+            _ => sets.iter()
+                .cloned()
+                .chain(upper.iter().map(|&e| Graphic::Upper(e)))
+                .find(|g| text.starts_with(g.escape()))
+                .map(|g| (g, g.escape().len())),
+        }
+    }
+
+    /// The number of bytes of each character of this set.
+    fn width(self) -> usize {
+        match self {
+            Graphic::Ascii | Graphic::Upper(_) => 1,
+            _ => 2,
+        }
+    }
+
+    /// Decode one character of this set, whose bytes have been taken as is
+    /// from the text.
+    fn decode(self, bytes: &[u8]) -> Result<String> {
+        let decoded = match self {
+            Graphic::Ascii => ASCII.decode(bytes, DecoderTrap::Call(decode_text_trap)),
+            Graphic::Upper(element) => element
+                .encoding()
+                .decode(bytes, DecoderTrap::Call(decode_text_trap)),
+            Graphic::Jis0208 => EUC_JP.decode(
+                &bytes.iter().map(|b| b | 0x80).collect::<Vec<_>>(),
+                DecoderTrap::Call(decode_text_trap),
+            ),
+            Graphic::Jis0212 => {
+                let bytes: Vec<u8> = Some(0x8F)
+                    .into_iter()
+                    .chain(bytes.iter().map(|b| b | 0x80))
+                    .collect();
+                EUC_JP.decode(&bytes, DecoderTrap::Call(decode_text_trap))
+            }
+            Graphic::Ksx1001 => WINDOWS_949.decode(bytes, DecoderTrap::Call(decode_text_trap)),
+            Graphic::Gb2312 => GB18030.decode(bytes, DecoderTrap::Call(decode_text_trap)),
+        };
+        decoded.map_err(|e| TextEncodingError::new(e).into())
+    }
+
+    /// Encode a character with this set, if it belongs to it.
+    fn encode(self, c: char) -> Option<Vec<u8>> {
+        let mut buf = [0; 4];
+        let c = c.encode_utf8(&mut buf);
+        let encoding: EncodingRef = match self {
+            Graphic::Ascii => ASCII,
+            Graphic::Upper(element) => element.encoding(),
+            // JIS X 0212 characters cannot be encoded by the EUC-JP encoder
+            Graphic::Jis0208 | Graphic::Jis0212 => EUC_JP,
+            Graphic::Ksx1001 => WINDOWS_949,
+            Graphic::Gb2312 => GB18030,
+        };
+        // the 96-character sets span 0xA0..=0xFF, the 94-character sets 0xA1..=0xFE
+        let lowest = match self {
+            Graphic::Ascii => 0x00,
+            Graphic::Upper(_) => 0xA0,
+            _ => 0xA1,
+        };
+        let bytes = encoding.encode(c, EncoderTrap::Strict).ok()?;
+        if bytes.len() != self.width() || bytes.iter().any(|&b| b < lowest) {
+            return None;
+        }
+        Some(match self {
+            Graphic::Jis0208 => bytes.into_iter().map(|b| b & 0x7F).collect(),
+            _ => bytes,
+        })
+    }
+}
+
+fn unrepresentable(c: char) -> TextEncodingError {
+    TextEncodingError::new(format!("unrepresentable character {:?}", c))
+}
+
+/// Whether the byte delimits values or component groups, after which the
+/// initial graphic sets are active again.
+fn is_delimiter(c: u8) -> bool {
+    matches!(c, b'\\' | b'=' | b'^' | b'\r' | b'\n' | b'\t' | 0x0C)
+}
+
+/// An enum type for the supported character sets, one for each defined
+/// term of Specific Character Set (0008,0005).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SpecificCharacterSet {
     /// The default character set.
-    Default, // TODO needs more
+    Default,
+    /// ISO_IR 100: Latin alphabet No. 1.
+    IsoIr100,
+    /// ISO_IR 101: Latin alphabet No. 2.
+    IsoIr101,
+    /// ISO_IR 109: Latin alphabet No. 3.
+    IsoIr109,
+    /// ISO_IR 110: Latin alphabet No. 4.
+    IsoIr110,
+    /// ISO_IR 144: Cyrillic.
+    IsoIr144,
+    /// ISO_IR 127: Arabic.
+    IsoIr127,
+    /// ISO_IR 126: Greek.
+    IsoIr126,
+    /// ISO_IR 138: Hebrew.
+    IsoIr138,
+    /// ISO_IR 148: Latin alphabet No. 5.
+    IsoIr148,
+    /// ISO_IR 203: Latin alphabet No. 9.
+    IsoIr203,
+    /// ISO_IR 13: Japanese romaji and katakana (JIS X 0201).
+    IsoIr13,
+    /// ISO_IR 166: Thai (TIS 620-2533).
+    IsoIr166,
     /// The Unicode character set defined in ISO IR 192, based on the UTF-8 encoding.
     IsoIr192,
+    /// The Chinese character set GB 18030.
+    Gb18030,
+    /// The Chinese character set GBK.
+    Gbk,
+    /// Character sets with ISO 2022 code extensions, in the order of the
+    /// values of Specific Character Set. The first one is active at the
+    /// start of each value and component group.
+    Iso2022(CodeElements),
 }
 
 impl SpecificCharacterSet {
-    pub fn from_code(uid: &str) -> Option<Self> {
+    /// Obtain the character set from the value of Specific Character Set,
+    /// which may hold multiple defined terms separated by backslashes.
+    /// Padding and the use of underscores or hyphens instead of spaces are
+    /// tolerated.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let codes: Vec<&str> = code.split('\\').collect();
+        SpecificCharacterSet::from_codes(&codes)
+    }
+
+    /// Obtain the character set from the values of Specific Character Set.
+    pub fn from_codes<S: AsRef<str>>(codes: &[S]) -> Option<Self> {
         use self::SpecificCharacterSet::*;
-        match uid {
-            "Default" | "ISO_IR_6" => Some(Default),
-            "ISO_IR_192" => Some(IsoIr192),
+
+        let terms: Vec<String> = codes
+            .iter()
+            .map(|c| {
+                c.as_ref()
+                    .trim_matches(&[' ', '\0'][..])
+                    .to_uppercase()
+                    .replace(&['_', '-'][..], " ")
+            })
+            .collect();
+        if terms.len() > 1 || terms.iter().any(|t| t.starts_with("ISO 2022 IR ")) {
+            let elements = terms
+                .iter()
+                .enumerate()
+                .map(|(i, term)| match term.as_str() {
+                    // the first value may be empty, standing for ISO 2022 IR 6
+                    "" if i == 0 => Some(CodeElement::IsoIr6),
+                    _ if term.starts_with("ISO 2022 IR ") => {
+                        CodeElement::from_number(&term["ISO 2022 IR ".len()..])
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            return Some(Iso2022(CodeElements::from_slice(&elements)));
+        }
+
+        match terms.first().map(String::as_str).unwrap_or("") {
+            "" | "DEFAULT" | "ISO IR 6" => Some(Default),
+            "ISO IR 100" => Some(IsoIr100),
+            "ISO IR 101" => Some(IsoIr101),
+            "ISO IR 109" => Some(IsoIr109),
+            "ISO IR 110" => Some(IsoIr110),
+            "ISO IR 144" => Some(IsoIr144),
+            "ISO IR 127" => Some(IsoIr127),
+            "ISO IR 126" => Some(IsoIr126),
+            "ISO IR 138" => Some(IsoIr138),
+            "ISO IR 148" => Some(IsoIr148),
+            "ISO IR 203" => Some(IsoIr203),
+            "ISO IR 13" => Some(IsoIr13),
+            "ISO IR 166" => Some(IsoIr166),
+            "ISO IR 192" => Some(IsoIr192),
+            "GB18030" => Some(Gb18030),
+            "GBK" => Some(Gbk),
+            _ => None,
+        }
+    }
+
+    /// The code element of a single-byte character set without code
+    /// extensions.
+    fn single_byte_element(&self) -> Option<CodeElement> {
+        use self::SpecificCharacterSet::*;
+        match *self {
+            IsoIr100 => Some(CodeElement::IsoIr100),
+            IsoIr101 => Some(CodeElement::IsoIr101),
+            IsoIr109 => Some(CodeElement::IsoIr109),
+            IsoIr110 => Some(CodeElement::IsoIr110),
+            IsoIr144 => Some(CodeElement::IsoIr144),
+            IsoIr127 => Some(CodeElement::IsoIr127),
+            IsoIr126 => Some(CodeElement::IsoIr126),
+            IsoIr138 => Some(CodeElement::IsoIr138),
+            IsoIr148 => Some(CodeElement::IsoIr148),
+            IsoIr203 => Some(CodeElement::IsoIr203),
+            IsoIr13 => Some(CodeElement::IsoIr13),
+            IsoIr166 => Some(CodeElement::IsoIr166),
             _ => None,
         }
     }
 
     /// Retrieve the respective text codec.
     pub fn get_codec(&self) -> Option<Box<TextCodec>> {
+        if let Some(element) = self.single_byte_element() {
+            return Some(Box::new(SingleByteCharacterSetCodec(element)));
+        }
         match *self {
-            SpecificCharacterSet::Default => Some(Box::new(DefaultCharacterSetCodec)),
             SpecificCharacterSet::IsoIr192 => Some(Box::new(Utf8CharacterSetCodec)),
+            SpecificCharacterSet::Gb18030 => Some(Box::new(Gb18030CharacterSetCodec)),
+            SpecificCharacterSet::Gbk => Some(Box::new(GbkCharacterSetCodec)),
+            SpecificCharacterSet::Iso2022(elements) if elements.len > 0 => {
+                Some(Box::new(Iso2022CharacterSetCodec(elements)))
+            }
+            _ => Some(Box::new(DefaultCharacterSetCodec)),
         }
     }
 }
@@ -144,6 +574,179 @@ impl TextCodec for Utf8CharacterSetCodec {
     }
 }
 
+/// Data type representing the GB 18030 character set.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Copy)]
+pub struct Gb18030CharacterSetCodec;
+
+impl TextCodec for Gb18030CharacterSetCodec {
+    fn decode(&self, text: &[u8]) -> Result<String> {
+        GB18030
+            .decode(text, DecoderTrap::Call(decode_text_trap))
+            .map_err(|e| TextEncodingError::new(e).into())
+    }
+
+    fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        GB18030
+            .encode(text, EncoderTrap::Strict)
+            .map_err(|e| TextEncodingError::new(e).into())
+    }
+}
+
+/// Data type representing the GBK character set.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Copy)]
+pub struct GbkCharacterSetCodec;
+
+impl TextCodec for GbkCharacterSetCodec {
+    fn decode(&self, text: &[u8]) -> Result<String> {
+        GBK.decode(text, DecoderTrap::Call(decode_text_trap))
+            .map_err(|e| TextEncodingError::new(e).into())
+    }
+
+    fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        GBK.encode(text, EncoderTrap::Strict)
+            .map_err(|e| TextEncodingError::new(e).into())
+    }
+}
+
+/// Data type representing a single-byte character set without code
+/// extensions, such as ISO_IR 100. Bytes below 0x80 are ASCII.
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub struct SingleByteCharacterSetCodec(CodeElement);
+
+impl TextCodec for SingleByteCharacterSetCodec {
+    fn decode(&self, text: &[u8]) -> Result<String> {
+        self.0
+            .encoding()
+            .decode(text, DecoderTrap::Call(decode_text_trap))
+            .map_err(|e| TextEncodingError::new(e).into())
+    }
+
+    fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            let bytes = Graphic::Ascii
+                .encode(c)
+                .or_else(|| Graphic::Upper(self.0).encode(c))
+                .ok_or_else(|| unrepresentable(c))?;
+            out.extend(bytes);
+        }
+        Ok(out)
+    }
+}
+
+/// Data type representing character sets with ISO 2022 code extensions.
+/// Escape sequences switch the graphic sets designated to G0 and G1, which
+/// return to those of the first code element at each value delimiter and
+/// person name component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Iso2022CharacterSetCodec(CodeElements);
+
+impl Iso2022CharacterSetCodec {
+    /// The graphic sets active at the start of a value.
+    fn initial(&self) -> (Graphic, Option<Graphic>) {
+        let first = self.0.as_slice().first().cloned().unwrap_or(CodeElement::IsoIr6);
+        (Graphic::Ascii, first.g1())
+    }
+
+    /// Append the escape sequence designating the given set.
+    fn designate(out: &mut Vec<u8>, set: Graphic) {
+        out.push(0x1B);
+        out.extend_from_slice(set.escape());
+    }
+}
+
+impl TextCodec for Iso2022CharacterSetCodec {
+    fn decode(&self, text: &[u8]) -> Result<String> {
+        let (initial_g0, initial_g1) = self.initial();
+        let (mut g0, mut g1) = (initial_g0, initial_g1);
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < text.len() {
+            let c = text[i];
+            if c == 0x1B {
+                if let Some((set, len)) = Graphic::from_escape(&text[i + 1..]) {
+                    if set.is_g0() {
+                        g0 = set;
+                    } else {
+                        g1 = Some(set);
+                    }
+                    i += 1 + len;
+                    continue;
+                }
+            }
+            let set = if c < 0x80 {
+                if c <= 0x20 || g0 == Graphic::Ascii {
+                    Graphic::Ascii
+                } else {
+                    g0
+                }
+            } else {
+                g1.unwrap_or(Graphic::Upper(CodeElement::IsoIr100))
+            };
+            if set == Graphic::Ascii && is_delimiter(c) {
+                g0 = initial_g0;
+                g1 = initial_g1;
+            }
+            let end = usize::min(i + set.width(), text.len());
+            out.push_str(&set.decode(&text[i..end])?);
+            i = end;
+        }
+        Ok(out)
+    }
+
+    fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let (initial_g0, initial_g1) = self.initial();
+        let (mut g0, mut g1) = (initial_g0, initial_g1);
+        let mut out = Vec::with_capacity(text.len());
+        for c in text.chars().map(Some).chain(Some(None)) {
+            // restore the initial sets before delimiters and at the end
+            if c.map(|c| c.is_ascii() && is_delimiter(c as u8)).unwrap_or(true) {
+                if g0 != initial_g0 {
+                    Self::designate(&mut out, initial_g0);
+                    g0 = initial_g0;
+                }
+                if g1 != initial_g1 {
+                    // G1 may have had no set designated initially
+                    if let Some(set) = initial_g1 {
+                        Self::designate(&mut out, set);
+                    }
+                    g1 = initial_g1;
+                }
+            }
+            let c = match c {
+                Some(c) => c,
+                None => break,
+            };
+            if let Some(bytes) = Graphic::Ascii.encode(c) {
+                if g0 != Graphic::Ascii {
+                    Self::designate(&mut out, Graphic::Ascii);
+                    g0 = Graphic::Ascii;
+                }
+                out.extend(bytes);
+                continue;
+            }
+            let candidate = self.0
+                .as_slice()
+                .iter()
+                .flat_map(|e| Some(e.g0()).into_iter().chain(e.g1()))
+                .filter(|&set| set != Graphic::Ascii)
+                .filter_map(|set| set.encode(c).map(|bytes| (set, bytes)))
+                .next();
+            let (set, bytes) = candidate
+                .ok_or_else(|| unrepresentable(c))?;
+            if set.is_g0() && g0 != set {
+                Self::designate(&mut out, set);
+                g0 = set;
+            } else if !set.is_g0() && g1 != Some(set) {
+                Self::designate(&mut out, set);
+                g1 = Some(set);
+            }
+            out.extend(bytes);
+        }
+        Ok(out)
+    }
+}
+
 /// The result of a text validation procedure (please see [`validate_iso_8859`]).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum TextValidationOutcome {
@@ -215,3 +818,130 @@ pub fn validate_cs(text: &[u8]) -> TextValidationOutcome {
         TextValidationOutcome::NotOk
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeElement, CodeElements, SpecificCharacterSet, TextCodec};
+
+    fn codec(code: &str) -> Box<TextCodec> {
+        SpecificCharacterSet::from_code(code)
+            .and_then(|cs| cs.get_codec())
+            .unwrap()
+    }
+
+    #[test]
+    fn defined_terms() {
+        assert_eq!(SpecificCharacterSet::from_code(""), Some(SpecificCharacterSet::Default));
+        assert_eq!(
+            SpecificCharacterSet::from_code("ISO_IR 192"),
+            Some(SpecificCharacterSet::IsoIr192)
+        );
+        assert_eq!(
+            SpecificCharacterSet::from_code("ISO_IR_100 "),
+            Some(SpecificCharacterSet::IsoIr100)
+        );
+        assert_eq!(SpecificCharacterSet::from_code("GB18030"), Some(SpecificCharacterSet::Gb18030));
+        assert_eq!(
+            SpecificCharacterSet::from_codes(&["", "ISO 2022 IR 87"]),
+            Some(SpecificCharacterSet::Iso2022(CodeElements::from_slice(&[
+                CodeElement::IsoIr6,
+                CodeElement::IsoIr87,
+            ])))
+        );
+        assert_eq!(
+            SpecificCharacterSet::from_code("ISO 2022 IR 13\\ISO 2022 IR 87"),
+            Some(SpecificCharacterSet::Iso2022(CodeElements::from_slice(&[
+                CodeElement::IsoIr13,
+                CodeElement::IsoIr87,
+            ])))
+        );
+        assert_eq!(SpecificCharacterSet::from_code("ISO_IR 999"), None);
+        assert_eq!(SpecificCharacterSet::from_code("ISO_IR 100\\ISO 2022 IR 87"), None);
+    }
+
+    #[test]
+    fn single_byte_character_sets() {
+        // examples from PS3.5 Annex I
+        let greek = codec("ISO_IR 126");
+        let bytes = b"\xc4\xe9\xef\xed\xf5\xf3\xe9\xef\xf2";
+        assert_eq!(greek.decode(bytes).unwrap(), "Διονυσιος");
+        assert_eq!(greek.encode("Διονυσιος").unwrap(), &bytes[..]);
+
+        let cyrillic = codec("ISO_IR 144");
+        let bytes = b"\xb8\xd2\xd0\xdd\xde\xd2";
+        assert_eq!(cyrillic.decode(bytes).unwrap(), "Иванов");
+        assert_eq!(cyrillic.encode("Иванов").unwrap(), &bytes[..]);
+
+        let latin = codec("ISO_IR 100");
+        assert_eq!(latin.decode(b"Buc^J\xe9r\xf4me").unwrap(), "Buc^Jérôme");
+        assert!(latin.encode("Иванов").is_err());
+
+        let katakana = codec("ISO_IR 13");
+        assert_eq!(katakana.decode(b"\xd4\xcf\xc0\xde").unwrap(), "ﾔﾏﾀﾞ");
+        let bytes = b"\xd4\xcf\xc0\xde^\xc0\xdb\xb3";
+        assert_eq!(katakana.encode("ﾔﾏﾀﾞ^ﾀﾛｳ").unwrap(), &bytes[..]);
+    }
+
+    #[test]
+    fn chinese_character_sets() {
+        // example from PS3.5 Annex J
+        let gb18030 = codec("GB18030");
+        let bytes = b"Wang^XiaoDong=\xcd\xf5^\xd0\xa1\xb6\xab=";
+        assert_eq!(gb18030.decode(bytes).unwrap(), "Wang^XiaoDong=王^小东=");
+        assert_eq!(gb18030.encode("Wang^XiaoDong=王^小东=").unwrap(), &bytes[..]);
+
+        let gbk = codec("GBK");
+        assert_eq!(gbk.decode(b"\xcd\xf5").unwrap(), "王");
+
+        // U+76C3 is encoded as B0 A0 in GBK, which is outside of GB 2312
+        let gb2312 = codec("\\ISO 2022 IR 58");
+        assert!(gb2312.encode("盃").is_err());
+    }
+
+    #[test]
+    fn iso_2022_japanese() {
+        // example from PS3.5 Annex H
+        let codec = codec("\\ISO 2022 IR 87");
+        let bytes: &[u8] = b"Yamada^Tarou=\x1b$B;3ED\x1b(B^\x1b$BB@O:\x1b(B=\
+                             \x1b$B$d$^$@\x1b(B^\x1b$B$?$m$&\x1b(B";
+        let name = "Yamada^Tarou=山田^太郎=やまだ^たろう";
+        assert_eq!(codec.decode(bytes).unwrap(), name);
+        assert_eq!(codec.encode(name).unwrap(), bytes);
+
+        // katakana are in G1 from the start, with no escape sequence
+        let codec = super::SpecificCharacterSet::from_code("ISO 2022 IR 13\\ISO 2022 IR 87")
+            .and_then(|cs| cs.get_codec())
+            .unwrap();
+        let bytes: &[u8] = b"\xd4\xcf\xc0\xde^\xc0\xdb\xb3=\x1b$B;3ED\x1b(B^\x1b$BB@O:\x1b(B=\
+                             \x1b$B$d$^$@\x1b(B^\x1b$B$?$m$&\x1b(B";
+        let name = "ﾔﾏﾀﾞ^ﾀﾛｳ=山田^太郎=やまだ^たろう";
+        assert_eq!(codec.decode(bytes).unwrap(), name);
+        assert_eq!(codec.encode(name).unwrap(), bytes);
+    }
+
+    #[test]
+    fn iso_2022_korean() {
+        // example from PS3.5 Annex I, where G1 is designated again in each
+        // component group
+        let codec = codec("\\ISO 2022 IR 149");
+        let bytes: &[u8] = b"Hong^Gildong=\x1b$)C\xfb\xf3^\x1b$)C\xd1\xce\xd4\xd7=\
+                             \x1b$)C\xc8\xab^\x1b$)C\xb1\xe6\xb5\xbf";
+        let name = "Hong^Gildong=洪^吉洞=홍^길동";
+        assert_eq!(codec.decode(bytes).unwrap(), name);
+        assert_eq!(codec.encode(name).unwrap(), bytes);
+    }
+
+    #[test]
+    fn iso_2022_switching_single_byte_sets() {
+        let codec = codec("ISO 2022 IR 100\\ISO 2022 IR 144");
+        let name = "Müller^Ivan=Мюллер^Иван";
+        let bytes: &[u8] = b"M\xfcller^Ivan=\x1b-L\xbc\xee\xdb\xdb\xd5\xe0\x1b-A^\
+                             \x1b-L\xb8\xd2\xd0\xdd\x1b-A";
+        assert_eq!(codec.encode(name).unwrap(), bytes);
+        assert_eq!(codec.decode(bytes).unwrap(), name);
+        // the initial set is active again after each component group
+        assert_eq!(codec.decode(b"\x1b-L\xbc=\xcc").unwrap(), "М=Ì");
+        assert_eq!(codec.decode(b"\x1b-L\xbc^\xcc").unwrap(), "М^Ì");
+        assert!(codec.encode("王").is_err());
+    }
+}
@@ -1,6 +1,7 @@
 //! This module includes a high level abstraction over a DICOM data element's value.
 
 use data::{Tag, Length};
+use data::person_name::PersonName;
 
 use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
 use error::CastValueError;
@@ -12,6 +13,7 @@ type C<T> = Vec<T>;
 /// Representation of a full DICOM value, which may be either primitive or
 /// another DICOM object.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Value<I> {
     /// Primitive value
     Primitive(PrimitiveValue),
@@ -22,6 +24,16 @@ pub enum Value<I> {
         /// The size in bytes.
         size: Length,
     },
+    /// An encapsulated pixel data sequence, as used by compressed
+    /// transfer syntaxes.
+    PixelSequence {
+        /// The value of the first item, the Basic Offset Table.
+        /// It is empty if the table is not present.
+        offset_table: C<u32>,
+        /// The remaining items, each one containing a fragment
+        /// of the compressed pixel data.
+        fragments: C<Vec<u8>>,
+    },
 }
 
 impl<I> Value<I>
@@ -30,10 +42,12 @@ where
 {
     /// Obtain the number of individual values.
     /// In a sequence item, this is the number of items.
+    /// In a pixel sequence, this is the number of fragments.
     pub fn multiplicity(&self) -> u32 {
         match *self {
             Value::Primitive(ref v) => v.multiplicity(),
             Value::Sequence{ ref items, ..} => items.len() as u32,
+            Value::PixelSequence { ref fragments, .. } => fragments.len() as u32,
         }
     }
 
@@ -53,6 +67,23 @@ where
         }
     }
 
+    /// Gets a reference to the fragments of an encapsulated pixel data sequence.
+    pub fn fragments(&self) -> Option<&[Vec<u8>]> {
+        match *self {
+            Value::PixelSequence { ref fragments, .. } => Some(fragments),
+            _ => None,
+        }
+    }
+
+    /// Gets a reference to the Basic Offset Table of an encapsulated
+    /// pixel data sequence.
+    pub fn offset_table(&self) -> Option<&[u32]> {
+        match *self {
+            Value::PixelSequence { ref offset_table, .. } => Some(offset_table),
+            _ => None,
+        }
+    }
+
     /// Retrieves the primitive value.
     pub fn into_primitive(self) -> Option<PrimitiveValue> {
         match self {
@@ -102,6 +133,26 @@ where
         }
     }
 
+    /// Get the first person name of a PN value.
+    pub fn to_person_name(&self) -> Result<PersonName, CastValueError> {
+        self.primitive()
+            .and_then(PrimitiveValue::person_name)
+            .ok_or_else(|| CastValueError {
+                requested: "person name",
+                got: self.value_type(),
+            })
+    }
+
+    /// Get all person names of a PN value.
+    pub fn to_person_names(&self) -> Result<Vec<PersonName>, CastValueError> {
+        self.primitive()
+            .and_then(PrimitiveValue::person_names)
+            .ok_or_else(|| CastValueError {
+                requested: "person name",
+                got: self.value_type(),
+            })
+    }
+
     pub fn to_tag(&self) -> Result<Tag, CastValueError> {
         match self {
             &Value::Primitive(PrimitiveValue::Tags(ref v)) => Ok(v[0]),
@@ -129,10 +180,17 @@ impl<I> From<PrimitiveValue> for Value<I> {
     }
 }
 
+impl From<PersonName> for PrimitiveValue {
+    fn from(name: PersonName) -> Self {
+        PrimitiveValue::Str(name.to_string())
+    }
+}
+
 /// An enum representing a primitive value from a DICOM element. The result of decoding
 /// an element's data value may be one of the enumerated types depending on its content
 /// and value representation.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PrimitiveValue {
     /// No data. Used for SQ (regardless of content) and any value of length 0.
     Empty,
@@ -236,6 +294,22 @@ impl PrimitiveValue {
         }
     }
 
+    /// Get a single person name. If it contains multiple names,
+    /// only the first one is returned. An empty value yields an empty name.
+    pub fn person_name(&self) -> Option<PersonName> {
+        self.person_names()
+            .map(|names| names.into_iter().next().unwrap_or_default())
+    }
+
+    /// Get a sequence of person names. An empty value yields no names.
+    pub fn person_names(&self) -> Option<Vec<PersonName>> {
+        match *self {
+            PrimitiveValue::Empty => Some(vec![]),
+            _ => self.strings()
+                .map(|v| v.into_iter().flat_map(PersonName::parse_all).collect()),
+        }
+    }
+
     /// Get a single DICOM tag.
     pub fn tag(&self) -> Option<Tag> {
         use self::PrimitiveValue::*;
@@ -329,6 +403,10 @@ pub enum ValueType {
     /// An item. Used for elements in a SQ, regardless of content.
     Item,
 
+    /// An encapsulated pixel data sequence, containing fragments
+    /// of compressed pixel data.
+    PixelSequence,
+
     /// A sequence of strings.
     /// Used for AE, AS, PN, SH, CS, LO, UI and UC.
     /// Can also be used for IS, SS, DS, DA, DT and TM when decoding
@@ -448,6 +526,7 @@ where
         match *self {
             Value::Primitive(ref v) => v.value_type(),
             Value::Sequence { .. } => ValueType::Item,
+            Value::PixelSequence { .. } => ValueType::PixelSequence,
         }
     }
 
@@ -455,6 +534,7 @@ where
         match *self {
             Value::Primitive(ref v) => v.size(),
             Value::Sequence { size, .. } => size,
+            Value::PixelSequence { .. } => Length::undefined(),
         }
     }
 }
@@ -5,6 +5,10 @@
 //! and it will be used by default
 //!
 //! When not using private tags, this dictionary should suffice.
+//!
+//! Attributes of repeating groups, such as the overlay attributes (60xx,eeee),
+//! are registered once with the first group of their range, and are found by
+//! any tag in that range.
 
 mod entries;
 
@@ -47,6 +51,23 @@ impl StandardDictionaryRegistry {
         self.by_tag.insert(entry.tag, entry);
         self
     }
+
+    fn get_by_tag(&self, tag: Tag) -> Option<&'static DictionaryEntryRef<'static>> {
+        self.by_tag
+            .get(&tag)
+            .or_else(|| self.by_tag.get(&repeating_group_base(tag)?))
+            .cloned()
+    }
+}
+
+/// Map a tag of a repeating group to the tag of the first group in its
+/// range, under which the attribute is registered.
+fn repeating_group_base(Tag(group, element): Tag) -> Option<Tag> {
+    if group & 0xFF01 == 0x6000 && group <= 0x601E {
+        Some(Tag(0x6000, element))
+    } else {
+        None
+    }
 }
 
 /// A data dictionary which consults the library's global DICOM attribute registry.
@@ -61,7 +82,7 @@ impl DataDictionary for StandardDataDictionary {
     }
 
     fn by_tag(&self, tag: Tag) -> Option<&Self::Entry> {
-        registry().by_tag.get(&tag).map(|r| *r)
+        registry().get_by_tag(tag)
     }
 }
 
@@ -73,7 +94,7 @@ impl<'a> DataDictionary for &'a StandardDataDictionary {
     }
 
     fn by_tag(&self, tag: Tag) -> Option<&'static DictionaryEntryRef<'static>> {
-        registry().by_tag.get(&tag).map(|r| *r)
+        registry().get_by_tag(tag)
     }
 }
 
@@ -91,6 +112,12 @@ fn init_dictionary() -> StandardDictionaryRegistry {
     for entry in META_ENTRIES {
         d.index(&entry);
     }
+    for entry in COMMAND_ENTRIES {
+        d.index(&entry);
+    }
+    for entry in OVERLAY_ENTRIES {
+        d.index(&entry);
+    }
     d
 }
 
@@ -158,3 +185,251 @@ const META_ENTRIES: &'static [E<'static>] = &[
         vr: VR::OB,
     },
 ];
+
+// command entries (0000,eeee), used in DIMSE messages
+const COMMAND_ENTRIES: &'static [E<'static>] = &[
+    E {
+        tag: Tag(0x0000, 0x0000),
+        alias: "CommandGroupLength",
+        vr: VR::UL,
+    },
+    E {
+        tag: Tag(0x0000, 0x0002),
+        alias: "AffectedSOPClassUID",
+        vr: VR::UI,
+    },
+    E {
+        tag: Tag(0x0000, 0x0003),
+        alias: "RequestedSOPClassUID",
+        vr: VR::UI,
+    },
+    E {
+        tag: Tag(0x0000, 0x0100),
+        alias: "CommandField",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x0110),
+        alias: "MessageID",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x0120),
+        alias: "MessageIDBeingRespondedTo",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x0600),
+        alias: "MoveDestination",
+        vr: VR::AE,
+    },
+    E {
+        tag: Tag(0x0000, 0x0700),
+        alias: "Priority",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x0800),
+        alias: "CommandDataSetType",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x0900),
+        alias: "Status",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x0901),
+        alias: "OffendingElement",
+        vr: VR::AT,
+    },
+    E {
+        tag: Tag(0x0000, 0x0902),
+        alias: "ErrorComment",
+        vr: VR::LO,
+    },
+    E {
+        tag: Tag(0x0000, 0x0903),
+        alias: "ErrorID",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x1000),
+        alias: "AffectedSOPInstanceUID",
+        vr: VR::UI,
+    },
+    E {
+        tag: Tag(0x0000, 0x1001),
+        alias: "RequestedSOPInstanceUID",
+        vr: VR::UI,
+    },
+    E {
+        tag: Tag(0x0000, 0x1002),
+        alias: "EventTypeID",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x1005),
+        alias: "AttributeIdentifierList",
+        vr: VR::AT,
+    },
+    E {
+        tag: Tag(0x0000, 0x1008),
+        alias: "ActionTypeID",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x1020),
+        alias: "NumberOfRemainingSuboperations",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x1021),
+        alias: "NumberOfCompletedSuboperations",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x1022),
+        alias: "NumberOfFailedSuboperations",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x1023),
+        alias: "NumberOfWarningSuboperations",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x0000, 0x1030),
+        alias: "MoveOriginatorApplicationEntityTitle",
+        vr: VR::AE,
+    },
+    E {
+        tag: Tag(0x0000, 0x1031),
+        alias: "MoveOriginatorMessageID",
+        vr: VR::US,
+    },
+];
+
+// overlay entries (60xx,eeee), registered with the first group
+const OVERLAY_ENTRIES: &'static [E<'static>] = &[
+    E {
+        tag: Tag(0x6000, 0x0010),
+        alias: "OverlayRows",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x6000, 0x0011),
+        alias: "OverlayColumns",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x6000, 0x0012),
+        alias: "OverlayPlanes",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x6000, 0x0015),
+        alias: "NumberOfFramesInOverlay",
+        vr: VR::IS,
+    },
+    E {
+        tag: Tag(0x6000, 0x0022),
+        alias: "OverlayDescription",
+        vr: VR::LO,
+    },
+    E {
+        tag: Tag(0x6000, 0x0040),
+        alias: "OverlayType",
+        vr: VR::CS,
+    },
+    E {
+        tag: Tag(0x6000, 0x0045),
+        alias: "OverlaySubtype",
+        vr: VR::LO,
+    },
+    E {
+        tag: Tag(0x6000, 0x0050),
+        alias: "OverlayOrigin",
+        vr: VR::SS,
+    },
+    E {
+        tag: Tag(0x6000, 0x0051),
+        alias: "ImageFrameOrigin",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x6000, 0x0052),
+        alias: "OverlayPlaneOrigin",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x6000, 0x0100),
+        alias: "OverlayBitsAllocated",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x6000, 0x0102),
+        alias: "OverlayBitPosition",
+        vr: VR::US,
+    },
+    E {
+        tag: Tag(0x6000, 0x1001),
+        alias: "OverlayActivationLayer",
+        vr: VR::CS,
+    },
+    E {
+        tag: Tag(0x6000, 0x1301),
+        alias: "ROIArea",
+        vr: VR::IS,
+    },
+    E {
+        tag: Tag(0x6000, 0x1302),
+        alias: "ROIMean",
+        vr: VR::DS,
+    },
+    E {
+        tag: Tag(0x6000, 0x1303),
+        alias: "ROIStandardDeviation",
+        vr: VR::DS,
+    },
+    E {
+        tag: Tag(0x6000, 0x1500),
+        alias: "OverlayLabel",
+        vr: VR::LO,
+    },
+    E {
+        tag: Tag(0x6000, 0x3000),
+        alias: "OverlayData",
+        vr: VR::OW,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::StandardDataDictionary;
+    use data::{Tag, VR};
+    use dictionary::{DataDictionary, DictionaryEntry};
+
+    #[test]
+    fn repeating_group_entries() {
+        let dict = StandardDataDictionary;
+        for &group in &[0x6000, 0x6002, 0x601E] {
+            let entry = dict.by_tag(Tag(group, 0x3000)).unwrap();
+            assert_eq!(entry.alias(), "OverlayData");
+            assert_eq!(entry.vr(), VR::OW);
+        }
+        assert!(dict.by_tag(Tag(0x6001, 0x3000)).is_none());
+        assert!(dict.by_tag(Tag(0x6020, 0x3000)).is_none());
+        assert_eq!(dict.by_name("OverlayOrigin").unwrap().tag(), Tag(0x6000, 0x0050));
+        assert_eq!(dict.by_tag(Tag(0x0010, 0x0010)).unwrap().alias(), "PatientName");
+    }
+
+    #[test]
+    fn command_entries() {
+        let dict = StandardDataDictionary;
+        let entry = dict.by_tag(Tag(0x0000, 0x0100)).unwrap();
+        assert_eq!(entry.alias(), "CommandField");
+        assert_eq!(entry.vr(), VR::US);
+        assert_eq!(dict.by_name("AffectedSOPInstanceUID").unwrap().tag(), Tag(0x0000, 0x1000));
+    }
+}
@@ -6,6 +6,8 @@ use std::io;
 use std::num::{ParseFloatError, ParseIntError};
 use std::result;
 
+use failure::{self, Compat};
+
 use data::dataset::DicomDataToken;
 use data::value::ValueType;
 
@@ -49,6 +51,58 @@ quick_error! {
         PixelDataOutOfBounds {
             description("Pixel data access index out of bounds")
         }
+        /// Raised when the number of samples given for pixel data does not
+        /// match its dimensions.
+        PixelDataLength(expected: usize, actual: usize) {
+            description("Pixel data length does not match its dimensions")
+            display(self_) -> ("{}: expected {} samples, got {}",
+                               self_.description(), expected, actual)
+        }
+        /// Raised when the attributes describing the pixel data of an image
+        /// are missing or inconsistent with each other.
+        InvalidImagePixelModule(message: &'static str) {
+            description("Invalid image pixel module")
+            display(self_) -> ("{}: {}", self_.description(), message)
+        }
+        /// Raised when the attributes of a waveform multiplex group are
+        /// missing or inconsistent with each other.
+        InvalidWaveform(message: &'static str) {
+            description("Invalid waveform")
+            display(self_) -> ("{}: {}", self_.description(), message)
+        }
+        /// Raised when compressed pixel data could not be decoded, either
+        /// because it is corrupted or because it uses unsupported features.
+        PixelDataDecode(message: &'static str) {
+            description("Could not decode pixel data")
+            display(self_) -> ("{}: {}", self_.description(), message)
+        }
+        /// Raised when a DICOM object could not be converted to or from
+        /// the DICOM JSON model.
+        #[cfg(feature = "json")]
+        InvalidJson(message: &'static str) {
+            description("Invalid DICOM JSON")
+            display(self_) -> ("{}: {}", self_.description(), message)
+        }
+        /// Raised when a JSON document could not be read or written.
+        #[cfg(feature = "json")]
+        Json(err: serde_json::Error) {
+            description("JSON error")
+            cause(err)
+            display(self_) -> ("{}: {}", self_.description(), err)
+        }
+        /// Raised when a DICOM object could not be converted to or from
+        /// the native DICOM model in XML.
+        InvalidXml(message: &'static str) {
+            description("Invalid native DICOM model")
+            display(self_) -> ("{}: {}", self_.description(), message)
+        }
+        /// Raised when an XML document could not be read or written.
+        Xml(err: Compat<quick_xml::Error>) {
+            description("XML error")
+            from(err: quick_xml::Error) -> (failure::Fail::compat(err))
+            cause(err)
+            display(self_) -> ("{}: {}", self_.description(), err)
+        }
         /// Raised when a data set parser couldn't fetch a value after a primitive
         /// data element's header.
         MissingElementValue {
@@ -94,6 +148,14 @@ quick_error! {
     }
 }
 
+// declared outside of `quick_error!`, which does not apply `cfg` to `from()`
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
 /// Type alias for a result from this library.
 pub type Result<T> = result::Result<T, Error>;
 
@@ -1,23 +1,193 @@
 use std::io::{Read, Write};
 use std::path::Path;
-use error::Result;
-use object::DicomObject;
+use error::{Error, Result};
+use dictionary::DataDictionary;
+use object::RootDicomObject;
+use object::mem::InMemDicomObject;
+use transfer_syntax::codec::get_registry;
 use DefaultDicomObject;
 
+/// Read a DICOM object from the given byte source, starting at the "DICM"
+/// magic code (the preamble must have been skipped).
 pub fn from_stream<F>(file: F) -> Result<DefaultDicomObject>
 where
-    F: Read,
+    F: Read + 'static,
 {
-    DefaultDicomObject::from_stream(file)
+    InMemDicomObject::from_stream(file)
 }
 
-pub fn open_file<P>(path: P) -> Result<DefaultDicomObject> 
+/// Read a DICOM object from a file.
+pub fn open_file<P>(path: P) -> Result<DefaultDicomObject>
 where
     P: AsRef<Path>
 {
-    DefaultDicomObject::open_file(path)
+    InMemDicomObject::open_file(path)
 }
 
-pub fn to_file<F: Write, D: DicomObject>(obj: &D, to: F) -> Result<()> {
-    unimplemented!()
+/// Write a DICOM object to the given destination as a DICOM file: a 128-byte
+/// preamble, the "DICM" magic code, the file meta group and the data set,
+/// encoded with the transfer syntax declared in the object's meta table.
+pub fn to_file<W, D>(obj: &RootDicomObject<InMemDicomObject<D>>, mut to: W) -> Result<()>
+where
+    W: Write,
+    D: DataDictionary + Clone,
+{
+    let ts = get_registry()
+        .get(&obj.meta().transfer_syntax)
+        .ok_or_else(|| Error::UnsupportedTransferSyntax)?;
+
+    to.write_all(&[0u8; 128])?;
+    obj.meta().write(&mut to)?;
+    obj.write_dataset(to, ts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_stream, open_file, to_file};
+    use data::{DataElement, Tag, VR};
+    use data::value::{PrimitiveValue, Value};
+    use meta::DicomMetaTableBuilder;
+    use object::RootDicomObject;
+    use object::mem::InMemDicomObject;
+    use std::fs::{self, File};
+    use std::io::Cursor;
+
+    #[test]
+    fn write_and_read_back_file() {
+        let meta = DicomMetaTableBuilder::new()
+            .information_version([0, 1])
+            .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7\0".to_owned())
+            .media_storage_sop_instance_uid("2.25.1234\0".to_owned())
+            .transfer_syntax("1.2.840.10008.1.2.1\0".to_owned())
+            .implementation_class_uid("1.2.345\0".to_owned())
+            .build()
+            .unwrap();
+
+        let mut obj = InMemDicomObject::create_empty();
+        obj.put(DataElement::new(
+            Tag(0x0010, 0x0010),
+            VR::PN,
+            Value::Primitive(PrimitiveValue::Str("Doe^John".to_owned())),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x0028, 0x0010),
+            VR::US,
+            Value::Primitive(PrimitiveValue::U16(vec![512])),
+        ));
+        let obj = RootDicomObject::new(meta, obj);
+
+        let mut buf = Vec::new();
+        to_file(&obj, &mut buf).unwrap();
+
+        assert_eq!(&buf[..128], &[0u8; 128][..]);
+        assert_eq!(&buf[128..132], b"DICM");
+
+        let read = from_stream(Cursor::new(buf[128..].to_vec())).unwrap();
+        assert_eq!(read.meta().transfer_syntax, obj.meta().transfer_syntax);
+        assert_eq!(read.meta().information_group_length, 110);
+        assert_eq!(
+            read.element(Tag(0x0010, 0x0010)).unwrap().value().as_string().unwrap(),
+            "Doe^John"
+        );
+        assert_eq!(
+            read.element(Tag(0x0028, 0x0010)).unwrap().value(),
+            &Value::Primitive(PrimitiveValue::U16(vec![512]))
+        );
+    }
+
+    #[test]
+    fn write_and_read_back_deflated_file() {
+        let meta = DicomMetaTableBuilder::new()
+            .information_version([0, 1])
+            .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7\0".to_owned())
+            .media_storage_sop_instance_uid("2.25.1234\0".to_owned())
+            .transfer_syntax("1.2.840.10008.1.2.1.99".to_owned())
+            .implementation_class_uid("1.2.345\0".to_owned())
+            .build()
+            .unwrap();
+
+        let mut obj = InMemDicomObject::create_empty();
+        obj.put(DataElement::new(
+            Tag(0x0010, 0x0010),
+            VR::PN,
+            Value::Primitive(PrimitiveValue::Str("Doe^John".to_owned())),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x7FE0, 0x0010),
+            VR::OW,
+            Value::Primitive(PrimitiveValue::U16(vec![0; 4096])),
+        ));
+        let obj = RootDicomObject::new(meta, obj);
+
+        let mut buf = Vec::new();
+        to_file(&obj, &mut buf).unwrap();
+
+        // the data set is much smaller than the uncompressed pixel data
+        let meta_len = 132 + 12 + obj.meta().calculate_group_length() as usize;
+        assert!(buf.len() - meta_len < 1024);
+
+        let read = from_stream(Cursor::new(buf[128..].to_vec())).unwrap();
+        assert_eq!(
+            read.element(Tag(0x0010, 0x0010)).unwrap().value().as_string().unwrap(),
+            "Doe^John"
+        );
+        assert_eq!(
+            read.element(Tag(0x7FE0, 0x0010)).unwrap().value(),
+            &Value::Primitive(PrimitiveValue::U16(vec![0; 4096]))
+        );
+    }
+
+    #[test]
+    fn round_trip_through_a_file() {
+        let meta = DicomMetaTableBuilder::new()
+            .information_version([0, 1])
+            .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7\0".to_owned())
+            .media_storage_sop_instance_uid("2.25.1234\0".to_owned())
+            .transfer_syntax("1.2.840.10008.1.2\0".to_owned())
+            .implementation_class_uid("1.2.345\0".to_owned())
+            .build()
+            .unwrap();
+
+        let mut obj = InMemDicomObject::create_empty();
+        obj.put(DataElement::new(
+            Tag(0x0008, 0x0060),
+            VR::CS,
+            Value::Primitive(PrimitiveValue::Str("OT".to_owned())),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x0010, 0x0010),
+            VR::PN,
+            Value::Primitive(PrimitiveValue::Str("Doe^John".to_owned())),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x0028, 0x0030),
+            VR::DS,
+            Value::Primitive(PrimitiveValue::F64(vec![0.5, 0.25])),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x7FE0, 0x0010),
+            VR::OW,
+            Value::Primitive(PrimitiveValue::U16(vec![1, 2, 3, 4])),
+        ));
+        let obj = RootDicomObject::new(meta, obj);
+
+        let directory = ::std::env::temp_dir()
+            .join(format!("dicom-core-round-trip-{}", ::std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let first = directory.join("first.dcm");
+        let second = directory.join("second.dcm");
+        to_file(&obj, File::create(&first).unwrap()).unwrap();
+
+        let read = open_file(&first).unwrap();
+        to_file(&read, File::create(&second).unwrap()).unwrap();
+        let read_again = open_file(&second).unwrap();
+
+        assert_eq!(read_again, read);
+        assert_eq!(fs::read(&second).unwrap(), fs::read(&first).unwrap());
+        assert_eq!(
+            read.element(Tag(0x0010, 0x0010)).unwrap().value().as_string().unwrap(),
+            "Doe^John"
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
 }
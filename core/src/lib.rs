@@ -1,7 +1,7 @@
 #![crate_type = "lib"]
 #![deny(trivial_casts, trivial_numeric_casts, unsafe_code, unstable_features)]
 #![warn(missing_debug_implementations, missing_docs, unused_qualifications, unused_import_braces)]
-#![recursion_limit="72"]
+#![recursion_limit="128"]
 
 //! This is a library for basic DICOM content reading and writing.
 //!
@@ -21,14 +21,23 @@
 //! # }
 //! ```
 
+extern crate base64;
 extern crate byteorder;
 extern crate chrono;
 extern crate encoding;
+extern crate failure;
+extern crate flate2;
 extern crate itertools;
 #[macro_use]
 extern crate lazy_static;
 #[macro_use]
 extern crate quick_error;
+extern crate quick_xml;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(any(feature = "json", test))]
+extern crate serde_json;
 
 pub mod data;
 pub mod dictionary;
@@ -43,13 +52,13 @@ pub use data::value::{Value as DicomValue, PrimitiveValue};
 pub use data::VR;
 pub use data::DataElement as DicomElement;
 pub use dictionary::{DataDictionary, StandardDataDictionary};
-pub use object::DicomObject;
+pub use object::{DicomObject, RootDicomObject};
 pub use error::{Error, Result};
 
 pub use object::mem::InMemDicomObject;
 
 mod util;
 
-type DefaultDicomObject = InMemDicomObject<StandardDataDictionary>;
+type DefaultDicomObject = RootDicomObject<InMemDicomObject<StandardDataDictionary>>;
 
 pub use file::{open_file, from_stream, to_file};
@@ -1,15 +1,31 @@
-//! Module containing data structures and readers of DICOM file meta information tables.
-use std::io::Read;
+//! Module containing data structures, readers and writers of DICOM file meta
+//! information tables.
+use std::io::{Read, Write};
 use error::{Error, Result, InvalidValueReadError};
 use byteorder::{ByteOrder, LittleEndian};
-use data::{Header, Length, Tag};
+use data::{DataElementHeader, Header, Length, Tag, VR};
 use data::decode;
 use data::decode::Decode;
+use data::printer::{DynamicDicomPrinter, Print};
 use data::text;
-use data::text::TextCodec;
+use data::text::{SpecificCharacterSet, TextCodec};
+use data::value::PrimitiveValue;
+use transfer_syntax::ExplicitVRLittleEndian;
 
 const DICM_MAGIC_CODE: [u8; 4] = [b'D', b'I', b'C', b'M'];
 
+/// The implementation class UID of this library, recorded in the file meta
+/// group of created objects and announced in association negotiation.
+pub const IMPLEMENTATION_CLASS_UID: &str = "2.25.180289325725536011146430153437403826271";
+
+/// The value of an element in the file meta group, which is either text
+/// or a sequence of bytes.
+#[derive(Debug, Clone, PartialEq)]
+enum MetaValue {
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
 /// DICOM Meta Information Table.
 ///
 /// This data type contains the relevant parts of the file meta information table, as
@@ -96,6 +112,91 @@ impl DicomMetaTable {
         DicomMetaTable::read_from(file)
     }
 
+    /// Write the table to the given destination, starting with the "DICM"
+    /// magic code. The group is always encoded in explicit VR little endian,
+    /// UIDs are padded to an even length with a null character, and the
+    /// file meta information group length is recalculated from the
+    /// encoded elements, regardless of `information_group_length`.
+    pub fn write<W: Write>(&self, mut to: W) -> Result<()> {
+        let printer = DynamicDicomPrinter::new_with(&ExplicitVRLittleEndian, SpecificCharacterSet::Default)?;
+
+        let mut group = Vec::new();
+        for (tag, vr, value) in self.elements() {
+            let value = match value {
+                MetaValue::Str(s) => PrimitiveValue::Str(s),
+                MetaValue::Bytes(v) => PrimitiveValue::U8(v),
+            };
+            let data = printer.encode_value(vr, &value)?;
+            let header = DataElementHeader::new(tag, vr, Length(data.len() as u32));
+            printer.encode_element_header(&mut group, header)?;
+            group.extend_from_slice(&data);
+        }
+
+        let mut buf = Vec::with_capacity(12);
+        buf.extend_from_slice(&DICM_MAGIC_CODE);
+        let header = DataElementHeader::new(Tag(0x0002, 0x0000), VR::UL, Length(4));
+        printer.encode_element_header(&mut buf, header)?;
+        let mut len = [0u8; 4];
+        LittleEndian::write_u32(&mut len, group.len() as u32);
+        buf.extend_from_slice(&len);
+
+        to.write_all(&buf)?;
+        to.write_all(&group)?;
+        Ok(())
+    }
+
+    /// Calculate the file meta information group length, in bytes, of this
+    /// table when written. This is the size of all elements in the group
+    /// after (0002,0000), including their headers.
+    pub fn calculate_group_length(&self) -> u32 {
+        self.elements()
+            .iter()
+            .map(|&(_, vr, ref value)| {
+                let header_len = match vr {
+                    VR::OB => 12,
+                    _ => 8,
+                };
+                let value_len = match *value {
+                    MetaValue::Str(ref s) => s.len() as u32,
+                    MetaValue::Bytes(ref v) => v.len() as u32,
+                };
+                header_len + ((value_len + 1) & !1)
+            })
+            .sum()
+    }
+
+    /// Collect the elements of the table in the order that they are written,
+    /// excluding the group length.
+    fn elements(&self) -> Vec<(Tag, VR, MetaValue)> {
+        let mut elements = vec![
+            (Tag(0x0002, 0x0001), VR::OB, MetaValue::Bytes(self.information_version.to_vec())),
+            (Tag(0x0002, 0x0002), VR::UI, MetaValue::Str(self.media_storage_sop_class_uid.clone())),
+            (
+                Tag(0x0002, 0x0003),
+                VR::UI,
+                MetaValue::Str(self.media_storage_sop_instance_uid.clone()),
+            ),
+            (Tag(0x0002, 0x0010), VR::UI, MetaValue::Str(self.transfer_syntax.clone())),
+            (Tag(0x0002, 0x0012), VR::UI, MetaValue::Str(self.implementation_class_uid.clone())),
+        ];
+        let optional = [
+            (Tag(0x0002, 0x0013), VR::SH, &self.implementation_version_name),
+            (Tag(0x0002, 0x0016), VR::AE, &self.source_application_entity_title),
+            (Tag(0x0002, 0x0017), VR::AE, &self.sending_application_entity_title),
+            (Tag(0x0002, 0x0018), VR::AE, &self.receiving_application_entity_title),
+            (Tag(0x0002, 0x0100), VR::UI, &self.private_information_creator_uid),
+        ];
+        for &(tag, vr, value) in optional.iter() {
+            if let Some(ref v) = *value {
+                elements.push((tag, vr, MetaValue::Str(v.clone())));
+            }
+        }
+        if let Some(ref v) = self.private_information {
+            elements.push((Tag(0x0002, 0x0102), VR::OB, MetaValue::Bytes(v.clone())));
+        }
+        elements
+    }
+
     fn read_from<S: Read>(mut file: S) -> Result<Self> {
         let mut buff: [u8; 4] = [0; 4];
         {
@@ -299,7 +400,8 @@ impl DicomMetaTableBuilder {
         DicomMetaTableBuilder::default()
     }
 
-    /// Define the meta information group length.
+    /// Define the meta information group length. If not defined,
+    /// it is calculated from the other elements of the table.
     pub fn group_length(mut self, value: u32) -> DicomMetaTableBuilder {
         self.information_group_length = Some(value);
         self
@@ -373,8 +475,6 @@ impl DicomMetaTableBuilder {
 
     /// Build the table.
     pub fn build(self) -> Result<DicomMetaTable> {
-        let group_length = self.information_group_length
-            .ok_or_else(|| Error::InvalidFormat)?;
         let information_version = self.information_version
             .ok_or_else(|| Error::InvalidFormat)?;
         let media_storage_sop_class_uid = self.media_storage_sop_class_uid
@@ -384,8 +484,8 @@ impl DicomMetaTableBuilder {
         let transfer_syntax = self.transfer_syntax.ok_or_else(|| Error::InvalidFormat)?;
         let implementation_class_uid = self.implementation_class_uid
             .ok_or_else(|| Error::InvalidFormat)?;
-        Ok(DicomMetaTable {
-            information_group_length: group_length,
+        let mut table = DicomMetaTable {
+            information_group_length: 0,
             information_version: information_version,
             media_storage_sop_class_uid: media_storage_sop_class_uid,
             media_storage_sop_instance_uid: media_storage_sop_instance_uid,
@@ -397,14 +497,18 @@ impl DicomMetaTableBuilder {
             receiving_application_entity_title: self.receiving_application_entity_title,
             private_information_creator_uid: self.private_information_creator_uid,
             private_information: self.private_information,
-        })
+        };
+        table.information_group_length = match self.information_group_length {
+            Some(len) => len,
+            None => table.calculate_group_length(),
+        };
+        Ok(table)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::DicomMetaTable;
-    use std::io::Cursor;
+    use super::{DicomMetaTable, DicomMetaTableBuilder};
 
     const TEST_META_1: &'static [u8] = &[
         // magic code
@@ -467,4 +571,57 @@ mod tests {
         assert_eq!(table.private_information, None);
     }
 
+    #[test]
+    fn write_meta_table_read_from_stream() {
+        let mut source = TEST_META_1;
+        let table = DicomMetaTable::from_stream(&mut source).unwrap();
+
+        let mut out = Vec::new();
+        table.write(&mut out).unwrap();
+        assert_eq!(&out[..], TEST_META_1);
+    }
+
+    #[test]
+    fn build_meta_table_with_group_length() {
+        let table = DicomMetaTableBuilder::new()
+            .information_version([0, 1])
+            .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.1\0".to_owned())
+            .media_storage_sop_instance_uid("1.2.3.4.5.12345678.1234567890.1234567.123456789.1234567\0".to_owned())
+            .transfer_syntax("1.2.840.10008.1.2.1\0".to_owned())
+            .implementation_class_uid("1.2.345.6.7890.1.234".to_owned())
+            .implementation_version_name("RUSTY_DICOM_269 ".to_owned())
+            .source_application_entity_title("".to_owned())
+            .build()
+            .unwrap();
+        assert_eq!(table.information_group_length, 200);
+
+        let mut out = Vec::new();
+        table.write(&mut out).unwrap();
+        assert_eq!(&out[..], TEST_META_1);
+    }
+
+    #[test]
+    fn write_meta_table_pads_uids() {
+        let table = DicomMetaTableBuilder::new()
+            .group_length(0)
+            .information_version([0, 1])
+            .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7".to_owned())
+            .media_storage_sop_instance_uid("2.25.123".to_owned())
+            .transfer_syntax("1.2.840.10008.1.2".to_owned())
+            .implementation_class_uid("1.2.345".to_owned())
+            .build()
+            .unwrap();
+        // 14 + (8 + 26) + (8 + 8) + (8 + 18) + (8 + 8)
+        assert_eq!(table.calculate_group_length(), 106);
+
+        let mut out = Vec::new();
+        table.write(&mut out).unwrap();
+        assert_eq!(out.len(), 4 + 12 + 106);
+
+        let table = DicomMetaTable::from_stream(&mut &out[..]).unwrap();
+        assert_eq!(table.information_group_length, 106);
+        assert_eq!(table.media_storage_sop_class_uid, "1.2.840.10008.5.1.4.1.1.7\0");
+        assert_eq!(table.transfer_syntax, "1.2.840.10008.1.2\0");
+        assert_eq!(table.implementation_class_uid, "1.2.345\0");
+    }
 }
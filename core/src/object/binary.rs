@@ -0,0 +1,93 @@
+//! Conversion of binary values to and from little endian bytes, as used by
+//! the inline binary data of the DICOM JSON and XML models.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use data::value::PrimitiveValue;
+use data::VR;
+
+/// Whether values of this representation are written as binary data.
+pub fn is_binary(vr: VR) -> bool {
+    matches!(vr, VR::OB | VR::OD | VR::OF | VR::OL | VR::OW | VR::UN)
+}
+
+/// Encode a binary value in little endian. Returns `None` if the value
+/// is not numeric.
+pub fn to_le_bytes(value: &PrimitiveValue) -> Option<Vec<u8>> {
+    let bytes = match *value {
+        PrimitiveValue::Empty => vec![],
+        PrimitiveValue::U8(ref v) => v.clone(),
+        PrimitiveValue::I16(ref v) => {
+            let mut buf = vec![0; v.len() * 2];
+            LittleEndian::write_i16_into(v, &mut buf);
+            buf
+        }
+        PrimitiveValue::U16(ref v) => {
+            let mut buf = vec![0; v.len() * 2];
+            LittleEndian::write_u16_into(v, &mut buf);
+            buf
+        }
+        PrimitiveValue::I32(ref v) => {
+            let mut buf = vec![0; v.len() * 4];
+            LittleEndian::write_i32_into(v, &mut buf);
+            buf
+        }
+        PrimitiveValue::U32(ref v) => {
+            let mut buf = vec![0; v.len() * 4];
+            LittleEndian::write_u32_into(v, &mut buf);
+            buf
+        }
+        PrimitiveValue::F32(ref v) => {
+            let mut buf = vec![0; v.len() * 4];
+            LittleEndian::write_f32_into(v, &mut buf);
+            buf
+        }
+        PrimitiveValue::F64(ref v) => {
+            let mut buf = vec![0; v.len() * 8];
+            LittleEndian::write_f64_into(v, &mut buf);
+            buf
+        }
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Decode little endian binary data according to its value
+/// representation. Returns `None` if the representation is not binary or
+/// if the data length is not a multiple of the value width.
+pub fn from_le_bytes(vr: VR, bytes: Vec<u8>) -> Option<PrimitiveValue> {
+    fn count(bytes: &[u8], width: usize) -> Option<usize> {
+        let count = bytes.len() / width;
+        if count * width == bytes.len() {
+            Some(count)
+        } else {
+            None
+        }
+    }
+
+    let value = match vr {
+        VR::OB | VR::UN => PrimitiveValue::U8(bytes),
+        VR::OW => {
+            let mut v = vec![0; count(&bytes, 2)?];
+            LittleEndian::read_u16_into(&bytes, &mut v);
+            PrimitiveValue::U16(v)
+        }
+        VR::OL => {
+            let mut v = vec![0; count(&bytes, 4)?];
+            LittleEndian::read_u32_into(&bytes, &mut v);
+            PrimitiveValue::U32(v)
+        }
+        VR::OF => {
+            let mut v = vec![0.; count(&bytes, 4)?];
+            LittleEndian::read_f32_into(&bytes, &mut v);
+            PrimitiveValue::F32(v)
+        }
+        VR::OD => {
+            let mut v = vec![0.; count(&bytes, 8)?];
+            LittleEndian::read_f64_into(&bytes, &mut v);
+            PrimitiveValue::F64(v)
+        }
+        _ => return None,
+    };
+    Some(value)
+}
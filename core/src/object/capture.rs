@@ -0,0 +1,392 @@
+//! Creation of Secondary Capture Image objects from raster images.
+//!
+//! The `SecondaryCaptureBuilder` takes 8 or 16-bit grayscale or RGB pixel
+//! data (see `object::pixeldata::import` for reading it from PNG images)
+//! and a few patient and study attributes, and builds a complete DICOM file
+//! object of the Secondary Capture Image Storage SOP class, encoded in
+//! Explicit VR Little Endian. New study, series and instance UIDs are
+//! generated unless given.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::Local;
+use data::{DataElement, Tag, VR};
+use data::value::{PrimitiveValue, Value};
+use dictionary::StandardDataDictionary;
+use error::{Error, Result};
+use meta::{DicomMetaTableBuilder, IMPLEMENTATION_CLASS_UID};
+use object::RootDicomObject;
+use object::mem::InMemDicomObject;
+use object::pixeldata::NativePixelData;
+
+/// Secondary Capture Image Storage
+pub const SECONDARY_CAPTURE_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.7";
+
+const EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
+
+/// Generate a new globally unique identifier under the `2.25` root, which
+/// is reserved for UIDs derived from UUIDs. The UUID is built from hashes
+/// of a counter and the current time, each seeded with the random keys of
+/// a new `RandomState`, and is marked as a version 4 UUID. It is not drawn
+/// from a cryptographically secure generator.
+pub fn generate_uid() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed) as u64;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() ^ u64::from(d.subsec_nanos()) << 32)
+        .unwrap_or(0);
+
+    // each hasher is seeded with random keys
+    let random = |salt: u64| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(salt);
+        hasher.write_u64(count);
+        hasher.write_u64(nanos);
+        hasher.finish()
+    };
+    let mut uuid = u128::from(random(0)) << 64 | u128::from(random(1));
+    // version 4, variant 1
+    uuid = uuid & !(0xF << 76) | 0x4 << 76;
+    uuid = uuid & !(0x3 << 62) | 0x2 << 62;
+    format!("2.25.{}", uuid)
+}
+
+/// A builder of Secondary Capture Image objects.
+#[derive(Debug, Default)]
+pub struct SecondaryCaptureBuilder {
+    pixel_data: Option<NativePixelData>,
+    patient_name: Option<String>,
+    patient_id: Option<String>,
+    patient_birth_date: Option<String>,
+    patient_sex: Option<String>,
+    study_instance_uid: Option<String>,
+    study_id: Option<String>,
+    study_date: Option<String>,
+    study_time: Option<String>,
+    study_description: Option<String>,
+    accession_number: Option<String>,
+    series_instance_uid: Option<String>,
+    series_number: Option<i32>,
+    series_description: Option<String>,
+    sop_instance_uid: Option<String>,
+    instance_number: Option<i32>,
+    modality: Option<String>,
+}
+
+impl SecondaryCaptureBuilder {
+    /// Create a new, empty builder.
+    pub fn new() -> SecondaryCaptureBuilder {
+        SecondaryCaptureBuilder::default()
+    }
+
+    /// Define the pixel data of the image: 8 or 16-bit unsigned samples,
+    /// either grayscale or RGB. All frames are included.
+    pub fn pixel_data(mut self, value: NativePixelData) -> SecondaryCaptureBuilder {
+        self.pixel_data = Some(value);
+        self
+    }
+
+    /// Define the patient's name, in the DICOM person name format
+    /// (e.g. `Doe^John`).
+    pub fn patient_name(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.patient_name = Some(value);
+        self
+    }
+
+    /// Define the patient ID.
+    pub fn patient_id(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.patient_id = Some(value);
+        self
+    }
+
+    /// Define the patient's birth date, as `YYYYMMDD`.
+    pub fn patient_birth_date(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.patient_birth_date = Some(value);
+        self
+    }
+
+    /// Define the patient's sex (`M`, `F` or `O`).
+    pub fn patient_sex(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.patient_sex = Some(value);
+        self
+    }
+
+    /// Define the study instance UID, so that the image is added to an
+    /// existing study. A new one is generated otherwise.
+    pub fn study_instance_uid(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.study_instance_uid = Some(value);
+        self
+    }
+
+    /// Define the study ID.
+    pub fn study_id(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.study_id = Some(value);
+        self
+    }
+
+    /// Define the study date, as `YYYYMMDD`. Defaults to the current date.
+    pub fn study_date(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.study_date = Some(value);
+        self
+    }
+
+    /// Define the study time, as `HHMMSS`. Defaults to the current time.
+    pub fn study_time(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.study_time = Some(value);
+        self
+    }
+
+    /// Define the study description.
+    pub fn study_description(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.study_description = Some(value);
+        self
+    }
+
+    /// Define the accession number.
+    pub fn accession_number(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.accession_number = Some(value);
+        self
+    }
+
+    /// Define the series instance UID, so that the image is added to an
+    /// existing series. A new one is generated otherwise.
+    pub fn series_instance_uid(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.series_instance_uid = Some(value);
+        self
+    }
+
+    /// Define the series number.
+    pub fn series_number(mut self, value: i32) -> SecondaryCaptureBuilder {
+        self.series_number = Some(value);
+        self
+    }
+
+    /// Define the series description.
+    pub fn series_description(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.series_description = Some(value);
+        self
+    }
+
+    /// Define the SOP instance UID. A new one is generated otherwise.
+    pub fn sop_instance_uid(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.sop_instance_uid = Some(value);
+        self
+    }
+
+    /// Define the instance number.
+    pub fn instance_number(mut self, value: i32) -> SecondaryCaptureBuilder {
+        self.instance_number = Some(value);
+        self
+    }
+
+    /// Define the modality. Defaults to `OT` (other).
+    pub fn modality(mut self, value: String) -> SecondaryCaptureBuilder {
+        self.modality = Some(value);
+        self
+    }
+
+    /// Build the DICOM object along with its file meta group.
+    pub fn build(self) -> Result<RootDicomObject<InMemDicomObject<StandardDataDictionary>>> {
+        let pixel_data = self.pixel_data.ok_or(Error::InvalidImagePixelModule(
+            "missing pixel data for the secondary capture image",
+        ))?;
+        let (rows, columns, samples, frames) = (
+            pixel_data.rows(),
+            pixel_data.columns(),
+            pixel_data.samples_per_pixel(),
+            pixel_data.number_of_frames(),
+        );
+        if rows == 0 || columns == 0 || rows > 0xFFFF || columns > 0xFFFF {
+            return Err(Error::InvalidImagePixelModule("unsupported image dimensions"));
+        }
+        let photometric_interpretation = match samples {
+            1 => "MONOCHROME2",
+            3 => "RGB",
+            _ => {
+                return Err(Error::InvalidImagePixelModule(
+                    "secondary capture images must be grayscale or RGB",
+                ))
+            }
+        };
+        let (bits, vr, data) = match pixel_data {
+            NativePixelData::U8(p) => (8, VR::OB, PrimitiveValue::U8(p.to_vector())),
+            NativePixelData::U16(p) => (16, VR::OW, PrimitiveValue::U16(p.to_vector())),
+            _ => {
+                return Err(Error::InvalidImagePixelModule(
+                    "secondary capture images must have 8 or 16-bit unsigned samples",
+                ))
+            }
+        };
+
+        let now = Local::now();
+        let study_instance_uid = self.study_instance_uid.unwrap_or_else(generate_uid);
+        let series_instance_uid = self.series_instance_uid.unwrap_or_else(generate_uid);
+        let sop_instance_uid = self.sop_instance_uid.unwrap_or_else(generate_uid);
+        let date = now.format("%Y%m%d").to_string();
+        let time = now.format("%H%M%S").to_string();
+        let text = |v: &str| PrimitiveValue::Str(v.to_owned());
+        let optional = |v: Option<String>| PrimitiveValue::Str(v.unwrap_or_default());
+        let number = |v: Option<i32>| {
+            v.map_or(PrimitiveValue::Empty, |v| PrimitiveValue::I32(vec![v]))
+        };
+        let us = |v: u16| PrimitiveValue::U16(vec![v]);
+
+        let mut elements = vec![
+            (Tag(0x0008, 0x0012), VR::DA, text(&date)),
+            (Tag(0x0008, 0x0013), VR::TM, text(&time)),
+            (Tag(0x0008, 0x0016), VR::UI, text(SECONDARY_CAPTURE_IMAGE_STORAGE)),
+            (Tag(0x0008, 0x0018), VR::UI, text(&sop_instance_uid)),
+            (Tag(0x0008, 0x0020), VR::DA, optional(self.study_date.or(Some(date)))),
+            (Tag(0x0008, 0x0030), VR::TM, optional(self.study_time.or(Some(time)))),
+            (Tag(0x0008, 0x0050), VR::SH, optional(self.accession_number)),
+            (Tag(0x0008, 0x0060), VR::CS, text(self.modality.as_ref().map_or("OT", |m| m))),
+            // workstation
+            (Tag(0x0008, 0x0064), VR::CS, text("WSD")),
+            (Tag(0x0008, 0x0090), VR::PN, text("")),
+            (Tag(0x0010, 0x0010), VR::PN, optional(self.patient_name)),
+            (Tag(0x0010, 0x0020), VR::LO, optional(self.patient_id)),
+            (Tag(0x0010, 0x0030), VR::DA, optional(self.patient_birth_date)),
+            (Tag(0x0010, 0x0040), VR::CS, optional(self.patient_sex)),
+            (Tag(0x0020, 0x000D), VR::UI, text(&study_instance_uid)),
+            (Tag(0x0020, 0x000E), VR::UI, text(&series_instance_uid)),
+            (Tag(0x0020, 0x0010), VR::SH, optional(self.study_id)),
+            (Tag(0x0020, 0x0011), VR::IS, number(self.series_number)),
+            (Tag(0x0020, 0x0013), VR::IS, number(self.instance_number)),
+            (Tag(0x0020, 0x0020), VR::CS, text("")),
+            (Tag(0x0028, 0x0002), VR::US, us(samples)),
+            (Tag(0x0028, 0x0004), VR::CS, text(photometric_interpretation)),
+            (Tag(0x0028, 0x0010), VR::US, us(rows as u16)),
+            (Tag(0x0028, 0x0011), VR::US, us(columns as u16)),
+            (Tag(0x0028, 0x0100), VR::US, us(bits)),
+            (Tag(0x0028, 0x0101), VR::US, us(bits)),
+            (Tag(0x0028, 0x0102), VR::US, us(bits - 1)),
+            (Tag(0x0028, 0x0103), VR::US, us(0)),
+            (Tag(0x7FE0, 0x0010), vr, data),
+        ];
+        if let Some(description) = self.study_description {
+            elements.push((Tag(0x0008, 0x1030), VR::LO, optional(Some(description))));
+        }
+        if let Some(description) = self.series_description {
+            elements.push((Tag(0x0008, 0x103E), VR::LO, optional(Some(description))));
+        }
+        if samples == 3 {
+            elements.push((Tag(0x0028, 0x0006), VR::US, us(0)));
+        }
+        if frames > 1 {
+            elements.push((Tag(0x0028, 0x0008), VR::IS, number(Some(frames as i32))));
+        }
+
+        let mut obj = InMemDicomObject::create_empty();
+        for (tag, vr, value) in elements {
+            obj.put(DataElement::new(tag, vr, Value::Primitive(value)));
+        }
+        let meta = DicomMetaTableBuilder::new()
+            .information_version([0, 1])
+            .media_storage_sop_class_uid(SECONDARY_CAPTURE_IMAGE_STORAGE.to_owned())
+            .media_storage_sop_instance_uid(sop_instance_uid)
+            .transfer_syntax(EXPLICIT_VR_LITTLE_ENDIAN.to_owned())
+            .implementation_class_uid(IMPLEMENTATION_CLASS_UID.to_owned())
+            .build()?;
+        Ok(RootDicomObject::new(meta, obj))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_uid, SecondaryCaptureBuilder, SECONDARY_CAPTURE_IMAGE_STORAGE};
+    use chrono::NaiveDate;
+    use data::Tag;
+    use data::value::PrimitiveValue;
+    use file::{from_stream, to_file};
+    use object::pixeldata::{InMemoryPixelData, NativePixelData};
+    use std::io::Cursor;
+
+    #[test]
+    fn generated_uids() {
+        let (a, b) = (generate_uid(), generate_uid());
+        assert_ne!(a, b);
+        for uid in &[a, b] {
+            assert!(uid.starts_with("2.25."));
+            assert!(uid.len() <= 64);
+            assert!(uid[5..].chars().all(|c| c.is_ascii_digit()));
+            assert!(!uid[5..].starts_with('0'));
+        }
+    }
+
+    #[test]
+    fn build_and_write_rgb_capture() {
+        let pixels = InMemoryPixelData::new((0..18).collect::<Vec<u8>>(), 2, 3, 8, 3).unwrap();
+        let obj = SecondaryCaptureBuilder::new()
+            .pixel_data(NativePixelData::U8(pixels))
+            .patient_name("Doe^John".to_owned())
+            .patient_id("12345".to_owned())
+            .study_date("20180501".to_owned())
+            .series_number(3)
+            .series_description("Heatmap".to_owned())
+            .build()
+            .unwrap();
+        assert_eq!(obj.meta().media_storage_sop_class_uid, SECONDARY_CAPTURE_IMAGE_STORAGE);
+        let sop_instance_uid = obj.element(Tag(0x0008, 0x0018))
+            .unwrap()
+            .value()
+            .as_string()
+            .unwrap()
+            .to_owned();
+        assert_eq!(obj.meta().media_storage_sop_instance_uid, sop_instance_uid);
+
+        let mut buf = Vec::new();
+        to_file(&obj, &mut buf).unwrap();
+        let obj = from_stream(Cursor::new(buf[128..].to_vec())).unwrap();
+        let string = |tag| {
+            obj.element(tag)
+                .unwrap()
+                .value()
+                .as_string()
+                .unwrap()
+                .trim_end_matches(&[' ', '\0'][..])
+                .to_owned()
+        };
+        assert_eq!(string(Tag(0x0010, 0x0010)), "Doe^John");
+        assert_eq!(
+            obj.element(Tag(0x0008, 0x0020)).unwrap().value().primitive(),
+            Some(&PrimitiveValue::Date(vec![NaiveDate::from_ymd(2018, 5, 1)]))
+        );
+        assert_eq!(string(Tag(0x0008, 0x0060)), "OT");
+        assert_eq!(string(Tag(0x0008, 0x103E)), "Heatmap");
+        assert_eq!(string(Tag(0x0008, 0x0018)), sop_instance_uid);
+
+        let (module, pixels) = obj.decode_pixel_data().unwrap();
+        assert_eq!(module.photometric_interpretation, "RGB");
+        assert_eq!((module.rows, module.columns), (2, 3));
+        assert_eq!(pixels.samples_f64()[17], 17.);
+    }
+
+    #[test]
+    fn build_16bit_grayscale_frames() {
+        let pixels = InMemoryPixelData::new_with_frames(vec![1000u16; 8], 2, 2, 2, 16, 1).unwrap();
+        let obj = SecondaryCaptureBuilder::new()
+            .pixel_data(NativePixelData::U16(pixels))
+            .study_instance_uid("1.2.3.4".to_owned())
+            .build()
+            .unwrap();
+        let (module, pixels) = obj.decode_pixel_data().unwrap();
+        assert_eq!(module.photometric_interpretation, "MONOCHROME2");
+        assert_eq!(module.number_of_frames, 2);
+        assert_eq!(module.bits_stored, 16);
+        assert_eq!(pixels.number_of_frames(), 2);
+        assert_eq!(
+            obj.element(Tag(0x0020, 0x000D)).unwrap().value().as_string().unwrap(),
+            "1.2.3.4"
+        );
+
+        assert!(SecondaryCaptureBuilder::new().build().is_err());
+        let signed = InMemoryPixelData::new(vec![0i16; 4], 2, 2, 16, 1).unwrap();
+        assert!(SecondaryCaptureBuilder::new()
+            .pixel_data(NativePixelData::I16(signed))
+            .build()
+            .is_err());
+    }
+}
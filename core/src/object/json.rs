@@ -0,0 +1,449 @@
+//! This module contains the conversion of in-memory DICOM objects to and
+//! from the DICOM JSON model, as specified in PS3.18, section F.2.
+//!
+//! Each data element is keyed by its tag in the form `GGGGEEEE`, and is
+//! described by an object holding its value representation (`"vr"`) and
+//! either its values (`"Value"`), its binary data encoded in base64
+//! (`"InlineBinary"`) or a reference to it (`"BulkDataURI"`).
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::Result;
+//! # fn run() -> Result<()> {
+//! use dicom_core::data::{DataElement, Tag, VR};
+//! use dicom_core::object::json;
+//! use dicom_core::{DicomValue, InMemDicomObject, PrimitiveValue};
+//!
+//! let mut obj = InMemDicomObject::create_empty();
+//! obj.put(DataElement::new(
+//!     Tag(0x0010, 0x0010),
+//!     VR::PN,
+//!     DicomValue::Primitive(PrimitiveValue::Str("Doe^Jane".to_owned())),
+//! ));
+//! let json = json::to_json(&obj)?;
+//! assert_eq!(json["00100010"]["vr"], "PN");
+//! assert_eq!(json["00100010"]["Value"][0]["Alphabetic"], "Doe^Jane");
+//! # Ok(())
+//! # }
+//! # run().unwrap();
+//! ```
+
+use base64;
+use serde_json::{self, Map, Value as JsonValue};
+use std::borrow::Cow;
+
+use data::datetime::{format_date, format_datetime, format_time};
+use data::person_name::{PersonName, PersonNameComponents};
+use data::value::{DicomValueType, PrimitiveValue, Value};
+use data::{DataElement, Header, Length, Tag, VR};
+use dictionary::DataDictionary;
+use error::{Error, Result};
+use object::binary::{is_binary, to_le_bytes};
+use object::mem::{InMemDicomObject, InMemElement};
+use object::model::{self, parse_tag_text, read_binary, tag_text};
+
+/// Convert a DICOM object to the DICOM JSON model. Binary values are
+/// always written inline. Infinite and NaN floating point values have no
+/// JSON representation, and make the conversion fail.
+pub fn to_json<D>(obj: &InMemDicomObject<D>) -> Result<JsonValue> {
+    to_json_with(obj, |_, _| None)
+}
+
+/// Convert a DICOM object to the DICOM JSON model, choosing where binary
+/// values go. The given function is called for each element with a binary
+/// value representation: returning a URI writes it as a `BulkDataURI`,
+/// whereas returning `None` writes the value inline.
+pub fn to_json_with<D, F>(obj: &InMemDicomObject<D>, mut bulk_data: F) -> Result<JsonValue>
+where
+    F: FnMut(Tag, &PrimitiveValue) -> Option<String>,
+{
+    write_object(obj, &mut bulk_data)
+}
+
+/// Convert a DICOM object to a string in the DICOM JSON model.
+pub fn to_json_string<D>(obj: &InMemDicomObject<D>) -> Result<String> {
+    Ok(serde_json::to_string(&to_json(obj)?)?)
+}
+
+/// Build a DICOM object from its DICOM JSON representation. Elements
+/// referring to bulk data are given an empty value.
+pub fn from_json<D>(json: &JsonValue, dict: D) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    read_object(json, dict, &mut |_: &str| Ok(None))
+}
+
+/// Build a DICOM object from its DICOM JSON representation, retrieving
+/// the bytes behind each `BulkDataURI` with the given function.
+pub fn from_json_with<D, F>(
+    json: &JsonValue,
+    dict: D,
+    mut bulk_data: F,
+) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+    F: FnMut(&str) -> Result<Vec<u8>>,
+{
+    read_object(json, dict, &mut |uri: &str| bulk_data(uri).map(Some))
+}
+
+/// Build a DICOM object from a string in the DICOM JSON model.
+pub fn from_json_str<D>(text: &str, dict: D) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    from_json(&serde_json::from_str(text)?, dict)
+}
+
+fn write_object<D, F>(obj: &InMemDicomObject<D>, bulk_data: &mut F) -> Result<JsonValue>
+where
+    F: FnMut(Tag, &PrimitiveValue) -> Option<String>,
+{
+    let mut map = Map::new();
+    for elem in obj {
+        map.insert(tag_text(elem.tag()), write_element(elem, bulk_data)?);
+    }
+    Ok(JsonValue::Object(map))
+}
+
+fn write_element<D, F>(elem: &InMemElement<D>, bulk_data: &mut F) -> Result<JsonValue>
+where
+    F: FnMut(Tag, &PrimitiveValue) -> Option<String>,
+{
+    let vr = elem.vr();
+    let mut attr = Map::new();
+    attr.insert("vr".to_owned(), JsonValue::from(vr.to_string()));
+    match *elem.value() {
+        Value::Sequence { ref items, .. } => {
+            if !items.is_empty() {
+                let items: Result<Vec<_>> =
+                    items.iter().map(|item| write_object(item, bulk_data)).collect();
+                attr.insert("Value".to_owned(), JsonValue::Array(items?));
+            }
+        }
+        Value::PixelSequence { .. } => {
+            return Err(Error::InvalidJson(
+                "encapsulated pixel data cannot be written in DICOM JSON",
+            ));
+        }
+        Value::Primitive(ref value) if is_binary(vr) => {
+            if let Some(uri) = bulk_data(elem.tag(), value) {
+                attr.insert("BulkDataURI".to_owned(), JsonValue::from(uri));
+            } else if !value.is_empty() {
+                let bytes = to_le_bytes(value)
+                    .ok_or(Error::InvalidJson("non-numeric value in a binary representation"))?;
+                let data = base64::encode(&bytes);
+                attr.insert("InlineBinary".to_owned(), JsonValue::from(data));
+            }
+        }
+        Value::Primitive(ref value) => {
+            if !value.is_empty() {
+                attr.insert("Value".to_owned(), JsonValue::Array(write_values(vr, value)?));
+            }
+        }
+    }
+    Ok(JsonValue::Object(attr))
+}
+
+fn write_values(vr: VR, value: &PrimitiveValue) -> Result<Vec<JsonValue>> {
+    let values = match *value {
+        PrimitiveValue::Empty => vec![],
+        PrimitiveValue::Str(ref s) => match vr {
+            VR::LT | VR::ST | VR::UT | VR::UR => vec![write_string(vr, s)],
+            _ => s.split('\\').map(|s| write_string(vr, s)).collect(),
+        },
+        PrimitiveValue::Strs(ref v) => v.iter().map(|s| write_string(vr, s)).collect(),
+        PrimitiveValue::Tags(ref v) => v.iter().map(|t| JsonValue::from(tag_text(*t))).collect(),
+        PrimitiveValue::U8(ref v) => v.iter().map(|x| JsonValue::from(*x)).collect(),
+        PrimitiveValue::I16(ref v) => v.iter().map(|x| JsonValue::from(*x)).collect(),
+        PrimitiveValue::U16(ref v) => v.iter().map(|x| JsonValue::from(*x)).collect(),
+        PrimitiveValue::I32(ref v) => v.iter().map(|x| JsonValue::from(*x)).collect(),
+        PrimitiveValue::U32(ref v) => v.iter().map(|x| JsonValue::from(*x)).collect(),
+        PrimitiveValue::F32(ref v) => {
+            v.iter().map(|x| write_float(f64::from(*x))).collect::<Result<_>>()?
+        }
+        PrimitiveValue::F64(ref v) => v.iter().map(|x| write_float(*x)).collect::<Result<_>>()?,
+        PrimitiveValue::Date(ref v) => v.iter().map(|d| JsonValue::from(format_date(d))).collect(),
+        PrimitiveValue::Time(ref v) => v.iter().map(|t| JsonValue::from(format_time(t))).collect(),
+        PrimitiveValue::DateTime(ref v) => {
+            v.iter().map(|dt| JsonValue::from(format_datetime(dt))).collect()
+        }
+    };
+    Ok(values)
+}
+
+/// Write a floating point number, which must be finite.
+fn write_float(x: f64) -> Result<JsonValue> {
+    if !x.is_finite() {
+        return Err(Error::InvalidJson("non-finite floating point value"));
+    }
+    Ok(JsonValue::from(x))
+}
+
+/// Write a single textual value. Padding is removed, and empty values
+/// become `null`.
+fn write_string(vr: VR, text: &str) -> JsonValue {
+    let text = text.trim_end_matches(&[' ', '\0'][..]);
+    if text.is_empty() {
+        return JsonValue::Null;
+    }
+    match vr {
+        VR::PN => {
+            let name = PersonName::parse(text);
+            let mut groups = Map::new();
+            let components = [
+                ("Alphabetic", &name.alphabetic),
+                ("Ideographic", &name.ideographic),
+                ("Phonetic", &name.phonetic),
+            ];
+            for &(key, group) in &components {
+                if !group.is_empty() {
+                    groups.insert(key.to_owned(), JsonValue::from(group.to_string()));
+                }
+            }
+            JsonValue::Object(groups)
+        }
+        VR::IS => {
+            let text = text.trim();
+            text.parse::<i64>().map(JsonValue::from).unwrap_or_else(|_| JsonValue::from(text))
+        }
+        VR::DS => {
+            let text = text.trim();
+            text.parse::<f64>().map(JsonValue::from).unwrap_or_else(|_| JsonValue::from(text))
+        }
+        _ => JsonValue::from(text),
+    }
+}
+
+fn read_object<D, F>(json: &JsonValue, dict: D, bulk_data: &mut F) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+    F: FnMut(&str) -> Result<Option<Vec<u8>>>,
+{
+    let map = json.as_object().ok_or(Error::InvalidJson("expected a JSON object"))?;
+    let elements: Vec<_> = map
+        .iter()
+        .map(|(key, attr)| read_element(key, attr, &dict, bulk_data))
+        .collect();
+    InMemDicomObject::from_iter_with_dict(elements, dict)
+}
+
+fn read_element<D, F>(
+    key: &str,
+    attr: &JsonValue,
+    dict: &D,
+    bulk_data: &mut F,
+) -> Result<InMemElement<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+    F: FnMut(&str) -> Result<Option<Vec<u8>>>,
+{
+    let tag = parse_tag_text(key, Error::InvalidJson)?;
+    let vr = attr
+        .get("vr")
+        .and_then(JsonValue::as_str)
+        .and_then(VR::from_str)
+        .ok_or(Error::InvalidJson("missing or unknown value representation"))?;
+
+    if vr == VR::SQ {
+        let items: Result<Vec<_>> = match attr.get("Value") {
+            Some(&JsonValue::Array(ref items)) => items
+                .iter()
+                .map(|item| read_object(item, dict.clone(), bulk_data))
+                .collect(),
+            Some(&JsonValue::Null) | None => Ok(vec![]),
+            Some(_) => return Err(Error::InvalidJson("expected an array of sequence items")),
+        };
+        let value = Value::Sequence {
+            items: items?,
+            size: Length::undefined(),
+        };
+        return Ok(DataElement::new(tag, vr, value));
+    }
+
+    let value = if let Some(data) = attr.get("InlineBinary") {
+        let data = data.as_str().ok_or(Error::InvalidJson("expected a base64 string"))?;
+        let bytes = base64::decode(data).map_err(|_| Error::InvalidJson("invalid base64 data"))?;
+        read_binary(vr, bytes, Error::InvalidJson)?
+    } else if let Some(uri) = attr.get("BulkDataURI") {
+        let uri = uri.as_str().ok_or(Error::InvalidJson("expected a bulk data URI"))?;
+        match bulk_data(uri)? {
+            Some(bytes) => read_binary(vr, bytes, Error::InvalidJson)?,
+            None => PrimitiveValue::Empty,
+        }
+    } else {
+        match attr.get("Value") {
+            Some(&JsonValue::Array(ref values)) => read_values(vr, values)?,
+            Some(&JsonValue::Null) | None => PrimitiveValue::Empty,
+            Some(_) => return Err(Error::InvalidJson("expected an array of values")),
+        }
+    };
+    Ok(DataElement::new(tag, vr, Value::Primitive(value)))
+}
+
+/// Read the values of an element, which are strings, numbers, or person
+/// name objects in the DICOM JSON model.
+fn read_values(vr: VR, values: &[JsonValue]) -> Result<PrimitiveValue> {
+    let texts: Result<Vec<Cow<str>>> = values
+        .iter()
+        .map(|v| match *v {
+            _ if vr == VR::PN => read_person_name(v).map(Cow::Owned),
+            JsonValue::Number(ref n) => Ok(Cow::Owned(n.to_string())),
+            _ => read_text(v).map(Cow::Borrowed),
+        })
+        .collect();
+    model::read_values(vr, &texts?, Error::InvalidJson)
+}
+
+/// Read a textual value, where `null` stands for an empty value.
+fn read_text(value: &JsonValue) -> Result<&str> {
+    match *value {
+        JsonValue::String(ref s) => Ok(s),
+        JsonValue::Null => Ok(""),
+        _ => Err(Error::InvalidJson("expected a string value")),
+    }
+}
+
+/// Read a person name object back into its encoded form.
+fn read_person_name(value: &JsonValue) -> Result<String> {
+    let groups = match *value {
+        JsonValue::Object(ref groups) => groups,
+        JsonValue::Null => return Ok(String::new()),
+        _ => return Err(Error::InvalidJson("expected a person name object")),
+    };
+    let group = |key: &str| -> Result<PersonNameComponents> {
+        match groups.get(key) {
+            Some(&JsonValue::String(ref s)) => Ok(PersonNameComponents::parse(s)),
+            Some(&JsonValue::Null) | None => Ok(PersonNameComponents::default()),
+            Some(_) => Err(Error::InvalidJson("expected a person name component group")),
+        }
+    };
+    let name = PersonName {
+        alphabetic: group("Alphabetic")?,
+        ideographic: group("Ideographic")?,
+        phonetic: group("Phonetic")?,
+    };
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_json, from_json_str, from_json_with, to_json, to_json_string, to_json_with};
+    use data::value::{PrimitiveValue, Value};
+    use data::Tag;
+    use dictionary::StandardDataDictionary;
+    use object::model::testing::{every_primitive_value, nested_sequences, Obj};
+    use serde_json::Value as JsonValue;
+
+    fn round_trip(obj: &Obj) -> Obj {
+        let text = to_json_string(obj).unwrap();
+        from_json_str(&text, StandardDataDictionary).unwrap()
+    }
+
+    #[test]
+    fn round_trip_every_primitive_value() {
+        let obj = every_primitive_value();
+        assert_eq!(round_trip(&obj), obj);
+    }
+
+    #[test]
+    fn round_trip_nested_sequences() {
+        let obj = nested_sequences();
+        let json = to_json(&obj).unwrap();
+        let item = &json["00400275"]["Value"][0]["00082218"]["Value"][1];
+        assert_eq!(item["00080104"]["Value"][0], "Brain");
+        assert!(json["00400275"]["Value"][0]["0040A730"].get("Value").is_none());
+        let parsed = from_json(&json, StandardDataDictionary).unwrap();
+        match *parsed.element(Tag(0x0040, 0x0275)).unwrap().value() {
+            Value::Sequence { ref items, .. } => assert_eq!(items.len(), 1),
+            ref value => panic!("expected a sequence, got {:?}", value),
+        }
+        assert_eq!(to_json(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn read_standard_json() {
+        let text = r#"{
+            "00080005": { "vr": "CS", "Value": ["ISO_IR 192"] },
+            "00100010": { "vr": "PN", "Value": [
+                { "Alphabetic": "Wang^XiaoDong", "Ideographic": "王^小東" }, null
+            ] },
+            "00200013": { "vr": "IS", "Value": ["42"] },
+            "00280011": { "vr": "US" },
+            "7FE00010": { "vr": "OW", "BulkDataURI": "http://example.com/pixels" },
+            "00283006": { "vr": "US", "Value": [1, 2] }
+        }"#;
+        let obj = from_json_str(text, StandardDataDictionary).unwrap();
+        let name = obj.element(Tag(0x0010, 0x0010)).unwrap();
+        assert_eq!(name.value().to_person_names().unwrap()[0].ideographic.given, "小東");
+        let value = |tag| obj.element(tag).unwrap().value().primitive().cloned();
+        assert_eq!(value(Tag(0x0020, 0x0013)), Some(PrimitiveValue::I32(vec![42])));
+        assert_eq!(value(Tag(0x0028, 0x0011)), Some(PrimitiveValue::Empty));
+        assert_eq!(value(Tag(0x7FE0, 0x0010)), Some(PrimitiveValue::Empty));
+
+        let json = ::serde_json::from_str(text).unwrap();
+        let obj = from_json_with(&json, StandardDataDictionary, |uri| {
+            assert_eq!(uri, "http://example.com/pixels");
+            Ok(vec![1, 0, 2, 0])
+        }).unwrap();
+        let pixels = obj.element(Tag(0x7FE0, 0x0010)).unwrap();
+        assert_eq!(pixels.value().primitive(), Some(&PrimitiveValue::U16(vec![1, 2])));
+
+        let json = to_json_with(&obj, |tag, _| {
+            if tag == Tag(0x7FE0, 0x0010) {
+                Some("pixels".to_owned())
+            } else {
+                None
+            }
+        }).unwrap();
+        assert_eq!(json["7FE00010"]["BulkDataURI"], "pixels");
+        assert_eq!(json["00200013"]["Value"][0], 42);
+        assert!(from_json_str("[]", StandardDataDictionary).is_err());
+    }
+
+    #[test]
+    fn split_multiple_string_values() {
+        use data::{DataElement, VR};
+        use object::mem::InMemDicomObject;
+
+        let mut obj = InMemDicomObject::create_empty();
+        let mut put = |tag, vr, text: &str| {
+            obj.put(DataElement::new(tag, vr, Value::Primitive(PrimitiveValue::Str(text.into()))))
+        };
+        put(Tag(0x0010, 0x1001), VR::PN, "Doe^John\\Roe^Richard");
+        put(Tag(0x0020, 0x0013), VR::IS, "1\\2 ");
+        put(Tag(0x0028, 0x0030), VR::DS, "0.5\\0.25");
+        put(Tag(0x0008, 0x0060), VR::CS, "OT\\");
+        put(Tag(0x0010, 0x4000), VR::LT, "first\\second");
+        let json = to_json(&obj).unwrap();
+        assert_eq!(json["00101001"]["Value"][0]["Alphabetic"], "Doe^John");
+        assert_eq!(json["00101001"]["Value"][1]["Alphabetic"], "Roe^Richard");
+        let values = |key: &str| json[key]["Value"].as_array().unwrap().clone();
+        assert_eq!(values("00200013"), vec![1, 2]);
+        assert_eq!(values("00280030"), vec![0.5, 0.25]);
+        assert_eq!(values("00080060"), vec![JsonValue::from("OT"), JsonValue::Null]);
+        assert_eq!(values("00104000"), vec!["first\\second"]);
+    }
+
+    #[test]
+    fn reject_non_finite_numbers() {
+        use data::{DataElement, VR};
+        use object::mem::InMemDicomObject;
+
+        let mut obj = InMemDicomObject::create_empty();
+        let value = Value::Primitive(PrimitiveValue::F64(vec![1.0, ::std::f64::NAN]));
+        obj.put(DataElement::new(Tag(0x0018, 0x9087), VR::FD, value));
+        assert!(to_json(&obj).is_err());
+
+        let value = Value::Primitive(PrimitiveValue::F32(vec![::std::f32::INFINITY]));
+        obj.put(DataElement::new(Tag(0x0018, 0x9087), VR::FL, value));
+        assert!(to_json(&obj).is_err());
+    }
+}
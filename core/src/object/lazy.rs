@@ -1,16 +1,29 @@
-use super::DicomObject;
-use data::dataset::DicomElementMarker;
-use data::parser::DynamicDicomParser;
-use data::value::Value;
+//! This module contains the implementation for a lazily evaluated DICOM object.
+//!
+//! A lazy DICOM object indexes the data set with element markers up front,
+//! and only reads an element's value from the source when it is first
+//! requested. The value is then kept in the object for subsequent accesses.
+use super::{DicomObject, RootDicomObject};
+use data::dataset::{character_set_of, DicomElementMarker, LazyDataSetReader};
+use data::parser::{DynamicDicomParser, Parse};
+use data::text::SpecificCharacterSet;
+use data::value::{PrimitiveValue, Value};
 use data::Header;
-use data::{DataElement, Length, Tag, VR};
-use dictionary::{DataDictionary, DictionaryEntry};
-use error::{Error, Result};
+use data::{DataElement, DataElementHeader, Length, SequenceItemHeader, Tag, VR};
+use dictionary::{DataDictionary, DictionaryEntry, StandardDataDictionary};
+use error::{Error, InvalidValueReadError, Result};
+use meta::DicomMetaTable;
+use object::mem::InMemDicomObject;
 use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::Path;
 use std::rc::Rc;
+use transfer_syntax::codec::get_registry;
+use transfer_syntax::{ImplicitVRLittleEndian, TransferSyntax};
 use util::ReadSeek;
 
 /// Data type for a lazily loaded DICOM object builder.
@@ -18,11 +31,9 @@ pub struct LazyDataSequence<S, P, D> {
     dict: D,
     source: RefCell<S>,
     parser: P,
-    seq: Vec<LazyDataElement>,
+    seq: Vec<LazyDataElement<D>>,
 }
 
-type LazyObj = DataElement<()>;
-
 impl<S, P, D> Debug for LazyDataSequence<S, P, D>
 where
     D: Debug,
@@ -35,12 +46,16 @@ where
     }
 }
 
-/// Data type for a lazily loaded DICOM object builder.
+/// Data type for a lazily loaded DICOM object.
+///
+/// Only the top level elements of the data set are indexed. The items of a
+/// sequence are fully loaded into memory once the sequence is requested.
 pub struct LazyDicomObject<S, P, D> {
     dict: D,
     source: RefCell<S>,
     parser: P,
-    entries: RefCell<HashMap<Tag, LazyDataElement>>,
+    charset: SpecificCharacterSet,
+    entries: RefCell<HashMap<Tag, Rc<LazyDataElement<D>>>>,
 }
 
 impl<S, P, D> Debug for LazyDicomObject<S, P, D>
@@ -58,32 +73,28 @@ where
 
 impl<'s, S: 's, D: 's> DicomObject for &'s LazyDicomObject<S, DynamicDicomParser, D>
 where
-    S: ReadSeek,
-    D: DataDictionary,
+    S: ReadSeek + 'static,
+    D: DataDictionary + Clone,
 {
-    type Element = Ref<'s, LazyDataElement>;
+    type Element = Rc<LazyDataElement<D>>;
 
     fn element(&self, tag: Tag) -> Result<Self::Element> {
-        {
-            let borrow = self.entries.borrow();
-            if !borrow.contains_key(&tag) {
-                return Err(Error::NoSuchDataElement);
-            }
-            let e = Ref::map(borrow, |m| m.get(&tag).expect("Element should exist"));
+        let marker = {
+            let entries = self.entries.borrow();
+            let e = entries.get(&tag).ok_or(Error::NoSuchDataElement)?;
             if e.is_loaded() {
-                return Ok(e);
+                return Ok(e.clone());
             }
-        }
-        {
-            let mut borrow = self.entries.borrow_mut();
-            let e = borrow.get_mut(&tag).expect("Element should exist");
-            let v: Value<_> = self.load_value(&e.marker).unwrap();
-            let data = e.value_mut();
-            unimplemented!() // TODO
-        }
-        Ok(Ref::map(self.entries.borrow(), |m| {
-            m.get(&tag).expect("Element should exist")
-        }))
+            e.marker
+        };
+
+        let value = self.load_value(&marker)?;
+        let e = Rc::new(LazyDataElement {
+            marker,
+            value: Some(value),
+        });
+        self.entries.borrow_mut().insert(tag, e.clone());
+        Ok(e)
     }
 
     fn element_by_name(&self, name: &str) -> Result<Self::Element> {
@@ -92,11 +103,122 @@ where
     }
 }
 
-impl<'s, S: 's, D> LazyDicomObject<S, DynamicDicomParser, D>
+impl LazyDicomObject<BufReader<File>, DynamicDicomParser, StandardDataDictionary> {
+    /// Create a lazy DICOM object by indexing a file.
+    pub fn open_file<P: AsRef<Path>>(path: P) -> Result<RootDicomObject<Self>> {
+        Self::open_file_with_dict(path, StandardDataDictionary)
+    }
+}
+
+impl<D> LazyDicomObject<BufReader<File>, DynamicDicomParser, D>
+where
+    D: DataDictionary + Clone,
+{
+    /// Create a lazy DICOM object by indexing a file,
+    /// using the given dictionary for name lookup.
+    pub fn open_file_with_dict<P: AsRef<Path>>(path: P, dict: D) -> Result<RootDicomObject<Self>> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        // skip the preamble
+        file.seek(SeekFrom::Start(128))?;
+
+        Self::from_stream_with_dict(file, dict)
+    }
+}
+
+impl<S> LazyDicomObject<S, DynamicDicomParser, StandardDataDictionary>
 where
-    S: ReadSeek,
-    D: DataDictionary,
+    S: ReadSeek + 'static,
 {
+    /// Create a lazy DICOM object by indexing a random access byte source,
+    /// starting at the "DICM" magic code.
+    pub fn from_stream(src: S) -> Result<RootDicomObject<Self>> {
+        Self::from_stream_with_dict(src, StandardDataDictionary)
+    }
+}
+
+impl<S, D> LazyDicomObject<S, DynamicDicomParser, D>
+where
+    S: ReadSeek + 'static,
+    D: DataDictionary + Clone,
+{
+    /// Create a lazy DICOM object by indexing a random access byte source,
+    /// starting at the "DICM" magic code, using the given dictionary for
+    /// name lookup.
+    pub fn from_stream_with_dict(mut src: S, dict: D) -> Result<RootDicomObject<Self>> {
+        let meta = DicomMetaTable::from_stream(&mut src)?;
+        let ts = get_registry()
+            .get(&meta.transfer_syntax)
+            .ok_or(Error::UnsupportedTransferSyntax)?;
+        let obj = Self::index(src, ts, dict)?;
+        Ok(RootDicomObject::new(meta, obj))
+    }
+
+    /// Create a lazy DICOM object by indexing the data set in the given
+    /// random access source, which is encoded in the given transfer syntax.
+    pub fn index(src: S, ts: &TransferSyntax, dict: D) -> Result<Self> {
+        // a deflated data set does not provide random access
+        if ts.is_deflated() {
+            return Err(Error::UnsupportedTransferSyntax);
+        }
+
+        let mut reader = LazyDataSetReader::new_with(src, ts, SpecificCharacterSet::Default)?;
+
+        let mut entries = HashMap::new();
+        let mut depth = 0;
+        for marker in reader.by_ref() {
+            let marker = marker?;
+            match marker.tag() {
+                Tag(0xFFFE, 0xE0DD) => depth -= 1,
+                Tag(0xFFFE, _) => {}
+                tag => {
+                    if depth == 0 {
+                        entries.insert(tag, Rc::new(LazyDataElement::new(marker)));
+                    }
+                    if marker.vr() == VR::SQ || marker.len().is_undefined() {
+                        depth += 1;
+                    }
+                }
+            }
+        }
+        let (source, parser) = reader.into_parts();
+
+        let mut obj = LazyDicomObject {
+            dict,
+            source: RefCell::new(source),
+            parser,
+            charset: SpecificCharacterSet::Default,
+            entries: RefCell::new(entries),
+        };
+
+        // the character set is needed before reading any text value
+        let charset = match (&obj).element(Tag(0x0008, 0x0005)) {
+            Ok(e) => e.value().and_then(Value::primitive).map(character_set_of),
+            Err(Error::NoSuchDataElement) => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(charset) = charset {
+            obj.parser.set_character_set(charset)?;
+            obj.charset = charset;
+        }
+
+        Ok(obj)
+    }
+
+    /// Retrieve the tags of all elements at the root of this object,
+    /// in no particular order.
+    pub fn tags(&self) -> Vec<Tag> {
+        self.entries.borrow().keys().cloned().collect()
+    }
+
+    /// Discard the cached value of the element with the given tag,
+    /// so that it is read again from the source on the next access.
+    pub fn unload(&self, tag: Tag) {
+        if let Some(e) = self.entries.borrow_mut().get_mut(&tag) {
+            *e = Rc::new(LazyDataElement::new(e.marker));
+        }
+    }
+
     fn lookup_name(&self, name: &str) -> Result<Tag> {
         self.dict
             .by_name(name)
@@ -104,10 +226,142 @@ where
             .map(|e| e.tag())
     }
 
-    fn load_value(&self, marker: &DicomElementMarker) -> Result<Value<LazyObj>> {
+    fn load_value(&self, marker: &DicomElementMarker) -> Result<Value<InMemDicomObject<D>>> {
         let mut borrow = self.source.borrow_mut();
         marker.move_to_start(&mut *borrow)?;
-        unimplemented!()
+        let source = &mut *borrow;
+        if marker.vr() == VR::SQ {
+            let items = self.read_items(&self.parser, source, marker.len())?;
+            Ok(Value::Sequence {
+                items,
+                size: marker.len(),
+            })
+        } else if marker.len().is_undefined() {
+            self.read_undefined_length(&self.parser, source, &marker.header)
+        } else {
+            let value = self.parser.read_value(source, &marker.header)?;
+            Ok(Value::Primitive(value))
+        }
+    }
+
+    /// Read the value of an element with undefined length other than a
+    /// sequence, starting after its header. This is either a sequence of
+    /// unknown type (UN), whose contents are always in implicit VR little
+    /// endian, or encapsulated pixel data.
+    fn read_undefined_length(
+        &self,
+        parser: &DynamicDicomParser,
+        source: &mut S,
+        header: &DataElementHeader,
+    ) -> Result<Value<InMemDicomObject<D>>> {
+        if header.vr() == VR::UN {
+            let parser = DynamicDicomParser::new_with(&ImplicitVRLittleEndian, self.charset)?;
+            Ok(Value::Sequence {
+                items: self.read_items(&parser, source, header.len())?,
+                size: header.len(),
+            })
+        } else if header.tag() == Tag(0x7FE0, 0x0010) {
+            self.read_pixel_sequence(parser, source)
+        } else {
+            Err(InvalidValueReadError::UnresolvedValueLength.into())
+        }
+    }
+
+    /// Read all items of a sequence, starting after its header.
+    fn read_items(
+        &self,
+        parser: &DynamicDicomParser,
+        source: &mut S,
+        len: Length,
+    ) -> Result<Vec<InMemDicomObject<D>>> {
+        let end = match len.get() {
+            Some(len) => Some(source.seek(SeekFrom::Current(0))? + u64::from(len)),
+            None => None,
+        };
+        let mut items = Vec::new();
+        loop {
+            if let Some(end) = end {
+                if source.seek(SeekFrom::Current(0))? >= end {
+                    break;
+                }
+            }
+            match parser.decode_item_header(source)? {
+                SequenceItemHeader::Item { len } => {
+                    items.push(self.read_item(parser, source, len)?)
+                }
+                SequenceItemHeader::SequenceDelimiter => break,
+                SequenceItemHeader::ItemDelimiter => return Err(Error::UnexpectedElement),
+            }
+        }
+        Ok(items)
+    }
+
+    /// Read the items of an encapsulated pixel data element,
+    /// starting after its header.
+    fn read_pixel_sequence(
+        &self,
+        parser: &DynamicDicomParser,
+        source: &mut S,
+    ) -> Result<Value<InMemDicomObject<D>>> {
+        let mut offset_table = None;
+        let mut fragments = Vec::new();
+        loop {
+            match parser.decode_item_header(source)? {
+                SequenceItemHeader::Item { len } => {
+                    let vr = if offset_table.is_none() { VR::UL } else { VR::OB };
+                    let header = DataElementHeader::new(Tag(0xFFFE, 0xE000), vr, len);
+                    match parser.read_value(source, &header)? {
+                        PrimitiveValue::U32(table) => offset_table = Some(table),
+                        PrimitiveValue::U8(data) => fragments.push(data),
+                        _ if offset_table.is_none() => offset_table = Some(Vec::new()),
+                        _ => fragments.push(Vec::new()),
+                    }
+                }
+                SequenceItemHeader::SequenceDelimiter => break,
+                SequenceItemHeader::ItemDelimiter => return Err(Error::UnexpectedElement),
+            }
+        }
+        Ok(Value::PixelSequence {
+            offset_table: offset_table.unwrap_or_default(),
+            fragments,
+        })
+    }
+
+    /// Read the contents of an item, starting after its header.
+    fn read_item(
+        &self,
+        parser: &DynamicDicomParser,
+        source: &mut S,
+        len: Length,
+    ) -> Result<InMemDicomObject<D>> {
+        let end = match len.get() {
+            Some(len) => Some(source.seek(SeekFrom::Current(0))? + u64::from(len)),
+            None => None,
+        };
+        let mut obj = InMemDicomObject::new_empty_with_dict(self.dict.clone());
+        loop {
+            if let Some(end) = end {
+                if source.seek(SeekFrom::Current(0))? >= end {
+                    break;
+                }
+            }
+            let header: DataElementHeader = parser.decode_header(source)?;
+            if header.tag() == Tag(0xFFFE, 0xE00D) {
+                break;
+            }
+            let value = if header.vr() == VR::SQ {
+                Value::Sequence {
+                    items: self.read_items(parser, source, header.len())?,
+                    size: header.len(),
+                }
+            } else if header.len().is_undefined() {
+                self.read_undefined_length(parser, source, &header)?
+            } else {
+                Value::Primitive(parser.read_value(source, &header)?)
+            };
+            obj.put(DataElement::new(header.tag(), header.vr(), value));
+        }
+        Ok(obj)
     }
 }
 
@@ -115,12 +369,12 @@ where
 /// This element makes no further assumptions of where the
 /// element really comes from, and cannot retrieve the value by itself.
 #[derive(Debug, Clone, PartialEq)]
-pub struct LazyDataElement {
+pub struct LazyDataElement<D> {
     marker: DicomElementMarker,
-    value: Option<()>,
+    value: Option<Value<InMemDicomObject<D>>>,
 }
 
-impl Header for LazyDataElement {
+impl<D> Header for LazyDataElement<D> {
     fn tag(&self) -> Tag {
         self.marker.tag()
     }
@@ -129,7 +383,7 @@ impl Header for LazyDataElement {
     }
 }
 
-impl<'a> Header for &'a LazyDataElement {
+impl<'a, D> Header for &'a LazyDataElement<D> {
     fn tag(&self) -> Tag {
         (**self).tag()
     }
@@ -138,7 +392,7 @@ impl<'a> Header for &'a LazyDataElement {
     }
 }
 
-impl<'s> Header for Ref<'s, LazyDataElement> {
+impl<'s, D> Header for Ref<'s, LazyDataElement<D>> {
     fn tag(&self) -> Tag {
         (**self).tag()
     }
@@ -147,7 +401,7 @@ impl<'s> Header for Ref<'s, LazyDataElement> {
     }
 }
 
-impl Header for Rc<LazyDataElement> {
+impl<D> Header for Rc<LazyDataElement<D>> {
     fn tag(&self) -> Tag {
         (**self).tag()
     }
@@ -156,11 +410,11 @@ impl Header for Rc<LazyDataElement> {
     }
 }
 
-impl LazyDataElement {
+impl<D> LazyDataElement<D> {
     /// Create a new lazy element with the given marker.
-    pub fn new(marker: DicomElementMarker) -> LazyDataElement {
+    pub fn new(marker: DicomElementMarker) -> LazyDataElement<D> {
         LazyDataElement {
-            marker: marker,
+            marker,
             value: None,
         }
     }
@@ -183,24 +437,208 @@ impl LazyDataElement {
         self.marker.len()
     }
 
-    // TODO lazy value evaluation
+    /// Retrieve the marker pointing to this element's position in the source.
+    pub fn marker(&self) -> &DicomElementMarker {
+        &self.marker
+    }
+
     /// Getter for this element's cached data value.
     /// It will only hold a value once explicitly read.
-    pub fn value(&self) -> Option<&()> {
+    pub fn value(&self) -> Option<&Value<InMemDicomObject<D>>> {
         self.value.as_ref()
     }
 
-    // TODO lazy value evaluation
     /// Mutable getter for this element's cached data container.
-    pub fn value_mut(&mut self) -> &mut Option<()> {
+    pub fn value_mut(&mut self) -> &mut Option<Value<InMemDicomObject<D>>> {
         &mut self.value
     }
 
+    /// Check whether the element's value has been read.
     pub fn is_loaded(&self) -> bool {
         self.value.is_some()
     }
 
+    /// Discard the element's cached value.
     pub fn clear_value(&mut self) {
         self.value = None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::LazyDicomObject;
+    use data::dataset::{DataSetWriter, SequenceLength};
+    use data::text::SpecificCharacterSet;
+    use data::value::{PrimitiveValue, Value};
+    use data::{DataElement, Header, Tag, VR};
+    use object::mem::InMemDicomObject;
+    use object::DicomObject;
+    use std::io::Cursor;
+    use transfer_syntax::ExplicitVRLittleEndian;
+
+    fn sample_object() -> InMemDicomObject<::StandardDataDictionary> {
+        let mut item = InMemDicomObject::create_empty();
+        item.put(DataElement::new(
+            Tag(0x0008, 0x0100),
+            VR::SH,
+            Value::Primitive(PrimitiveValue::Str("T-D1100 ".to_owned())),
+        ));
+
+        let mut obj = InMemDicomObject::create_empty();
+        obj.put(DataElement::new(
+            Tag(0x0008, 0x2218),
+            VR::SQ,
+            Value::Sequence {
+                items: vec![item.clone(), item],
+                size: ::data::Length::undefined(),
+            },
+        ));
+        obj.put(DataElement::new(
+            Tag(0x0010, 0x0010),
+            VR::PN,
+            Value::Primitive(PrimitiveValue::Str("Doe^John".to_owned())),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x7FE0, 0x0010),
+            VR::OW,
+            Value::Primitive(PrimitiveValue::U16(vec![0x0102; 4096])),
+        ));
+        obj
+    }
+
+    fn encode(obj: &InMemDicomObject<::StandardDataDictionary>, seq: SequenceLength) -> Vec<u8> {
+        let mut writer = DataSetWriter::new_with(
+            Vec::new(),
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap()
+            .sequence_length(seq);
+        writer.write_object(obj).unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn lazy_object_loads_on_demand() {
+        for &seq in &[SequenceLength::Undefined, SequenceLength::Defined] {
+            let data = encode(&sample_object(), seq);
+            let obj = LazyDicomObject::index(
+                Cursor::new(data),
+                &ExplicitVRLittleEndian,
+                ::StandardDataDictionary,
+            ).unwrap();
+
+            let mut tags = obj.tags();
+            tags.sort();
+            assert_eq!(
+                tags,
+                vec![Tag(0x0008, 0x2218), Tag(0x0010, 0x0010), Tag(0x7FE0, 0x0010)]
+            );
+            assert!(obj.entries.borrow().values().all(|e| !e.is_loaded()));
+
+            let name = (&obj).element_by_name("PatientName").unwrap();
+            assert_eq!(name.len(), ::data::Length(8));
+            assert_eq!(
+                name.value().unwrap().as_string().unwrap(),
+                "Doe^John"
+            );
+
+            let seq = (&obj).element(Tag(0x0008, 0x2218)).unwrap();
+            let items = seq.value().unwrap().item().unwrap();
+            assert_eq!(items.len(), 2);
+            for item in items {
+                assert_eq!(
+                    item.element(Tag(0x0008, 0x0100)).unwrap().value().as_string().unwrap(),
+                    "T-D1100 "
+                );
+            }
+
+            // pixel data was never touched
+            assert!(!obj.entries.borrow()[&Tag(0x7FE0, 0x0010)].is_loaded());
+
+            let pixels = (&obj).element(Tag(0x7FE0, 0x0010)).unwrap();
+            assert_eq!(
+                pixels.value().unwrap().primitive(),
+                Some(&PrimitiveValue::U16(vec![0x0102; 4096]))
+            );
+            obj.unload(Tag(0x7FE0, 0x0010));
+            assert!(!obj.entries.borrow()[&Tag(0x7FE0, 0x0010)].is_loaded());
+        }
+    }
+
+    #[test]
+    fn lazy_object_missing_element() {
+        let data = encode(&sample_object(), SequenceLength::Undefined);
+        let obj = LazyDicomObject::index(
+            Cursor::new(data),
+            &ExplicitVRLittleEndian,
+            ::StandardDataDictionary,
+        ).unwrap();
+        assert!((&obj).element(Tag(0x0010, 0x0020)).is_err());
+    }
+
+    #[test]
+    fn lazy_object_encapsulated_pixel_data() {
+        let mut obj = InMemDicomObject::create_empty();
+        obj.put(DataElement::new(
+            Tag(0x7FE0, 0x0010),
+            VR::OB,
+            Value::PixelSequence {
+                offset_table: vec![0, 12],
+                fragments: vec![vec![0xFF, 0xD8, 0xFF, 0xD9], vec![1, 2, 3]],
+            },
+        ));
+        obj.put(DataElement::new(
+            Tag(0x7FE0, 0x0020),
+            VR::OW,
+            Value::Primitive(PrimitiveValue::U16(vec![7])),
+        ));
+        let data = encode(&obj, SequenceLength::Undefined);
+        let obj = LazyDicomObject::index(
+            Cursor::new(data),
+            &ExplicitVRLittleEndian,
+            ::StandardDataDictionary,
+        ).unwrap();
+
+        // the element after the pixel data was indexed too
+        assert!((&obj).element(Tag(0x7FE0, 0x0020)).is_ok());
+
+        let pixels = (&obj).element(Tag(0x7FE0, 0x0010)).unwrap();
+        let value = pixels.value().unwrap();
+        assert_eq!(value.offset_table(), Some(&[0, 12][..]));
+        // odd length fragments are padded
+        assert_eq!(
+            value.fragments(),
+            Some(&[vec![0xFF, 0xD8, 0xFF, 0xD9], vec![1, 2, 3, 0]][..])
+        );
+    }
+
+    #[test]
+    fn lazy_object_un_sequence() {
+        // (0008,1115) UN, undefined length, with an item in implicit VR
+        // little endian holding (0010,0010) PN "Doe^J "
+        let data: &[u8] = &[
+            0x08, 0x00, 0x15, 0x11, b'U', b'N', 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+            0xFE, 0xFF, 0x00, 0xE0, 0xFF, 0xFF, 0xFF, 0xFF,
+            0x10, 0x00, 0x10, 0x00, 0x06, 0x00, 0x00, 0x00, b'D', b'o', b'e', b'^', b'J', b' ',
+            0xFE, 0xFF, 0x0D, 0xE0, 0x00, 0x00, 0x00, 0x00,
+            0xFE, 0xFF, 0xDD, 0xE0, 0x00, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x20, 0x00, b'L', b'O', 0x02, 0x00, b'4', b'2',
+        ];
+        let obj = LazyDicomObject::index(
+            Cursor::new(data),
+            &ExplicitVRLittleEndian,
+            ::StandardDataDictionary,
+        ).unwrap();
+
+        let mut tags = obj.tags();
+        tags.sort();
+        assert_eq!(tags, vec![Tag(0x0008, 0x1115), Tag(0x0010, 0x0020)]);
+
+        let seq = (&obj).element(Tag(0x0008, 0x1115)).unwrap();
+        let items = seq.value().unwrap().item().unwrap();
+        assert_eq!(items.len(), 1);
+        let name = items[0].element(Tag(0x0010, 0x0010)).unwrap();
+        assert_eq!(name.vr(), VR::PN);
+        assert_eq!(name.value().as_string().unwrap(), "Doe^J ");
+    }
+}
@@ -1,13 +1,18 @@
 //! This module contains the implementation for an in-memory DICOM object.
 
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use itertools::Itertools;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Write};
 use std::path::Path;
 
-use super::DicomObject;
-use data::dataset::{DataSetReader, DicomDataToken};
+use super::{DicomObject, RootDicomObject};
+use data::dataset::{DataSetReader, DataSetWriter, DicomDataToken};
 use data::parser::Parse;
 use data::text::SpecificCharacterSet;
 use data::value::{DicomValueType, Value, ValueType};
@@ -15,7 +20,9 @@ use data::{DataElement, Header, Length, Tag, VR};
 use dictionary::{DataDictionary, DictionaryEntry, StandardDataDictionary};
 use error::{DataSetSyntaxError, Error, Result};
 use meta::DicomMetaTable;
+use object::pixeldata::{decode_pixel_data, native_pixel_data, ImagePixelModule, NativePixelData};
 use transfer_syntax::codec::get_registry;
+use transfer_syntax::TransferSyntax;
 
 /// A full in-memory DICOM data element.
 pub type InMemElement<D> = DataElement<InMemDicomObject<D>>;
@@ -51,6 +58,38 @@ impl<D> DicomValueType for InMemDicomObject<D> {
     }
 }
 
+/// An object is serialized as the sequence of its data elements, leaving
+/// out the data dictionary, which is restored with its default value.
+#[cfg(feature = "serde")]
+impl<D> Serialize for InMemDicomObject<D> {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.entries.values())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, D> Deserialize<'de> for InMemDicomObject<D>
+where
+    D: DataDictionary,
+    D: Clone,
+    D: Default,
+{
+    fn deserialize<De>(deserializer: De) -> ::std::result::Result<Self, De::Error>
+    where
+        De: Deserializer<'de>,
+    {
+        let elements = Vec::<InMemElement<D>>::deserialize(deserializer)?;
+        Ok(InMemDicomObject {
+            entries: elements.into_iter().map(|e| (e.tag(), e)).collect(),
+            dict: D::default(),
+            len: Length::undefined(),
+        })
+    }
+}
+
 impl<'s, D: 's> DicomObject for &'s InMemDicomObject<D>
 where
     D: DataDictionary,
@@ -79,14 +118,14 @@ impl InMemDicomObject<StandardDataDictionary> {
     }
 
     /// Create a DICOM object by reading from a file.
-    pub fn open_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+    pub fn open_file<P: AsRef<Path>>(path: P) -> Result<RootDicomObject<Self>> {
         Self::open_file_with_dict(path, StandardDataDictionary)
     }
 
     /// Create a DICOM object by reading from a byte sources.
-    pub fn from_stream<S>(src: S) -> Result<Self>
+    pub fn from_stream<S>(src: S) -> Result<RootDicomObject<Self>>
     where
-        S: Read,
+        S: Read + 'static,
     {
         Self::from_stream_with_dict(src, StandardDataDictionary)
     }
@@ -98,6 +137,15 @@ impl InMemDicomObject<StandardDataDictionary> {
     {
         Self::from_iter_with_dict(iter, StandardDataDictionary)
     }
+
+    /// Create a DICOM object by reading a data set without a file meta
+    /// group, encoded in the given transfer syntax.
+    pub fn read_dataset<S>(src: S, ts: &TransferSyntax) -> Result<Self>
+    where
+        S: Read + 'static,
+    {
+        Self::read_dataset_with_dict(src, StandardDataDictionary, ts)
+    }
 }
 
 impl<D> InMemDicomObject<D>
@@ -127,8 +175,13 @@ where
         })
     }
 
+    /// Retrieve the data dictionary used by this object.
+    pub fn dictionary(&self) -> &D {
+        &self.dict
+    }
+
     /// Create a DICOM object by reading from a file.
-    pub fn open_file_with_dict<P: AsRef<Path>>(path: P, dict: D) -> Result<Self> {
+    pub fn open_file_with_dict<P: AsRef<Path>>(path: P, dict: D) -> Result<RootDicomObject<Self>> {
         let mut file = BufReader::new(File::open(path)?);
 
         // skip preamble
@@ -146,14 +199,57 @@ where
             .get(&meta.transfer_syntax)
             .ok_or(Error::UnsupportedTransferSyntax)?;
         let cs = SpecificCharacterSet::Default;
+        let file: Box<Read> = if ts.is_deflated() {
+            Box::new(DeflateDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
         let mut dataset = DataSetReader::new_with_dictionary(file, dict.clone(), ts, cs)?;
+        let obj = Self::build_object(&mut dataset, dict, false, Length::undefined())?;
+        Ok(RootDicomObject::new(meta, obj))
+    }
+
+    /// Create a DICOM object by reading a data set without a file meta
+    /// group, encoded in the given transfer syntax.
+    pub fn read_dataset_with_dict<S>(src: S, dict: D, ts: &TransferSyntax) -> Result<Self>
+    where
+        S: Read + 'static,
+    {
+        let cs = SpecificCharacterSet::Default;
+        let src: Box<Read> = if ts.is_deflated() {
+            Box::new(DeflateDecoder::new(src))
+        } else {
+            Box::new(src)
+        };
+        let mut dataset = DataSetReader::new_with_dictionary(src, dict.clone(), ts, cs)?;
         Self::build_object(&mut dataset, dict, false, Length::undefined())
     }
 
+    /// Write the data set of this object, without a file meta group,
+    /// encoded in the given transfer syntax.
+    pub fn write_dataset<W>(&self, to: W, ts: &TransferSyntax) -> Result<()>
+    where
+        W: Write,
+    {
+        let cs = SpecificCharacterSet::Default;
+        if ts.is_deflated() {
+            let to = DeflateEncoder::new(to, Compression::default());
+            let mut writer = DataSetWriter::new_with(to, ts, cs)?;
+            writer.write_object(self)?;
+            writer.finish()?;
+            writer.into_inner().finish()?;
+            Ok(())
+        } else {
+            let mut writer = DataSetWriter::new_with(to, ts, cs)?;
+            writer.write_object(self)?;
+            writer.finish()
+        }
+    }
+
     /// Create a DICOM object by reading from a byte source.
-    pub fn from_stream_with_dict<S>(src: S, dict: D) -> Result<Self>
+    pub fn from_stream_with_dict<S>(src: S, dict: D) -> Result<RootDicomObject<Self>>
     where
-        S: Read,
+        S: Read + 'static,
     {
         let mut file = BufReader::new(src);
 
@@ -165,8 +261,14 @@ where
             .get(&meta.transfer_syntax)
             .ok_or(Error::UnsupportedTransferSyntax)?;
         let cs = SpecificCharacterSet::Default;
+        let file: Box<Read> = if ts.is_deflated() {
+            Box::new(DeflateDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
         let mut dataset = DataSetReader::new_with_dictionary(file, dict.clone(), ts, cs)?;
-        Self::build_object(&mut dataset, dict, false, Length::undefined())
+        let obj = Self::build_object(&mut dataset, dict, false, Length::undefined())?;
+        Ok(RootDicomObject::new(meta, obj))
     }
 
     fn build_object<'s, S: 's, P>(
@@ -196,6 +298,10 @@ where
                         DicomDataToken::PrimitiveValue(v) => {
                             InMemElement::new(header.tag, header.vr, Value::Primitive(v))
                         }
+                        DicomDataToken::PixelSequenceStart => {
+                            let value = Self::build_pixel_sequence(&mut *dataset)?;
+                            InMemElement::new(header.tag, header.vr, value)
+                        }
                         token => {
                             return Err(DataSetSyntaxError::UnexpectedToken(token).into());
                         }
@@ -247,6 +353,37 @@ where
         Err(DataSetSyntaxError::PrematureEnd.into())
     }
 
+    fn build_pixel_sequence<'s, S: 's, P>(
+        dataset: &mut DataSetReader<S, P, D>,
+    ) -> Result<Value<InMemDicomObject<D>>>
+    where
+        S: Read,
+        P: Parse<Read + 's>,
+    {
+        let mut offset_table = None;
+        let mut fragments = vec![];
+        while let Some(token) = dataset.next() {
+            match token? {
+                DicomDataToken::OffsetTable(table) if offset_table.is_none() => {
+                    offset_table = Some(table);
+                }
+                DicomDataToken::ItemValue(data) if offset_table.is_some() => {
+                    fragments.push(data);
+                }
+                DicomDataToken::SequenceEnd => {
+                    return Ok(Value::PixelSequence {
+                        offset_table: offset_table.unwrap_or_default(),
+                        fragments,
+                    });
+                }
+                token => return Err(DataSetSyntaxError::UnexpectedToken(token).into()),
+            };
+        }
+
+        // iterator fully consumed without a sequence delimiter
+        Err(DataSetSyntaxError::PrematureEnd.into())
+    }
+
     fn lookup_name(&self, name: &str) -> Result<Tag> {
         self.dict
             .by_name(name)
@@ -269,6 +406,27 @@ where
         let tag = self.lookup_name(name)?;
         self.element(tag)
     }
+
+    /// Retrieve the native pixel data of this object, as described by
+    /// its image pixel module.
+    pub fn pixel_data(&self) -> Result<NativePixelData> {
+        native_pixel_data(self)
+    }
+}
+
+impl<D> RootDicomObject<InMemDicomObject<D>>
+where
+    D: DataDictionary + Clone,
+{
+    /// Retrieve the pixel data of this object in native form, decoding it
+    /// according to the transfer syntax in the meta table if needed.
+    /// See `object::pixeldata::decode_pixel_data` for more details.
+    pub fn decode_pixel_data(&self) -> Result<(ImagePixelModule, NativePixelData)> {
+        let ts = get_registry()
+            .get(&self.meta().transfer_syntax)
+            .ok_or(Error::UnsupportedTransferSyntax)?;
+        decode_pixel_data(self, ts)
+    }
 }
 
 impl<'a, D> IntoIterator for &'a InMemDicomObject<D> {
@@ -345,6 +503,58 @@ mod tests {
         assert_eq!(elem1, &another_patient_name);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn inmem_object_serde() {
+        use chrono::{FixedOffset, NaiveDate, NaiveTime, TimeZone};
+
+        let date = NaiveDate::from_ymd_opt(2018, 4, 5).unwrap();
+        let time = NaiveTime::from_hms_micro_opt(10, 20, 30, 250_000).unwrap();
+        let datetime = FixedOffset::east_opt(3600)
+            .unwrap()
+            .from_local_datetime(&date.and_time(time))
+            .unwrap();
+        let values = vec![
+            (VR::CS, PrimitiveValue::Empty),
+            (VR::LT, PrimitiveValue::Str("Some\\comments".to_owned())),
+            (VR::PN, PrimitiveValue::Strs(vec!["Doe^John".to_owned(), "Doe^Jane".to_owned()])),
+            (VR::AT, PrimitiveValue::Tags(vec![Tag(0x0018, 0x1063)])),
+            (VR::OB, PrimitiveValue::U8(vec![1, 2, 255])),
+            (VR::SS, PrimitiveValue::I16(vec![-3])),
+            (VR::US, PrimitiveValue::U16(vec![512])),
+            (VR::SL, PrimitiveValue::I32(vec![-70_000])),
+            (VR::UL, PrimitiveValue::U32(vec![4_000_000_000])),
+            (VR::FL, PrimitiveValue::F32(vec![0.5])),
+            (VR::FD, PrimitiveValue::F64(vec![-2.25])),
+            (VR::DA, PrimitiveValue::Date(vec![date])),
+            (VR::DT, PrimitiveValue::DateTime(vec![datetime])),
+            (VR::TM, PrimitiveValue::Time(vec![time])),
+        ];
+        let mut item = InMemDicomObject::create_empty();
+        for (i, (vr, value)) in values.into_iter().enumerate() {
+            item.put(DataElement::new(Tag(0x0009, 0x1000 + i as u16), vr, value.into()));
+        }
+        let mut obj = InMemDicomObject::create_empty();
+        obj.put(DataElement::new(
+            Tag(0x0040, 0x0275),
+            VR::SQ,
+            Value::Sequence {
+                items: vec![item.clone()],
+                size: Length::undefined(),
+            },
+        ));
+
+        let json = ::serde_json::to_string(&obj).unwrap();
+        assert!(json.contains("\"tag\":\"(0040,0275)\""));
+        let parsed: InMemDicomObject<StandardDataDictionary> =
+            ::serde_json::from_str(&json).unwrap();
+        assert_eq!(::serde_json::to_string(&parsed).unwrap(), json);
+        match *parsed.element(Tag(0x0040, 0x0275)).unwrap().value() {
+            Value::Sequence { ref items, .. } => assert_eq!(items[0], item),
+            ref value => panic!("expected a sequence, got {:?}", value),
+        }
+    }
+
     #[test]
     fn inmem_object_get_by_name() {
         let another_patient_name = DataElement::new(
@@ -357,4 +567,75 @@ mod tests {
         let elem1 = (&obj).element_by_name("PatientName").unwrap();
         assert_eq!(elem1, &another_patient_name);
     }
+
+    #[test]
+    fn inmem_object_dataset_round_trip() {
+        use std::io::Cursor;
+        use transfer_syntax::ImplicitVRLittleEndian;
+
+        let mut obj = InMemDicomObject::create_empty();
+        obj.put(DataElement::new(
+            Tag(0x0010, 0x0010),
+            VR::PN,
+            PrimitiveValue::Str("Doe^John".to_string()).into(),
+        ));
+        obj.put(DataElement::new(
+            Tag(0x0028, 0x0010),
+            VR::US,
+            PrimitiveValue::U16(vec![512]).into(),
+        ));
+
+        let mut buf = Vec::new();
+        obj.write_dataset(&mut buf, &ImplicitVRLittleEndian).unwrap();
+        // no file meta group, starting right at the first element
+        assert_eq!(&buf[..4], &[0x10, 0x00, 0x10, 0x00]);
+
+        let read = InMemDicomObject::read_dataset(Cursor::new(buf), &ImplicitVRLittleEndian)
+            .unwrap();
+        assert_eq!(
+            read.element(Tag(0x0010, 0x0010)).unwrap().value().as_string().unwrap(),
+            "Doe^John"
+        );
+        assert_eq!(
+            read.element(Tag(0x0028, 0x0010)).unwrap(),
+            obj.element(Tag(0x0028, 0x0010)).unwrap()
+        );
+    }
+
+    #[test]
+    fn inmem_object_encapsulated_pixel_data() {
+        use transfer_syntax::ExplicitVRLittleEndian;
+
+        // (7FE0,0010) OB, undefined length
+        //   item: offset table [0]
+        //   item: fragment [1, 2, 3, 4, 5, 6]
+        //   item: fragment [7, 8]
+        // sequence delimiter
+        let raw: &[u8] = &[
+            0xE0, 0x7F, 0x10, 0x00, b'O', b'B', 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+            0xFE, 0xFF, 0x00, 0xE0, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0xFE, 0xFF, 0x00, 0xE0, 0x06, 0x00, 0x00, 0x00, 1, 2, 3, 4, 5, 6,
+            0xFE, 0xFF, 0x00, 0xE0, 0x02, 0x00, 0x00, 0x00, 7, 8,
+            0xFE, 0xFF, 0xDD, 0xE0, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut dataset = DataSetReader::new_with(
+            raw,
+            &ExplicitVRLittleEndian,
+            SpecificCharacterSet::Default,
+        ).unwrap();
+        let obj = InMemDicomObject::build_object(
+            &mut dataset,
+            StandardDataDictionary,
+            false,
+            Length::undefined(),
+        ).unwrap();
+
+        let value = obj.element(Tag(0x7FE0, 0x0010)).unwrap().value();
+        assert_eq!(value.offset_table(), Some(&[0u32][..]));
+        assert_eq!(
+            value.fragments(),
+            Some(&[vec![1, 2, 3, 4, 5, 6], vec![7, 8]][..])
+        );
+        assert_eq!(value.multiplicity(), 2);
+    }
 }
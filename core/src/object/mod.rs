@@ -22,9 +22,15 @@ use data::Tag;
 use error::Result;
 use meta::DicomMetaTable;
 
+mod binary;
+pub mod capture;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod lazy;
 pub mod mem;
+mod model;
 pub mod pixeldata;
+pub mod xml;
 
 /// Trait type for a DICOM object.
 /// This is a high-level abstraction where an object is accessed and
@@ -55,10 +61,21 @@ pub struct RootDicomObject<T> {
 }
 
 impl<T> RootDicomObject<T> {
+    /// Create a root DICOM object from its meta information table
+    /// and the main data set.
+    pub fn new(meta: DicomMetaTable, obj: T) -> Self {
+        RootDicomObject { meta, obj }
+    }
+
     /// Retrieve the processed meta header table.
     pub fn meta(&self) -> &DicomMetaTable {
         &self.meta
     }
+
+    /// Retrieve the inner DICOM object, discarding the meta header table.
+    pub fn into_inner(self) -> T {
+        self.obj
+    }
 }
 
 impl<T> ::std::ops::Deref for RootDicomObject<T> {
@@ -0,0 +1,183 @@
+//! Reading of element values from the textual and binary forms which the
+//! DICOM JSON model and the Native DICOM Model (XML) have in common.
+//!
+//! Errors which are specific to each model are built with the given
+//! `invalid` function, such as `Error::InvalidJson`.
+
+use chrono::FixedOffset;
+use std::str::FromStr;
+
+use data::datetime::{parse_date, parse_datetime, parse_time};
+use data::value::PrimitiveValue;
+use data::{Tag, VR};
+use error::{Error, Result};
+use object::binary::from_le_bytes;
+
+/// Format an attribute tag as `GGGGEEEE`, in upper case hexadecimal digits.
+pub fn tag_text(tag: Tag) -> String {
+    format!("{:04X}{:04X}", tag.group(), tag.element())
+}
+
+/// Parse an attribute tag in the form `GGGGEEEE`. Surrounding whitespace
+/// is ignored.
+pub fn parse_tag_text(text: &str, invalid: fn(&'static str) -> Error) -> Result<Tag> {
+    let text = text.trim();
+    if text.len() != 8 || !text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid("invalid attribute tag"));
+    }
+    let hex = |digits: &str| {
+        u16::from_str_radix(digits, 16).map_err(|_| invalid("invalid attribute tag"))
+    };
+    Ok(Tag(hex(&text[0..4])?, hex(&text[4..8])?))
+}
+
+/// Decode the inline or bulk binary data of an element.
+pub fn read_binary(
+    vr: VR,
+    bytes: Vec<u8>,
+    invalid: fn(&'static str) -> Error,
+) -> Result<PrimitiveValue> {
+    from_le_bytes(vr, bytes).ok_or_else(|| invalid("invalid binary data for its representation"))
+}
+
+/// Read the values of an element from their textual form. Person names
+/// are expected in their encoded form (e.g. `Doe^Jane`).
+pub fn read_values<T: AsRef<str>>(
+    vr: VR,
+    texts: &[T],
+    invalid: fn(&'static str) -> Error,
+) -> Result<PrimitiveValue> {
+    if texts.is_empty() {
+        return Ok(PrimitiveValue::Empty);
+    }
+    let value = match vr {
+        VR::AT => PrimitiveValue::Tags(collect(texts, |t| parse_tag_text(t, invalid))?),
+        VR::DA => PrimitiveValue::Date(collect(texts, |t| Ok(parse_date(t.trim().as_bytes())?.0))?),
+        VR::TM => PrimitiveValue::Time(collect(texts, |t| Ok(parse_time(t.trim().as_bytes())?.0))?),
+        VR::DT => {
+            let utc = FixedOffset::east_opt(0).unwrap();
+            PrimitiveValue::DateTime(collect(texts, |t| parse_datetime(t.trim().as_bytes(), &utc))?)
+        }
+        VR::OB | VR::UN => PrimitiveValue::U8(numbers(texts, invalid)?),
+        VR::SS => PrimitiveValue::I16(numbers(texts, invalid)?),
+        VR::US | VR::OW => PrimitiveValue::U16(numbers(texts, invalid)?),
+        VR::IS | VR::SL => PrimitiveValue::I32(numbers(texts, invalid)?),
+        VR::UL | VR::OL => PrimitiveValue::U32(numbers(texts, invalid)?),
+        VR::FL | VR::OF => PrimitiveValue::F32(numbers(texts, invalid)?),
+        VR::DS | VR::FD | VR::OD => PrimitiveValue::F64(numbers(texts, invalid)?),
+        VR::LT | VR::ST | VR::UT | VR::UR => {
+            let texts: Vec<&str> = texts.iter().map(AsRef::as_ref).collect();
+            PrimitiveValue::Str(texts.join("\\"))
+        }
+        _ => PrimitiveValue::Strs(texts.iter().map(|t| t.as_ref().to_owned()).collect()),
+    };
+    Ok(value)
+}
+
+fn collect<T, U, F>(texts: &[T], mut f: F) -> Result<Vec<U>>
+where
+    T: AsRef<str>,
+    F: FnMut(&str) -> Result<U>,
+{
+    texts.iter().map(|t| f(t.as_ref())).collect()
+}
+
+fn numbers<T, U>(texts: &[T], invalid: fn(&'static str) -> Error) -> Result<Vec<U>>
+where
+    T: AsRef<str>,
+    U: FromStr,
+{
+    collect(texts, |t| t.trim().parse().map_err(|_| invalid("invalid numeric value")))
+}
+
+/// Objects whose round trip is tested through both models.
+#[cfg(test)]
+pub mod testing {
+    use chrono::{FixedOffset, NaiveDate, NaiveTime, TimeZone};
+    use data::value::{PrimitiveValue, Value};
+    use data::{DataElement, Length, Tag, VR};
+    use dictionary::StandardDataDictionary;
+    use object::mem::{InMemDicomObject, InMemElement};
+
+    pub type Obj = InMemDicomObject<StandardDataDictionary>;
+
+    pub fn elem(tag: Tag, vr: VR, value: PrimitiveValue) -> InMemElement<StandardDataDictionary> {
+        DataElement::new(tag, vr, Value::Primitive(value))
+    }
+
+    /// An object with a value of every primitive type, in each value
+    /// representation which can hold it.
+    pub fn every_primitive_value() -> Obj {
+        let offset = FixedOffset::west_opt(5 * 3600).unwrap();
+        let date = NaiveDate::from_ymd_opt(2018, 4, 5).unwrap();
+        let elements = vec![
+            elem(Tag(0x0008, 0x0005), VR::CS, PrimitiveValue::Empty),
+            elem(Tag(0x0008, 0x0016), VR::UI, PrimitiveValue::Strs(vec!["1.2.3".to_owned()])),
+            elem(Tag(0x0008, 0x0020), VR::DA, PrimitiveValue::Date(vec![
+                date,
+            ])),
+            elem(Tag(0x0008, 0x0030), VR::TM, PrimitiveValue::Time(vec![
+                NaiveTime::from_hms_micro_opt(10, 20, 30, 250_000).unwrap(),
+                NaiveTime::from_hms_opt(23, 59, 0).unwrap(),
+            ])),
+            elem(Tag(0x0008, 0x002A), VR::DT, PrimitiveValue::DateTime(vec![
+                offset
+                    .from_local_datetime(&date.and_hms_micro_opt(1, 2, 3, 4).unwrap())
+                    .unwrap(),
+            ])),
+            elem(Tag(0x0010, 0x0010), VR::PN, PrimitiveValue::Strs(vec![
+                "Yamada^Tarou=山田^太郎=やまだ^たろう".to_owned(),
+                "Adams^John Robert Quincy^^Rev.^B.A. M.Div.".to_owned(),
+                "Doe^Jane".to_owned(),
+            ])),
+            elem(Tag(0x0010, 0x4000), VR::LT, PrimitiveValue::Str("  <b> & </b>\\".to_owned())),
+            elem(Tag(0x0020, 0x0013), VR::IS, PrimitiveValue::I32(vec![-7])),
+            elem(Tag(0x0020, 0x0032), VR::DS, PrimitiveValue::F64(vec![-0.5, 12.25, 1e20])),
+            elem(Tag(0x0028, 0x0010), VR::US, PrimitiveValue::U16(vec![512])),
+            elem(Tag(0x0028, 0x0106), VR::SS, PrimitiveValue::I16(vec![-32768, 32767])),
+            elem(Tag(0x0018, 0x6020), VR::SL, PrimitiveValue::I32(vec![i32::min_value()])),
+            elem(Tag(0x0028, 0x6102), VR::UL, PrimitiveValue::U32(vec![4_000_000_000])),
+            elem(Tag(0x0018, 0x9089), VR::FD, PrimitiveValue::F64(vec![0.1, -2.0])),
+            elem(Tag(0x0018, 0x1702), VR::FL, PrimitiveValue::F32(vec![0.1, 1.5])),
+            elem(Tag(0x0028, 0x0009), VR::AT, PrimitiveValue::Tags(vec![
+                Tag(0x0018, 0x1063),
+                Tag(0x5400, 0x0100),
+            ])),
+            elem(Tag(0x0009, 0x0010), VR::UN, PrimitiveValue::U8(vec![1, 2, 3, 255])),
+            elem(Tag(0x0028, 0x1201), VR::OW, PrimitiveValue::U16(vec![0, 0x1234, 0xFFFF])),
+            elem(Tag(0x0066, 0x0016), VR::OF, PrimitiveValue::F32(vec![0.25, -8.5])),
+            elem(Tag(0x0066, 0x0040), VR::OL, PrimitiveValue::U32(vec![7, 0xDEAD_BEEF])),
+            elem(Tag(0x7FE0, 0x0009), VR::OD, PrimitiveValue::F64(vec![3.5e-10])),
+        ];
+        Obj::from_iter(elements.into_iter().map(Ok)).unwrap()
+    }
+
+    /// An object with a sequence in a sequence, whose inner sequence has two
+    /// items, next to an empty sequence.
+    ///
+    /// Undefined sequence lengths never compare equal, so objects read back
+    /// from this one have to be checked through their own representation.
+    pub fn nested_sequences() -> Obj {
+        let inner = Obj::from_iter(vec![
+            Ok(elem(Tag(0x0008, 0x0100), VR::SH, PrimitiveValue::Strs(vec!["T-A0100".to_owned()]))),
+            Ok(elem(Tag(0x0008, 0x0104), VR::LO, PrimitiveValue::Strs(vec!["Brain".to_owned()]))),
+        ]).unwrap();
+        let outer = Obj::from_iter(vec![
+            Ok(DataElement::new(Tag(0x0008, 0x2218), VR::SQ, Value::Sequence {
+                items: vec![inner.clone(), inner],
+                size: Length::undefined(),
+            })),
+            Ok(DataElement::new(Tag(0x0040, 0xA730), VR::SQ, Value::Sequence {
+                items: vec![],
+                size: Length::undefined(),
+            })),
+        ]).unwrap();
+        Obj::from_iter(vec![
+            Ok(elem(Tag(0x0020, 0x000D), VR::UI, PrimitiveValue::Strs(vec!["1.2.3.4".to_owned()]))),
+            Ok(DataElement::new(Tag(0x0040, 0x0275), VR::SQ, Value::Sequence {
+                items: vec![outer],
+                size: Length::undefined(),
+            })),
+        ]).unwrap()
+    }
+}
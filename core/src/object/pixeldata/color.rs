@@ -0,0 +1,379 @@
+//! Conversion of pixel data into 8-bit RGB or normalized grayscale values.
+//!
+//! The conversion depends on the photometric interpretation of the pixel
+//! data:
+//!
+//! - MONOCHROME1 and MONOCHROME2 samples are scaled to the range of stored
+//!   values, with MONOCHROME1 inverted so that the minimum value is black;
+//! - RGB samples are only scaled to 8 bits;
+//! - YBR_FULL, YBR_FULL_422 and the YBR_PARTIAL variants are converted to
+//!   RGB as described in part 3 of the standard (section C.7.6.3.1.2);
+//! - PALETTE COLOR samples are mapped through the red, green and blue
+//!   palette color lookup tables.
+//!
+//! The pixel data is expected in the form retrieved by `decode_pixel_data`,
+//! with the samples of each pixel next to each other.
+
+use data::Tag;
+use dictionary::DataDictionary;
+use error::{Error, Result};
+use object::mem::InMemDicomObject;
+use super::{native_pixel_data_with_module, ImagePixelModule, InMemoryPixelData,
+            NativePixelData};
+use super::lut::Lut;
+
+const RED_PALETTE_DESCRIPTOR: Tag = Tag(0x0028, 0x1101);
+const GREEN_PALETTE_DESCRIPTOR: Tag = Tag(0x0028, 0x1102);
+const BLUE_PALETTE_DESCRIPTOR: Tag = Tag(0x0028, 0x1103);
+const RED_PALETTE_DATA: Tag = Tag(0x0028, 0x1201);
+const GREEN_PALETTE_DATA: Tag = Tag(0x0028, 0x1202);
+const BLUE_PALETTE_DATA: Tag = Tag(0x0028, 0x1203);
+
+/// The red, green and blue lookup tables of a PALETTE COLOR image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    /// Red Palette Color Lookup Table
+    pub red: Lut,
+    /// Green Palette Color Lookup Table
+    pub green: Lut,
+    /// Blue Palette Color Lookup Table
+    pub blue: Lut,
+}
+
+impl Palette {
+    /// Read the palette color lookup tables of an object. The first value
+    /// mapped is read as a signed value if `signed` is true.
+    pub fn from_object<D>(obj: &InMemDicomObject<D>, signed: bool) -> Result<Palette>
+    where
+        D: DataDictionary + Clone,
+    {
+        let lut = |descriptor, data| {
+            Lut::from_elements(obj, descriptor, data, signed).map_err(|e| match e {
+                Error::NoSuchDataElement => {
+                    Error::InvalidImagePixelModule("missing palette color lookup table")
+                }
+                e => e,
+            })
+        };
+        Ok(Palette {
+            red: lut(RED_PALETTE_DESCRIPTOR, RED_PALETTE_DATA)?,
+            green: lut(GREEN_PALETTE_DESCRIPTOR, GREEN_PALETTE_DATA)?,
+            blue: lut(BLUE_PALETTE_DESCRIPTOR, BLUE_PALETTE_DATA)?,
+        })
+    }
+
+    /// Map a stored value to an 8-bit RGB color.
+    pub fn get(&self, v: f64) -> [u8; 3] {
+        let channel = |lut: &Lut| (lut.get(v) / lut.max_value() * 255.).round() as u8;
+        [channel(&self.red), channel(&self.green), channel(&self.blue)]
+    }
+}
+
+/// Retrieve the range of values which can be stored with the given image
+/// pixel module, as `(min, max)`.
+fn stored_range(module: &ImagePixelModule) -> (f64, f64) {
+    let bits = i32::from(module.bits_stored);
+    if module.pixel_representation == 1 {
+        (-(2f64.powi(bits - 1)), 2f64.powi(bits - 1) - 1.)
+    } else {
+        (0., 2f64.powi(bits) - 1.)
+    }
+}
+
+/// Check that the pixel data has the layout described by the module.
+fn check_layout(module: &ImagePixelModule, pixels: &NativePixelData) -> Result<()> {
+    if pixels.samples_per_pixel() != module.samples_per_pixel
+        || pixels.rows() != u32::from(module.rows)
+        || pixels.columns() != u32::from(module.columns)
+    {
+        return Err(Error::InvalidImagePixelModule(
+            "pixel data does not match the image pixel module",
+        ));
+    }
+    Ok(())
+}
+
+/// Convert a full range YCbCr color, with all components in 0..=255,
+/// to RGB.
+fn ybr_full_to_rgb(y: f64, cb: f64, cr: f64) -> [f64; 3] {
+    let (cb, cr) = (cb - 128., cr - 128.);
+    [
+        y + 1.402 * cr,
+        y - 0.344_136 * cb - 0.714_136 * cr,
+        y + 1.772 * cb,
+    ]
+}
+
+/// Convert a partial range YCbCr color, with all components in 0..=255,
+/// to RGB.
+fn ybr_partial_to_rgb(y: f64, cb: f64, cr: f64) -> [f64; 3] {
+    let (y, cb, cr) = (1.164_4 * (y - 16.), cb - 128., cr - 128.);
+    [
+        y + 1.596_0 * cr,
+        y - 0.391_8 * cb - 0.813_0 * cr,
+        y + 2.017_2 * cb,
+    ]
+}
+
+/// Convert all frames of pixel data into 8-bit RGB values.
+///
+/// The module must describe the pixel data as it is in memory (see
+/// `decode_pixel_data`). A palette is required for PALETTE COLOR images,
+/// and ignored otherwise.
+pub fn to_rgb8(
+    module: &ImagePixelModule,
+    pixels: &NativePixelData,
+    palette: Option<&Palette>,
+) -> Result<InMemoryPixelData<u8>> {
+    check_layout(module, pixels)?;
+    let samples = pixels.samples_f64();
+    let pixel_count = samples.len() / usize::from(module.samples_per_pixel);
+    let mut data = Vec::with_capacity(pixel_count * 3);
+    match module.photometric_interpretation.as_str() {
+        "MONOCHROME1" | "MONOCHROME2" => {
+            for v in normalize(module, &samples) {
+                let v = (v * 255.).round() as u8;
+                data.extend_from_slice(&[v, v, v]);
+            }
+        }
+        "PALETTE COLOR" => {
+            let palette = palette.ok_or(Error::InvalidImagePixelModule(
+                "missing palette color lookup table",
+            ))?;
+            for &v in &samples {
+                data.extend_from_slice(&palette.get(v));
+            }
+        }
+        pi => {
+            let convert: fn(f64, f64, f64) -> [f64; 3] = match pi {
+                "RGB" => |r, g, b| [r, g, b],
+                "YBR_FULL" | "YBR_FULL_422" => ybr_full_to_rgb,
+                "YBR_PARTIAL_422" | "YBR_PARTIAL_420" => ybr_partial_to_rgb,
+                _ => {
+                    return Err(Error::InvalidImagePixelModule(
+                        "unsupported photometric interpretation for color conversion",
+                    ))
+                }
+            };
+            let (min, max) = stored_range(module);
+            let scale = |v: f64| (v - min) / (max - min) * 255.;
+            for c in samples.chunks(3) {
+                let rgb = convert(scale(c[0]), scale(c[1]), scale(c[2]));
+                data.extend(rgb.iter().map(|v| v.round().clamp(0., 255.) as u8));
+            }
+        }
+    }
+    InMemoryPixelData::new_with_frames(
+        data,
+        pixels.number_of_frames(),
+        pixels.rows(),
+        pixels.columns(),
+        8,
+        3,
+    )
+}
+
+/// Scale monochrome samples to the range [0, 1], where 0 is black.
+fn normalize(module: &ImagePixelModule, samples: &[f64]) -> Vec<f64> {
+    let (min, max) = stored_range(module);
+    let invert = module.photometric_interpretation == "MONOCHROME1";
+    samples
+        .iter()
+        .map(|&v| {
+            let v = ((v - min) / (max - min)).clamp(0., 1.);
+            if invert {
+                1. - v
+            } else {
+                v
+            }
+        })
+        .collect()
+}
+
+/// Convert all frames of monochrome pixel data into grayscale values in the
+/// range [0, 1], where 0 is black. The values are scaled to the range of
+/// stored values, and MONOCHROME1 images are inverted.
+///
+/// The module must describe the pixel data as it is in memory (see
+/// `decode_pixel_data`).
+pub fn to_normalized_grayscale(
+    module: &ImagePixelModule,
+    pixels: &NativePixelData,
+) -> Result<InMemoryPixelData<f32>> {
+    check_layout(module, pixels)?;
+    match module.photometric_interpretation.as_str() {
+        "MONOCHROME1" | "MONOCHROME2" => {}
+        _ => {
+            return Err(Error::InvalidImagePixelModule(
+                "only monochrome images can be converted to grayscale",
+            ))
+        }
+    }
+    let data = normalize(module, &pixels.samples_f64())
+        .into_iter()
+        .map(|v| v as f32)
+        .collect();
+    InMemoryPixelData::new_with_frames(
+        data,
+        pixels.number_of_frames(),
+        pixels.rows(),
+        pixels.columns(),
+        32,
+        1,
+    )
+}
+
+/// Convert the native pixel data of an object into 8-bit RGB values,
+/// using the palette color lookup tables of the object if needed.
+pub fn render_rgb<D>(obj: &InMemDicomObject<D>) -> Result<InMemoryPixelData<u8>>
+where
+    D: DataDictionary + Clone,
+{
+    let (module, pixels) = native_pixel_data_with_module(obj)?;
+    if module.photometric_interpretation == "PALETTE COLOR" {
+        let palette = Palette::from_object(obj, module.pixel_representation == 1)?;
+        to_rgb8(&module, &pixels, Some(&palette))
+    } else {
+        to_rgb8(&module, &pixels, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_rgb, to_normalized_grayscale, to_rgb8, Palette};
+    use data::{Tag, VR};
+    use data::value::PrimitiveValue;
+    use object::mem::InMemDicomObject;
+    use object::pixeldata::{decode_pixel_data, PixelData};
+    use object::pixeldata::testing::{put, put_image_module, put_us, Object};
+    use transfer_syntax::ExplicitVRLittleEndian;
+
+    /// A 2x2 image with 8 bits allocated and the given pixel data.
+    fn image(pi: &str, samples: u16, planar: u16, data: Vec<u8>) -> Object {
+        let mut obj = InMemDicomObject::create_empty();
+        put_image_module(&mut obj, pi, [2, 2], [samples, 8, 8, 7, 0]);
+        put_us(&mut obj, Tag(0x0028, 0x0006), planar);
+        put(&mut obj, Tag(0x7FE0, 0x0010), VR::OB, PrimitiveValue::U8(data));
+        obj
+    }
+
+    #[test]
+    fn rgb_planar_configuration() {
+        let interleaved = image(
+            "RGB",
+            3,
+            0,
+            vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 10, 20, 30],
+        );
+        let planar = image(
+            "RGB",
+            3,
+            1,
+            vec![255, 0, 0, 10, 0, 255, 0, 20, 0, 0, 255, 30],
+        );
+        let a = render_rgb(&interleaved).unwrap();
+        let b = render_rgb(&planar).unwrap();
+        assert_eq!(a.data(), b.data());
+        assert_eq!(&a.data()[9..], &[10, 20, 30]);
+        assert_eq!(a.pixel_at(1, 1).unwrap(), 10);
+    }
+
+    #[test]
+    fn ybr_full_and_422() {
+        let full = image(
+            "YBR_FULL",
+            3,
+            0,
+            vec![128, 128, 128, 76, 85, 255, 150, 44, 21, 255, 128, 128],
+        );
+        let rgb = render_rgb(&full).unwrap();
+        assert_eq!(&rgb.data()[..3], &[128, 128, 128]);
+        // pure red and green, within rounding of the encoded values
+        assert!(rgb.data()[3] > 250 && rgb.data()[4] < 3 && rgb.data()[5] < 3);
+        assert!(rgb.data()[6] < 3 && rgb.data()[7] > 250 && rgb.data()[8] < 3);
+        assert_eq!(&rgb.data()[9..], &[255, 255, 255]);
+
+        // two pixels per group: Y1 Y2 Cb Cr
+        let subsampled = image("YBR_FULL_422", 3, 0, vec![0, 255, 128, 128, 76, 76, 85, 255]);
+        let (module, native) = decode_pixel_data(&subsampled, &ExplicitVRLittleEndian).unwrap();
+        assert_eq!(module.photometric_interpretation, "YBR_FULL");
+        assert_eq!(
+            native.samples_f64(),
+            vec![0., 128., 128., 255., 128., 128., 76., 85., 255., 76., 85., 255.]
+        );
+        let rgb = to_rgb8(&module, &native, None).unwrap();
+        assert_eq!(&rgb.data()[..6], &[0, 0, 0, 255, 255, 255]);
+        assert_eq!(rgb.data()[6..9], rgb.data()[9..]);
+
+        let odd = image("YBR_FULL_422", 3, 1, vec![0; 8]);
+        assert!(decode_pixel_data(&odd, &ExplicitVRLittleEndian).is_err());
+    }
+
+    #[test]
+    fn palette_color() {
+        let mut obj = image("PALETTE COLOR", 1, 0, vec![0, 1, 2, 7]);
+        for &(descriptor, data, values) in &[
+            (0x1101, 0x1201, [0xFFFF, 0x0000, 0x0000]),
+            (0x1102, 0x1202, [0x0000, 0xFFFF, 0x0000]),
+            (0x1103, 0x1203, [0x0000, 0x0000, 0x8080]),
+        ] {
+            put(
+                &mut obj,
+                Tag(0x0028, descriptor),
+                VR::US,
+                PrimitiveValue::U16(vec![3, 0, 16]),
+            );
+            put(
+                &mut obj,
+                Tag(0x0028, data),
+                VR::OW,
+                PrimitiveValue::U16(values.to_vec()),
+            );
+        }
+        let rgb = render_rgb(&obj).unwrap();
+        assert_eq!(rgb.samples_per_pixel(), 3);
+        assert_eq!(
+            rgb.data(),
+            &[255, 0, 0, 0, 255, 0, 0, 0, 128, 0, 0, 128][..]
+        );
+
+        // 8-bit entries packed in words
+        put(
+            &mut obj,
+            Tag(0x0028, 0x1101),
+            VR::US,
+            PrimitiveValue::U16(vec![4, 0, 8]),
+        );
+        put(
+            &mut obj,
+            Tag(0x0028, 0x1201),
+            VR::OW,
+            PrimitiveValue::U16(vec![0x4010, 0xFF80]),
+        );
+        let palette = Palette::from_object(&obj, false).unwrap();
+        assert_eq!(palette.red.data, vec![0x10, 0x40, 0x80, 0xFF]);
+        assert_eq!(palette.get(3.)[0], 255);
+
+        let obj = image("PALETTE COLOR", 1, 0, vec![0; 4]);
+        assert!(render_rgb(&obj).is_err());
+    }
+
+    #[test]
+    fn monochrome_inversion() {
+        let mono2 = image("MONOCHROME2", 1, 0, vec![0, 51, 204, 255]);
+        let mono1 = image("MONOCHROME1", 1, 0, vec![0, 51, 204, 255]);
+        let (module, native) = decode_pixel_data(&mono2, &ExplicitVRLittleEndian).unwrap();
+        let gray = to_normalized_grayscale(&module, &native).unwrap();
+        assert_eq!(gray.data(), &[0., 0.2, 0.8, 1.][..]);
+
+        let (module, native) = decode_pixel_data(&mono1, &ExplicitVRLittleEndian).unwrap();
+        let gray = to_normalized_grayscale(&module, &native).unwrap();
+        assert_eq!(gray.data(), &[1., 0.8, 0.2, 0.][..]);
+        let rgb = to_rgb8(&module, &native, None).unwrap();
+        assert_eq!(&rgb.data()[..6], &[255, 255, 255, 204, 204, 204]);
+
+        let color = image("RGB", 3, 0, vec![0; 12]);
+        let (module, native) = decode_pixel_data(&color, &ExplicitVRLittleEndian).unwrap();
+        assert!(to_normalized_grayscale(&module, &native).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Export of rendered frames to common image file formats.
+//!
+//! Grayscale images are rendered through the grayscale pipeline of the
+//! object (and thus after VOI windowing), into 8 or 16-bit values. Color
+//! images are converted to 8-bit RGB. Frames can then be written as PNG
+//! images or as netpbm images (PGM for grayscale, PPM for RGB).
+
+use std::io::Write;
+use flate2::Crc;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use dictionary::DataDictionary;
+use error::{Error, Result};
+use object::mem::InMemDicomObject;
+use super::{ImagePixelModule, InMemoryPixelData, NativePixelData, PixelData};
+use super::color::{to_rgb8, Palette};
+use super::lut::GrayscalePipeline;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// A sample type which can be written to an image file.
+pub trait ImageSample: Copy {
+    /// The number of bits of each sample.
+    const BITS: u8;
+
+    /// Write the sample in big endian.
+    fn write_be(self, to: &mut Vec<u8>);
+}
+
+impl ImageSample for u8 {
+    const BITS: u8 = 8;
+
+    fn write_be(self, to: &mut Vec<u8>) {
+        to.push(self);
+    }
+}
+
+impl ImageSample for u16 {
+    const BITS: u8 = 16;
+
+    fn write_be(self, to: &mut Vec<u8>) {
+        to.push((self >> 8) as u8);
+        to.push(self as u8);
+    }
+}
+
+/// Retrieve the samples of a frame in big endian, checking that the pixel
+/// data is grayscale or RGB and not empty.
+fn frame_bytes<P: ImageSample>(pixels: &InMemoryPixelData<P>, frame: u32) -> Result<Vec<u8>> {
+    match pixels.samples_per_pixel() {
+        1 | 3 => {}
+        _ => {
+            return Err(Error::InvalidImagePixelModule(
+                "only grayscale and RGB images can be exported",
+            ))
+        }
+    }
+    if pixels.rows() == 0 || pixels.columns() == 0 {
+        return Err(Error::InvalidImagePixelModule(
+            "images without rows or columns cannot be exported",
+        ));
+    }
+    let data = pixels.frame_data(frame)?;
+    let mut out = Vec::with_capacity(data.len() * usize::from(P::BITS / 8));
+    for &v in data {
+        v.write_be(&mut out);
+    }
+    Ok(out)
+}
+
+/// Write a frame of grayscale or RGB pixel data as a PNG image.
+pub fn write_png<W, P>(mut to: W, pixels: &InMemoryPixelData<P>, frame: u32) -> Result<()>
+where
+    W: Write,
+    P: ImageSample,
+{
+    let data = frame_bytes(pixels, frame)?;
+    let color_type = if pixels.samples_per_pixel() == 1 { 0 } else { 2 };
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&u32_be(pixels.columns()));
+    header.extend_from_slice(&u32_be(pixels.rows()));
+    // bit depth, color type, compression, filter and interlace methods
+    header.extend_from_slice(&[P::BITS, color_type, 0, 0, 0]);
+
+    // each row starts with its filter type (none)
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let row_length = data.len() / pixels.rows() as usize;
+    for row in data.chunks(row_length) {
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let compressed = encoder.finish()?;
+
+    to.write_all(&PNG_SIGNATURE)?;
+    write_png_chunk(&mut to, b"IHDR", &header)?;
+    write_png_chunk(&mut to, b"IDAT", &compressed)?;
+    write_png_chunk(&mut to, b"IEND", &[])?;
+    Ok(())
+}
+
+fn write_png_chunk<W: Write>(to: &mut W, chunk_type: &[u8; 4], data: &[u8]) -> Result<()> {
+    let mut crc = Crc::new();
+    crc.update(chunk_type);
+    crc.update(data);
+    to.write_all(&u32_be(data.len() as u32))?;
+    to.write_all(chunk_type)?;
+    to.write_all(data)?;
+    to.write_all(&u32_be(crc.sum()))?;
+    Ok(())
+}
+
+fn u32_be(v: u32) -> [u8; 4] {
+    [(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, v as u8]
+}
+
+/// Write a frame of pixel data as a binary netpbm image: PGM if it is
+/// grayscale, PPM if it is RGB.
+pub fn write_pnm<W, P>(mut to: W, pixels: &InMemoryPixelData<P>, frame: u32) -> Result<()>
+where
+    W: Write,
+    P: ImageSample,
+{
+    let data = frame_bytes(pixels, frame)?;
+    let magic = if pixels.samples_per_pixel() == 1 { "P5" } else { "P6" };
+    let max_value = (1u32 << P::BITS) - 1;
+    write!(
+        to,
+        "{}\n{} {}\n{}\n",
+        magic,
+        pixels.columns(),
+        pixels.rows(),
+        max_value
+    )?;
+    to.write_all(&data)?;
+    Ok(())
+}
+
+/// Pixel data rendered for display, ready to be exported.
+#[derive(Debug)]
+pub enum DisplayImage {
+    /// 8-bit grayscale
+    Gray8(InMemoryPixelData<u8>),
+    /// 16-bit grayscale
+    Gray16(InMemoryPixelData<u16>),
+    /// 8-bit RGB
+    Rgb8(InMemoryPixelData<u8>),
+}
+
+impl DisplayImage {
+    /// Render pixel data for display, using the attributes of the object
+    /// it was retrieved from. The module must describe the pixel data as it
+    /// is in memory (see `decode_pixel_data`). Grayscale images are rendered
+    /// with 16 bits per sample if `sixteen_bit` is true.
+    pub fn render<D>(
+        obj: &InMemDicomObject<D>,
+        module: &ImagePixelModule,
+        pixels: &NativePixelData,
+        sixteen_bit: bool,
+    ) -> Result<DisplayImage>
+    where
+        D: DataDictionary + Clone,
+    {
+        match module.photometric_interpretation.as_str() {
+            "MONOCHROME1" | "MONOCHROME2" => {
+                let pipeline = GrayscalePipeline::from_object(obj)?;
+                if sixteen_bit {
+                    pipeline.render_u16(pixels).map(DisplayImage::Gray16)
+                } else {
+                    pipeline.render(pixels).map(DisplayImage::Gray8)
+                }
+            }
+            "PALETTE COLOR" => {
+                let palette = Palette::from_object(obj, module.pixel_representation == 1)?;
+                to_rgb8(module, pixels, Some(&palette)).map(DisplayImage::Rgb8)
+            }
+            _ => to_rgb8(module, pixels, None).map(DisplayImage::Rgb8),
+        }
+    }
+
+    /// Retrieve the number of frames.
+    pub fn number_of_frames(&self) -> u32 {
+        match *self {
+            DisplayImage::Gray8(ref p) | DisplayImage::Rgb8(ref p) => p.number_of_frames(),
+            DisplayImage::Gray16(ref p) => p.number_of_frames(),
+        }
+    }
+
+    /// Write a frame as a PNG image.
+    pub fn write_png<W: Write>(&self, to: W, frame: u32) -> Result<()> {
+        match *self {
+            DisplayImage::Gray8(ref p) | DisplayImage::Rgb8(ref p) => write_png(to, p, frame),
+            DisplayImage::Gray16(ref p) => write_png(to, p, frame),
+        }
+    }
+
+    /// Write a frame as a PGM or PPM image.
+    pub fn write_pnm<W: Write>(&self, to: W, frame: u32) -> Result<()> {
+        match *self {
+            DisplayImage::Gray8(ref p) | DisplayImage::Rgb8(ref p) => write_pnm(to, p, frame),
+            DisplayImage::Gray16(ref p) => write_pnm(to, p, frame),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_png, write_pnm, DisplayImage};
+    use data::{Tag, VR};
+    use data::value::PrimitiveValue;
+    use flate2::read::ZlibDecoder;
+    use object::mem::InMemDicomObject;
+    use object::pixeldata::{decode_pixel_data, InMemoryPixelData};
+    use object::pixeldata::testing::{put, put_image_module};
+    use std::io::Read;
+    use transfer_syntax::ExplicitVRLittleEndian;
+
+    /// Split a PNG file into its chunks, as (type, data) pairs.
+    fn png_chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut chunks = Vec::new();
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = png[pos..pos + 4]
+                .iter()
+                .fold(0usize, |acc, &b| (acc << 8) | usize::from(b));
+            let chunk_type = String::from_utf8(png[pos + 4..pos + 8].to_vec()).unwrap();
+            chunks.push((chunk_type, png[pos + 8..pos + 8 + len].to_vec()));
+            pos += 12 + len;
+        }
+        chunks
+    }
+
+    #[test]
+    fn png_gray16_and_rgb8() {
+        let gray = InMemoryPixelData::new(vec![0u16, 0x1234, 0xFFFF, 7], 2, 2, 16, 1).unwrap();
+        let mut png = Vec::new();
+        write_png(&mut png, &gray, 0).unwrap();
+        // the CRC of an empty IEND chunk is well known
+        assert_eq!(&png[png.len() - 4..], &[0xAE, 0x42, 0x60, 0x82]);
+        let chunks = png_chunks(&png);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].0, "IHDR");
+        assert_eq!(chunks[0].1, vec![0, 0, 0, 2, 0, 0, 0, 2, 16, 0, 0, 0, 0]);
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&chunks[1].1[..])
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw, vec![0, 0, 0, 0x12, 0x34, 0, 0xFF, 0xFF, 0, 7]);
+
+        let rgb = InMemoryPixelData::new_with_frames(vec![9u8; 12], 2, 1, 2, 8, 3).unwrap();
+        let mut png = Vec::new();
+        write_png(&mut png, &rgb, 1).unwrap();
+        let chunks = png_chunks(&png);
+        assert_eq!(&chunks[0].1[8..10], &[8, 2]);
+        assert!(write_png(&mut Vec::new(), &rgb, 2).is_err());
+    }
+
+    #[test]
+    fn pgm_and_ppm() {
+        let gray = InMemoryPixelData::new(vec![1u8, 2, 3, 4, 5, 6], 2, 3, 8, 1).unwrap();
+        let mut pgm = Vec::new();
+        write_pnm(&mut pgm, &gray, 0).unwrap();
+        assert_eq!(pgm, b"P5\n3 2\n255\n\x01\x02\x03\x04\x05\x06".to_vec());
+
+        let rgb = InMemoryPixelData::new(vec![0x0102u16, 0x0304, 0x0506], 1, 1, 16, 3).unwrap();
+        let mut ppm = Vec::new();
+        write_pnm(&mut ppm, &rgb, 0).unwrap();
+        assert_eq!(ppm, b"P6\n1 1\n65535\n\x01\x02\x03\x04\x05\x06".to_vec());
+    }
+
+    #[test]
+    fn reject_empty_images() {
+        let no_rows = InMemoryPixelData::new(Vec::<u8>::new(), 0, 2, 8, 1).unwrap();
+        assert!(write_png(&mut Vec::new(), &no_rows, 0).is_err());
+        assert!(write_pnm(&mut Vec::new(), &no_rows, 0).is_err());
+        let no_columns = InMemoryPixelData::new(Vec::<u16>::new(), 2, 0, 16, 3).unwrap();
+        assert!(write_png(&mut Vec::new(), &no_columns, 0).is_err());
+    }
+
+    #[test]
+    fn render_windowed_image() {
+        let mut obj = InMemDicomObject::create_empty();
+        put_image_module(&mut obj, "MONOCHROME2", [1, 3], [1, 16, 12, 11, 0]);
+        put(&mut obj, Tag(0x0028, 0x1050), VR::DS, PrimitiveValue::F64(vec![100.5]));
+        put(&mut obj, Tag(0x0028, 0x1051), VR::DS, PrimitiveValue::F64(vec![101.]));
+        put(&mut obj, Tag(0x7FE0, 0x0010), VR::OW, PrimitiveValue::U16(vec![0, 100, 4000]));
+
+        let (module, pixels) = decode_pixel_data(&obj, &ExplicitVRLittleEndian).unwrap();
+        let image = DisplayImage::render(&obj, &module, &pixels, false).unwrap();
+        let mut pgm = Vec::new();
+        image.write_pnm(&mut pgm, 0).unwrap();
+        assert_eq!(&pgm[pgm.len() - 3..], &[0, 128, 255]);
+
+        let image = DisplayImage::render(&obj, &module, &pixels, true).unwrap();
+        assert_eq!(image.number_of_frames(), 1);
+        match image {
+            DisplayImage::Gray16(ref p) => assert_eq!(p.data(), &[0, 32_768, 65_535][..]),
+            _ => panic!("expected a 16-bit grayscale image"),
+        }
+    }
+}
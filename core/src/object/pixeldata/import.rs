@@ -0,0 +1,305 @@
+//! Import of raster images into pixel data.
+//!
+//! PNG images are read into 8 or 16-bit grayscale or RGB pixel data, which
+//! can then be stored in a new DICOM object (see the `capture` module).
+//! Alpha channels are discarded, and palette images are expanded to RGB.
+//! Interlaced images and bit depths below 8 are not supported.
+
+use std::io::Read;
+use flate2::Crc;
+use flate2::read::ZlibDecoder;
+use error::{Error, Result};
+use super::{InMemoryPixelData, NativePixelData};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+fn error(message: &'static str) -> Error {
+    Error::PixelDataDecode(message)
+}
+
+/// The contents of the IHDR chunk which matter for decoding.
+#[derive(Debug)]
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    /// The number of channels stored for each pixel.
+    fn channels(&self) -> usize {
+        match self.color_type {
+            0 | 3 => 1,
+            4 => 2,
+            2 => 3,
+            _ => 4,
+        }
+    }
+}
+
+fn u32_be(b: &[u8]) -> u32 {
+    b.iter().fold(0, |acc, &v| (acc << 8) | u32::from(v))
+}
+
+/// Read a PNG image into pixel data.
+pub fn read_png<R: Read>(mut source: R) -> Result<NativePixelData> {
+    let mut png = Vec::new();
+    source.read_to_end(&mut png)?;
+    if png.len() < 8 || png[..8] != PNG_SIGNATURE {
+        return Err(error("not a PNG image"));
+    }
+
+    let mut header = None;
+    let mut palette = None;
+    let mut compressed = Vec::new();
+    let mut pos = 8;
+    loop {
+        let chunk = png.get(pos..pos + 8).ok_or_else(|| error("PNG image is truncated"))?;
+        let len = u32_be(&chunk[..4]) as usize;
+        let chunk_type = &png[pos + 4..pos + 8];
+        let data = png.get(pos + 8..pos + 8 + len)
+            .ok_or_else(|| error("PNG image is truncated"))?;
+        let crc = png.get(pos + 8 + len..pos + 12 + len)
+            .ok_or_else(|| error("PNG image is truncated"))?;
+        let mut expected = Crc::new();
+        expected.update(chunk_type);
+        expected.update(data);
+        if expected.sum() != u32_be(crc) {
+            return Err(error("bad PNG chunk checksum"));
+        }
+        pos += 12 + len;
+
+        match chunk_type {
+            b"IHDR" => {
+                if data.len() != 13 {
+                    return Err(error("bad PNG header"));
+                }
+                if data[10] != 0 || data[11] != 0 {
+                    return Err(error("unsupported PNG compression or filter method"));
+                }
+                if data[12] != 0 {
+                    return Err(error("interlaced PNG images are not supported"));
+                }
+                let ihdr = Header {
+                    width: u32_be(&data[0..4]),
+                    height: u32_be(&data[4..8]),
+                    bit_depth: data[8],
+                    color_type: data[9],
+                };
+                match (ihdr.color_type, ihdr.bit_depth) {
+                    (0, 8) | (0, 16) | (2, 8) | (2, 16) | (3, 8) | (4, 8) | (4, 16)
+                    | (6, 8) | (6, 16) => {}
+                    _ => return Err(error("unsupported PNG color type or bit depth")),
+                }
+                if ihdr.width == 0 || ihdr.height == 0 || ihdr.width > 0xFFFF
+                    || ihdr.height > 0xFFFF
+                {
+                    return Err(error("unsupported PNG image dimensions"));
+                }
+                header = Some(ihdr);
+            }
+            b"PLTE" => palette = Some(data.to_vec()),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+    let header = header.ok_or_else(|| error("missing PNG header"))?;
+
+    let bytes = usize::from(header.bit_depth / 8);
+    let stride = header.channels() * bytes;
+    let row_length = header.width as usize * stride;
+    let raw_length = (row_length + 1) * header.height as usize;
+    // inflate no more than the image needs, whatever the stream declares
+    let mut raw = Vec::new();
+    ZlibDecoder::new(&compressed[..])
+        .take(raw_length as u64)
+        .read_to_end(&mut raw)?;
+    if raw.len() < raw_length {
+        return Err(error("PNG image data is too short"));
+    }
+    let data = unfilter(&raw, row_length, header.height as usize, stride)?;
+
+    // keep the gray or RGB channels of each pixel
+    let (rows, columns) = (header.height, header.width);
+    let color = header.color_type == 2 || header.color_type == 6;
+    let kept = if color { 3 } else { 1 };
+    if header.color_type == 3 {
+        let palette = palette.ok_or_else(|| error("missing PNG palette"))?;
+        let rgb = data.iter()
+            .map(|&i| {
+                palette
+                    .get(usize::from(i) * 3..usize::from(i) * 3 + 3)
+                    .ok_or_else(|| error("PNG palette index out of range"))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .concat();
+        return Ok(NativePixelData::U8(InMemoryPixelData::new(
+            rgb, rows, columns, 8, 3,
+        )?));
+    }
+    let samples = data.chunks(stride).flat_map(|p| p[..kept * bytes].to_vec());
+    Ok(if bytes == 1 {
+        NativePixelData::U8(InMemoryPixelData::new(
+            samples.collect(),
+            rows,
+            columns,
+            8,
+            kept as u16,
+        )?)
+    } else {
+        let samples: Vec<u8> = samples.collect();
+        NativePixelData::U16(InMemoryPixelData::new(
+            samples
+                .chunks(2)
+                .map(|b| u16::from(b[0]) << 8 | u16::from(b[1]))
+                .collect(),
+            rows,
+            columns,
+            16,
+            kept as u16,
+        )?)
+    })
+}
+
+/// Reverse the filter of each row of PNG image data, where `stride` is the
+/// number of bytes of each pixel.
+fn unfilter(raw: &[u8], row_length: usize, rows: usize, stride: usize) -> Result<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::with_capacity(row_length * rows);
+    for (r, row) in raw.chunks(row_length + 1).take(rows).enumerate() {
+        let (filter, row) = (row[0], &row[1..]);
+        let start = out.len();
+        for (i, &x) in row.iter().enumerate() {
+            let a = if i >= stride { out[start + i - stride] } else { 0 };
+            let b = if r > 0 { out[start + i - row_length] } else { 0 };
+            let c = if r > 0 && i >= stride {
+                out[start + i - row_length - stride]
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(error("bad PNG filter type")),
+            };
+            out.push(x.wrapping_add(predictor));
+        }
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_png, unfilter};
+
+    #[test]
+    fn unfilter_rows() {
+        // 2 rows of 2 RGB pixels, with the sub and up filters
+        let raw = [
+            1, 10, 20, 30, 5, 5, 5, //
+            2, 1, 1, 1, 1, 1, 1, //
+        ];
+        assert_eq!(
+            unfilter(&raw, 6, 2, 3).unwrap(),
+            vec![10, 20, 30, 15, 25, 35, 11, 21, 31, 16, 26, 36]
+        );
+        let raw = [3, 10, 20, 4, 1, 2];
+        assert_eq!(unfilter(&raw, 2, 1, 1).unwrap(), vec![10, 25]);
+        let raw = [0, 10, 20, 4, 1, 2];
+        // on a vertical gradient, paeth predicts from the pixel above
+        assert_eq!(unfilter(&raw, 2, 2, 1).unwrap(), vec![10, 20, 11, 22]);
+        assert!(unfilter(&[7, 0], 1, 1, 1).is_err());
+    }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn png_round_trip() {
+        use object::pixeldata::{InMemoryPixelData, NativePixelData, PixelData};
+        use object::pixeldata::export::write_png;
+
+        let gray =
+            InMemoryPixelData::new(vec![0u16, 0x1234, 0xFFFF, 7, 8, 9], 2, 3, 16, 1).unwrap();
+        let mut png = Vec::new();
+        write_png(&mut png, &gray, 0).unwrap();
+        match read_png(&png[..]).unwrap() {
+            NativePixelData::U16(p) => {
+                assert_eq!((p.rows(), p.columns(), p.samples_per_pixel()), (2, 3, 1));
+                assert_eq!(p.data(), gray.data());
+            }
+            p => panic!("unexpected pixel data {:?}", p),
+        }
+
+        let rgb = InMemoryPixelData::new((0..12).collect::<Vec<u8>>(), 1, 4, 8, 3).unwrap();
+        let mut png = Vec::new();
+        write_png(&mut png, &rgb, 0).unwrap();
+        match read_png(&png[..]).unwrap() {
+            NativePixelData::U8(p) => assert_eq!(p.data(), rgb.data()),
+            p => panic!("unexpected pixel data {:?}", p),
+        }
+
+        png[20] ^= 1;
+        assert!(read_png(&png[..]).is_err());
+        assert!(read_png(&b"GIF89a"[..]).is_err());
+    }
+
+    #[test]
+    fn inflate_no_more_than_the_image() {
+        use std::io::Write;
+        use flate2::{Compression, Crc};
+        use flate2::write::ZlibEncoder;
+        use object::pixeldata::{NativePixelData, PixelData};
+
+        fn chunk(png: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+            let mut crc = Crc::new();
+            crc.update(chunk_type);
+            crc.update(data);
+            let len = data.len() as u32;
+            png.extend_from_slice(&[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8]);
+            png.push(len as u8);
+            png.extend_from_slice(chunk_type);
+            png.extend_from_slice(data);
+            let sum = crc.sum();
+            png.extend_from_slice(&[(sum >> 24) as u8, (sum >> 16) as u8, (sum >> 8) as u8]);
+            png.push(sum as u8);
+        }
+
+        // a 1x1 grayscale image whose data inflates to 16 MiB
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        let zeros = vec![0u8; 1 << 20];
+        for _ in 0..16 {
+            encoder.write_all(&zeros).unwrap();
+        }
+        let compressed = encoder.finish().unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]);
+        chunk(&mut png, b"IDAT", &compressed);
+        chunk(&mut png, b"IEND", &[]);
+        match read_png(&png[..]).unwrap() {
+            NativePixelData::U8(p) => {
+                assert_eq!((p.rows(), p.columns()), (1, 1));
+                assert_eq!(p.data(), &[0]);
+            }
+            p => panic!("unexpected pixel data {:?}", p),
+        }
+    }
+}
@@ -0,0 +1,499 @@
+//! Rendering of grayscale pixel data into 8-bit display values.
+//!
+//! The grayscale pipeline has three stages, each of them described by
+//! attributes of the object:
+//!
+//! 1. the modality LUT, which maps stored values to modality values
+//!    (e.g. Hounsfield units), either with a linear rescale or a lookup table;
+//! 2. the VOI LUT, which selects the range of modality values of interest,
+//!    either with a window or a lookup table;
+//! 3. the presentation LUT shape, which may invert the output.
+
+use data::Tag;
+use data::value::PrimitiveValue;
+use dictionary::DataDictionary;
+use error::{Error, Result};
+use object::mem::InMemDicomObject;
+use super::{first_item, get_code, get_f64s, native_pixel_data, ImagePixelModule,
+            InMemoryPixelData, NativePixelData};
+
+const RESCALE_INTERCEPT: Tag = Tag(0x0028, 0x1052);
+const RESCALE_SLOPE: Tag = Tag(0x0028, 0x1053);
+const WINDOW_CENTER: Tag = Tag(0x0028, 0x1050);
+const WINDOW_WIDTH: Tag = Tag(0x0028, 0x1051);
+const VOI_LUT_FUNCTION: Tag = Tag(0x0028, 0x1056);
+const MODALITY_LUT_SEQUENCE: Tag = Tag(0x0028, 0x3000);
+const LUT_DESCRIPTOR: Tag = Tag(0x0028, 0x3002);
+const LUT_DATA: Tag = Tag(0x0028, 0x3006);
+const VOI_LUT_SEQUENCE: Tag = Tag(0x0028, 0x3010);
+const PRESENTATION_LUT_SHAPE: Tag = Tag(0x2050, 0x0020);
+
+/// A lookup table, as described by a LUT Descriptor and LUT Data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut {
+    /// The input value mapped to the first entry.
+    pub first_mapped: i32,
+    /// The number of bits of each entry.
+    pub bits: u16,
+    /// The table entries.
+    pub data: Vec<u16>,
+}
+
+impl Lut {
+    /// Read a lookup table from an item of a LUT sequence. The first value
+    /// mapped is read as a signed value if `signed` is true.
+    pub fn from_item<D>(item: &InMemDicomObject<D>, signed: bool) -> Result<Lut>
+    where
+        D: DataDictionary + Clone,
+    {
+        Lut::from_elements(item, LUT_DESCRIPTOR, LUT_DATA, signed)
+    }
+
+    /// Read a lookup table from the given descriptor and data elements of an
+    /// object, such as the Red Palette Color Lookup Table Descriptor and
+    /// Data. Tables of 8-bit entries may have two entries packed in each
+    /// 16-bit word of the data.
+    pub fn from_elements<D>(
+        obj: &InMemDicomObject<D>,
+        descriptor_tag: Tag,
+        data_tag: Tag,
+        signed: bool,
+    ) -> Result<Lut>
+    where
+        D: DataDictionary + Clone,
+    {
+        let descriptor: Vec<u16> = match obj.element(descriptor_tag)?.value().primitive() {
+            Some(&PrimitiveValue::U16(ref v)) => v.clone(),
+            Some(&PrimitiveValue::I16(ref v)) => v.iter().map(|&x| x as u16).collect(),
+            _ => return Err(Error::InvalidImagePixelModule("bad LUT descriptor")),
+        };
+        if descriptor.len() != 3 || descriptor[2] == 0 || descriptor[2] > 16 {
+            return Err(Error::InvalidImagePixelModule("bad LUT descriptor"));
+        }
+        let entries = if descriptor[0] == 0 {
+            65_536
+        } else {
+            usize::from(descriptor[0])
+        };
+        let first_mapped = if signed {
+            i32::from(descriptor[1] as i16)
+        } else {
+            i32::from(descriptor[1])
+        };
+
+        let mut data: Vec<u16> = match obj.element(data_tag)?.value().primitive() {
+            Some(&PrimitiveValue::U16(ref v)) if descriptor[2] == 8 && v.len() < entries => v
+                .iter()
+                .flat_map(|&x| vec![x & 0xFF, x >> 8])
+                .collect(),
+            Some(&PrimitiveValue::U16(ref v)) => v.clone(),
+            Some(&PrimitiveValue::I16(ref v)) => v.iter().map(|&x| x as u16).collect(),
+            Some(&PrimitiveValue::U8(ref v)) => v.iter().map(|&x| u16::from(x)).collect(),
+            _ => return Err(Error::InvalidImagePixelModule("bad LUT data")),
+        };
+        if data.len() < entries {
+            return Err(Error::InvalidImagePixelModule("LUT data is too short"));
+        }
+        data.truncate(entries);
+        Ok(Lut {
+            first_mapped,
+            bits: descriptor[2],
+            data,
+        })
+    }
+
+    /// Look up a value. Values out of the table's range are clamped to the
+    /// first or last entry.
+    pub fn get(&self, v: f64) -> f64 {
+        let last = self.data.len() as i64 - 1;
+        let i = (v.round() as i64 - i64::from(self.first_mapped)).clamp(0, last);
+        f64::from(self.data[i as usize])
+    }
+
+    /// Retrieve the maximum value an entry may have.
+    pub fn max_value(&self) -> f64 {
+        f64::from((1u32 << self.bits) - 1)
+    }
+}
+
+/// The modality LUT stage of the pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModalityLut {
+    /// A linear transformation, given by the Rescale Slope and Rescale Intercept.
+    Rescale {
+        /// Rescale Slope (0028,1053)
+        slope: f64,
+        /// Rescale Intercept (0028,1052)
+        intercept: f64,
+    },
+    /// A lookup table from the Modality LUT Sequence.
+    Lut(Lut),
+}
+
+impl ModalityLut {
+    /// Obtain the modality value of a stored value.
+    pub fn apply(&self, v: f64) -> f64 {
+        match *self {
+            ModalityLut::Rescale { slope, intercept } => v * slope + intercept,
+            ModalityLut::Lut(ref lut) => lut.get(v),
+        }
+    }
+}
+
+/// The function used to map a window of values to the output range,
+/// as in VOI LUT Function (0028,1056).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiLutFunction {
+    /// LINEAR, the default function
+    Linear,
+    /// LINEAR_EXACT
+    LinearExact,
+    /// SIGMOID
+    Sigmoid,
+}
+
+/// The VOI LUT stage of the pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VoiLut {
+    /// A window, given by the Window Center and Window Width.
+    Window {
+        /// Window Center (0028,1050)
+        center: f64,
+        /// Window Width (0028,1051)
+        width: f64,
+        /// VOI LUT Function (0028,1056)
+        function: VoiLutFunction,
+    },
+    /// A lookup table from the VOI LUT Sequence.
+    Lut(Lut),
+    /// No VOI transformation is defined: the full range of modality values
+    /// in the image is used.
+    Auto,
+}
+
+impl VoiLut {
+    /// Map a modality value to the range from 0 to 1.
+    /// Not applicable to `Auto`, which is resolved to a window first.
+    fn apply(&self, x: f64) -> f64 {
+        match *self {
+            VoiLut::Window {
+                center,
+                width,
+                function: VoiLutFunction::Linear,
+            } => {
+                let width = width.max(1.);
+                let low = center - 0.5 - (width - 1.) / 2.;
+                let high = center - 0.5 + (width - 1.) / 2.;
+                if x <= low {
+                    0.
+                } else if x > high {
+                    1.
+                } else if width <= 1. {
+                    1.
+                } else {
+                    (x - (center - 0.5)) / (width - 1.) + 0.5
+                }
+            }
+            VoiLut::Window {
+                center,
+                width,
+                function: VoiLutFunction::LinearExact,
+            } => {
+                if x <= center - width / 2. {
+                    0.
+                } else if x > center + width / 2. {
+                    1.
+                } else {
+                    (x - center) / width + 0.5
+                }
+            }
+            VoiLut::Window {
+                center,
+                width,
+                function: VoiLutFunction::Sigmoid,
+            } => 1. / (1. + (-4. * (x - center) / width).exp()),
+            VoiLut::Lut(ref lut) => lut.get(x) / lut.max_value(),
+            VoiLut::Auto => x,
+        }
+    }
+}
+
+/// The shape of the presentation LUT, as in Presentation LUT Shape (2050,0020).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentationLutShape {
+    /// Minimum values are displayed as black.
+    Identity,
+    /// Minimum values are displayed as white.
+    Inverse,
+}
+
+/// The full grayscale rendering pipeline of an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrayscalePipeline {
+    /// The modality LUT stage
+    pub modality: ModalityLut,
+    /// The VOI LUT stage
+    pub voi: VoiLut,
+    /// The presentation LUT stage
+    pub presentation: PresentationLutShape,
+}
+
+impl GrayscalePipeline {
+    /// Build the pipeline described by the attributes of an object.
+    ///
+    /// When more than one window is defined, the first one is used.
+    /// The presentation LUT shape is inverse for MONOCHROME1 images,
+    /// unless the object says otherwise.
+    pub fn from_object<D>(obj: &InMemDicomObject<D>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        let module = ImagePixelModule::from_object(obj)?;
+        let signed = module.pixel_representation == 1;
+
+        let modality = match first_item(obj, MODALITY_LUT_SEQUENCE)? {
+            Some(item) => ModalityLut::Lut(Lut::from_item(item, signed)?),
+            None => {
+                let first = |v: Option<Vec<f64>>| v.and_then(|v| v.first().cloned());
+                ModalityLut::Rescale {
+                    slope: first(get_f64s(obj, RESCALE_SLOPE)?).unwrap_or(1.),
+                    intercept: first(get_f64s(obj, RESCALE_INTERCEPT)?).unwrap_or(0.),
+                }
+            }
+        };
+
+        // modality values may be negative even if stored values are not
+        let signed_modality = match modality {
+            ModalityLut::Rescale { intercept, .. } => signed || intercept < 0.,
+            ModalityLut::Lut(_) => false,
+        };
+        let center = get_f64s(obj, WINDOW_CENTER)?;
+        let width = get_f64s(obj, WINDOW_WIDTH)?;
+        let voi = match first_item(obj, VOI_LUT_SEQUENCE)? {
+            Some(item) => VoiLut::Lut(Lut::from_item(item, signed_modality)?),
+            None => match (center, width) {
+                (Some(ref center), Some(ref width)) if !center.is_empty() && !width.is_empty() => {
+                    let function = match get_code(obj, VOI_LUT_FUNCTION)? {
+                        None => VoiLutFunction::Linear,
+                        Some(ref f) if f == "LINEAR" => VoiLutFunction::Linear,
+                        Some(ref f) if f == "LINEAR_EXACT" => VoiLutFunction::LinearExact,
+                        Some(ref f) if f == "SIGMOID" => VoiLutFunction::Sigmoid,
+                        Some(_) => {
+                            return Err(Error::InvalidImagePixelModule("bad VOI LUT function"))
+                        }
+                    };
+                    if width[0] <= 0. {
+                        return Err(Error::InvalidImagePixelModule("bad window width"));
+                    }
+                    VoiLut::Window {
+                        center: center[0],
+                        width: width[0],
+                        function,
+                    }
+                }
+                _ => VoiLut::Auto,
+            },
+        };
+
+        let presentation = match get_code(obj, PRESENTATION_LUT_SHAPE)? {
+            Some(ref shape) if shape == "INVERSE" => PresentationLutShape::Inverse,
+            Some(_) => PresentationLutShape::Identity,
+            None if module.photometric_interpretation == "MONOCHROME1" => {
+                PresentationLutShape::Inverse
+            }
+            None => PresentationLutShape::Identity,
+        };
+
+        Ok(GrayscalePipeline {
+            modality,
+            voi,
+            presentation,
+        })
+    }
+
+    /// Render all frames of grayscale pixel data into display values.
+    pub fn render(&self, pixels: &NativePixelData) -> Result<InMemoryPixelData<u8>> {
+        let data = self.display_values(pixels)?
+            .into_iter()
+            .map(|y| (y * 255.).round() as u8)
+            .collect();
+        InMemoryPixelData::new_with_frames(
+            data,
+            pixels.number_of_frames(),
+            pixels.rows(),
+            pixels.columns(),
+            8,
+            1,
+        )
+    }
+
+    /// Render all frames of grayscale pixel data into 16-bit display values.
+    pub fn render_u16(&self, pixels: &NativePixelData) -> Result<InMemoryPixelData<u16>> {
+        let data = self.display_values(pixels)?
+            .into_iter()
+            .map(|y| (y * 65_535.).round() as u16)
+            .collect();
+        InMemoryPixelData::new_with_frames(
+            data,
+            pixels.number_of_frames(),
+            pixels.rows(),
+            pixels.columns(),
+            16,
+            1,
+        )
+    }
+
+    /// Apply the pipeline to all samples, producing values between 0 and 1.
+    fn display_values(&self, pixels: &NativePixelData) -> Result<Vec<f64>> {
+        if pixels.samples_per_pixel() != 1 {
+            return Err(Error::InvalidImagePixelModule(
+                "only grayscale images can be rendered with a grayscale pipeline",
+            ));
+        }
+        let values: Vec<f64> = pixels
+            .samples_f64()
+            .into_iter()
+            .map(|v| self.modality.apply(v))
+            .collect();
+
+        let voi = match self.voi {
+            VoiLut::Auto => {
+                let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                VoiLut::Window {
+                    center: (min + max + 1.) / 2.,
+                    width: (max - min + 1.).max(1.),
+                    function: VoiLutFunction::Linear,
+                }
+            }
+            ref voi => voi.clone(),
+        };
+
+        Ok(values
+            .into_iter()
+            .map(|x| {
+                let y = voi.apply(x).clamp(0., 1.);
+                match self.presentation {
+                    PresentationLutShape::Identity => y,
+                    PresentationLutShape::Inverse => 1. - y,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Render the native grayscale pixel data of an object into display values,
+/// using the pipeline described by its attributes.
+pub fn render_grayscale<D>(obj: &InMemDicomObject<D>) -> Result<InMemoryPixelData<u8>>
+where
+    D: DataDictionary + Clone,
+{
+    let pipeline = GrayscalePipeline::from_object(obj)?;
+    pipeline.render(&native_pixel_data(obj)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_grayscale, GrayscalePipeline, ModalityLut, PresentationLutShape, VoiLut,
+                VoiLutFunction};
+    use data::{DataElement, Length, Tag, VR};
+    use data::value::{PrimitiveValue, Value};
+    use object::mem::InMemDicomObject;
+    use object::pixeldata::PixelData;
+    use object::pixeldata::testing::{put, put_image_module, Object};
+
+    /// A 2x2 unsigned 16-bit image with the given stored values.
+    fn image(pi: &str, values: Vec<u16>) -> Object {
+        let mut obj = InMemDicomObject::create_empty();
+        put_image_module(&mut obj, pi, [2, 2], [1, 16, 16, 15, 0]);
+        put(&mut obj, Tag(0x7FE0, 0x0010), VR::OW, PrimitiveValue::U16(values));
+        obj
+    }
+
+    fn ct_image() -> Object {
+        let mut obj = image("MONOCHROME2", vec![0, 1024, 1064, 2000]);
+        put(&mut obj, Tag(0x0028, 0x1052), VR::DS, PrimitiveValue::F64(vec![-1024.]));
+        put(&mut obj, Tag(0x0028, 0x1053), VR::DS, PrimitiveValue::F64(vec![1.]));
+        put(&mut obj, Tag(0x0028, 0x1050), VR::DS, PrimitiveValue::Str("40\\60".to_owned()));
+        put(&mut obj, Tag(0x0028, 0x1051), VR::DS, PrimitiveValue::F64(vec![400., 500.]));
+        obj
+    }
+
+    fn lut_item(descriptor: Vec<u16>, data: Vec<u16>) -> Object {
+        let mut item = InMemDicomObject::create_empty();
+        put(&mut item, Tag(0x0028, 0x3002), VR::US, PrimitiveValue::U16(descriptor));
+        put(&mut item, Tag(0x0028, 0x3006), VR::OW, PrimitiveValue::U16(data));
+        item
+    }
+
+    #[test]
+    fn rescale_and_window() {
+        let obj = ct_image();
+        let pipeline = GrayscalePipeline::from_object(&obj).unwrap();
+        assert_eq!(
+            pipeline,
+            GrayscalePipeline {
+                modality: ModalityLut::Rescale {
+                    slope: 1.,
+                    intercept: -1024.,
+                },
+                voi: VoiLut::Window {
+                    center: 40.,
+                    width: 400.,
+                    function: VoiLutFunction::Linear,
+                },
+                presentation: PresentationLutShape::Identity,
+            }
+        );
+        let rendered = render_grayscale(&obj).unwrap();
+        assert_eq!(rendered.bits_per_pixel(), 8);
+        assert_eq!(rendered.to_vector(), vec![0, 102, 128, 255]);
+    }
+
+    #[test]
+    fn window_functions() {
+        let mut obj = ct_image();
+        put(&mut obj, Tag(0x0028, 0x1056), VR::CS, PrimitiveValue::Str("LINEAR_EXACT".into()));
+        assert_eq!(render_grayscale(&obj).unwrap().to_vector(), vec![0, 102, 128, 255]);
+
+        put(&mut obj, Tag(0x0028, 0x1056), VR::CS, PrimitiveValue::Str("SIGMOID ".into()));
+        let rendered = render_grayscale(&obj).unwrap().to_vector();
+        assert_eq!(&rendered[1..3], &[102, 128]);
+        assert!(rendered[0] < 2 && rendered[3] > 253);
+
+        put(&mut obj, Tag(0x0028, 0x1056), VR::CS, PrimitiveValue::Str("CUBIC".into()));
+        assert!(render_grayscale(&obj).is_err());
+    }
+
+    #[test]
+    fn modality_and_voi_lut_sequences() {
+        let mut obj = image("MONOCHROME2", vec![0, 1, 2, 3]);
+        let modality = lut_item(vec![4, 0, 16], vec![999, 1000, 1001, 5000]);
+        let voi = lut_item(vec![3, 1000, 8], vec![10, 128, 255]);
+        for &(tag, ref item) in &[(Tag(0x0028, 0x3000), modality), (Tag(0x0028, 0x3010), voi)] {
+            obj.put(DataElement::new(
+                tag,
+                VR::SQ,
+                Value::Sequence {
+                    items: vec![item.clone()],
+                    size: Length::undefined(),
+                },
+            ));
+        }
+        assert_eq!(render_grayscale(&obj).unwrap().to_vector(), vec![10, 10, 128, 255]);
+    }
+
+    #[test]
+    fn presentation_lut_shape() {
+        let mut obj = image("MONOCHROME1", vec![0, 1, 2, 3]);
+        // no window: the full range of values is used
+        assert_eq!(render_grayscale(&obj).unwrap().to_vector(), vec![255, 170, 85, 0]);
+
+        put(&mut obj, Tag(0x2050, 0x0020), VR::CS, PrimitiveValue::Str("IDENTITY".into()));
+        assert_eq!(render_grayscale(&obj).unwrap().to_vector(), vec![0, 85, 170, 255]);
+
+        let mut obj = ct_image();
+        put(&mut obj, Tag(0x2050, 0x0020), VR::CS, PrimitiveValue::Str("INVERSE".into()));
+        assert_eq!(render_grayscale(&obj).unwrap().to_vector(), vec![255, 153, 127, 0]);
+    }
+}
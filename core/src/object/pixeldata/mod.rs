@@ -0,0 +1,1124 @@
+//! Module for the pixel data trait and implementations.
+//!
+//! In order to facilitate typical pixel data manipulation, this crate
+//! provides a common interface for retrieving that content as an image
+//! or a multi-dimensional array.
+//! The `lut` module renders grayscale pixel data into display values,
+//! and the `color` module converts pixel data into RGB or normalized
+//! grayscale values. With the `export` feature, the `export` module writes
+//! rendered frames to PNG and netpbm images. The `import` module, which is
+//! always available, reads PNG images into pixel data. The `overlay` module
+//! reads overlay planes as bit masks, and the `waveform` module decodes
+//! waveform data into channel samples.
+
+pub mod color;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod import;
+pub mod lut;
+pub mod overlay;
+pub mod waveform;
+
+use byteorder::{ByteOrder, LittleEndian};
+use std::borrow::Cow;
+use std::fmt;
+use data::Tag;
+use data::value::PrimitiveValue;
+use dictionary::DataDictionary;
+use error::{Error, Result};
+use object::mem::InMemDicomObject;
+use transfer_syntax::{FrameDescription, TransferSyntax};
+
+/** Implemented by DICOM pixel data blocks retrieved from objects.
+ *
+ * Pixel data elements typically represent 2D images. This trait provides
+ * access to Pixel Data (7FE0,0010), Overlay Data (60xx,3000), or Waveform Data (5400,1010)
+ * in a similar fashion to a two-dimensional array.
+ * Multi-frame pixel data is a sequence of such arrays, all with the same
+ * dimensions.
+ * `PV` is a type used to represent a pixel.
+ */
+pub trait PixelData<PV> {
+    // TODO this API is a work in progress.
+
+    /// Get the number of rows (height) of the slice.
+    fn rows(&self) -> u32;
+
+    /// Get the number of columns (width) of the slice.
+    fn columns(&self) -> u32;
+
+    /// Get the number of bits used to represent each pixel.
+    fn bits_per_pixel(&self) -> u32;
+
+    /// Retrieve the number of samples (channels) per pixel.
+    fn samples_per_pixel(&self) -> u16;
+
+    /// Retrieve the number of frames.
+    fn number_of_frames(&self) -> u32 {
+        1
+    }
+
+    /// Obtain the pixel value in the given position of the first frame.
+    /// Can return PixelDataOutOfBounds error when the given coordinates
+    /// are out of the slice's boundaries.
+    fn pixel_at(&self, width: u32, height: u32) -> Result<PV>;
+
+    /// Obtain a mutable reference to the pixel value in the given position
+    /// of the first frame.
+    /// Can return PixelDataOutOfBounds error when the given coordinates
+    /// are out of the slice's boundaries.
+    fn pixel_at_mut(&mut self, width: u32, height: u32) -> Result<&mut PV>;
+
+    /// Obtain the pixel value in the given position of a frame.
+    /// Can return PixelDataOutOfBounds error when the frame or the
+    /// coordinates are out of bounds.
+    fn frame_pixel_at(&self, frame: u32, width: u32, height: u32) -> Result<PV> {
+        if frame == 0 {
+            self.pixel_at(width, height)
+        } else {
+            Err(Error::PixelDataOutOfBounds)
+        }
+    }
+}
+
+/// DICOM pixel data that is completely stored in memory.
+/// Pixels are stored in row-major order with no padding, one frame after
+/// the other. When there is more than one sample per pixel, the samples of
+/// each pixel are stored next to each other (color-by-pixel).
+pub struct InMemoryPixelData<P> {
+    data: Vec<P>,
+    frames: u32,
+    rows: u32,
+    cols: u32,
+    bpp: u32,
+    samples: u16,
+}
+
+impl<P: fmt::Debug> fmt::Debug for InMemoryPixelData<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "InMemoryPixelData[data={:?}, frames={}, rows={}, cols={}, bbp={}, samples={}]",
+            &self.data, self.frames, self.rows, self.cols, self.bpp, self.samples
+        )
+    }
+}
+
+impl<P> InMemoryPixelData<P> {
+    /// Create a single-frame slice from its samples, in row-major order
+    /// and interleaved by pixel.
+    ///
+    /// Fails with `PixelDataLength` if the length of `data` is not
+    /// `rows * cols * samples`.
+    pub fn new(data: Vec<P>, rows: u32, cols: u32, bpp: u32, samples: u16) -> Result<Self> {
+        Self::new_with_frames(data, 1, rows, cols, bpp, samples)
+    }
+
+    /// Create multi-frame pixel data from the samples of all frames,
+    /// one frame after the other.
+    ///
+    /// Fails with `PixelDataLength` if the length of `data` is not
+    /// `frames * rows * cols * samples`.
+    pub fn new_with_frames(
+        data: Vec<P>,
+        frames: u32,
+        rows: u32,
+        cols: u32,
+        bpp: u32,
+        samples: u16,
+    ) -> Result<Self> {
+        let expected = frames as usize * rows as usize * cols as usize * samples as usize;
+        if data.len() != expected {
+            return Err(Error::PixelDataLength(expected, data.len()));
+        }
+        Ok(InMemoryPixelData {
+            data,
+            frames,
+            rows,
+            cols,
+            bpp,
+            samples,
+        })
+    }
+
+    /// Obtain a slice of all samples, in the same order as in `to_vector`.
+    pub fn data(&self) -> &[P] {
+        &self.data
+    }
+
+    /// Obtain a slice of the samples of the given frame.
+    pub fn frame_data(&self, frame: u32) -> Result<&[P]> {
+        if frame >= self.frames {
+            return Err(Error::PixelDataOutOfBounds);
+        }
+        let len = self.frame_len();
+        let start = frame as usize * len;
+        Ok(&self.data[start..start + len])
+    }
+
+    /// Obtain a mutable slice of the samples of the given frame.
+    pub fn frame_data_mut(&mut self, frame: u32) -> Result<&mut [P]> {
+        if frame >= self.frames {
+            return Err(Error::PixelDataOutOfBounds);
+        }
+        let len = self.frame_len();
+        let start = frame as usize * len;
+        Ok(&mut self.data[start..start + len])
+    }
+
+    /// Obtain a reference to the sample of the given channel of a pixel
+    /// in the first frame.
+    pub fn sample_at(&self, w: u32, h: u32, sample: u16) -> Result<&P> {
+        self.frame_sample_at(0, w, h, sample)
+    }
+
+    /// Obtain a reference to the sample of the given channel of a pixel
+    /// in the given frame.
+    pub fn frame_sample_at(&self, frame: u32, w: u32, h: u32, sample: u16) -> Result<&P> {
+        self.check_bounds(w, h)?;
+        if sample >= self.samples {
+            return Err(Error::PixelDataOutOfBounds);
+        }
+        let i = (h * self.cols + w) as usize * self.samples as usize + sample as usize;
+        Ok(&self.frame_data(frame)?[i])
+    }
+
+    fn frame_len(&self) -> usize {
+        self.rows as usize * self.cols as usize * self.samples as usize
+    }
+
+    fn check_bounds(&self, w: u32, h: u32) -> Result<()> {
+        if w >= self.cols || h >= self.rows {
+            Err(Error::PixelDataOutOfBounds)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fetch the internal data vector, destroying the slice in the process.
+    pub fn to_vector(self) -> Vec<P> {
+        self.data
+    }
+}
+
+impl<P: Clone> InMemoryPixelData<P> {
+    /// Obtain a copy of a single frame.
+    pub fn frame(&self, frame: u32) -> Result<InMemoryPixelData<P>> {
+        let data = self.frame_data(frame)?.to_vec();
+        InMemoryPixelData::new(data, self.rows, self.cols, self.bpp, self.samples)
+    }
+
+    /// Iterate over copies of each frame.
+    pub fn frames(&self) -> Frames<P> {
+        Frames {
+            pixel_data: self,
+            next: 0,
+        }
+    }
+}
+
+/// An iterator over the frames of in-memory pixel data.
+#[derive(Debug)]
+pub struct Frames<'a, P: 'a> {
+    pixel_data: &'a InMemoryPixelData<P>,
+    next: u32,
+}
+
+impl<'a, P: Clone> Iterator for Frames<'a, P> {
+    type Item = InMemoryPixelData<P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.pixel_data.frame(self.next).ok()?;
+        self.next += 1;
+        Some(frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.pixel_data.frames - self.next) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, P: Clone> ExactSizeIterator for Frames<'a, P> {}
+
+/// Gather the compressed data of each frame in an encapsulated pixel data
+/// element, given its basic offset table and its fragments.
+///
+/// The frame boundaries are taken from the offset table when it is not
+/// empty. Otherwise, each fragment is assumed to be a frame, unless the
+/// object has a single frame, in which case all fragments are joined.
+/// When there are more fragments than frames, a new frame is started at
+/// each fragment beginning with a JPEG or JPEG 2000 start marker, and an
+/// error is returned if these do not match the number of frames.
+pub fn encapsulated_frames(
+    offset_table: &[u32],
+    fragments: &[Vec<u8>],
+    number_of_frames: u32,
+) -> Result<Vec<Vec<u8>>> {
+    let number_of_frames = number_of_frames as usize;
+    if offset_table.is_empty() {
+        if number_of_frames == 1 {
+            return Ok(vec![fragments.concat()]);
+        }
+        if number_of_frames == fragments.len() {
+            return Ok(fragments.to_vec());
+        }
+        return frames_by_start_marker(fragments, number_of_frames);
+    }
+    if offset_table.len() != number_of_frames {
+        return Err(Error::PixelDataDecode(
+            "offset table does not match the number of frames",
+        ));
+    }
+
+    let mut frames: Vec<Vec<u8>> = vec![Vec::new(); number_of_frames];
+    // offsets refer to the first byte of each fragment's item header
+    let mut position = 0u64;
+    for fragment in fragments {
+        let frame = offset_table
+            .iter()
+            .rposition(|&offset| u64::from(offset) <= position)
+            .ok_or(Error::PixelDataDecode("bad offset table"))?;
+        frames[frame].extend_from_slice(fragment);
+        position += 8 + fragment.len() as u64;
+    }
+    Ok(frames)
+}
+
+/// Group fragments into frames, starting a new frame at each fragment that
+/// begins with a start of image (JPEG) or start of codestream (JPEG 2000)
+/// marker.
+fn frames_by_start_marker(
+    fragments: &[Vec<u8>],
+    number_of_frames: usize,
+) -> Result<Vec<Vec<u8>>> {
+    let mut frames: Vec<Vec<u8>> = Vec::with_capacity(number_of_frames);
+    for fragment in fragments {
+        let starts_frame = fragment.starts_with(&[0xFF, 0xD8])
+            || fragment.starts_with(&[0xFF, 0x4F]);
+        match frames.last_mut() {
+            Some(frame) if !starts_frame => frame.extend_from_slice(fragment),
+            None if !starts_frame => {
+                return Err(Error::PixelDataDecode(
+                    "cannot determine frame boundaries without an offset table",
+                ));
+            }
+            _ => frames.push(fragment.clone()),
+        }
+    }
+    if frames.len() != number_of_frames {
+        return Err(Error::PixelDataDecode(
+            "cannot determine frame boundaries without an offset table",
+        ));
+    }
+    Ok(frames)
+}
+
+impl<P> PixelData<P> for InMemoryPixelData<P>
+where
+    P: Clone,
+{
+    fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    fn columns(&self) -> u32 {
+        self.cols
+    }
+
+    fn bits_per_pixel(&self) -> u32 {
+        self.bpp
+    }
+
+    fn samples_per_pixel(&self) -> u16 {
+        self.samples
+    }
+
+    fn number_of_frames(&self) -> u32 {
+        self.frames
+    }
+
+    fn pixel_at(&self, w: u32, h: u32) -> Result<P> {
+        self.check_bounds(w, h).map(move |_| {
+            let i = (h * self.cols + w) as usize * self.samples as usize;
+            self.data[i].clone()
+        })
+    }
+
+    fn pixel_at_mut(&mut self, w: u32, h: u32) -> Result<&mut P> {
+        self.check_bounds(w, h).map(move |_| {
+            let i = (h * self.cols + w) as usize * self.samples as usize;
+            &mut self.data[i]
+        })
+    }
+
+    fn frame_pixel_at(&self, frame: u32, w: u32, h: u32) -> Result<P> {
+        self.frame_sample_at(frame, w, h, 0).map(Clone::clone)
+    }
+}
+
+/// The attributes of the image pixel module which describe how the pixel
+/// data of an object is laid out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePixelModule {
+    /// Samples per Pixel (0028,0002)
+    pub samples_per_pixel: u16,
+    /// Photometric Interpretation (0028,0004), without padding
+    pub photometric_interpretation: String,
+    /// Planar Configuration (0028,0006), or 0 if not present
+    pub planar_configuration: u16,
+    /// Number of Frames (0028,0008), or 1 if not present
+    pub number_of_frames: u32,
+    /// Rows (0028,0010)
+    pub rows: u16,
+    /// Columns (0028,0011)
+    pub columns: u16,
+    /// Bits Allocated (0028,0100)
+    pub bits_allocated: u16,
+    /// Bits Stored (0028,0101)
+    pub bits_stored: u16,
+    /// High Bit (0028,0102)
+    pub high_bit: u16,
+    /// Pixel Representation (0028,0103): 0 for unsigned, 1 for signed
+    pub pixel_representation: u16,
+}
+
+const SAMPLES_PER_PIXEL: Tag = Tag(0x0028, 0x0002);
+const PHOTOMETRIC_INTERPRETATION: Tag = Tag(0x0028, 0x0004);
+const PLANAR_CONFIGURATION: Tag = Tag(0x0028, 0x0006);
+const NUMBER_OF_FRAMES: Tag = Tag(0x0028, 0x0008);
+const ROWS: Tag = Tag(0x0028, 0x0010);
+const COLUMNS: Tag = Tag(0x0028, 0x0011);
+const BITS_ALLOCATED: Tag = Tag(0x0028, 0x0100);
+const BITS_STORED: Tag = Tag(0x0028, 0x0101);
+const HIGH_BIT: Tag = Tag(0x0028, 0x0102);
+const PIXEL_REPRESENTATION: Tag = Tag(0x0028, 0x0103);
+const PIXEL_DATA: Tag = Tag(0x7FE0, 0x0010);
+
+/// Fetch a single unsigned short value, if the element is present.
+fn get_u16<D>(obj: &InMemDicomObject<D>, tag: Tag, message: &'static str) -> Result<Option<u16>>
+where
+    D: DataDictionary + Clone,
+{
+    match obj.element(tag) {
+        Ok(e) => e.value()
+            .primitive()
+            .and_then(PrimitiveValue::uint16)
+            .map(Some)
+            .ok_or(Error::InvalidImagePixelModule(message)),
+        Err(Error::NoSuchDataElement) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Fetch a single unsigned short value of a required element.
+fn require_u16<D>(obj: &InMemDicomObject<D>, tag: Tag, message: &'static str) -> Result<u16>
+where
+    D: DataDictionary + Clone,
+{
+    get_u16(obj, tag, message)?.ok_or(Error::InvalidImagePixelModule(message))
+}
+
+/// Fetch all numeric values of an element, if it is present.
+fn get_f64s<D>(obj: &InMemDicomObject<D>, tag: Tag) -> Result<Option<Vec<f64>>>
+where
+    D: DataDictionary + Clone,
+{
+    let value = match obj.element(tag) {
+        Ok(e) => e.value(),
+        Err(Error::NoSuchDataElement) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let values = match value.primitive() {
+        Some(&PrimitiveValue::F64(ref v)) => v.clone(),
+        Some(&PrimitiveValue::F32(ref v)) => v.iter().map(|&x| f64::from(x)).collect(),
+        Some(&PrimitiveValue::I32(ref v)) => v.iter().map(|&x| f64::from(x)).collect(),
+        Some(&PrimitiveValue::U16(ref v)) => v.iter().map(|&x| f64::from(x)).collect(),
+        Some(&PrimitiveValue::I16(ref v)) => v.iter().map(|&x| f64::from(x)).collect(),
+        _ => value
+            .as_string()
+            .ok()
+            .and_then(|v| {
+                v.split('\\')
+                    .map(|s| s.trim_matches(&[' ', '\0'][..]).parse().ok())
+                    .collect()
+            })
+            .ok_or(Error::InvalidImagePixelModule("bad numeric value"))?,
+    };
+    Ok(Some(values))
+}
+
+/// Fetch the text of a code string element without padding, if present.
+fn get_code<D>(obj: &InMemDicomObject<D>, tag: Tag) -> Result<Option<String>>
+where
+    D: DataDictionary + Clone,
+{
+    match obj.element(tag) {
+        Ok(e) => Ok(e.value()
+            .as_string()
+            .ok()
+            .map(|v| v.trim_matches(&[' ', '\0'][..]).to_owned())),
+        Err(Error::NoSuchDataElement) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Fetch the first item of a sequence element, if present.
+fn first_item<D>(obj: &InMemDicomObject<D>, tag: Tag) -> Result<Option<&InMemDicomObject<D>>>
+where
+    D: DataDictionary + Clone,
+{
+    match obj.element(tag) {
+        Ok(e) => Ok(e.value().item().and_then(|items| items.first())),
+        Err(Error::NoSuchDataElement) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl ImagePixelModule {
+    /// Collect the image pixel module attributes from the given object,
+    /// and check whether they are consistent.
+    pub fn from_object<D>(obj: &InMemDicomObject<D>) -> Result<Self>
+    where
+        D: DataDictionary + Clone,
+    {
+        let photometric_interpretation = match obj.element(PHOTOMETRIC_INTERPRETATION) {
+            Ok(e) => e.value()
+                .as_string()
+                .map_err(|_| Error::InvalidImagePixelModule("bad photometric interpretation"))?
+                .trim_end_matches(&[' ', '\0'][..])
+                .to_owned(),
+            Err(Error::NoSuchDataElement) => {
+                return Err(Error::InvalidImagePixelModule(
+                    "missing photometric interpretation",
+                ))
+            }
+            Err(e) => return Err(e),
+        };
+        let number_of_frames = match obj.element(NUMBER_OF_FRAMES) {
+            Ok(e) => {
+                // integer strings are usually parsed, but may be kept as text
+                let frames = match e.value().primitive().and_then(PrimitiveValue::int32) {
+                    Some(v) if v >= 0 => Some(v as u32),
+                    Some(_) => None,
+                    None => e.value()
+                        .as_string()
+                        .ok()
+                        .and_then(|v| v.trim_matches(&[' ', '\0'][..]).parse().ok()),
+                };
+                frames.ok_or(Error::InvalidImagePixelModule("bad number of frames"))?
+            }
+            Err(Error::NoSuchDataElement) => 1,
+            Err(e) => return Err(e),
+        };
+
+        let module = ImagePixelModule {
+            samples_per_pixel: require_u16(obj, SAMPLES_PER_PIXEL, "bad samples per pixel")?,
+            photometric_interpretation,
+            planar_configuration: get_u16(obj, PLANAR_CONFIGURATION, "bad planar configuration")?
+                .unwrap_or(0),
+            number_of_frames,
+            rows: require_u16(obj, ROWS, "bad number of rows")?,
+            columns: require_u16(obj, COLUMNS, "bad number of columns")?,
+            bits_allocated: require_u16(obj, BITS_ALLOCATED, "bad bits allocated")?,
+            bits_stored: require_u16(obj, BITS_STORED, "bad bits stored")?,
+            high_bit: require_u16(obj, HIGH_BIT, "bad high bit")?,
+            pixel_representation: require_u16(
+                obj,
+                PIXEL_REPRESENTATION,
+                "bad pixel representation",
+            )?,
+        };
+        module.validate()?;
+        Ok(module)
+    }
+
+    /// Check whether the attributes are consistent with each other.
+    pub fn validate(&self) -> Result<()> {
+        let fail = |message| Err(Error::InvalidImagePixelModule(message));
+        if self.rows == 0 || self.columns == 0 {
+            return fail("image has no pixels");
+        }
+        if self.number_of_frames == 0 {
+            return fail("image has no frames");
+        }
+        match self.bits_allocated {
+            8 | 16 | 32 => {}
+            _ => return fail("unsupported bits allocated"),
+        }
+        if self.bits_stored == 0 || self.bits_stored > self.bits_allocated {
+            return fail("bits stored inconsistent with bits allocated");
+        }
+        if self.high_bit >= self.bits_allocated || self.high_bit + 1 < self.bits_stored {
+            return fail("high bit inconsistent with bits stored");
+        }
+        if self.pixel_representation > 1 {
+            return fail("bad pixel representation");
+        }
+        if self.planar_configuration > 1 {
+            return fail("bad planar configuration");
+        }
+        if self.is_subsampled() && (self.columns % 2 == 1 || self.planar_configuration != 0) {
+            return fail("bad layout of horizontally subsampled pixel data");
+        }
+        let expected_samples = match self.photometric_interpretation.as_str() {
+            "MONOCHROME1" | "MONOCHROME2" | "PALETTE COLOR" => 1,
+            "RGB" | "YBR_FULL" | "YBR_FULL_422" | "YBR_PARTIAL_422" | "YBR_PARTIAL_420"
+            | "YBR_ICT" | "YBR_RCT" => 3,
+            _ => return fail("unsupported photometric interpretation"),
+        };
+        if self.samples_per_pixel != expected_samples {
+            return fail("samples per pixel inconsistent with photometric interpretation");
+        }
+        Ok(())
+    }
+
+    /// Check whether native pixel data is stored with horizontally
+    /// subsampled chrominance. With YBR_FULL_422, each pair of pixels is
+    /// stored as two luminance samples followed by one blue and one red
+    /// chrominance sample.
+    pub fn is_subsampled(&self) -> bool {
+        self.photometric_interpretation == "YBR_FULL_422"
+    }
+
+    /// Retrieve the number of samples stored in each frame.
+    pub fn frame_samples(&self) -> usize {
+        let pixels = usize::from(self.rows) * usize::from(self.columns);
+        if self.is_subsampled() {
+            pixels * 2
+        } else {
+            pixels * usize::from(self.samples_per_pixel)
+        }
+    }
+
+    /// Retrieve the length of each frame of native pixel data, in bytes.
+    pub fn frame_length(&self) -> usize {
+        self.frame_samples() * usize::from(self.bits_allocated / 8)
+    }
+
+    /// Describe a single frame, as expected by pixel data codecs.
+    pub fn frame_description(&self) -> FrameDescription {
+        FrameDescription {
+            rows: self.rows,
+            columns: self.columns,
+            samples_per_pixel: self.samples_per_pixel,
+            bits_allocated: self.bits_allocated,
+            photometric_interpretation: self.photometric_interpretation.clone(),
+        }
+    }
+
+    /// Extract the stored value of each sample in a frame of native pixel
+    /// data, in little endian, and append them to `out`. Samples are
+    /// reordered to be interleaved by pixel if the planar configuration is 1,
+    /// and subsampled chrominance is replicated so that every pixel has
+    /// three samples.
+    fn frame_values(&self, data: &[u8], out: &mut Vec<i64>) {
+        let bytes = usize::from(self.bits_allocated / 8);
+        let shift = self.high_bit + 1 - self.bits_stored;
+        let mask = (1u64 << self.bits_stored) - 1;
+        let signed = self.pixel_representation == 1;
+        let bits_stored = self.bits_stored;
+        let value = |b: &[u8]| {
+            let raw = b.iter()
+                .rev()
+                .fold(0u64, |acc, &v| (acc << 8) | u64::from(v));
+            let v = (raw >> shift) & mask;
+            if signed && v >> (bits_stored - 1) == 1 {
+                v as i64 - (1i64 << bits_stored)
+            } else {
+                v as i64
+            }
+        };
+        let samples = &data[..self.frame_length()];
+
+        if self.is_subsampled() {
+            for v in samples.chunks(bytes * 4) {
+                let (y0, y1) = (value(&v[..bytes]), value(&v[bytes..bytes * 2]));
+                let (cb, cr) = (value(&v[bytes * 2..bytes * 3]), value(&v[bytes * 3..]));
+                out.extend_from_slice(&[y0, cb, cr, y1, cb, cr]);
+            }
+        } else if self.samples_per_pixel > 1 && self.planar_configuration == 1 {
+            let samples_per_pixel = usize::from(self.samples_per_pixel);
+            let plane_length = samples.len() / samples_per_pixel;
+            for pixel in 0..plane_length / bytes {
+                for sample in 0..samples_per_pixel {
+                    let start = sample * plane_length + pixel * bytes;
+                    out.push(value(&samples[start..start + bytes]));
+                }
+            }
+        } else {
+            out.extend(samples.chunks(bytes).map(value));
+        }
+    }
+}
+
+/// Pixel data in its native form, in the data type which corresponds to
+/// the bits allocated and pixel representation of the image.
+#[derive(Debug)]
+pub enum NativePixelData {
+    /// 8 bits allocated, unsigned
+    U8(InMemoryPixelData<u8>),
+    /// 8 bits allocated, signed
+    I8(InMemoryPixelData<i8>),
+    /// 16 bits allocated, unsigned
+    U16(InMemoryPixelData<u16>),
+    /// 16 bits allocated, signed
+    I16(InMemoryPixelData<i16>),
+    /// 32 bits allocated, unsigned
+    U32(InMemoryPixelData<u32>),
+    /// 32 bits allocated, signed
+    I32(InMemoryPixelData<i32>),
+}
+
+macro_rules! for_each_variant {
+    ($self_: expr, $p: ident => $e: expr) => {
+        match *$self_ {
+            NativePixelData::U8(ref $p) => $e,
+            NativePixelData::I8(ref $p) => $e,
+            NativePixelData::U16(ref $p) => $e,
+            NativePixelData::I16(ref $p) => $e,
+            NativePixelData::U32(ref $p) => $e,
+            NativePixelData::I32(ref $p) => $e,
+        }
+    };
+}
+
+impl NativePixelData {
+    /// Get the number of rows of each frame.
+    pub fn rows(&self) -> u32 {
+        for_each_variant!(self, p => p.rows())
+    }
+
+    /// Get the number of columns of each frame.
+    pub fn columns(&self) -> u32 {
+        for_each_variant!(self, p => p.columns())
+    }
+
+    /// Retrieve the number of samples (channels) per pixel.
+    pub fn samples_per_pixel(&self) -> u16 {
+        for_each_variant!(self, p => p.samples_per_pixel())
+    }
+
+    /// Retrieve the number of frames.
+    pub fn number_of_frames(&self) -> u32 {
+        for_each_variant!(self, p => p.number_of_frames())
+    }
+
+    /// Obtain a copy of all samples as floating point numbers.
+    pub fn samples_f64(&self) -> Vec<f64> {
+        for_each_variant!(self, p => p.data().iter().map(|&v| f64::from(v)).collect())
+    }
+}
+
+/// Obtain the bytes of a native pixel data value, in little endian.
+fn native_bytes(value: &PrimitiveValue) -> Result<Cow<'_, [u8]>> {
+    match *value {
+        PrimitiveValue::U8(ref v) => Ok(Cow::Borrowed(v)),
+        PrimitiveValue::U16(ref v) => {
+            let mut buf = vec![0; v.len() * 2];
+            LittleEndian::write_u16_into(v, &mut buf);
+            Ok(Cow::Owned(buf))
+        }
+        PrimitiveValue::I16(ref v) => {
+            let mut buf = vec![0; v.len() * 2];
+            LittleEndian::write_i16_into(v, &mut buf);
+            Ok(Cow::Owned(buf))
+        }
+        PrimitiveValue::U32(ref v) => {
+            let mut buf = vec![0; v.len() * 4];
+            LittleEndian::write_u32_into(v, &mut buf);
+            Ok(Cow::Owned(buf))
+        }
+        _ => Err(Error::InvalidImagePixelModule("bad pixel data value")),
+    }
+}
+
+/// Retrieve the native pixel data of an object, as described by its image
+/// pixel module. All frames are included.
+pub fn native_pixel_data<D>(obj: &InMemDicomObject<D>) -> Result<NativePixelData>
+where
+    D: DataDictionary + Clone,
+{
+    native_pixel_data_with_module(obj).map(|(_, data)| data)
+}
+
+/// Retrieve the native pixel data of an object, along with the image pixel
+/// module which describes it once loaded into memory.
+fn native_pixel_data_with_module<D>(
+    obj: &InMemDicomObject<D>,
+) -> Result<(ImagePixelModule, NativePixelData)>
+where
+    D: DataDictionary + Clone,
+{
+    let mut module = ImagePixelModule::from_object(obj)?;
+    let value = obj.element(PIXEL_DATA)?.value();
+    let value = match value.primitive() {
+        Some(v) => v,
+        None if value.fragments().is_some() => {
+            return Err(Error::PixelDataDecode("pixel data is encapsulated"))
+        }
+        None => return Err(Error::InvalidImagePixelModule("bad pixel data value")),
+    };
+    let data = build_pixel_data(&module, &native_bytes(value)?)?;
+    if module.is_subsampled() {
+        module.photometric_interpretation = "YBR_FULL".to_owned();
+    }
+    module.planar_configuration = 0;
+    Ok((module, data))
+}
+
+/// Retrieve the pixel data of an object in native form, decoding it with
+/// the pixel data codec of the given transfer syntax if it is encapsulated.
+///
+/// The returned image pixel module describes the pixel data as it was
+/// decoded: samples are always interleaved by pixel, and the photometric
+/// interpretation may have changed in the process (e.g. from YBR_FULL_422
+/// to RGB).
+pub fn decode_pixel_data<D>(
+    obj: &InMemDicomObject<D>,
+    ts: &TransferSyntax,
+) -> Result<(ImagePixelModule, NativePixelData)>
+where
+    D: DataDictionary + Clone,
+{
+    let mut module = ImagePixelModule::from_object(obj)?;
+    let value = obj.element(PIXEL_DATA)?.value();
+    let (offset_table, fragments) = match (value.offset_table(), value.fragments()) {
+        (Some(offset_table), Some(fragments)) => (offset_table, fragments),
+        _ => return native_pixel_data_with_module(obj),
+    };
+
+    let codec = ts.pixel_data_codec()
+        .ok_or(Error::UnsupportedTransferSyntax)?;
+    let description = module.frame_description();
+    let frames = encapsulated_frames(offset_table, fragments, module.number_of_frames)?;
+    // codecs always produce one value for each sample of each pixel
+    module.photometric_interpretation = match codec
+        .decoded_photometric_interpretation(&description)
+    {
+        "YBR_FULL_422" => "YBR_FULL",
+        pi => pi,
+    }.to_owned();
+    module.planar_configuration = 0;
+    let mut bytes = Vec::with_capacity(module.frame_length() * frames.len());
+    for frame in frames {
+        let frame = codec.decode_frame(&frame, &description)?;
+        if frame.len() != module.frame_length() {
+            return Err(Error::PixelDataDecode(
+                "decoded frame does not match the image pixel module",
+            ));
+        }
+        bytes.extend(frame);
+    }
+    let data = build_pixel_data(&module, &bytes)?;
+    Ok((module, data))
+}
+
+/// Build typed pixel data from all frames of native pixel data.
+fn build_pixel_data(module: &ImagePixelModule, bytes: &[u8]) -> Result<NativePixelData> {
+    let frames = module.number_of_frames as usize;
+    let len = module.frame_length();
+    if bytes.len() < len * frames {
+        return Err(Error::InvalidImagePixelModule(
+            "pixel data is shorter than described",
+        ));
+    }
+
+    let pixels = usize::from(module.rows) * usize::from(module.columns);
+    let mut values = Vec::with_capacity(pixels * usize::from(module.samples_per_pixel) * frames);
+    for frame in bytes.chunks(len).take(frames) {
+        module.frame_values(frame, &mut values);
+    }
+    let (rows, cols, samples) = (
+        u32::from(module.rows),
+        u32::from(module.columns),
+        module.samples_per_pixel,
+    );
+    let bpp = u32::from(module.bits_allocated);
+    macro_rules! pixel_data {
+        ($variant: ident, $t: ty) => {
+            NativePixelData::$variant(InMemoryPixelData::new_with_frames(
+                values.into_iter().map(|v| v as $t).collect(),
+                module.number_of_frames,
+                rows,
+                cols,
+                bpp,
+                samples,
+            )?)
+        };
+    }
+    Ok(match (module.bits_allocated, module.pixel_representation) {
+        (8, 0) => pixel_data!(U8, u8),
+        (8, _) => pixel_data!(I8, i8),
+        (16, 0) => pixel_data!(U16, u16),
+        (16, _) => pixel_data!(I16, i16),
+        (32, 0) => pixel_data!(U32, u32),
+        _ => pixel_data!(I32, i32),
+    })
+}
+
+/// Helpers for building objects in the tests of this module and its children.
+#[cfg(test)]
+mod testing {
+    use data::{DataElement, Tag, VR};
+    use data::value::{PrimitiveValue, Value};
+    use dictionary::StandardDataDictionary;
+    use object::mem::InMemDicomObject;
+
+    pub type Object = InMemDicomObject<StandardDataDictionary>;
+
+    pub fn put(obj: &mut Object, tag: Tag, vr: VR, value: PrimitiveValue) {
+        obj.put(DataElement::new(tag, vr, Value::Primitive(value)));
+    }
+
+    pub fn put_us(obj: &mut Object, tag: Tag, v: u16) {
+        put(obj, tag, VR::US, PrimitiveValue::U16(vec![v]));
+    }
+
+    /// Put the attributes of an image pixel module with the given photometric
+    /// interpretation, rows and columns, and the other attributes in this
+    /// order: samples per pixel, bits allocated, bits stored, high bit,
+    /// pixel representation.
+    pub fn put_image_module(obj: &mut Object, pi: &str, size: [u16; 2], attributes: [u16; 5]) {
+        put(obj, Tag(0x0028, 0x0004), VR::CS, PrimitiveValue::Str(pi.to_owned()));
+        put_us(obj, Tag(0x0028, 0x0002), attributes[0]);
+        put_us(obj, Tag(0x0028, 0x0010), size[0]);
+        put_us(obj, Tag(0x0028, 0x0011), size[1]);
+        put_us(obj, Tag(0x0028, 0x0100), attributes[1]);
+        put_us(obj, Tag(0x0028, 0x0101), attributes[2]);
+        put_us(obj, Tag(0x0028, 0x0102), attributes[3]);
+        put_us(obj, Tag(0x0028, 0x0103), attributes[4]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_pixel_data, encapsulated_frames, ImagePixelModule, NativePixelData,
+                PixelData};
+    use super::testing::{put, put_image_module, put_us, Object};
+    use data::{DataElement, Tag, VR};
+    use data::value::{PrimitiveValue, Value};
+    use error::Error;
+    use file::{from_stream, to_file};
+    use meta::DicomMetaTableBuilder;
+    use object::RootDicomObject;
+    use object::mem::InMemDicomObject;
+    use std::io::Cursor;
+    use transfer_syntax::{rle, ExplicitVRLittleEndian, JPEGBaseline};
+
+    /// Build a 2x2 image with the given attributes, in this order:
+    /// samples per pixel, bits allocated, bits stored, high bit,
+    /// pixel representation.
+    fn image(pi: &str, attributes: [u16; 5], pixel_data: PrimitiveValue) -> Object {
+        let mut obj = InMemDicomObject::create_empty();
+        put_image_module(&mut obj, pi, [2, 2], attributes);
+        put(&mut obj, Tag(0x7FE0, 0x0010), VR::OW, pixel_data);
+        obj
+    }
+
+    #[test]
+    fn native_pixel_data_16bit_signed() {
+        // 12 bits stored, with garbage in the upper bits
+        let obj = image(
+            "MONOCHROME2",
+            [1, 16, 12, 11, 1],
+            PrimitiveValue::U16(vec![0x0001, 0x0FFF, 0xF800, 0x07FF]),
+        );
+        let module = ImagePixelModule::from_object(&obj).unwrap();
+        assert_eq!(module.number_of_frames, 1);
+        assert_eq!(module.frame_length(), 8);
+        match obj.pixel_data().unwrap() {
+            NativePixelData::I16(p) => {
+                assert_eq!(p.rows(), 2);
+                assert_eq!(p.to_vector(), vec![1, -1, -2048, 2047]);
+            }
+            p => panic!("unexpected pixel data {:?}", p),
+        }
+    }
+
+    #[test]
+    fn native_pixel_data_8bit_rgb() {
+        let mut obj = image(
+            "RGB ",
+            [3, 8, 8, 7, 0],
+            PrimitiveValue::U8((0..12).collect()),
+        );
+        match obj.pixel_data().unwrap() {
+            NativePixelData::U8(p) => {
+                assert_eq!(p.samples_per_pixel(), 3);
+                assert_eq!(p.sample_at(1, 0, 2).unwrap(), &5);
+            }
+            p => panic!("unexpected pixel data {:?}", p),
+        }
+
+        // color-by-plane
+        put_us(&mut obj, Tag(0x0028, 0x0006), 1);
+        match obj.pixel_data().unwrap() {
+            NativePixelData::U8(p) => {
+                assert_eq!(p.to_vector(), vec![0, 4, 8, 1, 5, 9, 2, 6, 10, 3, 7, 11]);
+            }
+            p => panic!("unexpected pixel data {:?}", p),
+        }
+    }
+
+    fn put_number_of_frames(obj: &mut Object, frames: &str) {
+        put(obj, Tag(0x0028, 0x0008), VR::IS, PrimitiveValue::Str(frames.to_owned()));
+    }
+
+    #[test]
+    fn native_multi_frame_pixel_data() {
+        let mut obj = image(
+            "MONOCHROME2",
+            [1, 8, 8, 7, 0],
+            PrimitiveValue::U8((0..12).collect()),
+        );
+        put_number_of_frames(&mut obj, "3 ");
+        let pixels = match obj.pixel_data().unwrap() {
+            NativePixelData::U8(p) => p,
+            p => panic!("unexpected pixel data {:?}", p),
+        };
+        assert_eq!(pixels.number_of_frames(), 3);
+        assert_eq!(pixels.pixel_at(1, 1).unwrap(), 3);
+        assert_eq!(pixels.frame_pixel_at(2, 1, 0).unwrap(), 9);
+        assert!(pixels.frame_pixel_at(3, 0, 0).is_err());
+        assert_eq!(pixels.frame_data(1).unwrap(), &[4, 5, 6, 7]);
+        let frames: Vec<_> = pixels.frames().map(|f| f.to_vector()).collect();
+        assert_eq!(frames, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9, 10, 11]]);
+        assert_eq!(pixels.frame(1).unwrap().number_of_frames(), 1);
+
+        // not enough data for all frames
+        put_number_of_frames(&mut obj, "4");
+        assert!(obj.pixel_data().is_err());
+    }
+
+    #[test]
+    fn decode_multi_frame_rle_file() {
+        let description = ImagePixelModule {
+            samples_per_pixel: 1,
+            photometric_interpretation: "MONOCHROME2".to_owned(),
+            planar_configuration: 0,
+            number_of_frames: 2,
+            rows: 2,
+            columns: 2,
+            bits_allocated: 16,
+            bits_stored: 16,
+            high_bit: 15,
+            pixel_representation: 0,
+        }.frame_description();
+        let frames: Vec<Vec<u8>> = vec![
+            rle::encode(&[1, 0, 2, 0, 3, 0, 4, 0], &description).unwrap(),
+            rle::encode(&[0, 1, 0, 2, 0, 3, 0, 4], &description).unwrap(),
+        ];
+
+        let mut obj = image("MONOCHROME2", [1, 16, 16, 15, 0], PrimitiveValue::Empty);
+        put_number_of_frames(&mut obj, "2");
+        obj.put(DataElement::new(
+            Tag(0x7FE0, 0x0010),
+            VR::OB,
+            Value::PixelSequence {
+                offset_table: vec![0, 8 + frames[0].len() as u32],
+                fragments: frames,
+            },
+        ));
+        assert!(obj.pixel_data().is_err());
+        assert!(decode_pixel_data(&obj, &ExplicitVRLittleEndian).is_err());
+
+        let meta = DicomMetaTableBuilder::new()
+            .information_version([0, 1])
+            .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7\0".to_owned())
+            .media_storage_sop_instance_uid("2.25.1234\0".to_owned())
+            .transfer_syntax("1.2.840.10008.1.2.5\0".to_owned())
+            .implementation_class_uid("1.2.345\0".to_owned())
+            .build()
+            .unwrap();
+        let mut buf = Vec::new();
+        to_file(&RootDicomObject::new(meta, obj), &mut buf).unwrap();
+        let obj = from_stream(Cursor::new(buf[128..].to_vec())).unwrap();
+
+        let (module, pixels) = obj.decode_pixel_data().unwrap();
+        assert_eq!(module.number_of_frames, 2);
+        match pixels {
+            NativePixelData::U16(p) => {
+                assert_eq!(p.to_vector(), vec![1, 2, 3, 4, 256, 512, 768, 1024]);
+            }
+            p => panic!("unexpected pixel data {:?}", p),
+        }
+    }
+
+    #[test]
+    fn decode_jpeg_pixel_data() {
+        let mut obj = image("YBR_FULL_422", [3, 8, 8, 7, 0], PrimitiveValue::Empty);
+        put_us(&mut obj, Tag(0x0028, 0x0010), 16);
+        put_us(&mut obj, Tag(0x0028, 0x0011), 16);
+        obj.put(DataElement::new(
+            Tag(0x7FE0, 0x0010),
+            VR::OB,
+            Value::PixelSequence {
+                offset_table: vec![],
+                fragments: vec![include_bytes!("../../../testdata/python.jpg").to_vec()],
+            },
+        ));
+        let (module, pixels) = decode_pixel_data(&obj, &JPEGBaseline).unwrap();
+        assert_eq!(module.photometric_interpretation, "RGB");
+        assert_eq!(pixels.rows(), 16);
+        assert_eq!(pixels.samples_per_pixel(), 3);
+        assert_eq!(pixels.number_of_frames(), 1);
+    }
+
+    #[test]
+    fn inconsistent_image_pixel_module() {
+        let pixels = || PrimitiveValue::U8(vec![0; 12]);
+        let is_invalid = |obj: Object| match obj.pixel_data() {
+            Err(Error::InvalidImagePixelModule(_)) => true,
+            _ => false,
+        };
+        assert!(is_invalid(image("MONOCHROME2", [3, 8, 8, 7, 0], pixels())));
+        assert!(is_invalid(image("RGB", [3, 8, 12, 11, 0], pixels())));
+        assert!(is_invalid(image("RGB", [3, 8, 8, 3, 0], pixels())));
+        assert!(is_invalid(image("RGB", [3, 12, 12, 11, 0], pixels())));
+        assert!(is_invalid(image("RGB", [3, 8, 8, 7, 2], pixels())));
+        assert!(is_invalid(image("RGB", [3, 8, 8, 7, 0], PrimitiveValue::U8(vec![0; 6]))));
+        assert!(is_invalid(image("RGB", [3, 8, 8, 7, 0], PrimitiveValue::Str("x".into()))));
+        assert!(is_invalid(InMemDicomObject::create_empty()));
+    }
+
+    #[test]
+    fn split_encapsulated_frames() {
+        let fragments = vec![vec![1, 2], vec![3, 4, 5, 6], vec![7, 8]];
+
+        let frames = encapsulated_frames(&[], &fragments, 1).unwrap();
+        assert_eq!(frames, vec![vec![1, 2, 3, 4, 5, 6, 7, 8]]);
+
+        let frames = encapsulated_frames(&[], &fragments, 3).unwrap();
+        assert_eq!(frames, fragments);
+
+        let frames = encapsulated_frames(&[0, 22], &fragments, 2).unwrap();
+        assert_eq!(frames, vec![vec![1, 2, 3, 4, 5, 6], vec![7, 8]]);
+
+        assert!(encapsulated_frames(&[], &fragments, 2).is_err());
+        assert!(encapsulated_frames(&[0], &fragments, 2).is_err());
+    }
+
+    #[test]
+    fn split_encapsulated_frames_without_offset_table() {
+        let fragments = vec![
+            vec![0xFF, 0xD8, 1],
+            vec![2, 3],
+            vec![0xFF, 0xD8, 4],
+            vec![5],
+            vec![6],
+        ];
+        let frames = encapsulated_frames(&[], &fragments, 2).unwrap();
+        assert_eq!(frames, vec![vec![0xFF, 0xD8, 1, 2, 3], vec![0xFF, 0xD8, 4, 5, 6]]);
+
+        let fragments = vec![vec![0xFF, 0x4F, 1], vec![2], vec![0xFF, 0x4F, 3], vec![4]];
+        let frames = encapsulated_frames(&[], &fragments, 2).unwrap();
+        assert_eq!(frames, vec![vec![0xFF, 0x4F, 1, 2], vec![0xFF, 0x4F, 3, 4]]);
+
+        // the number of start markers does not match the number of frames
+        assert!(encapsulated_frames(&[], &fragments, 3).is_err());
+        // the first fragment does not start a frame
+        let fragments = vec![vec![1], vec![0xFF, 0xD8], vec![0xFF, 0xD8]];
+        assert!(encapsulated_frames(&[], &fragments, 2).is_err());
+    }
+}
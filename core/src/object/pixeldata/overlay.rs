@@ -0,0 +1,357 @@
+//! Overlay planes, as described by the Overlay Plane module.
+//!
+//! An object may have up to 16 overlay planes, each in one of the even
+//! groups from 0x6000 to 0x601E. The overlay bits are usually stored in the
+//! Overlay Data (60xx,3000) element, one bit per pixel. Older objects may
+//! instead embed them in unused high bits of the pixel data, in which case
+//! the Overlay Bits Allocated (60xx,0100) matches the bits allocated of the
+//! image and the Overlay Bit Position (60xx,0102) tells which bit to use.
+
+use data::Tag;
+use data::value::PrimitiveValue;
+use dictionary::DataDictionary;
+use error::{Error, Result};
+use object::mem::InMemDicomObject;
+use super::{get_code, get_u16, native_bytes, require_u16, ImagePixelModule, InMemoryPixelData,
+            PixelData, PIXEL_DATA};
+
+const OVERLAY_ROWS: u16 = 0x0010;
+const OVERLAY_COLUMNS: u16 = 0x0011;
+const NUMBER_OF_FRAMES_IN_OVERLAY: u16 = 0x0015;
+const OVERLAY_DESCRIPTION: u16 = 0x0022;
+const OVERLAY_TYPE: u16 = 0x0040;
+const OVERLAY_ORIGIN: u16 = 0x0050;
+const IMAGE_FRAME_ORIGIN: u16 = 0x0051;
+const OVERLAY_BITS_ALLOCATED: u16 = 0x0100;
+const OVERLAY_BIT_POSITION: u16 = 0x0102;
+const OVERLAY_LABEL: u16 = 0x1500;
+const OVERLAY_DATA: u16 = 0x3000;
+
+fn error(message: &'static str) -> Error {
+    Error::InvalidImagePixelModule(message)
+}
+
+/// An overlay plane, with one bit mask per overlay frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    /// The group of the overlay attributes (0x6000 to 0x601E).
+    pub group: u16,
+    /// Overlay Rows (60xx,0010)
+    pub rows: u16,
+    /// Overlay Columns (60xx,0011)
+    pub columns: u16,
+    /// Overlay Type (60xx,0040): `G` for graphics, `R` for a region of
+    /// interest
+    pub overlay_type: String,
+    /// Overlay Origin (60xx,0050): the row and column of the image at which
+    /// the first overlay pixel is placed, starting at 1
+    pub origin: (i16, i16),
+    /// Image Frame Origin (60xx,0051): the image frame of the first overlay
+    /// frame, starting at 1
+    pub image_frame_origin: u16,
+    /// Overlay Label (60xx,1500)
+    pub label: Option<String>,
+    /// Overlay Description (60xx,0022)
+    pub description: Option<String>,
+    /// Whether the overlay was embedded in the pixel data.
+    pub embedded: bool,
+    masks: Vec<Vec<bool>>,
+}
+
+impl Overlay {
+    /// Read the overlay plane in the given group of an object. Overlays
+    /// embedded in the pixel data are only supported for native pixel data.
+    pub fn from_object<D>(obj: &InMemDicomObject<D>, group: u16) -> Result<Overlay>
+    where
+        D: DataDictionary + Clone,
+    {
+        if group & 0xFF01 != 0x6000 || group > 0x601E {
+            return Err(error("bad overlay group"));
+        }
+        let tag = |element| Tag(group, element);
+        let rows = require_u16(obj, tag(OVERLAY_ROWS), "bad overlay rows")?;
+        let columns = require_u16(obj, tag(OVERLAY_COLUMNS), "bad overlay columns")?;
+        if rows == 0 || columns == 0 {
+            return Err(error("overlay has no pixels"));
+        }
+        let origin = match obj.element(tag(OVERLAY_ORIGIN)) {
+            Ok(e) => match e.value().primitive() {
+                Some(&PrimitiveValue::I16(ref v)) if v.len() == 2 => (v[0], v[1]),
+                Some(&PrimitiveValue::U16(ref v)) if v.len() == 2 => (v[0] as i16, v[1] as i16),
+                _ => return Err(error("bad overlay origin")),
+            },
+            Err(Error::NoSuchDataElement) => (1, 1),
+            Err(e) => return Err(e),
+        };
+        let number_of_frames = match obj.element(tag(NUMBER_OF_FRAMES_IN_OVERLAY)) {
+            Ok(e) => {
+                let frames = match e.value().primitive().and_then(PrimitiveValue::int32) {
+                    Some(v) => Some(v),
+                    None => e.value()
+                        .as_string()
+                        .ok()
+                        .and_then(|v| v.trim_matches(&[' ', '\0'][..]).parse().ok()),
+                };
+                match frames {
+                    Some(v) if v > 0 => v as usize,
+                    _ => return Err(error("bad number of frames in overlay")),
+                }
+            }
+            Err(Error::NoSuchDataElement) => 1,
+            Err(e) => return Err(e),
+        };
+        let bits_allocated =
+            get_u16(obj, tag(OVERLAY_BITS_ALLOCATED), "bad overlay bits allocated")?.unwrap_or(1);
+        let bit_position = get_u16(obj, tag(OVERLAY_BIT_POSITION), "bad overlay bit position")?
+            .unwrap_or(0);
+
+        let len = usize::from(rows) * usize::from(columns);
+        let data = match obj.element(tag(OVERLAY_DATA)) {
+            Ok(e) => Some(e.value()
+                .primitive()
+                .ok_or_else(|| error("bad overlay data"))
+                .and_then(native_bytes)?),
+            Err(Error::NoSuchDataElement) => None,
+            Err(e) => return Err(e),
+        };
+        let (masks, embedded) = match data {
+            Some(data) => {
+                if bits_allocated != 1 {
+                    return Err(error("bad overlay bits allocated"));
+                }
+                // the bits of all frames are packed together
+                if data.len() * 8 < len * number_of_frames {
+                    return Err(error("overlay data is too short"));
+                }
+                let bits: Vec<bool> = (0..len * number_of_frames)
+                    .map(|i| data[i / 8] >> (i % 8) & 1 == 1)
+                    .collect();
+                (bits.chunks(len).map(|m| m.to_vec()).collect(), false)
+            }
+            None => (
+                embedded_masks(obj, rows, columns, bits_allocated, bit_position)?,
+                true,
+            ),
+        };
+
+        Ok(Overlay {
+            group,
+            rows,
+            columns,
+            overlay_type: get_code(obj, tag(OVERLAY_TYPE))?.unwrap_or_else(|| "G".to_owned()),
+            origin,
+            image_frame_origin: get_u16(obj, tag(IMAGE_FRAME_ORIGIN), "bad image frame origin")?
+                .unwrap_or(1),
+            label: get_code(obj, tag(OVERLAY_LABEL))?,
+            description: get_code(obj, tag(OVERLAY_DESCRIPTION))?,
+            embedded,
+            masks,
+        })
+    }
+
+    /// Retrieve the number of overlay frames.
+    pub fn number_of_frames(&self) -> u32 {
+        self.masks.len() as u32
+    }
+
+    /// Obtain the bit mask which applies to the given image frame (starting
+    /// at 0), in row-major order. Returns `None` if the overlay does not
+    /// cover that frame.
+    pub fn frame_mask(&self, frame: u32) -> Option<&[bool]> {
+        let first = if self.embedded {
+            0
+        } else {
+            i64::from(self.image_frame_origin.max(1)) - 1
+        };
+        let i = i64::from(frame) - first;
+        if i < 0 {
+            return None;
+        }
+        self.masks.get(i as usize).map(Vec::as_slice)
+    }
+
+    /// Burn the overlay into all frames of a rendered image, by setting the
+    /// samples of each pixel covered by the overlay to the given color.
+    /// The color must have one value per sample of the image.
+    pub fn burn_in<P: Copy>(&self, image: &mut InMemoryPixelData<P>, color: &[P]) -> Result<()> {
+        let samples = usize::from(image.samples_per_pixel());
+        if color.len() != samples {
+            return Err(error("overlay color does not match the samples per pixel"));
+        }
+        let (rows, columns) = (i64::from(image.rows()), i64::from(image.columns()));
+        for frame in 0..image.number_of_frames() {
+            let mask = match self.frame_mask(frame) {
+                Some(mask) => mask,
+                None => continue,
+            };
+            let data = image.frame_data_mut(frame)?;
+            for (i, _) in mask.iter().enumerate().filter(|&(_, &set)| set) {
+                let row = i64::from(self.origin.0) - 1 + (i / usize::from(self.columns)) as i64;
+                let column = i64::from(self.origin.1) - 1 + (i % usize::from(self.columns)) as i64;
+                if row < 0 || column < 0 || row >= rows || column >= columns {
+                    continue;
+                }
+                let start = (row * columns + column) as usize * samples;
+                data[start..start + samples].copy_from_slice(color);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Extract the masks of an overlay embedded in the native pixel data,
+/// one per image frame.
+fn embedded_masks<D>(
+    obj: &InMemDicomObject<D>,
+    rows: u16,
+    columns: u16,
+    bits_allocated: u16,
+    bit_position: u16,
+) -> Result<Vec<Vec<bool>>>
+where
+    D: DataDictionary + Clone,
+{
+    let module = ImagePixelModule::from_object(obj)?;
+    if bits_allocated != module.bits_allocated || module.samples_per_pixel != 1
+        || bit_position >= bits_allocated
+    {
+        return Err(error("bad embedded overlay"));
+    }
+    if rows != module.rows || columns != module.columns {
+        return Err(error("embedded overlay does not match the image dimensions"));
+    }
+    let data = obj.element(PIXEL_DATA)?
+        .value()
+        .primitive()
+        .ok_or_else(|| error("overlays in encapsulated pixel data are not supported"))
+        .and_then(native_bytes)?;
+
+    let bytes = usize::from(bits_allocated / 8);
+    let frame_length = module.frame_length();
+    let frames = module.number_of_frames as usize;
+    if data.len() < frame_length * frames {
+        return Err(error("pixel data is shorter than described"));
+    }
+    let (byte, bit) = (usize::from(bit_position / 8), bit_position % 8);
+    Ok(data[..frame_length * frames]
+        .chunks(frame_length)
+        .map(|frame| {
+            frame
+                .chunks(bytes)
+                .map(|sample| sample[byte] >> bit & 1 == 1)
+                .collect()
+        })
+        .collect())
+}
+
+/// Read all overlay planes of an object, in group order.
+pub fn overlays<D>(obj: &InMemDicomObject<D>) -> Result<Vec<Overlay>>
+where
+    D: DataDictionary + Clone,
+{
+    let mut overlays = Vec::new();
+    for group in (0x6000..=0x601E).step_by(2) {
+        match obj.element(Tag(group, OVERLAY_ROWS)) {
+            Ok(_) => overlays.push(Overlay::from_object(obj, group)?),
+            Err(Error::NoSuchDataElement) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(overlays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{overlays, Overlay};
+    use data::{Tag, VR};
+    use data::value::PrimitiveValue;
+    use object::mem::InMemDicomObject;
+    use object::pixeldata::InMemoryPixelData;
+    use object::pixeldata::testing::{put, put_image_module, Object};
+
+    /// A 3x4 overlay in the given group.
+    fn overlay(obj: &mut Object, group: u16) {
+        put(obj, Tag(group, 0x0010), VR::US, PrimitiveValue::U16(vec![3]));
+        put(obj, Tag(group, 0x0011), VR::US, PrimitiveValue::U16(vec![4]));
+        put(obj, Tag(group, 0x0040), VR::CS, PrimitiveValue::Str("G ".to_owned()));
+    }
+
+    #[test]
+    fn separate_overlay_data() {
+        let mut obj = InMemDicomObject::create_empty();
+        overlay(&mut obj, 0x6002);
+        put(&mut obj, Tag(0x6002, 0x0050), VR::SS, PrimitiveValue::I16(vec![2, 0]));
+        put(&mut obj, Tag(0x6002, 0x0100), VR::US, PrimitiveValue::U16(vec![1]));
+        put(&mut obj, Tag(0x6002, 0x0102), VR::US, PrimitiveValue::U16(vec![0]));
+        put(&mut obj, Tag(0x6002, 0x1500), VR::LO, PrimitiveValue::Str("ROI".to_owned()));
+        // first and last pixels of the first row, middle pixels of the last
+        put(&mut obj, Tag(0x6002, 0x3000), VR::OW, PrimitiveValue::U16(vec![0x0609]));
+
+        let overlays = overlays(&obj).unwrap();
+        assert_eq!(overlays.len(), 1);
+        let overlay = &overlays[0];
+        assert_eq!(overlay.group, 0x6002);
+        assert_eq!(overlay.overlay_type, "G");
+        assert_eq!(overlay.label, Some("ROI".to_owned()));
+        assert!(!overlay.embedded);
+        assert_eq!(overlay.number_of_frames(), 1);
+        let mask: Vec<u8> = overlay.frame_mask(0).unwrap().iter().map(|&b| b as u8).collect();
+        assert_eq!(mask, vec![1, 0, 0, 1, 0, 0, 0, 0, 0, 1, 1, 0]);
+        assert!(overlay.frame_mask(1).is_none());
+
+        // the origin is one row down and one column to the left
+        let mut image = InMemoryPixelData::new(vec![0u8; 3 * 4 * 3], 3, 4, 8, 3).unwrap();
+        overlay.burn_in(&mut image, &[255, 0, 0]).unwrap();
+        let red: Vec<u8> = image.data().chunks(3).map(|p| (p[0] == 255) as u8).collect();
+        assert_eq!(red, vec![0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0]);
+        assert!(overlay.burn_in(&mut image, &[255]).is_err());
+    }
+
+    #[test]
+    fn multi_frame_overlay_data() {
+        let mut obj = InMemDicomObject::create_empty();
+        overlay(&mut obj, 0x6000);
+        put(&mut obj, Tag(0x6000, 0x0015), VR::IS, PrimitiveValue::I32(vec![2]));
+        put(&mut obj, Tag(0x6000, 0x0051), VR::US, PrimitiveValue::U16(vec![2]));
+        // frames are packed without padding: bit 12 is the first of frame 2
+        put(&mut obj, Tag(0x6000, 0x3000), VR::OB, PrimitiveValue::U8(vec![0, 0x10, 0]));
+        let overlay = Overlay::from_object(&obj, 0x6000).unwrap();
+        assert_eq!(overlay.number_of_frames(), 2);
+        assert!(overlay.frame_mask(0).is_none());
+        assert!(overlay.frame_mask(1).unwrap().iter().all(|&b| !b));
+        assert!(overlay.frame_mask(2).unwrap()[0]);
+        assert!(overlay.frame_mask(3).is_none());
+
+        let mut image = InMemoryPixelData::new_with_frames(vec![7u16; 24], 2, 3, 4, 16, 1).unwrap();
+        overlay.burn_in(&mut image, &[0xFFFF]).unwrap();
+        assert_eq!(image.frame_data(0).unwrap(), &[7; 12][..]);
+        assert_eq!(image.frame_data(1).unwrap()[..2], [7, 7]);
+
+        put(&mut obj, Tag(0x6000, 0x0015), VR::IS, PrimitiveValue::I32(vec![3]));
+        assert!(Overlay::from_object(&obj, 0x6000).is_err());
+        assert!(Overlay::from_object(&obj, 0x6001).is_err());
+    }
+
+    #[test]
+    fn embedded_overlay() {
+        let mut obj = InMemDicomObject::create_empty();
+        put_image_module(&mut obj, "MONOCHROME2", [3, 4], [1, 16, 12, 11, 0]);
+        let mut pixels = vec![0x0123u16; 12];
+        pixels[5] |= 1 << 12;
+        pixels[6] |= 1 << 13;
+        put(&mut obj, Tag(0x7FE0, 0x0010), VR::OW, PrimitiveValue::U16(pixels));
+        overlay(&mut obj, 0x6000);
+        put(&mut obj, Tag(0x6000, 0x0100), VR::US, PrimitiveValue::U16(vec![16]));
+        put(&mut obj, Tag(0x6000, 0x0102), VR::US, PrimitiveValue::U16(vec![12]));
+
+        let overlay = Overlay::from_object(&obj, 0x6000).unwrap();
+        assert!(overlay.embedded);
+        let mask = overlay.frame_mask(0).unwrap();
+        assert_eq!(mask.iter().filter(|&&b| b).count(), 1);
+        assert!(mask[5]);
+
+        // the overlay bits are not part of the stored values
+        let pixels = obj.pixel_data().unwrap().samples_f64();
+        assert_eq!(pixels[5], f64::from(0x0123));
+    }
+}
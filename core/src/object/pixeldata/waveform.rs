@@ -0,0 +1,583 @@
+//! Decoding of waveform data.
+//!
+//! Each item of the Waveform Sequence (5400,0100) is a multiplex group: a
+//! set of channels sampled at the same frequency, whose samples are stored
+//! interleaved in Waveform Data (5400,1010). The channels of a group can be
+//! retrieved either as raw sample values or as values scaled by the channel
+//! sensitivity and baseline.
+
+use data::Tag;
+use data::value::PrimitiveValue;
+use dictionary::DataDictionary;
+use error::{Error, Result};
+use object::mem::InMemDicomObject;
+use super::{first_item, get_code, get_f64s, get_u16, native_bytes, require_u16};
+
+const WAVEFORM_SEQUENCE: Tag = Tag(0x5400, 0x0100);
+const MULTIPLEX_GROUP_TIME_OFFSET: Tag = Tag(0x0018, 0x1068);
+const WAVEFORM_ORIGINALITY: Tag = Tag(0x003A, 0x0004);
+const NUMBER_OF_WAVEFORM_CHANNELS: Tag = Tag(0x003A, 0x0005);
+const NUMBER_OF_WAVEFORM_SAMPLES: Tag = Tag(0x003A, 0x0010);
+const SAMPLING_FREQUENCY: Tag = Tag(0x003A, 0x001A);
+const MULTIPLEX_GROUP_LABEL: Tag = Tag(0x003A, 0x0020);
+const CHANNEL_DEFINITION_SEQUENCE: Tag = Tag(0x003A, 0x0200);
+const WAVEFORM_CHANNEL_NUMBER: Tag = Tag(0x003A, 0x0202);
+const CHANNEL_LABEL: Tag = Tag(0x003A, 0x0203);
+const CHANNEL_SOURCE_SEQUENCE: Tag = Tag(0x003A, 0x0208);
+const CHANNEL_SENSITIVITY: Tag = Tag(0x003A, 0x0210);
+const CHANNEL_SENSITIVITY_UNITS_SEQUENCE: Tag = Tag(0x003A, 0x0211);
+const CHANNEL_SENSITIVITY_CORRECTION_FACTOR: Tag = Tag(0x003A, 0x0212);
+const CHANNEL_BASELINE: Tag = Tag(0x003A, 0x0213);
+const CHANNEL_TIME_SKEW: Tag = Tag(0x003A, 0x0214);
+const CHANNEL_SAMPLE_SKEW: Tag = Tag(0x003A, 0x0215);
+const CHANNEL_OFFSET: Tag = Tag(0x003A, 0x0218);
+const WAVEFORM_BITS_STORED: Tag = Tag(0x003A, 0x021A);
+const FILTER_LOW_FREQUENCY: Tag = Tag(0x003A, 0x0220);
+const FILTER_HIGH_FREQUENCY: Tag = Tag(0x003A, 0x0221);
+const NOTCH_FILTER_FREQUENCY: Tag = Tag(0x003A, 0x0222);
+const WAVEFORM_BITS_ALLOCATED: Tag = Tag(0x5400, 0x1004);
+const WAVEFORM_SAMPLE_INTERPRETATION: Tag = Tag(0x5400, 0x1006);
+const WAVEFORM_PADDING_VALUE: Tag = Tag(0x5400, 0x100A);
+const WAVEFORM_DATA: Tag = Tag(0x5400, 0x1010);
+const CODE_VALUE: Tag = Tag(0x0008, 0x0100);
+const CODE_MEANING: Tag = Tag(0x0008, 0x0104);
+
+fn error(message: &'static str) -> Error {
+    Error::InvalidWaveform(message)
+}
+
+/// The data type of waveform samples, as given by Waveform Sample
+/// Interpretation (5400,1006).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SampleInterpretation {
+    /// Signed 8 bit linear.
+    SB,
+    /// Unsigned 8 bit linear.
+    UB,
+    /// 8 bit mu-law (G.711).
+    MB,
+    /// 8 bit A-law (G.711).
+    AB,
+    /// Signed 16 bit linear.
+    SS,
+    /// Unsigned 16 bit linear.
+    US,
+    /// Signed 32 bit linear.
+    SL,
+    /// Unsigned 32 bit linear.
+    UL,
+}
+
+impl SampleInterpretation {
+    /// Obtain the sample interpretation from its code string.
+    pub fn from_code(code: &str) -> Option<SampleInterpretation> {
+        use self::SampleInterpretation::*;
+        match code.trim_matches(&[' ', '\0'][..]) {
+            "SB" => Some(SB),
+            "UB" => Some(UB),
+            "MB" => Some(MB),
+            "AB" => Some(AB),
+            "SS" => Some(SS),
+            "US" => Some(US),
+            "SL" => Some(SL),
+            "UL" => Some(UL),
+            _ => None,
+        }
+    }
+
+    /// The number of bits allocated to each sample of this interpretation.
+    pub fn bits_allocated(self) -> u16 {
+        use self::SampleInterpretation::*;
+        match self {
+            SB | UB | MB | AB => 8,
+            SS | US => 16,
+            SL | UL => 32,
+        }
+    }
+
+    /// Decode one little endian sample of this interpretation.
+    fn decode(self, b: &[u8]) -> i64 {
+        use self::SampleInterpretation::*;
+        match self {
+            SB => i64::from(b[0] as i8),
+            UB => i64::from(b[0]),
+            MB => i64::from(mu_law(b[0])),
+            AB => i64::from(a_law(b[0])),
+            SS => i64::from(i16::from(b[0]) | i16::from(b[1]) << 8),
+            US => i64::from(u16::from(b[0]) | u16::from(b[1]) << 8),
+            SL => i64::from(le_u32(b) as i32),
+            UL => i64::from(le_u32(b)),
+        }
+    }
+}
+
+fn le_u32(b: &[u8]) -> u32 {
+    b[..4].iter().rev().fold(0, |acc, &v| (acc << 8) | u32::from(v))
+}
+
+/// Expand a G.711 mu-law sample into a 16 bit linear value.
+fn mu_law(v: u8) -> i16 {
+    let v = !v;
+    let exponent = (v >> 4) & 0x07;
+    let magnitude = ((i16::from(v & 0x0F) << 3) + 0x84) << exponent;
+    if v & 0x80 != 0 {
+        0x84 - magnitude
+    } else {
+        magnitude - 0x84
+    }
+}
+
+/// Expand a G.711 A-law sample into a 16 bit linear value.
+fn a_law(v: u8) -> i16 {
+    let v = v ^ 0x55;
+    let exponent = (v >> 4) & 0x07;
+    let mantissa = i16::from(v & 0x0F) << 4;
+    let magnitude = match exponent {
+        0 => mantissa + 8,
+        e => (mantissa + 0x108) << (e - 1),
+    };
+    if v & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+/// The definition of a channel in a multiplex group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveformChannel {
+    /// Waveform Channel Number (003A,0202)
+    pub number: Option<i32>,
+    /// Channel Label (003A,0203)
+    pub label: Option<String>,
+    /// The code meaning of the Channel Source Sequence (003A,0208)
+    pub source: Option<String>,
+    /// Channel Sensitivity (003A,0210), in sensitivity units per raw
+    /// sample value
+    pub sensitivity: Option<f64>,
+    /// The code value of the Channel Sensitivity Units Sequence
+    /// (003A,0211), such as `uV` or `mm[Hg]`
+    pub sensitivity_units: Option<String>,
+    /// Channel Sensitivity Correction Factor (003A,0212), 1 if absent
+    pub sensitivity_correction_factor: f64,
+    /// Channel Baseline (003A,0213), 0 if absent
+    pub baseline: f64,
+    /// Channel Time Skew (003A,0214) in seconds, if present
+    pub time_skew: Option<f64>,
+    /// Channel Sample Skew (003A,0215), if present
+    pub sample_skew: Option<f64>,
+    /// Channel Offset (003A,0218), if present
+    pub offset: Option<f64>,
+    /// Waveform Bits Stored (003A,021A)
+    pub bits_stored: Option<u16>,
+    /// Filter Low Frequency (003A,0220) in Hz
+    pub filter_low_frequency: Option<f64>,
+    /// Filter High Frequency (003A,0221) in Hz
+    pub filter_high_frequency: Option<f64>,
+    /// Notch Filter Frequency (003A,0222) in Hz
+    pub notch_filter_frequency: Option<f64>,
+}
+
+fn get_f64<D>(obj: &InMemDicomObject<D>, tag: Tag) -> Result<Option<f64>>
+where
+    D: DataDictionary + Clone,
+{
+    Ok(get_f64s(obj, tag)?.and_then(|v| v.first().cloned()))
+}
+
+fn get_u32<D>(obj: &InMemDicomObject<D>, tag: Tag, message: &'static str) -> Result<u32>
+where
+    D: DataDictionary + Clone,
+{
+    match obj.element(tag)?.value().primitive() {
+        Some(&PrimitiveValue::U32(ref v)) if !v.is_empty() => Ok(v[0]),
+        Some(&PrimitiveValue::U16(ref v)) if !v.is_empty() => Ok(u32::from(v[0])),
+        _ => get_f64(obj, tag)
+            .ok()
+            .and_then(|v| v)
+            .filter(|&v| v >= 0. && v <= f64::from(u32::MAX))
+            .map(|v| v as u32)
+            .ok_or_else(|| error(message)),
+    }
+}
+
+/// Fetch a code string element of the first item of a code sequence.
+fn get_item_code<D>(obj: &InMemDicomObject<D>, sequence: Tag, tag: Tag) -> Result<Option<String>>
+where
+    D: DataDictionary + Clone,
+{
+    match first_item(obj, sequence)? {
+        Some(item) => get_code(item, tag),
+        None => Ok(None),
+    }
+}
+
+impl WaveformChannel {
+    /// Read a channel definition from an item of the Channel Definition
+    /// Sequence.
+    pub fn from_item<D>(item: &InMemDicomObject<D>) -> Result<WaveformChannel>
+    where
+        D: DataDictionary + Clone,
+    {
+        Ok(WaveformChannel {
+            number: get_f64(item, WAVEFORM_CHANNEL_NUMBER)?.map(|v| v as i32),
+            label: get_code(item, CHANNEL_LABEL)?,
+            source: get_item_code(item, CHANNEL_SOURCE_SEQUENCE, CODE_MEANING)?,
+            sensitivity: get_f64(item, CHANNEL_SENSITIVITY)?,
+            sensitivity_units: get_item_code(
+                item,
+                CHANNEL_SENSITIVITY_UNITS_SEQUENCE,
+                CODE_VALUE,
+            )?,
+            sensitivity_correction_factor: get_f64(item, CHANNEL_SENSITIVITY_CORRECTION_FACTOR)?
+                .unwrap_or(1.),
+            baseline: get_f64(item, CHANNEL_BASELINE)?.unwrap_or(0.),
+            time_skew: get_f64(item, CHANNEL_TIME_SKEW)?,
+            sample_skew: get_f64(item, CHANNEL_SAMPLE_SKEW)?,
+            offset: get_f64(item, CHANNEL_OFFSET)?,
+            bits_stored: get_u16(item, WAVEFORM_BITS_STORED, "bad waveform bits stored")?,
+            filter_low_frequency: get_f64(item, FILTER_LOW_FREQUENCY)?,
+            filter_high_frequency: get_f64(item, FILTER_HIGH_FREQUENCY)?,
+            notch_filter_frequency: get_f64(item, NOTCH_FILTER_FREQUENCY)?,
+        })
+    }
+
+    /// Scale a raw sample value of this channel into sensitivity units,
+    /// as `raw * sensitivity * correction factor + baseline`.
+    /// Values are returned unchanged if the channel has no sensitivity.
+    pub fn scale(&self, raw: i64) -> f64 {
+        match self.sensitivity {
+            Some(sensitivity) => {
+                raw as f64 * sensitivity * self.sensitivity_correction_factor + self.baseline
+            }
+            None => raw as f64,
+        }
+    }
+}
+
+/// A multiplex group of a waveform, with its samples decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiplexGroup {
+    /// Multiplex Group Label (003A,0020)
+    pub label: Option<String>,
+    /// Waveform Originality (003A,0004), `ORIGINAL` or `DERIVED`
+    pub originality: Option<String>,
+    /// Multiplex Group Time Offset (0018,1068) in milliseconds
+    pub time_offset: Option<f64>,
+    /// Sampling Frequency (003A,001A) in Hz
+    pub sampling_frequency: f64,
+    /// Number of Waveform Samples (003A,0010) of each channel
+    pub number_of_samples: u32,
+    /// Waveform Bits Allocated (5400,1004)
+    pub bits_allocated: u16,
+    /// Waveform Sample Interpretation (5400,1006)
+    pub interpretation: SampleInterpretation,
+    /// Waveform Padding Value (5400,100A), which marks missing samples
+    pub padding_value: Option<i64>,
+    /// The definitions of the channels, in the order they are multiplexed
+    pub channels: Vec<WaveformChannel>,
+    /// The raw sample values, interleaved by channel
+    samples: Vec<i64>,
+}
+
+impl MultiplexGroup {
+    /// Read a multiplex group from an item of the Waveform Sequence.
+    pub fn from_item<D>(item: &InMemDicomObject<D>) -> Result<MultiplexGroup>
+    where
+        D: DataDictionary + Clone,
+    {
+        let number_of_channels = require_u16(
+            item,
+            NUMBER_OF_WAVEFORM_CHANNELS,
+            "bad number of waveform channels",
+        ).map_err(|_| error("bad number of waveform channels"))?;
+        let number_of_samples = get_u32(
+            item,
+            NUMBER_OF_WAVEFORM_SAMPLES,
+            "bad number of waveform samples",
+        )?;
+        let sampling_frequency = get_f64(item, SAMPLING_FREQUENCY)?
+            .filter(|&f| f > 0.)
+            .ok_or_else(|| error("bad sampling frequency"))?;
+        let bits_allocated = require_u16(
+            item,
+            WAVEFORM_BITS_ALLOCATED,
+            "bad waveform bits allocated",
+        ).map_err(|_| error("bad waveform bits allocated"))?;
+        let interpretation = get_code(item, WAVEFORM_SAMPLE_INTERPRETATION)?
+            .and_then(|code| SampleInterpretation::from_code(&code))
+            .ok_or_else(|| error("unsupported waveform sample interpretation"))?;
+        if interpretation.bits_allocated() != bits_allocated {
+            return Err(error("waveform bits allocated do not match the sample interpretation"));
+        }
+
+        let channels = item.element(CHANNEL_DEFINITION_SEQUENCE)
+            .ok()
+            .and_then(|e| e.value().item())
+            .ok_or_else(|| error("missing channel definition sequence"))?
+            .iter()
+            .map(WaveformChannel::from_item)
+            .collect::<Result<Vec<_>>>()?;
+        if channels.len() != usize::from(number_of_channels) {
+            return Err(error("channel definitions do not match the number of channels"));
+        }
+
+        let sample_size = usize::from(bits_allocated / 8);
+        let decode = |bytes: &[u8]| -> Vec<i64> {
+            bytes
+                .chunks(sample_size)
+                .filter(|b| b.len() == sample_size)
+                .map(|b| interpretation.decode(b))
+                .collect()
+        };
+        let padding_value = match item.element(WAVEFORM_PADDING_VALUE) {
+            Ok(e) => {
+                let value = e.value().primitive().ok_or_else(|| error("bad padding value"))?;
+                decode(&native_bytes(value).map_err(|_| error("bad padding value"))?)
+                    .first()
+                    .cloned()
+            }
+            Err(Error::NoSuchDataElement) => None,
+            Err(e) => return Err(e),
+        };
+        let data = item.element(WAVEFORM_DATA)?
+            .value()
+            .primitive()
+            .ok_or_else(|| error("bad waveform data"))?;
+        let mut samples = decode(&native_bytes(data).map_err(|_| error("bad waveform data"))?);
+        let expected = number_of_samples as usize * channels.len();
+        if samples.len() < expected {
+            return Err(error("waveform data is too short"));
+        }
+        samples.truncate(expected);
+
+        Ok(MultiplexGroup {
+            label: get_code(item, MULTIPLEX_GROUP_LABEL)?,
+            originality: get_code(item, WAVEFORM_ORIGINALITY)?,
+            time_offset: get_f64(item, MULTIPLEX_GROUP_TIME_OFFSET)?,
+            sampling_frequency,
+            number_of_samples,
+            bits_allocated,
+            interpretation,
+            padding_value,
+            channels,
+            samples,
+        })
+    }
+
+    /// The duration of the group in seconds.
+    pub fn duration(&self) -> f64 {
+        f64::from(self.number_of_samples) / self.sampling_frequency
+    }
+
+    /// Retrieve the raw sample values of a channel, or `None` if there is
+    /// no channel at this index.
+    pub fn raw_channel(&self, channel: usize) -> Option<Vec<i64>> {
+        if channel >= self.channels.len() {
+            return None;
+        }
+        Some(
+            self.samples
+                .iter()
+                .skip(channel)
+                .step_by(self.channels.len())
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Retrieve the sample values of a channel, scaled by the channel's
+    /// sensitivity, baseline and sensitivity correction factor. Padding
+    /// samples are returned as NaN.
+    pub fn channel_samples(&self, channel: usize) -> Option<Vec<f64>> {
+        let definition = self.channels.get(channel)?;
+        let raw = self.raw_channel(channel)?;
+        Some(
+            raw.into_iter()
+                .map(|v| {
+                    if Some(v) == self.padding_value {
+                        f64::NAN
+                    } else {
+                        definition.scale(v)
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Retrieve the scaled sample values of every channel.
+    pub fn all_channel_samples(&self) -> Vec<Vec<f64>> {
+        (0..self.channels.len())
+            .filter_map(|c| self.channel_samples(c))
+            .collect()
+    }
+}
+
+/// Read all multiplex groups of the Waveform Sequence of an object. An
+/// empty list is returned if the object has no waveform.
+pub fn waveforms<D>(obj: &InMemDicomObject<D>) -> Result<Vec<MultiplexGroup>>
+where
+    D: DataDictionary + Clone,
+{
+    let items = match obj.element(WAVEFORM_SEQUENCE) {
+        Ok(e) => e.value()
+            .item()
+            .ok_or_else(|| error("waveform sequence is not a sequence"))?,
+        Err(Error::NoSuchDataElement) => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    items.iter().map(MultiplexGroup::from_item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{a_law, mu_law, waveforms, MultiplexGroup, SampleInterpretation,
+                WaveformChannel};
+    use data::{DataElement, Length, Tag, VR};
+    use data::value::{PrimitiveValue, Value};
+    use object::mem::InMemDicomObject;
+    use object::pixeldata::testing::{put, Object};
+
+    fn put_items(obj: &mut Object, tag: Tag, items: Vec<Object>) {
+        obj.put(DataElement::new(
+            tag,
+            VR::SQ,
+            Value::Sequence {
+                items,
+                size: Length::undefined(),
+            },
+        ));
+    }
+
+    fn text(v: &str) -> PrimitiveValue {
+        PrimitiveValue::Str(v.to_owned())
+    }
+
+    fn channel(label: &str, sensitivity: f64, baseline: f64) -> Object {
+        let mut ch = InMemDicomObject::create_empty();
+        put(&mut ch, Tag(0x003A, 0x0203), VR::SH, text(label));
+        let mut source = InMemDicomObject::create_empty();
+        put(&mut source, Tag(0x0008, 0x0100), VR::SH, text("5.6.3-9-1"));
+        put(&mut source, Tag(0x0008, 0x0104), VR::LO, text(&format!("Lead {}", label)));
+        put_items(&mut ch, Tag(0x003A, 0x0208), vec![source]);
+        put(&mut ch, Tag(0x003A, 0x0210), VR::DS, PrimitiveValue::F64(vec![sensitivity]));
+        let mut units = InMemDicomObject::create_empty();
+        put(&mut units, Tag(0x0008, 0x0100), VR::SH, text("uV"));
+        put_items(&mut ch, Tag(0x003A, 0x0211), vec![units]);
+        put(&mut ch, Tag(0x003A, 0x0213), VR::DS, PrimitiveValue::F64(vec![baseline]));
+        put(&mut ch, Tag(0x003A, 0x021A), VR::US, PrimitiveValue::U16(vec![16]));
+        ch
+    }
+
+    /// A group of 3 leads with 4 samples each, stored as signed 16 bits.
+    fn ecg_group() -> Object {
+        let mut group = InMemDicomObject::create_empty();
+        put(&mut group, Tag(0x003A, 0x0005), VR::US, PrimitiveValue::U16(vec![3]));
+        put(&mut group, Tag(0x003A, 0x0010), VR::UL, PrimitiveValue::U32(vec![4]));
+        put(&mut group, Tag(0x003A, 0x001A), VR::DS, PrimitiveValue::F64(vec![500.]));
+        put(&mut group, Tag(0x003A, 0x0020), VR::CS, text("RHYTHM"));
+        let channels = vec![
+            channel("I", 2.5, 0.),
+            channel("II", 2.5, 10.),
+            channel("III", 0.5, 0.),
+        ];
+        put_items(&mut group, Tag(0x003A, 0x0200), channels);
+        put(&mut group, Tag(0x5400, 0x1004), VR::US, PrimitiveValue::U16(vec![16]));
+        put(&mut group, Tag(0x5400, 0x1006), VR::CS, text("SS"));
+        put(&mut group, Tag(0x5400, 0x100A), VR::OW, PrimitiveValue::U16(vec![0x8000]));
+        let samples: Vec<i16> = vec![1, 2, 3, -4, -5, -6, 100, 200, 300, -32768, 0, 7];
+        put(
+            &mut group,
+            Tag(0x5400, 0x1010),
+            VR::OW,
+            PrimitiveValue::U16(samples.iter().map(|&v| v as u16).collect()),
+        );
+        group
+    }
+
+    #[test]
+    fn decode_multiplex_groups() {
+        let mut obj = InMemDicomObject::create_empty();
+        assert!(waveforms(&obj).unwrap().is_empty());
+
+        // a second group of a single mu-law channel without sensitivity
+        let mut audio = InMemDicomObject::create_empty();
+        put(&mut audio, Tag(0x003A, 0x0005), VR::US, PrimitiveValue::U16(vec![1]));
+        put(&mut audio, Tag(0x003A, 0x0010), VR::UL, PrimitiveValue::U32(vec![3]));
+        put(&mut audio, Tag(0x003A, 0x001A), VR::DS, PrimitiveValue::F64(vec![8000.]));
+        put_items(&mut audio, Tag(0x003A, 0x0200), vec![InMemDicomObject::create_empty()]);
+        put(&mut audio, Tag(0x5400, 0x1004), VR::US, PrimitiveValue::U16(vec![8]));
+        put(&mut audio, Tag(0x5400, 0x1006), VR::CS, text("MB"));
+        put(&mut audio, Tag(0x5400, 0x1010), VR::OB, PrimitiveValue::U8(vec![0xFF, 0x00, 0x80]));
+        put_items(&mut obj, Tag(0x5400, 0x0100), vec![ecg_group(), audio]);
+
+        let groups = waveforms(&obj).unwrap();
+        assert_eq!(groups.len(), 2);
+        let ecg = &groups[0];
+        assert_eq!(ecg.label, Some("RHYTHM".to_owned()));
+        assert_eq!(ecg.interpretation, SampleInterpretation::SS);
+        assert_eq!(ecg.number_of_samples, 4);
+        assert_eq!(ecg.duration(), 0.008);
+        assert_eq!(ecg.channels.len(), 3);
+        assert_eq!(ecg.channels[1].label, Some("II".to_owned()));
+        assert_eq!(ecg.channels[1].source, Some("Lead II".to_owned()));
+        assert_eq!(ecg.channels[1].sensitivity_units, Some("uV".to_owned()));
+        assert_eq!(ecg.channels[1].bits_stored, Some(16));
+        assert_eq!(ecg.padding_value, Some(-32768));
+
+        assert_eq!(ecg.raw_channel(0), Some(vec![1, -4, 100, -32768]));
+        assert_eq!(ecg.channel_samples(1), Some(vec![15., -2.5, 510., 10.]));
+        assert_eq!(ecg.channel_samples(2), Some(vec![1.5, -3., 150., 3.5]));
+        let lead_i = ecg.channel_samples(0).unwrap();
+        assert_eq!(lead_i[..3], [2.5, -10., 250.]);
+        assert!(lead_i[3].is_nan());
+        assert_eq!(ecg.channel_samples(3), None);
+        assert_eq!(ecg.all_channel_samples().len(), 3);
+
+        let audio = &groups[1];
+        assert_eq!(audio.channels[0].sensitivity, None);
+        assert_eq!(audio.channel_samples(0), Some(vec![0., -32124., 32124.]));
+    }
+
+    #[test]
+    fn scale_with_baseline_and_correction_factor() {
+        let mut item = channel("II", 2.5, 10.);
+        put(&mut item, Tag(0x003A, 0x0212), VR::DS, PrimitiveValue::F64(vec![0.5]));
+        let channel = WaveformChannel::from_item(&item).unwrap();
+        assert_eq!(channel.sensitivity_correction_factor, 0.5);
+        assert_eq!(channel.baseline, 10.);
+        assert_eq!(channel.scale(4), 15.);
+        assert_eq!(channel.scale(-8), 0.);
+    }
+
+    #[test]
+    fn inconsistent_multiplex_groups() {
+        let mut group = ecg_group();
+        put(&mut group, Tag(0x003A, 0x0005), VR::US, PrimitiveValue::U16(vec![2]));
+        assert!(MultiplexGroup::from_item(&group).is_err());
+
+        let mut group = ecg_group();
+        put(&mut group, Tag(0x003A, 0x0010), VR::UL, PrimitiveValue::U32(vec![5]));
+        assert!(MultiplexGroup::from_item(&group).is_err());
+
+        let mut group = ecg_group();
+        put(&mut group, Tag(0x5400, 0x1004), VR::US, PrimitiveValue::U16(vec![8]));
+        assert!(MultiplexGroup::from_item(&group).is_err());
+
+        let mut group = ecg_group();
+        put(&mut group, Tag(0x5400, 0x1006), VR::CS, text("XX"));
+        assert!(MultiplexGroup::from_item(&group).is_err());
+    }
+
+    #[test]
+    fn g711_expansion() {
+        assert_eq!(mu_law(0xFF), 0);
+        assert_eq!(mu_law(0x7F), 0);
+        assert_eq!(mu_law(0x00), -32124);
+        assert_eq!(mu_law(0x80), 32124);
+        assert_eq!(a_law(0xD5), 8);
+        assert_eq!(a_law(0x55), -8);
+        assert_eq!(a_law(0xAA), 32256);
+        assert_eq!(a_law(0x2A), -32256);
+    }
+}
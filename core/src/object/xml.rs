@@ -0,0 +1,562 @@
+//! This module contains the conversion of in-memory DICOM objects to and
+//! from the Native DICOM Model, the XML representation of DICOM data sets
+//! specified in PS3.19, section A.1.
+//!
+//! A `NativeDicomModel` document holds one `DicomAttribute` element per
+//! data element, identified by its `tag` and `vr` attributes and described
+//! by `Value`, `PersonName`, `Item`, `InlineBinary` or `BulkData` child
+//! elements. The writer annotates each attribute with its `keyword` from
+//! the object's data dictionary, and the reader resolves attributes given
+//! only by keyword through the same dictionary.
+//!
+//! # Example
+//!
+//! ```
+//! # use dicom_core::Result;
+//! # fn run() -> Result<()> {
+//! use dicom_core::object::xml;
+//! use dicom_core::data::Tag;
+//! use dicom_core::StandardDataDictionary;
+//!
+//! let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+//! <NativeDicomModel xml:space="preserve">
+//!   <DicomAttribute keyword="Modality">
+//!     <Value number="1">MR</Value>
+//!   </DicomAttribute>
+//! </NativeDicomModel>"#;
+//! let obj = xml::from_xml_str(text, StandardDataDictionary)?;
+//! assert_eq!(obj.element(Tag(0x0008, 0x0060))?.as_string()?, "MR");
+//! # Ok(())
+//! # }
+//! # run().unwrap();
+//! ```
+
+use base64;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use std::io::{BufRead, Write};
+
+use data::datetime::{format_date, format_datetime, format_time};
+use data::person_name::{PersonName, PersonNameComponents};
+use data::printer::format_ds;
+use data::value::{DicomValueType, PrimitiveValue, Value};
+use data::{DataElement, Header, Length, Tag, VR};
+use dictionary::{DataDictionary, DictionaryEntry};
+use error::{Error, Result};
+use object::binary::{is_binary, to_le_bytes};
+use object::mem::{InMemDicomObject, InMemElement};
+use object::model::{parse_tag_text, read_binary, read_values, tag_text};
+
+/// The person name component groups, in the order of their encoding.
+const NAME_GROUPS: [&str; 3] = ["Alphabetic", "Ideographic", "Phonetic"];
+
+/// The person name components, in the order of their encoding.
+const NAME_COMPONENTS: [&str; 5] =
+    ["FamilyName", "GivenName", "MiddleName", "NamePrefix", "NameSuffix"];
+
+/// Write a DICOM object as a Native DICOM Model document, in UTF-8 and
+/// indented by two spaces. The values of OB, OW, OF, OD, OL and UN
+/// elements are encoded in base64 into `InlineBinary` child elements.
+pub fn to_xml<D, W>(obj: &InMemDicomObject<D>, to: W) -> Result<()>
+where
+    D: DataDictionary,
+    D: Clone,
+    W: Write,
+{
+    to_xml_with(obj, to, |_, _| None)
+}
+
+/// Write a DICOM object as a Native DICOM Model document, with the option
+/// of moving binary values out of it.
+///
+/// `bulk_data` receives the tag and value of each element of OB, OW, OF,
+/// OD, OL or UN representation. When it yields a URI, the attribute gets a
+/// `<BulkData uri="..."/>` child in place of its `InlineBinary` one; it is
+/// then up to the caller to make the value available there.
+pub fn to_xml_with<D, W, F>(obj: &InMemDicomObject<D>, to: W, mut bulk_data: F) -> Result<()>
+where
+    D: DataDictionary,
+    D: Clone,
+    W: Write,
+    F: FnMut(Tag, &PrimitiveValue) -> Option<String>,
+{
+    let mut writer = Writer::new_with_indent(to, b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"UTF-8"), None)))?;
+    let mut root = BytesStart::borrowed_name(b"NativeDicomModel");
+    root.push_attribute(("xml:space", "preserve"));
+    writer.write_event(Event::Start(root))?;
+    write_object(&mut writer, obj, &mut bulk_data)?;
+    writer.write_event(Event::End(BytesEnd::borrowed(b"NativeDicomModel")))?;
+    Ok(())
+}
+
+/// Convert a DICOM object to a string holding a Native DICOM Model
+/// document.
+pub fn to_xml_string<D>(obj: &InMemDicomObject<D>) -> Result<String>
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    let mut buf = Vec::new();
+    to_xml(obj, &mut buf)?;
+    Ok(String::from_utf8(buf).expect("XML output should be valid UTF-8"))
+}
+
+/// Build a DICOM object from a Native DICOM Model document. `BulkData`
+/// references are not followed, so the attributes holding them are read
+/// with an empty value; see `from_xml_with` to resolve them.
+pub fn from_xml<D, R>(src: R, dict: D) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+    R: BufRead,
+{
+    let root = read_tree(src)?;
+    read_document(&root, dict, &mut |_: &str| Ok(None))
+}
+
+/// Build a DICOM object from a Native DICOM Model document.
+///
+/// `bulk_data` is called with the `uri` attribute of each `BulkData`
+/// element, and must return the little endian bytes of the value found
+/// there. Failing to do so aborts the whole read with that error.
+pub fn from_xml_with<D, R, F>(src: R, dict: D, mut bulk_data: F) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+    R: BufRead,
+    F: FnMut(&str) -> Result<Vec<u8>>,
+{
+    let root = read_tree(src)?;
+    read_document(&root, dict, &mut |uri: &str| bulk_data(uri).map(Some))
+}
+
+/// Build a DICOM object from a string holding a Native DICOM Model
+/// document.
+pub fn from_xml_str<D>(text: &str, dict: D) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+{
+    from_xml(text.as_bytes(), dict)
+}
+
+fn write_object<D, W, F>(
+    writer: &mut Writer<W>,
+    obj: &InMemDicomObject<D>,
+    bulk_data: &mut F,
+) -> Result<()>
+where
+    D: DataDictionary,
+    D: Clone,
+    W: Write,
+    F: FnMut(Tag, &PrimitiveValue) -> Option<String>,
+{
+    for elem in obj {
+        write_attribute(writer, obj.dictionary(), elem, bulk_data)?;
+    }
+    Ok(())
+}
+
+fn write_attribute<D, W, F>(
+    writer: &mut Writer<W>,
+    dict: &D,
+    elem: &InMemElement<D>,
+    bulk_data: &mut F,
+) -> Result<()>
+where
+    D: DataDictionary,
+    D: Clone,
+    W: Write,
+    F: FnMut(Tag, &PrimitiveValue) -> Option<String>,
+{
+    let tag = elem.tag();
+    let vr = elem.vr();
+    let mut start = BytesStart::borrowed_name(b"DicomAttribute");
+    start.push_attribute(("tag", tag_text(tag).as_str()));
+    start.push_attribute(("vr", vr.to_string()));
+    if let Some(entry) = dict.by_tag(tag) {
+        start.push_attribute(("keyword", entry.alias()));
+    }
+    writer.write_event(Event::Start(start))?;
+
+    match *elem.value() {
+        Value::Sequence { ref items, .. } => {
+            for (i, item) in items.iter().enumerate() {
+                let mut start = BytesStart::borrowed_name(b"Item");
+                start.push_attribute(("number", (i + 1).to_string().as_str()));
+                writer.write_event(Event::Start(start))?;
+                write_object(writer, item, bulk_data)?;
+                writer.write_event(Event::End(BytesEnd::borrowed(b"Item")))?;
+            }
+        }
+        Value::PixelSequence { .. } => {
+            return Err(Error::InvalidXml(
+                "encapsulated pixel data cannot be written in the native DICOM model",
+            ));
+        }
+        Value::Primitive(ref value) if is_binary(vr) => {
+            if let Some(uri) = bulk_data(tag, value) {
+                let mut start = BytesStart::borrowed_name(b"BulkData");
+                start.push_attribute(("uri", uri.as_str()));
+                writer.write_event(Event::Empty(start))?;
+            } else if !value.is_empty() {
+                let bytes = to_le_bytes(value)
+                    .ok_or(Error::InvalidXml("non-numeric value in a binary representation"))?;
+                write_text_element(writer, b"InlineBinary", None, &base64::encode(&bytes))?;
+            }
+        }
+        Value::Primitive(ref value) if vr == VR::PN => {
+            for (i, text) in value_texts(vr, value).iter().enumerate() {
+                write_person_name(writer, i + 1, &PersonName::parse(text))?;
+            }
+        }
+        Value::Primitive(ref value) => {
+            for (i, text) in value_texts(vr, value).iter().enumerate() {
+                write_text_element(writer, b"Value", Some(i + 1), text)?;
+            }
+        }
+    }
+    writer.write_event(Event::End(BytesEnd::borrowed(b"DicomAttribute")))?;
+    Ok(())
+}
+
+fn write_text_element<W: Write>(
+    writer: &mut Writer<W>,
+    name: &[u8],
+    number: Option<usize>,
+    text: &str,
+) -> Result<()> {
+    let mut start = BytesStart::borrowed_name(name);
+    if let Some(number) = number {
+        start.push_attribute(("number", number.to_string().as_str()));
+    }
+    writer.write_event(Event::Start(start))?;
+    writer.write_event(Event::Text(BytesText::from_plain_str(text)))?;
+    writer.write_event(Event::End(BytesEnd::borrowed(name)))?;
+    Ok(())
+}
+
+fn write_person_name<W: Write>(
+    writer: &mut Writer<W>,
+    number: usize,
+    name: &PersonName,
+) -> Result<()> {
+    let mut start = BytesStart::borrowed_name(b"PersonName");
+    start.push_attribute(("number", number.to_string().as_str()));
+    writer.write_event(Event::Start(start))?;
+    let groups = [&name.alphabetic, &name.ideographic, &name.phonetic];
+    for (group_name, group) in NAME_GROUPS.iter().zip(groups.iter()) {
+        if group.is_empty() {
+            continue;
+        }
+        writer.write_event(Event::Start(BytesStart::borrowed_name(group_name.as_bytes())))?;
+        let components = [
+            &group.family,
+            &group.given,
+            &group.middle,
+            &group.prefix,
+            &group.suffix,
+        ];
+        for (component_name, component) in NAME_COMPONENTS.iter().zip(components.iter()) {
+            if !component.is_empty() {
+                write_text_element(writer, component_name.as_bytes(), None, component)?;
+            }
+        }
+        writer.write_event(Event::End(BytesEnd::borrowed(group_name.as_bytes())))?;
+    }
+    writer.write_event(Event::End(BytesEnd::borrowed(b"PersonName")))?;
+    Ok(())
+}
+
+/// Obtain the textual form of each value, without padding.
+fn value_texts(vr: VR, value: &PrimitiveValue) -> Vec<String> {
+    let trim = |s: &str| s.trim_end_matches(&[' ', '\0'][..]).to_owned();
+    match *value {
+        PrimitiveValue::Empty => vec![],
+        PrimitiveValue::Str(ref s) => match vr {
+            VR::LT | VR::ST | VR::UT | VR::UR => vec![trim(s)],
+            _ => s.split('\\').map(trim).collect(),
+        },
+        PrimitiveValue::Strs(ref v) => v.iter().map(|s| trim(s)).collect(),
+        PrimitiveValue::Tags(ref v) => v.iter().map(|t| tag_text(*t)).collect(),
+        PrimitiveValue::U8(ref v) => v.iter().map(|x| x.to_string()).collect(),
+        PrimitiveValue::I16(ref v) => v.iter().map(|x| x.to_string()).collect(),
+        PrimitiveValue::U16(ref v) => v.iter().map(|x| x.to_string()).collect(),
+        PrimitiveValue::I32(ref v) => v.iter().map(|x| x.to_string()).collect(),
+        PrimitiveValue::U32(ref v) => v.iter().map(|x| x.to_string()).collect(),
+        PrimitiveValue::F32(ref v) => v.iter().map(|x| x.to_string()).collect(),
+        PrimitiveValue::F64(ref v) if vr == VR::DS => v.iter().map(|x| format_ds(*x)).collect(),
+        PrimitiveValue::F64(ref v) => v.iter().map(|x| x.to_string()).collect(),
+        PrimitiveValue::Date(ref v) => v.iter().map(format_date).collect(),
+        PrimitiveValue::Time(ref v) => v.iter().map(format_time).collect(),
+        PrimitiveValue::DateTime(ref v) => v.iter().map(format_datetime).collect(),
+    }
+}
+
+/// A minimal XML element tree, holding only what the native DICOM model
+/// needs.
+#[derive(Debug)]
+struct Node {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+    text: String,
+}
+
+impl Node {
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|a| a.0 == key).map(|a| a.1.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// The children with the given name, sorted by their `number`
+    /// attribute.
+    fn numbered(&self, name: &str) -> Vec<&Node> {
+        let mut nodes: Vec<_> = self.children.iter().filter(|c| c.name == name).collect();
+        nodes.sort_by_key(|c| c.attribute("number").and_then(|n| n.trim().parse::<usize>().ok()));
+        nodes
+    }
+}
+
+fn read_tree<R: BufRead>(src: R) -> Result<Node> {
+    let mut reader = Reader::from_reader(src);
+    reader.expand_empty_elements(true);
+    let mut buf = Vec::new();
+    let mut stack: Vec<Node> = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(ref e) => {
+                let mut attributes = Vec::new();
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    let key = String::from_utf8_lossy(attr.key).into_owned();
+                    attributes.push((key, attr.unescape_and_decode_value(&reader)?));
+                }
+                stack.push(Node {
+                    name: String::from_utf8_lossy(e.name()).into_owned(),
+                    attributes,
+                    children: vec![],
+                    text: String::new(),
+                });
+            }
+            Event::End(_) => {
+                let node = stack.pop().ok_or(Error::InvalidXml("unbalanced element"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => return Ok(node),
+                }
+            }
+            Event::Text(ref e) => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&e.unescape_and_decode(&reader)?);
+                }
+            }
+            Event::CData(ref e) => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&reader.decode(e.escaped()));
+                }
+            }
+            Event::Eof => return Err(Error::InvalidXml("missing NativeDicomModel element")),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+fn read_document<D, F>(root: &Node, dict: D, bulk_data: &mut F) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+    F: FnMut(&str) -> Result<Option<Vec<u8>>>,
+{
+    if root.name != "NativeDicomModel" {
+        return Err(Error::InvalidXml("missing NativeDicomModel element"));
+    }
+    read_object(root, dict, bulk_data)
+}
+
+fn read_object<D, F>(node: &Node, dict: D, bulk_data: &mut F) -> Result<InMemDicomObject<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+    F: FnMut(&str) -> Result<Option<Vec<u8>>>,
+{
+    let elements: Vec<_> = node
+        .children
+        .iter()
+        .filter(|c| c.name == "DicomAttribute")
+        .map(|c| read_attribute(c, &dict, bulk_data))
+        .collect();
+    InMemDicomObject::from_iter_with_dict(elements, dict)
+}
+
+fn read_attribute<D, F>(node: &Node, dict: &D, bulk_data: &mut F) -> Result<InMemElement<D>>
+where
+    D: DataDictionary,
+    D: Clone,
+    F: FnMut(&str) -> Result<Option<Vec<u8>>>,
+{
+    let tag = match (node.attribute("tag"), node.attribute("keyword")) {
+        (Some(tag), _) => parse_tag_text(tag, Error::InvalidXml)?,
+        (None, Some(keyword)) => dict
+            .by_name(keyword.trim())
+            .map(|e| e.tag())
+            .ok_or(Error::InvalidXml("unknown attribute keyword"))?,
+        (None, None) => return Err(Error::InvalidXml("missing attribute tag")),
+    };
+    let vr = match node.attribute("vr") {
+        Some(vr) => {
+            VR::from_str(vr.trim()).ok_or(Error::InvalidXml("unknown value representation"))?
+        }
+        None => dict
+            .by_tag(tag)
+            .map(|e| e.vr())
+            .ok_or(Error::InvalidXml("missing value representation"))?,
+    };
+
+    if vr == VR::SQ {
+        let items: Result<Vec<_>> = node
+            .numbered("Item")
+            .into_iter()
+            .map(|item| read_object(item, dict.clone(), bulk_data))
+            .collect();
+        let value = Value::Sequence {
+            items: items?,
+            size: Length::undefined(),
+        };
+        return Ok(DataElement::new(tag, vr, value));
+    }
+
+    let value = if let Some(data) = node.child("InlineBinary") {
+        let data: String = data.text.split_whitespace().collect();
+        let bytes = base64::decode(&data).map_err(|_| Error::InvalidXml("invalid base64 data"))?;
+        read_binary(vr, bytes, Error::InvalidXml)?
+    } else if let Some(bulk) = node.child("BulkData") {
+        let uri = bulk.attribute("uri").ok_or(Error::InvalidXml("missing bulk data URI"))?;
+        match bulk_data(uri)? {
+            Some(bytes) => read_binary(vr, bytes, Error::InvalidXml)?,
+            None => PrimitiveValue::Empty,
+        }
+    } else if vr == VR::PN {
+        let names: Vec<_> = node.numbered("PersonName").into_iter().map(read_person_name).collect();
+        if names.is_empty() {
+            PrimitiveValue::Empty
+        } else {
+            PrimitiveValue::Strs(names)
+        }
+    } else {
+        let texts: Vec<_> = node.numbered("Value").into_iter().map(|v| v.text.as_str()).collect();
+        read_values(vr, &texts, Error::InvalidXml)?
+    };
+    Ok(DataElement::new(tag, vr, Value::Primitive(value)))
+}
+
+/// Read a `PersonName` element back into its encoded form.
+fn read_person_name(node: &Node) -> String {
+    let group = |name: &str| {
+        node.child(name).map_or_else(PersonNameComponents::default, |group| {
+            let component = |name: &str| {
+                group.child(name).map_or_else(String::new, |c| c.text.clone())
+            };
+            PersonNameComponents {
+                family: component(NAME_COMPONENTS[0]),
+                given: component(NAME_COMPONENTS[1]),
+                middle: component(NAME_COMPONENTS[2]),
+                prefix: component(NAME_COMPONENTS[3]),
+                suffix: component(NAME_COMPONENTS[4]),
+            }
+        })
+    };
+    let name = PersonName {
+        alphabetic: group(NAME_GROUPS[0]),
+        ideographic: group(NAME_GROUPS[1]),
+        phonetic: group(NAME_GROUPS[2]),
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_xml_str, from_xml_with, to_xml_string, to_xml_with};
+    use error::Error;
+    use std::error::Error as BaseError;
+    use data::value::{PrimitiveValue, Value};
+    use data::{Tag, VR};
+    use dictionary::StandardDataDictionary;
+    use object::model::testing::{every_primitive_value, nested_sequences};
+
+    #[test]
+    fn round_trip_every_primitive_value() {
+        let obj = every_primitive_value();
+        let text = to_xml_string(&obj).unwrap();
+        assert!(text.contains(r#"<DicomAttribute tag="00100010" vr="PN" keyword="PatientName">"#));
+        assert!(text.contains("<FamilyName>山田</FamilyName>"));
+        assert_eq!(from_xml_str(&text, StandardDataDictionary).unwrap(), obj);
+    }
+
+    #[test]
+    fn round_trip_nested_sequences() {
+        let obj = nested_sequences();
+        let text = to_xml_string(&obj).unwrap();
+        assert!(text.contains(r#"<Item number="2">"#));
+        let parsed = from_xml_str(&text, StandardDataDictionary).unwrap();
+        match *parsed.element(Tag(0x0040, 0x0275)).unwrap().value() {
+            Value::Sequence { ref items, .. } => assert_eq!(items.len(), 1),
+            ref value => panic!("expected a sequence, got {:?}", value),
+        }
+        assert_eq!(to_xml_string(&parsed).unwrap(), text);
+    }
+
+    #[test]
+    fn read_keywords_and_bulk_data() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<NativeDicomModel xml:space="preserve">
+  <DicomAttribute keyword="PatientName">
+    <PersonName number="1">
+      <Alphabetic><FamilyName>Wang</FamilyName><GivenName>XiaoDong</GivenName></Alphabetic>
+      <Ideographic><FamilyName>王</FamilyName><GivenName>小東</GivenName></Ideographic>
+    </PersonName>
+  </DicomAttribute>
+  <DicomAttribute tag="00280030" vr="DS">
+    <Value number="2">0.5</Value>
+    <Value number="1">0.25</Value>
+  </DicomAttribute>
+  <DicomAttribute tag="7FE00010" vr="OW"><BulkData uri="pixels"/></DicomAttribute>
+</NativeDicomModel>"#;
+        let obj = from_xml_with(text.as_bytes(), StandardDataDictionary, |uri| {
+            assert_eq!(uri, "pixels");
+            Ok(vec![1, 0, 2, 0])
+        }).unwrap();
+        let name = obj.element(Tag(0x0010, 0x0010)).unwrap();
+        assert_eq!(name.vr(), VR::PN);
+        assert_eq!(name.value().to_person_name().unwrap().ideographic.given, "小東");
+        let spacing = obj.element(Tag(0x0028, 0x0030)).unwrap();
+        assert_eq!(spacing.value().primitive(), Some(&PrimitiveValue::F64(vec![0.25, 0.5])));
+        let pixels = obj.element(Tag(0x7FE0, 0x0010)).unwrap();
+        assert_eq!(pixels.value().primitive(), Some(&PrimitiveValue::U16(vec![1, 2])));
+
+        let obj = from_xml_str(text, StandardDataDictionary).unwrap();
+        let pixels = obj.element(Tag(0x7FE0, 0x0010)).unwrap();
+        assert_eq!(pixels.value().primitive(), Some(&PrimitiveValue::Empty));
+        let mut out = Vec::new();
+        to_xml_with(&obj, &mut out, |_, _| Some("pixels".to_owned())).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains(r#"<BulkData uri="pixels"/>"#));
+
+        let text = r#"<NativeDicomModel><DicomAttribute keyword="NoSuch"/></NativeDicomModel>"#;
+        assert!(from_xml_str(text, StandardDataDictionary).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn malformed_documents_keep_their_cause() {
+        let text = "<NativeDicomModel><DicomAttribute></NativeDicomModel>";
+        match from_xml_str(text, StandardDataDictionary) {
+            Err(ref e @ Error::Xml(_)) => assert!(e.cause().is_some()),
+            other => panic!("expected an XML error, got {:?}", other),
+        }
+    }
+}
@@ -1,26 +1,30 @@
 //! This module contains the concept of a DICOM codec registry.
 //! A codec registry maps a DICOM UID of a transfer syntax into the respective
 //! transfer syntax' encoding and decoding component.
-//! 
+//!
+//! The global registry is populated with the transfer syntaxes supported by
+//! this crate. More transfer syntaxes (such as ones with proprietary pixel
+//! data codecs) can be added at run time with `CodecRegistry::register`.
 
 extern crate lazy_static;
 
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::{PoisonError, RwLock};
 use transfer_syntax;
 use transfer_syntax::TransferSyntax;
 
-type DynTransferSyntax<'ts> = Box<(TransferSyntax + Send + Sync + 'ts)>;
 type DynTransferSyntaxRef<'ts> = &'ts (TransferSyntax + Send + Sync);
 
 /// Data type for a registry of DICOM codecs.
-pub struct CodecRegistry<'ts> {
-    m: HashMap<&'static str, DynTransferSyntax<'ts>>,
+pub struct CodecRegistry {
+    m: RwLock<HashMap<&'static str, DynTransferSyntaxRef<'static>>>,
 }
 
-impl<'ts> fmt::Debug for CodecRegistry<'ts> {
+impl fmt::Debug for CodecRegistry {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let entries: HashMap<&str, &str> = self.m.iter()
+        let m = self.m.read().unwrap_or_else(PoisonError::into_inner);
+        let entries: HashMap<&str, &str> = m.iter()
             .map(|(uid, ts)| (*uid, ts.name()))
             .collect();
         f.debug_struct("CodecRegistry")
@@ -29,9 +33,23 @@ impl<'ts> fmt::Debug for CodecRegistry<'ts> {
     }
 }
 
-impl<'ts> CodecRegistry<'ts> {
+impl Default for CodecRegistry {
+    fn default() -> CodecRegistry {
+        CodecRegistry {
+            m: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl CodecRegistry {
+    /// Create an empty registry. The transfer syntaxes supported by this
+    /// crate are only found in the global registry (see `get_registry`).
+    pub fn new() -> CodecRegistry {
+        CodecRegistry::default()
+    }
+
     /// Obtain a DICOM codec by transfer syntax UID.
-    pub fn get<U: AsRef<str>>(&'ts self, uid: U) -> Option<DynTransferSyntaxRef<'ts>> {
+    pub fn get<U: AsRef<str>>(&self, uid: U) -> Option<DynTransferSyntaxRef<'static>> {
         let ts_uid = {
             let uid = uid.as_ref();
             if uid.chars().rev().next() == Some('\0') {
@@ -40,34 +58,161 @@ impl<'ts> CodecRegistry<'ts> {
                 &uid
             }
         };
-        self.m.get(ts_uid).map(|b| b.as_ref())
+        let m = self.m.read().unwrap_or_else(PoisonError::into_inner);
+        m.get(ts_uid).cloned()
+    }
+
+    /// Register a transfer syntax, so that it can be retrieved by its UID.
+    /// If a transfer syntax with the same UID was already registered,
+    /// it is replaced and the previous one is returned.
+    ///
+    /// Registered transfer syntaxes are never deallocated.
+    pub fn register<T>(&self, ts: T) -> Option<DynTransferSyntaxRef<'static>>
+    where
+        T: TransferSyntax + Send + Sync + 'static,
+    {
+        self.register_static(Box::leak(Box::new(ts)))
+    }
+
+    /// Register a transfer syntax with a static lifetime, so that it can be
+    /// retrieved by its UID. If a transfer syntax with the same UID was
+    /// already registered, it is replaced and the previous one is returned.
+    pub fn register_static(
+        &self,
+        ts: DynTransferSyntaxRef<'static>,
+    ) -> Option<DynTransferSyntaxRef<'static>> {
+        let mut m = self.m.write().unwrap_or_else(PoisonError::into_inner);
+        m.insert(ts.uid(), ts)
+    }
+
+    /// Retrieve the UIDs of all registered transfer syntaxes, in no
+    /// particular order.
+    pub fn uids(&self) -> Vec<&'static str> {
+        let m = self.m.read().unwrap_or_else(PoisonError::into_inner);
+        m.keys().cloned().collect()
     }
 }
 
 lazy_static! {
-    static ref REGISTRY: CodecRegistry<'static> = {
-        CodecRegistry { m: initialize_codecs() }
+    static ref REGISTRY: CodecRegistry = {
+        CodecRegistry { m: RwLock::new(initialize_codecs()) }
     };
 }
 
 /// Retrieve the global codec registry.
-pub fn get_registry() -> &'static CodecRegistry<'static> {
+pub fn get_registry() -> &'static CodecRegistry {
     &REGISTRY
 }
 
-fn initialize_codecs() -> HashMap<&'static str, DynTransferSyntax<'static>> {
-    let mut m = HashMap::<&'static str, DynTransferSyntax<'static>>::new();
+fn initialize_codecs() -> HashMap<&'static str, DynTransferSyntaxRef<'static>> {
+    let mut m = HashMap::<&'static str, DynTransferSyntaxRef<'static>>::new();
 
-    let ts = transfer_syntax::ExplicitVRLittleEndian;
-    m.insert(ts.uid(), Box::from(ts));
-    let ts = transfer_syntax::ImplicitVRLittleEndian;
-    m.insert(ts.uid(), Box::from(ts));
-    let ts = transfer_syntax::ExplicitVRBigEndian;
-    m.insert(ts.uid(), Box::from(ts));
-    let ts = transfer_syntax::DeflatedExplicitVRLittleEndian;
-    m.insert(ts.uid(), Box::from(ts));
-    let ts = transfer_syntax::JPEGBaseline;
-    m.insert(ts.uid(), Box::from(ts));
+    static EXPLICIT_VR_LE: transfer_syntax::ExplicitVRLittleEndian =
+        transfer_syntax::ExplicitVRLittleEndian;
+    m.insert(EXPLICIT_VR_LE.uid(), &EXPLICIT_VR_LE);
+    static IMPLICIT_VR_LE: transfer_syntax::ImplicitVRLittleEndian =
+        transfer_syntax::ImplicitVRLittleEndian;
+    m.insert(IMPLICIT_VR_LE.uid(), &IMPLICIT_VR_LE);
+    static EXPLICIT_VR_BE: transfer_syntax::ExplicitVRBigEndian =
+        transfer_syntax::ExplicitVRBigEndian;
+    m.insert(EXPLICIT_VR_BE.uid(), &EXPLICIT_VR_BE);
+    static DEFLATED_EXPLICIT_VR_LE: transfer_syntax::DeflatedExplicitVRLittleEndian =
+        transfer_syntax::DeflatedExplicitVRLittleEndian;
+    m.insert(DEFLATED_EXPLICIT_VR_LE.uid(), &DEFLATED_EXPLICIT_VR_LE);
+    static JPEG_BASELINE: transfer_syntax::JPEGBaseline = transfer_syntax::JPEGBaseline;
+    m.insert(JPEG_BASELINE.uid(), &JPEG_BASELINE);
+    static RLE_LOSSLESS: transfer_syntax::RLELossless = transfer_syntax::RLELossless;
+    m.insert(RLE_LOSSLESS.uid(), &RLE_LOSSLESS);
 
     m
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{get_registry, CodecRegistry};
+    use error::Result;
+    use transfer_syntax::{FrameDescription, PixelDataCodec, TransferSyntax};
+    use util::Endianness;
+
+    /// A codec which inverts every byte.
+    #[derive(Debug)]
+    struct InvertCodec;
+
+    impl PixelDataCodec for InvertCodec {
+        fn decode_frame(&self, data: &[u8], _: &FrameDescription) -> Result<Vec<u8>> {
+            Ok(data.iter().map(|b| !b).collect())
+        }
+
+        fn encode_frame(&self, data: &[u8], _: &FrameDescription) -> Result<Vec<u8>> {
+            Ok(data.iter().map(|b| !b).collect())
+        }
+    }
+
+    #[derive(Debug)]
+    struct InvertedTransferSyntax;
+
+    impl TransferSyntax for InvertedTransferSyntax {
+        fn uid(&self) -> &'static str {
+            "2.25.316723412837593817591739127839"
+        }
+
+        fn name(&self) -> &'static str {
+            "Inverted Pixel Data"
+        }
+
+        fn endianness(&self) -> Endianness {
+            Endianness::LE
+        }
+
+        fn pixel_data_codec(&self) -> Option<&PixelDataCodec> {
+            Some(&InvertCodec)
+        }
+    }
+
+    #[test]
+    fn builtin_transfer_syntaxes() {
+        let registry = get_registry();
+        let ts = registry.get("1.2.840.10008.1.2.1\0").unwrap();
+        assert_eq!(ts.name(), "Explicit VR Little Endian");
+        assert!(ts.pixel_data_codec().is_none());
+
+        let ts = registry.get("1.2.840.10008.1.2.4.50").unwrap();
+        assert!(ts.pixel_data_codec().is_some());
+        assert!(registry.uids().contains(&"1.2.840.10008.1.2"));
+
+        let ts = registry.get("1.2.840.10008.1.2.5").unwrap();
+        assert_eq!(ts.name(), "RLE Lossless");
+        assert!(ts.pixel_data_codec().is_some());
+    }
+
+    static INVERTED: InvertedTransferSyntax = InvertedTransferSyntax;
+
+    #[test]
+    fn register_transfer_syntax() {
+        let registry = CodecRegistry::new();
+        let uid = INVERTED.uid();
+        assert!(registry.get(uid).is_none());
+        assert!(registry.register_static(&INVERTED).is_none());
+        assert_eq!(registry.uids(), vec![uid]);
+
+        let ts = registry.get(uid).unwrap();
+        assert_eq!(ts.name(), "Inverted Pixel Data");
+        let description = FrameDescription {
+            rows: 1,
+            columns: 2,
+            samples_per_pixel: 1,
+            bits_allocated: 8,
+            photometric_interpretation: "MONOCHROME2".to_owned(),
+        };
+        let codec = ts.pixel_data_codec().unwrap();
+        let encoded = codec.encode_frame(&[0x00, 0x0F], &description).unwrap();
+        assert_eq!(encoded, vec![0xFF, 0xF0]);
+        assert_eq!(codec.decode_frame(&encoded, &description).unwrap(), vec![0x00, 0x0F]);
+
+        // registering again replaces the previous entry
+        assert!(registry.register_static(&INVERTED).is_some());
+        assert_eq!(registry.uids().len(), 1);
+        // the global registry is left untouched
+        assert!(get_registry().get(uid).is_none());
+    }
+}
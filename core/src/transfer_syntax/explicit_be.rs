@@ -190,7 +190,7 @@ where
     type Writer = W;
 
     fn encode_tag(&self, to: &mut W, tag: Tag) -> Result<()> {
-        let mut buf = [0u8, 4];
+        let mut buf = [0u8; 4];
         BigEndian::write_u16(&mut buf[..], tag.group());
         BigEndian::write_u16(&mut buf[2..], tag.element());
         to.write_all(&buf)?;
@@ -239,7 +239,7 @@ where
     fn encode_item_header(&self, to: &mut W, len: u32) -> Result<()> {
         let mut buf = [0u8; 8];
         BigEndian::write_u16(&mut buf, 0xFFFE);
-        BigEndian::write_u16(&mut buf, 0xE000);
+        BigEndian::write_u16(&mut buf[2..], 0xE000);
         BigEndian::write_u32(&mut buf[4..], len);
         to.write_all(&buf)?;
         Ok(())
@@ -248,7 +248,7 @@ where
     fn encode_item_delimiter(&self, to: &mut W) -> Result<()> {
         let mut buf = [0u8; 8];
         BigEndian::write_u16(&mut buf, 0xFFFE);
-        BigEndian::write_u16(&mut buf, 0xE00D);
+        BigEndian::write_u16(&mut buf[2..], 0xE00D);
         // remaining bytes are already zero, so it's ready to write
         to.write_all(&buf)?;
         Ok(())
@@ -257,7 +257,7 @@ where
     fn encode_sequence_delimiter(&self, to: &mut W) -> Result<()> {
         let mut buf = [0u8; 8];
         BigEndian::write_u16(&mut buf, 0xFFFE);
-        BigEndian::write_u16(&mut buf, 0xE0DD);
+        BigEndian::write_u16(&mut buf[2..], 0xE0DD);
         // remaining bytes are already zero, so it's ready to write
         to.write_all(&buf)?;
         Ok(())
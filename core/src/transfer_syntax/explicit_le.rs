@@ -191,7 +191,7 @@ where
     type Writer = W;
 
     fn encode_tag(&self, to: &mut W, tag: Tag) -> Result<()> {
-        let mut buf = [0u8, 4];
+        let mut buf = [0u8; 4];
         LittleEndian::write_u16(&mut buf[..], tag.group());
         LittleEndian::write_u16(&mut buf[2..], tag.element());
         to.write_all(&buf)?;
@@ -238,7 +238,7 @@ where
     fn encode_item_header(&self, to: &mut W, len: u32) -> Result<()> {
         let mut buf = [0u8; 8];
         LittleEndian::write_u16(&mut buf, 0xFFFE);
-        LittleEndian::write_u16(&mut buf, 0xE000);
+        LittleEndian::write_u16(&mut buf[2..], 0xE000);
         LittleEndian::write_u32(&mut buf[4..], len);
         try!(to.write_all(&buf));
         Ok(())
@@ -247,7 +247,7 @@ where
     fn encode_item_delimiter(&self, to: &mut W) -> Result<()> {
         let mut buf = [0u8; 8];
         LittleEndian::write_u16(&mut buf, 0xFFFE);
-        LittleEndian::write_u16(&mut buf, 0xE00D);
+        LittleEndian::write_u16(&mut buf[2..], 0xE00D);
         try!(to.write_all(&buf));
         Ok(())
     }
@@ -255,7 +255,7 @@ where
     fn encode_sequence_delimiter(&self, to: &mut W) -> Result<()> {
         let mut buf = [0u8; 8];
         LittleEndian::write_u16(&mut buf, 0xFFFE);
-        LittleEndian::write_u16(&mut buf, 0xE0DD);
+        LittleEndian::write_u16(&mut buf[2..], 0xE0DD);
         try!(to.write_all(&buf));
         Ok(())
     }
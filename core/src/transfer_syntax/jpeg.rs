@@ -0,0 +1,748 @@
+//! A decoder of JPEG Baseline (Process 1) images, as found in the
+//! encapsulated pixel data of the transfer syntax with the same name.
+//!
+//! Only the baseline process is supported: sequential DCT-based coding
+//! with Huffman entropy coding and 8-bit samples. Progressive, hierarchical,
+//! lossless and arithmetic-coded images are rejected.
+
+use error::{Error, Result};
+use object::pixeldata::InMemoryPixelData;
+use std::f32::consts::PI;
+
+/// The position of each coefficient in zig-zag order, in natural
+/// (row-major) order.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27,
+    20, 13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58,
+    59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Decode a JPEG Baseline image into 8-bit samples, interleaved by pixel.
+///
+/// If `convert_ybr` is true and the image has three components, these are
+/// taken as YCbCr and converted to RGB. Otherwise, the samples are kept as
+/// they were encoded.
+///
+/// Since every block takes at least two bits of entropy-coded data, images
+/// declaring more blocks than the data could hold are rejected before any
+/// sample buffer is allocated.
+pub fn decode(data: &[u8], convert_ybr: bool) -> Result<InMemoryPixelData<u8>> {
+    let mut decoder = Decoder::new(None);
+    decoder.decode(data)?;
+    decoder.into_pixel_data(convert_ybr)
+}
+
+/// Decode a JPEG Baseline image like `decode`, rejecting it unless its
+/// frame header declares the given number of rows and columns.
+pub fn decode_with_size(
+    data: &[u8],
+    convert_ybr: bool,
+    rows: u16,
+    columns: u16,
+) -> Result<InMemoryPixelData<u8>> {
+    let mut decoder = Decoder::new(Some((usize::from(rows), usize::from(columns))));
+    decoder.decode(data)?;
+    decoder.into_pixel_data(convert_ybr)
+}
+
+fn error(message: &'static str) -> Error {
+    Error::PixelDataDecode(message)
+}
+
+/// A Huffman table, in the form described in Annex F.2.2.3 of the standard.
+#[derive(Debug, Clone)]
+struct HuffmanTable {
+    maxcode: [i32; 17],
+    mincode: [i32; 17],
+    valptr: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl HuffmanTable {
+    fn new(counts: &[u8], values: Vec<u8>) -> Result<HuffmanTable> {
+        let mut table = HuffmanTable {
+            maxcode: [-1; 17],
+            mincode: [0; 17],
+            valptr: [0; 17],
+            values,
+        };
+        let mut code = 0i32;
+        let mut k = 0i32;
+        for l in 1..17 {
+            let n = i32::from(counts[l - 1]);
+            if n > 0 {
+                table.valptr[l] = k;
+                table.mincode[l] = code;
+                code += n;
+                k += n;
+                table.maxcode[l] = code - 1;
+            }
+            code <<= 1;
+        }
+        if k as usize > table.values.len() {
+            return Err(error("bad Huffman table"));
+        }
+        Ok(table)
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u8> {
+        let mut code = reader.bits(1) as i32;
+        for l in 1..17 {
+            if code <= self.maxcode[l] {
+                let i = self.valptr[l] + code - self.mincode[l];
+                return Ok(self.values[i as usize]);
+            }
+            code = (code << 1) | reader.bits(1) as i32;
+        }
+        Err(error("bad Huffman code"))
+    }
+}
+
+/// A reader of the entropy-coded segments of a scan.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    acc: u32,
+    nbits: u32,
+    /// whether a marker was reached, in which case only zeros are read
+    at_marker: bool,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        BitReader {
+            data,
+            pos,
+            acc: 0,
+            nbits: 0,
+            at_marker: false,
+        }
+    }
+
+    fn fill(&mut self) {
+        while self.nbits <= 24 {
+            let mut byte = 0;
+            if !self.at_marker && self.pos < self.data.len() {
+                byte = self.data[self.pos];
+                if byte == 0xFF {
+                    if self.data.get(self.pos + 1) == Some(&0) {
+                        // stuffed byte
+                        self.pos += 2;
+                    } else {
+                        self.at_marker = true;
+                        byte = 0;
+                    }
+                } else {
+                    self.pos += 1;
+                }
+            }
+            self.acc |= u32::from(byte) << (24 - self.nbits);
+            self.nbits += 8;
+        }
+    }
+
+    /// Read `n` bits (up to 16) as an unsigned integer.
+    fn bits(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        if self.nbits < n {
+            self.fill();
+        }
+        let v = self.acc >> (32 - n);
+        self.acc <<= n;
+        self.nbits -= n;
+        v
+    }
+
+    /// Read `n` bits as a signed value, as in the EXTEND procedure.
+    fn receive_extend(&mut self, n: u32) -> i32 {
+        if n == 0 {
+            return 0;
+        }
+        let v = self.bits(n) as i32;
+        if v < 1 << (n - 1) {
+            v - (1 << n) + 1
+        } else {
+            v
+        }
+    }
+
+    /// Discard the remaining bits and skip the following restart marker.
+    fn restart(&mut self) -> Result<()> {
+        self.acc = 0;
+        self.nbits = 0;
+        self.at_marker = false;
+        match (self.data.get(self.pos), self.data.get(self.pos + 1)) {
+            (Some(&0xFF), Some(&m)) if (0xD0..=0xD7).contains(&m) => {
+                self.pos += 2;
+                Ok(())
+            }
+            _ => Err(error("missing restart marker")),
+        }
+    }
+
+    /// Retrieve the position of the next marker after the scan.
+    fn end_position(&self) -> usize {
+        let mut pos = self.pos;
+        while pos + 1 < self.data.len() {
+            let m = self.data[pos + 1];
+            if self.data[pos] == 0xFF && m != 0 && !(0xD0..=0xD7).contains(&m) {
+                break;
+            }
+            pos += 1;
+        }
+        pos
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    tq: usize,
+    /// blocks per line in the component's plane
+    bw: usize,
+    /// block lines in the component's plane
+    bh: usize,
+    /// the decoded samples, padded to whole blocks
+    plane: Vec<u8>,
+    dc_pred: i32,
+    dc_table: usize,
+    ac_table: usize,
+}
+
+#[derive(Debug)]
+struct Decoder {
+    expected_size: Option<(usize, usize)>,
+    width: usize,
+    height: usize,
+    components: Vec<Component>,
+    qtables: [[u16; 64]; 4],
+    dc_tables: [Option<HuffmanTable>; 4],
+    ac_tables: [Option<HuffmanTable>; 4],
+    restart_interval: usize,
+    hmax: usize,
+    vmax: usize,
+    idct_table: Vec<f32>,
+}
+
+/// Divide, rounding up. `usize::div_ceil` is too recent for the supported
+/// compilers.
+#[allow(unknown_lints, clippy::manual_div_ceil)]
+fn ceil_div(n: usize, d: usize) -> usize {
+    (n + d - 1) / d
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<usize> {
+    if pos + 1 >= data.len() {
+        return Err(error("unexpected end of data"));
+    }
+    Ok((usize::from(data[pos]) << 8) | usize::from(data[pos + 1]))
+}
+
+impl Decoder {
+    fn new(expected_size: Option<(usize, usize)>) -> Self {
+        Decoder {
+            expected_size,
+            width: 0,
+            height: 0,
+            components: Vec::new(),
+            qtables: [[0; 64]; 4],
+            dc_tables: [None, None, None, None],
+            ac_tables: [None, None, None, None],
+            restart_interval: 0,
+            hmax: 1,
+            vmax: 1,
+            idct_table: Vec::new(),
+        }
+    }
+
+    fn decode(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+            return Err(error("missing start of image marker"));
+        }
+        let mut pos = 2;
+        loop {
+            // find the next marker, skipping any fill bytes
+            while pos < data.len() && data[pos] != 0xFF {
+                pos += 1;
+            }
+            while pos < data.len() && data[pos] == 0xFF {
+                pos += 1;
+            }
+            if pos >= data.len() {
+                return Err(error("missing end of image marker"));
+            }
+            let marker = data[pos];
+            pos += 1;
+            match marker {
+                0xD9 => break,
+                0xD0..=0xD7 | 0x01 => continue,
+                _ => {}
+            }
+            let len = read_u16(data, pos)?;
+            if len < 2 || pos + len > data.len() {
+                return Err(error("bad marker segment length"));
+            }
+            let segment = &data[pos + 2..pos + len];
+            pos += len;
+            match marker {
+                0xC0 | 0xC1 => self.read_frame(segment, data.len())?,
+                0xC2..=0xCF if marker != 0xC4 && marker != 0xC8 && marker != 0xCC => {
+                    return Err(error("only baseline JPEG images are supported"));
+                }
+                0xC4 => self.read_huffman_tables(segment)?,
+                0xCC => return Err(error("arithmetic coding is not supported")),
+                0xDB => self.read_quantization_tables(segment)?,
+                0xDD => {
+                    self.restart_interval = read_u16(segment, 0)?;
+                }
+                0xDA => {
+                    pos = self.read_scan(segment, data, pos)?;
+                }
+                _ => {
+                    // application data, comments and others are ignored
+                }
+            }
+        }
+        if self.components.is_empty() {
+            return Err(error("missing frame header"));
+        }
+        Ok(())
+    }
+
+    fn read_frame(&mut self, segment: &[u8], data_len: usize) -> Result<()> {
+        if !self.components.is_empty() {
+            return Err(error("more than one frame"));
+        }
+        if segment.len() < 6 || segment[0] != 8 {
+            return Err(error("only 8-bit samples are supported"));
+        }
+        self.height = read_u16(segment, 1)?;
+        self.width = read_u16(segment, 3)?;
+        let n = usize::from(segment[5]);
+        if self.width == 0 || self.height == 0 {
+            return Err(error("image dimensions are not defined"));
+        }
+        if let Some(size) = self.expected_size {
+            if size != (self.height, self.width) {
+                return Err(error("JPEG image does not match the image pixel description"));
+            }
+        }
+        if (n != 1 && n != 3) || segment.len() < 6 + 3 * n {
+            return Err(error("unsupported number of components"));
+        }
+        for i in 0..n {
+            let c = &segment[6 + 3 * i..9 + 3 * i];
+            let (h, v) = (usize::from(c[1] >> 4), usize::from(c[1] & 15));
+            if h == 0 || h > 4 || v == 0 || v > 4 || c[2] > 3 {
+                return Err(error("bad component parameters"));
+            }
+            self.components.push(Component {
+                id: c[0],
+                h,
+                v,
+                tq: usize::from(c[2]),
+                ..Component::default()
+            });
+        }
+        self.hmax = self.components.iter().map(|c| c.h).max().unwrap_or(1);
+        self.vmax = self.components.iter().map(|c| c.v).max().unwrap_or(1);
+        let mcux = ceil_div(self.width, 8 * self.hmax);
+        let mcuy = ceil_div(self.height, 8 * self.vmax);
+        let blocks: usize = self.components.iter().map(|c| mcux * c.h * mcuy * c.v).sum();
+        if blocks > data_len.saturating_mul(4) {
+            return Err(error("image dimensions exceed the size of the data"));
+        }
+        for c in &mut self.components {
+            c.bw = mcux * c.h;
+            c.bh = mcuy * c.v;
+            c.plane = vec![0; c.bw * c.bh * 64];
+        }
+        Ok(())
+    }
+
+    fn read_quantization_tables(&mut self, mut segment: &[u8]) -> Result<()> {
+        while !segment.is_empty() {
+            let precision = segment[0] >> 4;
+            let id = usize::from(segment[0] & 15);
+            if id > 3 {
+                return Err(error("bad quantization table"));
+            }
+            let size = if precision == 0 { 64 } else { 128 };
+            if segment.len() < 1 + size {
+                return Err(error("bad quantization table"));
+            }
+            for k in 0..64 {
+                self.qtables[id][k] = if precision == 0 {
+                    u16::from(segment[1 + k])
+                } else {
+                    read_u16(segment, 1 + 2 * k)? as u16
+                };
+            }
+            segment = &segment[1 + size..];
+        }
+        Ok(())
+    }
+
+    fn read_huffman_tables(&mut self, mut segment: &[u8]) -> Result<()> {
+        while !segment.is_empty() {
+            if segment.len() < 17 {
+                return Err(error("bad Huffman table"));
+            }
+            let class = segment[0] >> 4;
+            let id = usize::from(segment[0] & 15);
+            if class > 1 || id > 3 {
+                return Err(error("bad Huffman table"));
+            }
+            let counts = &segment[1..17];
+            let total = counts.iter().map(|&n| usize::from(n)).sum::<usize>();
+            if segment.len() < 17 + total {
+                return Err(error("bad Huffman table"));
+            }
+            let table = HuffmanTable::new(counts, segment[17..17 + total].to_vec())?;
+            if class == 0 {
+                self.dc_tables[id] = Some(table);
+            } else {
+                self.ac_tables[id] = Some(table);
+            }
+            segment = &segment[17 + total..];
+        }
+        Ok(())
+    }
+
+    /// Read a scan header and decode the following entropy-coded data,
+    /// returning the position after the scan.
+    fn read_scan(&mut self, segment: &[u8], data: &[u8], pos: usize) -> Result<usize> {
+        if self.components.is_empty() {
+            return Err(error("scan before frame header"));
+        }
+        let n = usize::from(*segment.first().unwrap_or(&0));
+        if n == 0 || n > 4 || segment.len() < 1 + 2 * n + 3 {
+            return Err(error("bad scan header"));
+        }
+        let mut scan = Vec::with_capacity(n);
+        for i in 0..n {
+            let id = segment[1 + 2 * i];
+            let tables = segment[2 + 2 * i];
+            let index = self.components
+                .iter()
+                .position(|c| c.id == id)
+                .ok_or_else(|| error("scan refers to unknown component"))?;
+            let c = &mut self.components[index];
+            c.dc_table = usize::from(tables >> 4);
+            c.ac_table = usize::from(tables & 15);
+            if c.dc_table > 3 || c.ac_table > 3 {
+                return Err(error("bad scan header"));
+            }
+            scan.push(index);
+        }
+
+        if self.idct_table.is_empty() {
+            self.idct_table = idct_table();
+        }
+        for c in &mut self.components {
+            c.dc_pred = 0;
+        }
+
+        let mut reader = BitReader::new(data, pos);
+        if n == 1 {
+            // non-interleaved: one block per MCU, only covering the image
+            let index = scan[0];
+            let (bw, bh) = {
+                let c = &self.components[index];
+                let w = ceil_div(self.width * c.h, self.hmax);
+                let h = ceil_div(self.height * c.v, self.vmax);
+                (ceil_div(w, 8), ceil_div(h, 8))
+            };
+            for i in 0..bw * bh {
+                if i > 0 && self.restart_interval > 0 && i % self.restart_interval == 0 {
+                    reader.restart()?;
+                    self.components[index].dc_pred = 0;
+                }
+                self.decode_block(&mut reader, index, i % bw, i / bw)?;
+            }
+        } else {
+            let mcux = self.components[0].bw / self.components[0].h;
+            let mcuy = self.components[0].bh / self.components[0].v;
+            for i in 0..mcux * mcuy {
+                if i > 0 && self.restart_interval > 0 && i % self.restart_interval == 0 {
+                    reader.restart()?;
+                    for c in &mut self.components {
+                        c.dc_pred = 0;
+                    }
+                }
+                let (mx, my) = (i % mcux, i / mcux);
+                for &index in &scan {
+                    let (h, v) = (self.components[index].h, self.components[index].v);
+                    for by in 0..v {
+                        for bx in 0..h {
+                            self.decode_block(&mut reader, index, mx * h + bx, my * v + by)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(reader.end_position())
+    }
+
+    /// Decode a single 8x8 block and write its samples into the
+    /// component's plane.
+    fn decode_block(
+        &mut self,
+        reader: &mut BitReader,
+        index: usize,
+        bx: usize,
+        by: usize,
+    ) -> Result<()> {
+        let mut coefs = [0f32; 64];
+        {
+            let c = &mut self.components[index];
+            let dc_table = self.dc_tables[c.dc_table]
+                .as_ref()
+                .ok_or_else(|| error("missing Huffman table"))?;
+            let ac_table = self.ac_tables[c.ac_table]
+                .as_ref()
+                .ok_or_else(|| error("missing Huffman table"))?;
+            let q = &self.qtables[c.tq];
+
+            let t = dc_table.decode(reader)?;
+            if t > 11 {
+                return Err(error("bad DC coefficient"));
+            }
+            c.dc_pred = c.dc_pred
+                .checked_add(reader.receive_extend(u32::from(t)))
+                .ok_or_else(|| error("bad DC coefficient"))?;
+            let dc = c.dc_pred
+                .checked_mul(i32::from(q[0]))
+                .ok_or_else(|| error("bad DC coefficient"))?;
+            coefs[0] = dc as f32;
+
+            let mut k = 1;
+            while k < 64 {
+                let rs = ac_table.decode(reader)?;
+                let (r, s) = (usize::from(rs >> 4), u32::from(rs & 15));
+                if s == 0 {
+                    if r == 15 {
+                        k += 16;
+                        continue;
+                    }
+                    break;
+                }
+                k += r;
+                if k > 63 {
+                    return Err(error("bad AC coefficient"));
+                }
+                coefs[ZIGZAG[k]] = (reader.receive_extend(s) * i32::from(q[k])) as f32;
+                k += 1;
+            }
+        }
+
+        let table = &self.idct_table;
+        let c = &mut self.components[index];
+        let stride = c.bw * 8;
+        let mut tmp = [0f32; 64];
+        for v in 0..8 {
+            for x in 0..8 {
+                let mut sum = 0.;
+                for u in 0..8 {
+                    sum += table[x * 8 + u] * coefs[v * 8 + u];
+                }
+                tmp[v * 8 + x] = sum;
+            }
+        }
+        for y in 0..8 {
+            let row = (by * 8 + y) * stride + bx * 8;
+            for x in 0..8 {
+                let mut sum = 0.;
+                for v in 0..8 {
+                    sum += table[y * 8 + v] * tmp[v * 8 + x];
+                }
+                let sample = (sum / 4. + 128.).round();
+                c.plane[row + x] = sample.clamp(0., 255.) as u8;
+            }
+        }
+        Ok(())
+    }
+
+    fn into_pixel_data(self, convert_ybr: bool) -> Result<InMemoryPixelData<u8>> {
+        let samples = self.components.len();
+        let mut data = Vec::with_capacity(self.width * self.height * samples);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                for c in &self.components {
+                    // upsampling by replication
+                    let cx = x * c.h / self.hmax;
+                    let cy = y * c.v / self.vmax;
+                    data.push(c.plane[cy * c.bw * 8 + cx]);
+                }
+            }
+        }
+        if convert_ybr && samples == 3 {
+            for pixel in data.chunks_mut(3) {
+                let (r, g, b) = ybr_to_rgb(pixel[0], pixel[1], pixel[2]);
+                pixel[0] = r;
+                pixel[1] = g;
+                pixel[2] = b;
+            }
+        }
+        InMemoryPixelData::new(
+            data,
+            self.height as u32,
+            self.width as u32,
+            8,
+            samples as u16,
+        )
+    }
+}
+
+/// Build the table of `C(u) * cos((2x + 1) * u * PI / 16)`, indexed by `x * 8 + u`.
+fn idct_table() -> Vec<f32> {
+    let mut table = vec![0.; 64];
+    for x in 0..8 {
+        for u in 0..8 {
+            let c = if u == 0 { 1. / 2f32.sqrt() } else { 1. };
+            table[x * 8 + u] = c * (((2 * x + 1) * u) as f32 * PI / 16.).cos();
+        }
+    }
+    table
+}
+
+/// Convert a full range YCbCr sample to RGB, as specified in JFIF.
+fn ybr_to_rgb(y: u8, cb: u8, cr: u8) -> (u8, u8, u8) {
+    let y = f32::from(y);
+    let cb = f32::from(cb) - 128.;
+    let cr = f32::from(cr) - 128.;
+    let clamp = |v: f32| v.round().clamp(0., 255.) as u8;
+    (
+        clamp(y + 1.402 * cr),
+        clamp(y - 0.344_136 * cb - 0.714_136 * cr),
+        clamp(y + 1.772 * cb),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, decode_with_size};
+    use data::value::Value;
+    use dictionary::StandardDataDictionary;
+    use object::mem::InMemDicomObject;
+    use object::pixeldata::PixelData;
+    use transfer_syntax::JPEGBaseline;
+
+    const PYTHON_JPG: &'static [u8] = include_bytes!("../../testdata/python.jpg");
+    const PYTHON_PPM: &'static [u8] = include_bytes!("../../testdata/python.ppm");
+    const GRAY_2X2_JPG: &'static [u8] = include_bytes!("../../testdata/2x2.jpg");
+
+    #[test]
+    fn decode_color_image() {
+        let image = decode(PYTHON_JPG, true).unwrap();
+        assert_eq!(image.rows(), 16);
+        assert_eq!(image.columns(), 16);
+        assert_eq!(image.samples_per_pixel(), 3);
+
+        // compare with the original image, in binary PPM format
+        let expected = &PYTHON_PPM[PYTHON_PPM.len() - 16 * 16 * 3..];
+        let data = image.to_vector();
+        assert_eq!(data.len(), expected.len());
+        let error: f32 = data.iter()
+            .zip(expected)
+            .map(|(&a, &b)| (f32::from(a) - f32::from(b)).abs())
+            .sum();
+        // chroma subsampling blurs the sharp edges of this image
+        let mean_error = error / data.len() as f32;
+        assert!(mean_error < 12., "mean error too high: {}", mean_error);
+
+        // luminance is much closer to the original
+        let ybr = decode(PYTHON_JPG, false).unwrap().to_vector();
+        let error: f32 = ybr.chunks(3)
+            .zip(expected.chunks(3))
+            .map(|(a, b)| {
+                let y = 0.299 * f32::from(b[0]) + 0.587 * f32::from(b[1])
+                    + 0.114 * f32::from(b[2]);
+                (f32::from(a[0]) - y).abs()
+            })
+            .sum();
+        let mean_error = error / 256.;
+        assert!(mean_error < 3., "mean luminance error too high: {}", mean_error);
+    }
+
+    #[test]
+    fn decode_without_color_conversion() {
+        let ybr = decode(PYTHON_JPG, false).unwrap().to_vector();
+        let rgb = decode(PYTHON_JPG, true).unwrap().to_vector();
+        assert_eq!(ybr.len(), rgb.len());
+        assert_ne!(ybr, rgb);
+    }
+
+    #[test]
+    fn decode_grayscale_image() {
+        let image = decode(GRAY_2X2_JPG, true).unwrap();
+        assert_eq!(image.rows(), 2);
+        assert_eq!(image.columns(), 2);
+        assert_eq!(image.samples_per_pixel(), 1);
+        // the only block has a DC difference of 0 and no AC coefficients,
+        // so all samples are at the level shift
+        assert_eq!(image.to_vector(), vec![128; 4]);
+    }
+
+    #[test]
+    fn reject_dc_overflow() {
+        // a tall image where every block adds the largest DC difference,
+        // with a DC quantization step of 255
+        let mut data = GRAY_2X2_JPG[..0x148].to_vec();
+        data[0x19] = 0xFF;
+        // 33600 rows and 8 columns, for 4200 blocks
+        data[0x5E..0x62].copy_from_slice(&[0x83, 0x40, 0x00, 0x08]);
+        for _ in 0..4200 {
+            // DC category 11 with all bits set, then end of block
+            data.extend_from_slice(&[0xFF, 0x00, 0x7F, 0xFA]);
+        }
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        assert!(decode(&data, true).is_err());
+    }
+
+    #[test]
+    fn reject_oversized_frame_headers() {
+        // 65535 rows and columns in a frame header of a 2x2 image
+        let mut data = GRAY_2X2_JPG.to_vec();
+        data[0x5E..0x62].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+        assert!(decode(&data, true).is_err());
+        assert!(decode_with_size(&data, true, 2, 2).is_err());
+
+        assert!(decode_with_size(GRAY_2X2_JPG, true, 2, 2).is_ok());
+        assert!(decode_with_size(GRAY_2X2_JPG, true, 2, 4).is_err());
+    }
+
+    #[test]
+    fn reject_bad_data() {
+        assert!(decode(&[], true).is_err());
+        assert!(decode(&[0xFF, 0xD8, 0xFF, 0xD9], true).is_err());
+        assert!(decode(&PYTHON_JPG[..200], true).is_err());
+    }
+
+    #[test]
+    fn decode_encapsulated_frames() {
+        // a single frame split into two fragments, followed by another frame
+        let value: Value<InMemDicomObject<StandardDataDictionary>> = Value::PixelSequence {
+            offset_table: vec![0, 16 + PYTHON_JPG.len() as u32],
+            fragments: vec![
+                PYTHON_JPG[..256].to_vec(),
+                PYTHON_JPG[256..].to_vec(),
+                GRAY_2X2_JPG.to_vec(),
+            ],
+        };
+        let frames = JPEGBaseline
+            .decode_frames(&value, 2, "YBR_FULL_422")
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].samples_per_pixel(), 3);
+        assert_eq!(frames[0].data(), &decode(PYTHON_JPG, true).unwrap().to_vector()[..]);
+        assert_eq!(frames[1].rows(), 2);
+
+        let frames = JPEGBaseline.decode_frames(&value, 2, "RGB ").unwrap();
+        assert_eq!(frames[0].data(), &decode(PYTHON_JPG, false).unwrap().to_vector()[..]);
+    }
+}
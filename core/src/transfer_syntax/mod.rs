@@ -6,18 +6,24 @@ pub mod codec;
 pub mod explicit_le;
 pub mod explicit_be;
 pub mod implicit_le;
+pub mod jpeg;
+pub mod rle;
 
 use std::io::{Read, Write};
 use data::decode::basic::BasicDecoder;
 use data::decode::Decode;
+use data::encode::basic::BasicEncoder;
 use data::encode::Encode;
+use data::value::{DicomValueType, Value};
+use error::{Error, Result};
+use object::pixeldata::{encapsulated_frames, InMemoryPixelData, PixelData};
 use util::Endianness;
 
 /// A decoder with its type erased.
 pub type DynamicDecoder = Box<Decode<Source = Read>>;
 
 /// An encoder with its type erased.
-pub type DynamicEncoder = Box<Encode<Writer = Write>>;
+pub type DynamicEncoder<'w> = Box<Encode<Writer = Write + 'w> + 'w>;
 
 /// Trait for a DICOM transfer syntax. Trait implementers make an entry
 /// point for obtaining the decoder and/or encoder that can handle DICOM objects
@@ -40,7 +46,7 @@ pub trait TransferSyntax {
 
     /// Retrieve the appropriate data element encoder for this transfer syntax.
     /// Can yield none if encoding is not supported.
-    fn get_encoder(&self) -> Option<DynamicEncoder> {
+    fn get_encoder<'w>(&self) -> Option<DynamicEncoder<'w>> {
         None
     }
 
@@ -48,6 +54,62 @@ pub trait TransferSyntax {
     fn get_basic_decoder(&self) -> BasicDecoder {
         BasicDecoder::from(self.endianness())
     }
+
+    /// Obtain a dynamic basic encoder, based on this transfer syntax' expected endianness.
+    fn get_basic_encoder(&self) -> BasicEncoder {
+        BasicEncoder::from(self.endianness())
+    }
+
+    /// Check whether the data set (everything after the file meta group)
+    /// is compressed as a whole with the deflate algorithm, in which case
+    /// it has to be inflated before decoding and deflated after encoding.
+    fn is_deflated(&self) -> bool {
+        false
+    }
+
+    /// Retrieve the codec for the pixel data of this transfer syntax.
+    /// Yields none if the pixel data is native (not encapsulated),
+    /// or if there is no codec available for it.
+    fn pixel_data_codec(&self) -> Option<&PixelDataCodec> {
+        None
+    }
+}
+
+/// The layout of a single frame of native pixel data, as described by the
+/// attributes of the image pixel module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameDescription {
+    /// Rows (0028,0010)
+    pub rows: u16,
+    /// Columns (0028,0011)
+    pub columns: u16,
+    /// Samples per Pixel (0028,0002)
+    pub samples_per_pixel: u16,
+    /// Bits Allocated (0028,0100)
+    pub bits_allocated: u16,
+    /// Photometric Interpretation (0028,0004), without padding
+    pub photometric_interpretation: String,
+}
+
+/// Trait for a compressor/decompressor of the frames in encapsulated
+/// pixel data. Native pixel data is in little endian, with the samples
+/// of each pixel next to each other.
+pub trait PixelDataCodec {
+    /// Decode a single compressed frame into native pixel data.
+    fn decode_frame(&self, data: &[u8], description: &FrameDescription) -> Result<Vec<u8>>;
+
+    /// Retrieve the photometric interpretation of the frames produced by
+    /// `decode_frame`, which is usually the one in the description.
+    fn decoded_photometric_interpretation<'a>(&self, description: &'a FrameDescription) -> &'a str {
+        &description.photometric_interpretation
+    }
+
+    /// Encode a single frame of native pixel data.
+    /// Fails with `UnsupportedTransferSyntax` if encoding is not supported.
+    fn encode_frame(&self, data: &[u8], description: &FrameDescription) -> Result<Vec<u8>> {
+        let _ = (data, description);
+        Err(Error::UnsupportedTransferSyntax)
+    }
 }
 
 /// Retrieve the default transfer syntax.
@@ -77,7 +139,7 @@ impl TransferSyntax for ImplicitVRLittleEndian {
         ))
     }
 
-    fn get_encoder<'w>(&self) -> Option<DynamicEncoder> {
+    fn get_encoder<'w>(&self) -> Option<DynamicEncoder<'w>> {
         Some(Box::new(
             implicit_le::ImplicitVRLittleEndianEncoder::default(),
         ))
@@ -106,7 +168,7 @@ impl TransferSyntax for ExplicitVRLittleEndian {
         ))
     }
 
-    fn get_encoder(&self) -> Option<DynamicEncoder> {
+    fn get_encoder<'w>(&self) -> Option<DynamicEncoder<'w>> {
         Some(Box::new(
             explicit_le::ExplicitVRLittleEndianEncoder::default(),
         ))
@@ -133,30 +195,215 @@ impl TransferSyntax for ExplicitVRBigEndian {
         Some(Box::new(explicit_be::ExplicitVRBigEndianDecoder::default()))
     }
 
-    fn get_encoder<'w>(&self) -> Option<DynamicEncoder> {
+    fn get_encoder<'w>(&self) -> Option<DynamicEncoder<'w>> {
         Some(Box::new(explicit_be::ExplicitVRBigEndianEncoder::default()))
     }
 }
 
-macro_rules! declare_stub_ts {
-    ($name: ident, $uid: expr) => (declare_stub_ts!($name, $uid, $name));
-    ($name: ident, $uid: expr, $alias: expr) => (
-        /// Transfer syntax: $alias
-        /// 
-        /// UID: $uid
-        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-        pub struct $name;
-        impl TransferSyntax for $name {
-            fn uid(&self) -> &'static str { $uid }
+/// Transfer syntax: DeflatedExplicitVRLittleEndian
+///
+/// The data set is encoded in explicit VR little endian, then compressed
+/// with the deflate algorithm (without a zlib header).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeflatedExplicitVRLittleEndian;
+impl TransferSyntax for DeflatedExplicitVRLittleEndian {
+    fn uid(&self) -> &'static str {
+        "1.2.840.10008.1.2.1.99"
+    }
 
-            fn name(&self) -> &'static str { $alias }
+    fn name(&self) -> &'static str {
+        "Deflated Explicit VR Little Endian"
+    }
 
-            fn endianness(&self) -> Endianness { Endianness::LE }
+    fn endianness(&self) -> Endianness {
+        Endianness::LE
+    }
+
+    fn get_decoder(&self) -> Option<DynamicDecoder> {
+        ExplicitVRLittleEndian.get_decoder()
+    }
+
+    fn get_encoder<'w>(&self) -> Option<DynamicEncoder<'w>> {
+        ExplicitVRLittleEndian.get_encoder()
+    }
+
+    fn is_deflated(&self) -> bool {
+        true
+    }
+}
+
+/// Transfer syntax: JPEGBaseline (Process 1)
+///
+/// The data set is encoded in explicit VR little endian, whereas the
+/// pixel data is encapsulated, with each frame compressed as a baseline
+/// JPEG image with 8-bit samples.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct JPEGBaseline;
+impl TransferSyntax for JPEGBaseline {
+    fn uid(&self) -> &'static str {
+        "1.2.840.10008.1.2.4.50"
+    }
+
+    fn name(&self) -> &'static str {
+        "JPEG Baseline"
+    }
+
+    fn endianness(&self) -> Endianness {
+        Endianness::LE
+    }
+
+    fn get_decoder(&self) -> Option<DynamicDecoder> {
+        ExplicitVRLittleEndian.get_decoder()
+    }
+
+    fn get_encoder<'w>(&self) -> Option<DynamicEncoder<'w>> {
+        ExplicitVRLittleEndian.get_encoder()
+    }
+
+    fn pixel_data_codec(&self) -> Option<&PixelDataCodec> {
+        Some(self)
+    }
+}
+
+/// Frames in the YBR_FULL or YBR_FULL_422 photometric interpretations
+/// are decoded to RGB.
+impl PixelDataCodec for JPEGBaseline {
+    fn decode_frame(&self, data: &[u8], description: &FrameDescription) -> Result<Vec<u8>> {
+        let convert_ybr = description.photometric_interpretation.starts_with("YBR");
+        let frame =
+            jpeg::decode_with_size(data, convert_ybr, description.rows, description.columns)?;
+        if frame.samples_per_pixel() != description.samples_per_pixel {
+            return Err(Error::PixelDataDecode(
+                "JPEG image does not match the image pixel description",
+            ));
         }
-    )
+        Ok(frame.to_vector())
+    }
+
+    fn decoded_photometric_interpretation<'a>(&self, description: &'a FrameDescription) -> &'a str {
+        if description.photometric_interpretation.starts_with("YBR") {
+            "RGB"
+        } else {
+            &description.photometric_interpretation
+        }
+    }
+}
+
+impl JPEGBaseline {
+    /// Decode the frames of an encapsulated pixel data value.
+    ///
+    /// Images in the YBR_FULL or YBR_FULL_422 photometric interpretations
+    /// are converted to RGB. Monochrome and RGB images are kept as is.
+    pub fn decode_frames<I>(
+        &self,
+        value: &Value<I>,
+        number_of_frames: u32,
+        photometric_interpretation: &str,
+    ) -> Result<Vec<InMemoryPixelData<u8>>>
+    where
+        I: DicomValueType,
+    {
+        let (offset_table, fragments) = match (value.offset_table(), value.fragments()) {
+            (Some(offset_table), Some(fragments)) => (offset_table, fragments),
+            _ => {
+                return Err(Error::PixelDataDecode(
+                    "pixel data is not encapsulated",
+                ))
+            }
+        };
+        let convert_ybr = photometric_interpretation
+            .trim_end_matches(&[' ', '\0'][..])
+            .starts_with("YBR");
+        encapsulated_frames(offset_table, fragments, number_of_frames)?
+            .iter()
+            .map(|frame| jpeg::decode(frame, convert_ybr))
+            .collect()
+    }
+}
+
+/// Transfer syntax: RLELossless
+///
+/// The data set is encoded in explicit VR little endian, whereas the
+/// pixel data is encapsulated, with each frame compressed with the
+/// run-length encoding scheme of Annex G.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RLELossless;
+impl TransferSyntax for RLELossless {
+    fn uid(&self) -> &'static str {
+        "1.2.840.10008.1.2.5"
+    }
+
+    fn name(&self) -> &'static str {
+        "RLE Lossless"
+    }
+
+    fn endianness(&self) -> Endianness {
+        Endianness::LE
+    }
+
+    fn get_decoder(&self) -> Option<DynamicDecoder> {
+        ExplicitVRLittleEndian.get_decoder()
+    }
+
+    fn get_encoder<'w>(&self) -> Option<DynamicEncoder<'w>> {
+        ExplicitVRLittleEndian.get_encoder()
+    }
+
+    fn pixel_data_codec(&self) -> Option<&PixelDataCodec> {
+        Some(self)
+    }
 }
 
-// These stub definitions provide some level of transfer syntax awareness,
-// even though they are not supported.
-declare_stub_ts!(DeflatedExplicitVRLittleEndian, "1.2.840.10008.1.2.1.99", "Deflated Explicit VR Little Endian");
-declare_stub_ts!(JPEGBaseline, "1.2.840.10008.1.2.4.50", "JPEG Baseline");
+impl PixelDataCodec for RLELossless {
+    fn decode_frame(&self, data: &[u8], description: &FrameDescription) -> Result<Vec<u8>> {
+        rle::decode(data, description)
+    }
+
+    fn encode_frame(&self, data: &[u8], description: &FrameDescription) -> Result<Vec<u8>> {
+        rle::encode(data, description)
+    }
+}
+
+impl RLELossless {
+    /// Decode a frame with 8 bits allocated per sample.
+    pub fn decode_frame_u8(
+        &self,
+        data: &[u8],
+        description: &FrameDescription,
+    ) -> Result<InMemoryPixelData<u8>> {
+        if description.bits_allocated != 8 {
+            return Err(Error::PixelDataDecode("expected 8 bits allocated"));
+        }
+        let native = rle::decode(data, description)?;
+        InMemoryPixelData::new(
+            native,
+            u32::from(description.rows),
+            u32::from(description.columns),
+            8,
+            description.samples_per_pixel,
+        )
+    }
+
+    /// Decode a frame with 16 bits allocated per sample.
+    pub fn decode_frame_u16(
+        &self,
+        data: &[u8],
+        description: &FrameDescription,
+    ) -> Result<InMemoryPixelData<u16>> {
+        if description.bits_allocated != 16 {
+            return Err(Error::PixelDataDecode("expected 16 bits allocated"));
+        }
+        let native = rle::decode(data, description)?;
+        let samples = native
+            .chunks(2)
+            .map(|b| u16::from(b[0]) | u16::from(b[1]) << 8)
+            .collect();
+        InMemoryPixelData::new(
+            samples,
+            u32::from(description.rows),
+            u32::from(description.columns),
+            16,
+            description.samples_per_pixel,
+        )
+    }
+}
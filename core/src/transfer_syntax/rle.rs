@@ -0,0 +1,292 @@
+//! An encoder and decoder of the RLE Lossless compression scheme, as defined
+//! in Annex G of part 5 of the standard.
+//!
+//! Each frame starts with a 64-byte header listing the offsets of up to 15
+//! segments. There is one segment per byte of each sample, from the most
+//! significant byte of the first sample to the least significant byte of the
+//! last sample. Each segment is compressed with the PackBits algorithm.
+
+use error::{Error, Result};
+use transfer_syntax::FrameDescription;
+
+const HEADER_LENGTH: usize = 64;
+const MAX_SEGMENTS: usize = 15;
+
+fn error(message: &'static str) -> Error {
+    Error::PixelDataDecode(message)
+}
+
+/// Check the description and retrieve the number of bytes per sample.
+fn bytes_per_sample(description: &FrameDescription) -> Result<usize> {
+    match description.bits_allocated {
+        8 | 16 | 32 => {}
+        _ => return Err(error("unsupported bits allocated for RLE")),
+    }
+    let bytes = usize::from(description.bits_allocated / 8);
+    let segments = bytes * usize::from(description.samples_per_pixel);
+    if segments == 0 || segments > MAX_SEGMENTS {
+        return Err(error("unsupported number of samples per pixel for RLE"));
+    }
+    Ok(bytes)
+}
+
+/// Decode an RLE compressed frame into native pixel data (little endian,
+/// with the samples of each pixel next to each other).
+pub fn decode(data: &[u8], description: &FrameDescription) -> Result<Vec<u8>> {
+    let bytes = bytes_per_sample(description)?;
+    let samples = usize::from(description.samples_per_pixel);
+    let pixels = usize::from(description.rows) * usize::from(description.columns);
+    if data.len() < HEADER_LENGTH {
+        return Err(error("missing RLE header"));
+    }
+
+    let header: Vec<usize> = data[..HEADER_LENGTH]
+        .chunks(4)
+        .map(|b| {
+            usize::from(b[0]) | usize::from(b[1]) << 8 | usize::from(b[2]) << 16
+                | usize::from(b[3]) << 24
+        })
+        .collect();
+    let number_of_segments = header[0];
+    if number_of_segments != bytes * samples {
+        return Err(error("unexpected number of RLE segments"));
+    }
+    let offsets = &header[1..=number_of_segments];
+
+    let stride = bytes * samples;
+    let mut out = vec![0; pixels * stride];
+    let mut segment = Vec::with_capacity(pixels);
+    for (i, &start) in offsets.iter().enumerate() {
+        let end = offsets.get(i + 1).cloned().unwrap_or(data.len());
+        if start < HEADER_LENGTH || start > end || end > data.len() {
+            return Err(error("bad RLE segment offset"));
+        }
+        segment.clear();
+        unpack_bits(&data[start..end], pixels, &mut segment)?;
+
+        // segments go from the most significant byte to the least
+        let (sample, byte) = (i / bytes, bytes - 1 - i % bytes);
+        let offset = sample * bytes + byte;
+        for (p, &v) in segment.iter().enumerate() {
+            out[p * stride + offset] = v;
+        }
+    }
+    Ok(out)
+}
+
+/// Decompress a segment until `len` bytes were produced.
+fn unpack_bits(data: &[u8], len: usize, out: &mut Vec<u8>) -> Result<()> {
+    let mut pos = 0;
+    while out.len() < len {
+        let n = *data.get(pos).ok_or_else(|| error("RLE segment too short"))? as i8;
+        pos += 1;
+        if n >= 0 {
+            let count = n as usize + 1;
+            let literal = data.get(pos..pos + count)
+                .ok_or_else(|| error("RLE segment too short"))?;
+            out.extend_from_slice(literal);
+            pos += count;
+        } else if n != -128 {
+            let count = (1 - i32::from(n)) as usize;
+            let v = *data.get(pos).ok_or_else(|| error("RLE segment too short"))?;
+            out.extend((0..count).map(|_| v));
+            pos += 1;
+        }
+    }
+    if out.len() > len {
+        return Err(error("RLE segment too long"));
+    }
+    Ok(())
+}
+
+/// Encode a frame of native pixel data (little endian, with the samples of
+/// each pixel next to each other) with RLE compression.
+pub fn encode(data: &[u8], description: &FrameDescription) -> Result<Vec<u8>> {
+    let bytes = bytes_per_sample(description)?;
+    let samples = usize::from(description.samples_per_pixel);
+    let columns = usize::from(description.columns);
+    let pixels = usize::from(description.rows) * columns;
+    let stride = bytes * samples;
+    if data.len() != pixels * stride {
+        return Err(Error::PixelDataOutOfBounds);
+    }
+
+    let number_of_segments = bytes * samples;
+    let mut out = vec![0; HEADER_LENGTH];
+    out[..4].copy_from_slice(&u32_le(number_of_segments as u32));
+    let mut plane = Vec::with_capacity(pixels);
+    for i in 0..number_of_segments {
+        let offset = out.len() as u32;
+        out[4 + 4 * i..8 + 4 * i].copy_from_slice(&u32_le(offset));
+
+        let (sample, byte) = (i / bytes, bytes - 1 - i % bytes);
+        plane.clear();
+        plane.extend(data.iter().skip(sample * bytes + byte).step_by(stride));
+        // each row is encoded separately
+        for row in plane.chunks(columns.max(1)) {
+            pack_bits(row, &mut out);
+        }
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+    Ok(out)
+}
+
+fn u32_le(v: u32) -> [u8; 4] {
+    [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]
+}
+
+/// Compress a sequence of bytes with the PackBits algorithm.
+fn pack_bits(data: &[u8], out: &mut Vec<u8>) {
+    let mut pos = 0;
+    while pos < data.len() {
+        let run = data[pos..]
+            .iter()
+            .take(128)
+            .take_while(|&&v| v == data[pos])
+            .count();
+        if run >= 3 || (run == 2 && pos + 2 == data.len()) {
+            out.push((1 - run as i32) as u8);
+            out.push(data[pos]);
+            pos += run;
+            continue;
+        }
+
+        // gather a literal run, stopping before the next replicate run
+        let start = pos;
+        while pos < data.len() && pos - start < 128 {
+            if pos + 2 < data.len() && data[pos] == data[pos + 1] && data[pos] == data[pos + 2] {
+                break;
+            }
+            pos += 1;
+        }
+        out.push((pos - start - 1) as u8);
+        out.extend_from_slice(&data[start..pos]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, pack_bits, unpack_bits};
+    use object::pixeldata::PixelData;
+    use transfer_syntax::{FrameDescription, RLELossless};
+
+    fn description(bits_allocated: u16, samples_per_pixel: u16) -> FrameDescription {
+        FrameDescription {
+            rows: 3,
+            columns: 4,
+            samples_per_pixel,
+            bits_allocated,
+            photometric_interpretation: if samples_per_pixel == 3 {
+                "RGB".to_owned()
+            } else {
+                "MONOCHROME2".to_owned()
+            },
+        }
+    }
+
+    #[test]
+    fn unpack_bits_sample() {
+        // example from the PackBits documentation
+        let packed = [
+            0xFE, 0xAA, 0x02, 0x80, 0x00, 0x2A, 0xFD, 0xAA, 0x03, 0x80, 0x00, 0x2A, 0x22, 0xF7,
+            0xAA,
+        ];
+        let mut out = Vec::new();
+        unpack_bits(&packed, 24, &mut out).unwrap();
+        assert_eq!(
+            out,
+            vec![
+                0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0xAA, 0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A,
+                0x22, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA,
+            ]
+        );
+        assert!(unpack_bits(&packed[..10], 24, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn pack_bits_round_trip() {
+        let mut data = vec![1, 2, 3, 3, 3, 3, 4, 5, 5];
+        data.extend(vec![9; 300]);
+        data.extend((0..=255).collect::<Vec<u8>>());
+        let mut packed = Vec::new();
+        pack_bits(&data, &mut packed);
+        assert!(packed.len() < data.len());
+        let mut out = Vec::new();
+        unpack_bits(&packed, data.len(), &mut out).unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn decode_8bit_frame() {
+        let mut frame = vec![0u8; 64];
+        frame[0] = 1;
+        frame[4] = 64;
+        // 12 bytes: a run of 8 zeros and a literal of 4
+        frame.extend_from_slice(&[0xF9, 0x00, 0x03, 1, 2, 3, 4]);
+        frame.push(0);
+        let native = decode(&frame, &description(8, 1)).unwrap();
+        assert_eq!(native, vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn encode_and_decode_frames() {
+        for &(bits, samples) in &[(8, 1), (8, 3), (16, 1), (16, 3)] {
+            let description = description(bits, samples);
+            let len = 12 * usize::from(samples) * usize::from(bits / 8);
+            let native: Vec<u8> = (0..len).map(|i| (i / 5) as u8).collect();
+
+            let encoded = encode(&native, &description).unwrap();
+            assert_eq!(encoded.len() % 2, 0);
+            assert_eq!(
+                u32::from(encoded[0]),
+                u32::from(samples) * u32::from(bits / 8)
+            );
+            assert_eq!(decode(&encoded, &description).unwrap(), native);
+        }
+    }
+
+    #[test]
+    fn decode_16bit_byte_order() {
+        let description = FrameDescription {
+            rows: 1,
+            columns: 2,
+            ..description(16, 1)
+        };
+        let encoded = encode(&[0x34, 0x12, 0x78, 0x56], &description).unwrap();
+        // the first segment holds the most significant bytes
+        assert_eq!(&encoded[64..67], &[0x01, 0x12, 0x56]);
+        assert_eq!(
+            decode(&encoded, &description).unwrap(),
+            vec![0x34, 0x12, 0x78, 0x56]
+        );
+    }
+
+    #[test]
+    fn decode_to_pixel_data() {
+        let description = description(16, 1);
+        let native: Vec<u8> = (0..24).collect();
+        let encoded = encode(&native, &description).unwrap();
+        let pixels = RLELossless.decode_frame_u16(&encoded, &description).unwrap();
+        assert_eq!(pixels.rows(), 3);
+        assert_eq!(pixels.columns(), 4);
+        assert_eq!(pixels.pixel_at(1, 0).unwrap(), 0x0302);
+        assert!(RLELossless.decode_frame_u8(&encoded, &description).is_err());
+    }
+
+    #[test]
+    fn reject_bad_frames() {
+        let description = description(8, 1);
+        assert!(decode(&[0; 10], &description).is_err());
+        let mut frame = encode(&[7; 12], &description).unwrap();
+        frame[0] = 2;
+        assert!(decode(&frame, &description).is_err());
+        let twelve_bits = FrameDescription {
+            bits_allocated: 12,
+            ..description.clone()
+        };
+        assert!(decode(&frame, &twelve_bits).is_err());
+        assert!(encode(&[0; 5], &description).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! This module contains the requesting side of association negotiation.
+use std::collections::VecDeque;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use association::{
+    abort_socket, max_pdu_length_of, Association, PresentationContext, IMPLEMENTATION_CLASS_UID,
+};
+use dicom_core::transfer_syntax::{ExplicitVRLittleEndian, ImplicitVRLittleEndian, TransferSyntax};
+use error::{Error, Result};
+use pdu::{
+    read_pdu, write_pdu, AbortRQServiceProviderReason, AbortRQSource, Pdu,
+    PresentationContextProposed, PresentationContextResultReason, UserVariableItem,
+    DEFAULT_MAX_PDU, DICOM_APPLICATION_CONTEXT_NAME, MINIMUM_PDU_SIZE,
+};
+
+/// A builder for requesting an association with another node, which
+/// acts as the service class provider (SCP).
+///
+/// # Example
+///
+/// ```no_run
+/// use dicom_ul::association::ClientAssociationOptions;
+/// # fn run() -> dicom_ul::Result<()> {
+/// let association = ClientAssociationOptions::new()
+///     .calling_ae_title("ECHO-SCU".to_owned())
+///     .called_ae_title("ANY-SCP".to_owned())
+///     .with_abstract_syntax("1.2.840.10008.1.1".to_owned())
+///     .establish("127.0.0.1:104")?;
+/// association.release()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientAssociationOptions {
+    calling_ae_title: String,
+    called_ae_title: String,
+    presentation_contexts: Vec<(String, Vec<String>)>,
+    max_pdu_length: u32,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl Default for ClientAssociationOptions {
+    fn default() -> ClientAssociationOptions {
+        ClientAssociationOptions {
+            calling_ae_title: "THIS-SCU".to_owned(),
+            called_ae_title: "ANY-SCP".to_owned(),
+            presentation_contexts: vec![],
+            max_pdu_length: DEFAULT_MAX_PDU,
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+}
+
+impl ClientAssociationOptions {
+    /// Create a new set of options with no presentation contexts.
+    pub fn new() -> ClientAssociationOptions {
+        ClientAssociationOptions::default()
+    }
+
+    /// Define the AE title of this node.
+    pub fn calling_ae_title(mut self, value: String) -> ClientAssociationOptions {
+        self.calling_ae_title = value;
+        self
+    }
+
+    /// Define the AE title of the node to associate with.
+    pub fn called_ae_title(mut self, value: String) -> ClientAssociationOptions {
+        self.called_ae_title = value;
+        self
+    }
+
+    /// Propose a presentation context for the given abstract syntax, with
+    /// the explicit and implicit VR little endian transfer syntaxes.
+    pub fn with_abstract_syntax(self, abstract_syntax: String) -> ClientAssociationOptions {
+        let transfer_syntaxes = vec![
+            ExplicitVRLittleEndian.uid().to_owned(),
+            ImplicitVRLittleEndian.uid().to_owned(),
+        ];
+        self.with_presentation_context(abstract_syntax, transfer_syntaxes)
+    }
+
+    /// Propose a presentation context for the given abstract syntax and
+    /// transfer syntaxes, in order of preference.
+    pub fn with_presentation_context(
+        mut self,
+        abstract_syntax: String,
+        transfer_syntaxes: Vec<String>,
+    ) -> ClientAssociationOptions {
+        self.presentation_contexts.push((abstract_syntax, transfer_syntaxes));
+        self
+    }
+
+    /// Define the maximum length of the P-DATA-TF PDUs which this node
+    /// accepts.
+    pub fn max_pdu_length(mut self, value: u32) -> ClientAssociationOptions {
+        self.max_pdu_length = value;
+        self
+    }
+
+    /// Define how long to wait for data from the other node, both during
+    /// negotiation and on the established association. There is no time
+    /// limit by default.
+    pub fn read_timeout(mut self, value: Duration) -> ClientAssociationOptions {
+        self.read_timeout = Some(value);
+        self
+    }
+
+    /// Define how long to wait for data to be sent to the other node.
+    /// There is no time limit by default.
+    pub fn write_timeout(mut self, value: Duration) -> ClientAssociationOptions {
+        self.write_timeout = Some(value);
+        self
+    }
+
+    /// Connect to the given address and negotiate an association.
+    pub fn establish<A: ToSocketAddrs>(&self, address: A) -> Result<Association> {
+        if self.presentation_contexts.is_empty() {
+            return Err(Error::InvalidOptions("no presentation contexts were proposed"));
+        }
+        if self.presentation_contexts.len() > 128 {
+            return Err(Error::InvalidOptions("too many presentation contexts"));
+        }
+        if self.max_pdu_length < MINIMUM_PDU_SIZE {
+            return Err(Error::InvalidOptions("maximum PDU length out of bounds"));
+        }
+
+        let proposed: Vec<_> = self
+            .presentation_contexts
+            .iter()
+            .enumerate()
+            .map(|(i, pc)| PresentationContextProposed {
+                id: (i * 2 + 1) as u8,
+                abstract_syntax: pc.0.clone(),
+                transfer_syntaxes: pc.1.clone(),
+            })
+            .collect();
+
+        let mut socket = TcpStream::connect(address)?;
+        socket.set_read_timeout(self.read_timeout)?;
+        socket.set_write_timeout(self.write_timeout)?;
+        write_pdu(
+            &mut socket,
+            &Pdu::AssociationRQ {
+                protocol_version: 1,
+                calling_ae_title: self.calling_ae_title.clone(),
+                called_ae_title: self.called_ae_title.clone(),
+                application_context_name: DICOM_APPLICATION_CONTEXT_NAME.to_owned(),
+                presentation_contexts: proposed.clone(),
+                user_variables: vec![
+                    UserVariableItem::MaxLength(self.max_pdu_length),
+                    UserVariableItem::ImplementationClassUID(IMPLEMENTATION_CLASS_UID.to_owned()),
+                ],
+            },
+        )?;
+
+        match read_pdu(&mut socket, self.max_pdu_length)? {
+            Pdu::AssociationAC {
+                presentation_contexts,
+                user_variables,
+                ..
+            } => {
+                let accepted: Vec<_> = presentation_contexts
+                    .into_iter()
+                    .filter(|pc| pc.reason == PresentationContextResultReason::Acceptance)
+                    .filter_map(|pc| {
+                        proposed.iter().find(|p| p.id == pc.id).map(|p| PresentationContext {
+                            id: pc.id,
+                            abstract_syntax: p.abstract_syntax.clone(),
+                            transfer_syntax: pc.transfer_syntax,
+                        })
+                    })
+                    .collect();
+                let peer_max_pdu_length = match max_pdu_length_of(&user_variables) {
+                    Ok(length) => length,
+                    Err(e) => {
+                        abort_socket(
+                            &mut socket,
+                            AbortRQSource::ServiceProvider(
+                                AbortRQServiceProviderReason::InvalidPduParameter,
+                            ),
+                        );
+                        return Err(e);
+                    }
+                };
+                if accepted.is_empty() {
+                    abort_socket(&mut socket, AbortRQSource::ServiceUser);
+                    return Err(Error::NoAcceptedPresentationContexts);
+                }
+                Ok(Association {
+                    socket,
+                    peer_ae_title: self.called_ae_title.clone(),
+                    max_pdu_length: self.max_pdu_length,
+                    peer_max_pdu_length,
+                    presentation_contexts: accepted,
+                    pending: VecDeque::new(),
+                })
+            }
+            Pdu::AssociationRJ { result, source } => Err(Error::Rejected(result, source)),
+            Pdu::AbortRQ { .. } => Err(Error::Aborted),
+            pdu => {
+                abort_socket(
+                    &mut socket,
+                    AbortRQSource::ServiceProvider(AbortRQServiceProviderReason::UnexpectedPdu),
+                );
+                Err(Error::UnexpectedPdu(Box::new(pdu)))
+            }
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! This module contains the establishment and use of associations between
+//! two DICOM nodes over TCP.
+//!
+//! An association is requested by a client with
+//! [`ClientAssociationOptions`](client/struct.ClientAssociationOptions.html)
+//! and accepted by a server with
+//! [`ServerAssociationOptions`](server/struct.ServerAssociationOptions.html).
+//! Both yield an [`Association`](struct.Association.html), which carries
+//! DIMSE messages in P-DATA-TF PDUs until it is released or aborted.
+use std::collections::VecDeque;
+use std::net::{Shutdown, TcpStream};
+
+use error::{Error, Result};
+use pdu::{
+    read_pdu, write_pdu, AbortRQServiceProviderReason, AbortRQSource, PDataValue,
+    PDataValueType, Pdu, UserVariableItem, DEFAULT_MAX_PDU, MAXIMUM_PDU_SIZE, MINIMUM_PDU_SIZE,
+    PDV_HEADER_SIZE,
+};
+
+pub mod client;
+pub mod server;
+
+pub use self::client::ClientAssociationOptions;
+pub use self::server::ServerAssociationOptions;
+
+/// The implementation class UID announced in association negotiation.
+pub use dicom_core::meta::IMPLEMENTATION_CLASS_UID;
+
+/// A presentation context accepted during association negotiation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentationContext {
+    /// The presentation context ID.
+    pub id: u8,
+    /// The abstract syntax UID of the context.
+    pub abstract_syntax: String,
+    /// The UID of the transfer syntax agreed for this context.
+    pub transfer_syntax: String,
+}
+
+/// An established association with another DICOM node.
+///
+/// Messages sent through the association are split into P-DATA-TF PDUs
+/// which fit in the maximum PDU length of the peer, and messages received
+/// are reassembled from their fragments.
+#[derive(Debug)]
+pub struct Association {
+    socket: TcpStream,
+    peer_ae_title: String,
+    max_pdu_length: u32,
+    peer_max_pdu_length: u32,
+    presentation_contexts: Vec<PresentationContext>,
+    pending: VecDeque<PDataValue>,
+}
+
+impl Association {
+    /// Retrieve the AE title of the other node.
+    pub fn peer_ae_title(&self) -> &str {
+        &self.peer_ae_title
+    }
+
+    /// Retrieve the maximum length of the P-DATA-TF PDUs which this node
+    /// accepts.
+    pub fn max_pdu_length(&self) -> u32 {
+        self.max_pdu_length
+    }
+
+    /// Retrieve the maximum length of the P-DATA-TF PDUs which the other
+    /// node accepts, where 0 stands for no limit.
+    pub fn peer_max_pdu_length(&self) -> u32 {
+        self.peer_max_pdu_length
+    }
+
+    /// Retrieve the presentation contexts accepted for this association.
+    pub fn presentation_contexts(&self) -> &[PresentationContext] {
+        &self.presentation_contexts
+    }
+
+    /// Send a single PDU to the other node.
+    pub fn send(&mut self, pdu: &Pdu) -> Result<()> {
+        write_pdu(&mut self.socket, pdu)
+    }
+
+    /// Receive a single PDU from the other node. An A-ABORT results in an
+    /// `Aborted` error.
+    pub fn receive(&mut self) -> Result<Pdu> {
+        match read_pdu(&mut self.socket, self.max_pdu_length)? {
+            Pdu::AbortRQ { .. } => Err(Error::Aborted),
+            pdu => Ok(pdu),
+        }
+    }
+
+    /// Send the command set or data set of a message in the given
+    /// presentation context, split into as many P-DATA-TF PDUs as needed.
+    pub fn send_pdata(
+        &mut self,
+        presentation_context_id: u8,
+        value_type: PDataValueType,
+        data: &[u8],
+    ) -> Result<()> {
+        let max_pdu_length = if self.peer_max_pdu_length == 0 {
+            MAXIMUM_PDU_SIZE
+        } else {
+            self.peer_max_pdu_length
+        };
+        let chunk_size = max_pdu_length
+            .checked_sub(PDV_HEADER_SIZE)
+            .filter(|&size| size > 0)
+            .ok_or(Error::InvalidPdu("maximum PDU length leaves no room for data"))?
+            as usize;
+        let mut chunks = data.chunks(chunk_size).peekable();
+        if chunks.peek().is_none() {
+            return self.send(&Pdu::PData {
+                data: vec![PDataValue {
+                    presentation_context_id,
+                    value_type,
+                    is_last: true,
+                    data: vec![],
+                }],
+            });
+        }
+        while let Some(chunk) = chunks.next() {
+            let is_last = chunks.peek().is_none();
+            self.send(&Pdu::PData {
+                data: vec![PDataValue {
+                    presentation_context_id,
+                    value_type,
+                    is_last,
+                    data: chunk.to_vec(),
+                }],
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Receive the next command set or data set from the other node,
+    /// reassembled from its fragments. The returned value is always
+    /// marked as the last fragment.
+    ///
+    /// If the other node requests the association to be released, the
+    /// release is confirmed and `None` is returned.
+    pub fn receive_pdata(&mut self) -> Result<Option<PDataValue>> {
+        let mut message: Option<PDataValue> = None;
+        loop {
+            while let Some(value) = self.pending.pop_front() {
+                let current = match message.take() {
+                    Some(mut current) => {
+                        if current.presentation_context_id != value.presentation_context_id
+                            || current.value_type != value.value_type
+                        {
+                            return Err(Error::InvalidPdu("interleaved message fragments"));
+                        }
+                        current.data.extend_from_slice(&value.data);
+                        current.is_last = value.is_last;
+                        current
+                    }
+                    None => value,
+                };
+                if current.is_last {
+                    return Ok(Some(current));
+                }
+                message = Some(current);
+            }
+
+            match self.receive()? {
+                Pdu::PData { data } => self.pending.extend(data),
+                Pdu::ReleaseRQ => {
+                    self.send(&Pdu::ReleaseRP)?;
+                    let _ = self.socket.shutdown(Shutdown::Both);
+                    return Ok(None);
+                }
+                pdu => {
+                    self.abort_with(AbortRQSource::ServiceProvider(
+                        AbortRQServiceProviderReason::UnexpectedPdu,
+                    ));
+                    return Err(Error::UnexpectedPdu(Box::new(pdu)));
+                }
+            }
+        }
+    }
+
+    /// Release the association, waiting for the other node to confirm.
+    pub fn release(mut self) -> Result<()> {
+        self.send(&Pdu::ReleaseRQ)?;
+        loop {
+            match self.receive()? {
+                Pdu::ReleaseRP => break,
+                // data still in transit is discarded
+                Pdu::PData { .. } => {}
+                pdu => return Err(Error::UnexpectedPdu(Box::new(pdu))),
+            }
+        }
+        let _ = self.socket.shutdown(Shutdown::Both);
+        Ok(())
+    }
+
+    /// Abort the association, without waiting for a response.
+    pub fn abort(mut self) -> Result<()> {
+        self.send(&Pdu::AbortRQ {
+            source: AbortRQSource::ServiceUser,
+        })?;
+        let _ = self.socket.shutdown(Shutdown::Both);
+        Ok(())
+    }
+
+    fn abort_with(&mut self, source: AbortRQSource) {
+        abort_socket(&mut self.socket, source);
+    }
+}
+
+/// Abort an association through its socket, ignoring any errors.
+fn abort_socket(socket: &mut TcpStream, source: AbortRQSource) {
+    let _ = write_pdu(socket, &Pdu::AbortRQ { source });
+    let _ = socket.shutdown(Shutdown::Both);
+}
+
+/// Obtain the maximum PDU length from a list of user variables. A length
+/// other than 0 (no limit) below `MINIMUM_PDU_SIZE` is refused.
+fn max_pdu_length_of(user_variables: &[UserVariableItem]) -> Result<u32> {
+    let length = user_variables
+        .iter()
+        .filter_map(|v| match *v {
+            UserVariableItem::MaxLength(length) => Some(length),
+            _ => None,
+        })
+        .next()
+        .unwrap_or(DEFAULT_MAX_PDU);
+    if length != 0 && length < MINIMUM_PDU_SIZE {
+        return Err(Error::InvalidPdu("maximum PDU length below the minimum"));
+    }
+    Ok(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    const VERIFICATION: &str = "1.2.840.10008.1.1";
+    const CT_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+
+    fn spawn_server<F>(options: ServerAssociationOptions, f: F) -> (String, thread::JoinHandle<()>)
+    where
+        F: FnOnce(Result<Association>) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            f(options.establish(socket));
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn associate_echo_and_release() {
+        let options = ServerAssociationOptions::new()
+            .ae_title("ECHO-SCP".to_owned())
+            .with_abstract_syntax(VERIFICATION.to_owned());
+        let (addr, server) = spawn_server(options, |association| {
+            let mut association = association.unwrap();
+            assert_eq!(association.peer_ae_title(), "ECHO-SCU");
+            while let Some(message) = association.receive_pdata().unwrap() {
+                association
+                    .send_pdata(message.presentation_context_id, message.value_type, &message.data)
+                    .unwrap();
+            }
+        });
+
+        let mut association = ClientAssociationOptions::new()
+            .calling_ae_title("ECHO-SCU".to_owned())
+            .called_ae_title("ECHO-SCP".to_owned())
+            .with_abstract_syntax(VERIFICATION.to_owned())
+            .establish(&addr[..])
+            .unwrap();
+        assert_eq!(association.peer_ae_title(), "ECHO-SCP");
+        assert_eq!(association.presentation_contexts().len(), 1);
+        let pc = association.presentation_contexts()[0].clone();
+        assert_eq!(pc.abstract_syntax, VERIFICATION);
+        assert_eq!(pc.transfer_syntax, "1.2.840.10008.1.2.1");
+
+        association.send_pdata(pc.id, PDataValueType::Command, &[1, 2, 3, 4]).unwrap();
+        let reply = association.receive_pdata().unwrap().unwrap();
+        assert_eq!(reply.presentation_context_id, pc.id);
+        assert_eq!(reply.value_type, PDataValueType::Command);
+        assert!(reply.is_last);
+        assert_eq!(reply.data, vec![1, 2, 3, 4]);
+
+        association.release().unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn fragmented_pdata() {
+        let data: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+        let expected = data.clone();
+        let options = ServerAssociationOptions::new().max_pdu_length(4096);
+        let (addr, server) = spawn_server(options, move |association| {
+            let mut association = association.unwrap();
+            let message = association.receive_pdata().unwrap().unwrap();
+            assert_eq!(message.value_type, PDataValueType::Data);
+            assert_eq!(message.data, expected);
+            association
+                .send_pdata(message.presentation_context_id, message.value_type, &message.data)
+                .unwrap();
+            assert_eq!(association.receive_pdata().unwrap(), None);
+        });
+
+        let mut association = ClientAssociationOptions::new()
+            .with_abstract_syntax(CT_IMAGE_STORAGE.to_owned())
+            .max_pdu_length(8192)
+            .establish(&addr[..])
+            .unwrap();
+        assert_eq!(association.peer_max_pdu_length(), 4096);
+        let pc_id = association.presentation_contexts()[0].id;
+
+        // each PDU must fit in the maximum length of the server
+        association.send_pdata(pc_id, PDataValueType::Data, &data).unwrap();
+        let reply = association.receive_pdata().unwrap().unwrap();
+        assert_eq!(reply.data, data);
+
+        association.release().unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn max_pdu_length_above_the_limit_of_other_pdus() {
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        let expected = data.clone();
+        let options = ServerAssociationOptions::new().max_pdu_length(1 << 20);
+        let (addr, server) = spawn_server(options, move |association| {
+            let mut association = association.unwrap();
+            let message = association.receive_pdata().unwrap().unwrap();
+            assert_eq!(message.data, expected);
+            assert_eq!(association.receive_pdata().unwrap(), None);
+        });
+
+        let mut association = ClientAssociationOptions::new()
+            .with_abstract_syntax(CT_IMAGE_STORAGE.to_owned())
+            .max_pdu_length(1 << 20)
+            .establish(&addr[..])
+            .unwrap();
+        assert_eq!(association.peer_max_pdu_length(), 1 << 20);
+        let pc_id = association.presentation_contexts()[0].id;
+        association.send_pdata(pc_id, PDataValueType::Data, &data).unwrap();
+
+        association.release().unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn time_out_waiting_for_the_request() {
+        let options = ServerAssociationOptions::new()
+            .read_timeout(::std::time::Duration::from_millis(100))
+            .write_timeout(::std::time::Duration::from_millis(100));
+        let (addr, server) = spawn_server(options, |association| match association {
+            Err(Error::Io(_)) => {}
+            other => panic!("unexpected outcome {:?}", other),
+        });
+        // connect without ever sending the association request
+        let _socket = TcpStream::connect(&addr[..]).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn negotiate_presentation_contexts() {
+        let options = ServerAssociationOptions::new()
+            .with_abstract_syntax(VERIFICATION.to_owned())
+            .with_abstract_syntax(CT_IMAGE_STORAGE.to_owned());
+        let (addr, server) = spawn_server(options, |association| {
+            let mut association = association.unwrap();
+            assert_eq!(association.presentation_contexts().len(), 1);
+            assert_eq!(association.receive_pdata().unwrap(), None);
+        });
+
+        // an unknown transfer syntax and an unknown abstract syntax
+        let association = ClientAssociationOptions::new()
+            .with_presentation_context(CT_IMAGE_STORAGE.to_owned(), vec!["1.2.3.4".to_owned()])
+            .with_presentation_context("1.2.3.4.5".to_owned(), vec!["1.2.840.10008.1.2".to_owned()])
+            .with_presentation_context(
+                VERIFICATION.to_owned(),
+                vec!["1.2.3.4".to_owned(), "1.2.840.10008.1.2".to_owned()],
+            )
+            .establish(&addr[..])
+            .unwrap();
+        assert_eq!(
+            association.presentation_contexts(),
+            &[PresentationContext {
+                id: 5,
+                abstract_syntax: VERIFICATION.to_owned(),
+                transfer_syntax: "1.2.840.10008.1.2".to_owned(),
+            }][..]
+        );
+        association.release().unwrap();
+        server.join().unwrap();
+
+        // no acceptable presentation contexts
+        let (addr, server) = spawn_server(ServerAssociationOptions::new(), |association| {
+            match association {
+                Err(Error::NoAcceptedPresentationContexts) => {}
+                other => panic!("unexpected outcome {:?}", other),
+            }
+        });
+        let result = ClientAssociationOptions::new()
+            .with_presentation_context(VERIFICATION.to_owned(), vec!["1.2.3.4".to_owned()])
+            .establish(&addr[..]);
+        match result {
+            Err(Error::NoAcceptedPresentationContexts) => {}
+            other => panic!("unexpected outcome {:?}", other),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn reject_called_ae_title() {
+        let options = ServerAssociationOptions::new()
+            .ae_title("STORE-SCP".to_owned())
+            .strict(true);
+        let (addr, server) = spawn_server(options, |association| {
+            assert!(association.is_err());
+        });
+        let result = ClientAssociationOptions::new()
+            .called_ae_title("SOMEONE-ELSE".to_owned())
+            .with_abstract_syntax(VERIFICATION.to_owned())
+            .establish(&addr[..]);
+        match result {
+            Err(Error::Rejected(_, source)) => assert_eq!(
+                source,
+                ::pdu::AssociationRJSource::ServiceUser(
+                    ::pdu::AssociationRJServiceUserReason::CalledAETitleNotRecognized,
+                )
+            ),
+            other => panic!("unexpected outcome {:?}", other),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn reject_small_max_pdu_length() {
+        let (addr, server) = spawn_server(ServerAssociationOptions::new(), |association| {
+            match association {
+                Err(Error::InvalidPdu(_)) => {}
+                other => panic!("unexpected outcome {:?}", other),
+            }
+        });
+        let mut socket = TcpStream::connect(&addr[..]).unwrap();
+        let request = Pdu::AssociationRQ {
+            protocol_version: 1,
+            calling_ae_title: "THIS-SCU".to_owned(),
+            called_ae_title: "ANY-SCP".to_owned(),
+            application_context_name: ::pdu::DICOM_APPLICATION_CONTEXT_NAME.to_owned(),
+            presentation_contexts: vec![::pdu::PresentationContextProposed {
+                id: 1,
+                abstract_syntax: VERIFICATION.to_owned(),
+                transfer_syntaxes: vec!["1.2.840.10008.1.2".to_owned()],
+            }],
+            user_variables: vec![UserVariableItem::MaxLength(MINIMUM_PDU_SIZE - 1)],
+        };
+        write_pdu(&mut socket, &request).unwrap();
+        match read_pdu(&mut socket, DEFAULT_MAX_PDU).unwrap() {
+            Pdu::AbortRQ { source } => assert_eq!(
+                source,
+                AbortRQSource::ServiceProvider(AbortRQServiceProviderReason::InvalidPduParameter)
+            ),
+            pdu => panic!("expected an abort, got {:?}", pdu),
+        }
+        server.join().unwrap();
+
+        // no limit at all is fine
+        assert_eq!(max_pdu_length_of(&[UserVariableItem::MaxLength(0)]).unwrap(), 0);
+    }
+}
@@ -0,0 +1,257 @@
+//! This module contains the accepting side of association negotiation.
+use std::collections::VecDeque;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use association::{
+    abort_socket, max_pdu_length_of, Association, PresentationContext, IMPLEMENTATION_CLASS_UID,
+};
+use dicom_core::transfer_syntax::codec::get_registry;
+use error::{Error, Result};
+use pdu::{
+    read_pdu, write_pdu, AbortRQServiceProviderReason, AbortRQSource, AssociationRJResult,
+    AssociationRJServiceProviderASCEReason, AssociationRJServiceUserReason, AssociationRJSource,
+    Pdu, PresentationContextProposed, PresentationContextResult, PresentationContextResultReason,
+    UserVariableItem, DEFAULT_MAX_PDU, DICOM_APPLICATION_CONTEXT_NAME, MINIMUM_PDU_SIZE,
+};
+
+/// A builder for accepting associations requested by other nodes, thus
+/// acting as the service class provider (SCP).
+///
+/// Presentation contexts are accepted for the registered abstract
+/// syntaxes, or for any abstract syntax if none were registered, with the
+/// first proposed transfer syntax which is known to the transfer syntax
+/// registry of `dicom-core`.
+///
+/// # Example
+///
+/// ```no_run
+/// use dicom_ul::association::ServerAssociationOptions;
+/// use std::net::TcpListener;
+/// # fn run() -> dicom_ul::Result<()> {
+/// let options = ServerAssociationOptions::new()
+///     .ae_title("ECHO-SCP".to_owned())
+///     .with_abstract_syntax("1.2.840.10008.1.1".to_owned());
+/// let listener = TcpListener::bind("0.0.0.0:104")?;
+/// for socket in listener.incoming() {
+///     let mut association = options.establish(socket?)?;
+///     while let Some(message) = association.receive_pdata()? {
+///         // handle the message
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerAssociationOptions {
+    ae_title: String,
+    strict: bool,
+    abstract_syntaxes: Vec<String>,
+    max_pdu_length: u32,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl Default for ServerAssociationOptions {
+    fn default() -> ServerAssociationOptions {
+        ServerAssociationOptions {
+            ae_title: "THIS-SCP".to_owned(),
+            strict: false,
+            abstract_syntaxes: vec![],
+            max_pdu_length: DEFAULT_MAX_PDU,
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+}
+
+impl ServerAssociationOptions {
+    /// Create a new set of options which accepts any abstract syntax.
+    pub fn new() -> ServerAssociationOptions {
+        ServerAssociationOptions::default()
+    }
+
+    /// Define the AE title of this node.
+    pub fn ae_title(mut self, value: String) -> ServerAssociationOptions {
+        self.ae_title = value;
+        self
+    }
+
+    /// Define whether association requests addressed to another AE title
+    /// are rejected.
+    pub fn strict(mut self, value: bool) -> ServerAssociationOptions {
+        self.strict = value;
+        self
+    }
+
+    /// Accept presentation contexts for the given abstract syntax.
+    pub fn with_abstract_syntax(mut self, abstract_syntax: String) -> ServerAssociationOptions {
+        self.abstract_syntaxes.push(abstract_syntax);
+        self
+    }
+
+    /// Define the maximum length of the P-DATA-TF PDUs which this node
+    /// accepts.
+    pub fn max_pdu_length(mut self, value: u32) -> ServerAssociationOptions {
+        self.max_pdu_length = value;
+        self
+    }
+
+    /// Define how long to wait for data from the other node, both during
+    /// negotiation and on the established association. There is no time
+    /// limit by default.
+    pub fn read_timeout(mut self, value: Duration) -> ServerAssociationOptions {
+        self.read_timeout = Some(value);
+        self
+    }
+
+    /// Define how long to wait for data to be sent to the other node.
+    /// There is no time limit by default.
+    pub fn write_timeout(mut self, value: Duration) -> ServerAssociationOptions {
+        self.write_timeout = Some(value);
+        self
+    }
+
+    /// Negotiate an association with the node connected through the given
+    /// socket.
+    pub fn establish(&self, mut socket: TcpStream) -> Result<Association> {
+        if self.max_pdu_length < MINIMUM_PDU_SIZE {
+            return Err(Error::InvalidOptions("maximum PDU length out of bounds"));
+        }
+        socket.set_read_timeout(self.read_timeout)?;
+        socket.set_write_timeout(self.write_timeout)?;
+
+        let (
+            protocol_version,
+            calling_ae_title,
+            called_ae_title,
+            application_context_name,
+            presentation_contexts,
+            user_variables,
+        ) = match read_pdu(&mut socket, self.max_pdu_length)? {
+            Pdu::AssociationRQ {
+                protocol_version,
+                calling_ae_title,
+                called_ae_title,
+                application_context_name,
+                presentation_contexts,
+                user_variables,
+            } => (
+                protocol_version,
+                calling_ae_title,
+                called_ae_title,
+                application_context_name,
+                presentation_contexts,
+                user_variables,
+            ),
+            Pdu::AbortRQ { .. } => return Err(Error::Aborted),
+            pdu => {
+                abort_socket(
+                    &mut socket,
+                    AbortRQSource::ServiceProvider(AbortRQServiceProviderReason::UnexpectedPdu),
+                );
+                return Err(Error::UnexpectedPdu(Box::new(pdu)));
+            }
+        };
+
+        let peer_max_pdu_length = match max_pdu_length_of(&user_variables) {
+            Ok(length) => length,
+            Err(e) => {
+                abort_socket(
+                    &mut socket,
+                    AbortRQSource::ServiceProvider(
+                        AbortRQServiceProviderReason::InvalidPduParameter,
+                    ),
+                );
+                return Err(e);
+            }
+        };
+
+        let rejection = if protocol_version & 1 == 0 {
+            Some(AssociationRJSource::ServiceProviderASCE(
+                AssociationRJServiceProviderASCEReason::ProtocolVersionNotSupported,
+            ))
+        } else if application_context_name != DICOM_APPLICATION_CONTEXT_NAME {
+            Some(AssociationRJSource::ServiceUser(
+                AssociationRJServiceUserReason::ApplicationContextNameNotSupported,
+            ))
+        } else if self.strict && called_ae_title != self.ae_title {
+            Some(AssociationRJSource::ServiceUser(
+                AssociationRJServiceUserReason::CalledAETitleNotRecognized,
+            ))
+        } else {
+            None
+        };
+        if let Some(source) = rejection {
+            let result = AssociationRJResult::Permanent;
+            write_pdu(&mut socket, &Pdu::AssociationRJ { result, source })?;
+            return Err(Error::Rejected(result, source));
+        }
+
+        let results: Vec<_> = presentation_contexts.iter().map(|pc| self.negotiate(pc)).collect();
+        write_pdu(
+            &mut socket,
+            &Pdu::AssociationAC {
+                protocol_version: 1,
+                calling_ae_title: calling_ae_title.clone(),
+                called_ae_title,
+                application_context_name,
+                presentation_contexts: results.clone(),
+                user_variables: vec![
+                    UserVariableItem::MaxLength(self.max_pdu_length),
+                    UserVariableItem::ImplementationClassUID(IMPLEMENTATION_CLASS_UID.to_owned()),
+                ],
+            },
+        )?;
+
+        let accepted: Vec<_> = presentation_contexts
+            .into_iter()
+            .zip(results)
+            .filter(|pair| pair.1.reason == PresentationContextResultReason::Acceptance)
+            .map(|(pc, result)| PresentationContext {
+                id: pc.id,
+                abstract_syntax: pc.abstract_syntax,
+                transfer_syntax: result.transfer_syntax,
+            })
+            .collect();
+        if accepted.is_empty() {
+            // the requesting node is expected to abort the association
+            return Err(Error::NoAcceptedPresentationContexts);
+        }
+
+        Ok(Association {
+            socket,
+            peer_ae_title: calling_ae_title,
+            max_pdu_length: self.max_pdu_length,
+            peer_max_pdu_length,
+            presentation_contexts: accepted,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Decide on the outcome of a proposed presentation context.
+    fn negotiate(&self, pc: &PresentationContextProposed) -> PresentationContextResult {
+        let reason = if !self.abstract_syntaxes.is_empty()
+            && !self.abstract_syntaxes.contains(&pc.abstract_syntax)
+        {
+            PresentationContextResultReason::AbstractSyntaxNotSupported
+        } else {
+            let registry = get_registry();
+            match pc.transfer_syntaxes.iter().find(|ts| registry.get(ts).is_some()) {
+                Some(ts) => {
+                    return PresentationContextResult {
+                        id: pc.id,
+                        reason: PresentationContextResultReason::Acceptance,
+                        transfer_syntax: ts.clone(),
+                    };
+                }
+                None => PresentationContextResultReason::TransferSyntaxesNotSupported,
+            }
+        };
+        PresentationContextResult {
+            id: pc.id,
+            reason,
+            transfer_syntax: pc.transfer_syntaxes.first().cloned().unwrap_or_default(),
+        }
+    }
+}
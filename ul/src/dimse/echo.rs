@@ -0,0 +1,81 @@
+//! This module contains the verification service (C-ECHO), which checks
+//! that two nodes can communicate at the DIMSE level.
+use association::Association;
+use dicom_core::data::{Tag, VR};
+use dicom_core::PrimitiveValue;
+use dimse::{
+    command, command_field, command_u16, receive_message, send_message, status, Command, Message,
+    C_ECHO_RQ, C_ECHO_RSP, NO_DATA_SET,
+};
+use error::{Error, Result};
+use pdu::Pdu;
+
+/// Verification SOP Class
+pub const VERIFICATION_SOP_CLASS: &str = "1.2.840.10008.1.1";
+
+/// Create a C-ECHO-RQ command set.
+pub fn echo_rq(message_id: u16) -> Command {
+    command(vec![
+        (Tag(0x0000, 0x0002), VR::UI, PrimitiveValue::Str(VERIFICATION_SOP_CLASS.to_owned())),
+        (Tag(0x0000, 0x0100), VR::US, PrimitiveValue::U16(vec![C_ECHO_RQ])),
+        (Tag(0x0000, 0x0110), VR::US, PrimitiveValue::U16(vec![message_id])),
+        (Tag(0x0000, 0x0800), VR::US, PrimitiveValue::U16(vec![NO_DATA_SET])),
+    ])
+}
+
+/// Create a C-ECHO-RSP command set in response to the request with the
+/// given message ID.
+pub fn echo_rsp(message_id: u16, status: u16) -> Command {
+    command(vec![
+        (Tag(0x0000, 0x0002), VR::UI, PrimitiveValue::Str(VERIFICATION_SOP_CLASS.to_owned())),
+        (Tag(0x0000, 0x0100), VR::US, PrimitiveValue::U16(vec![C_ECHO_RSP])),
+        (Tag(0x0000, 0x0120), VR::US, PrimitiveValue::U16(vec![message_id])),
+        (Tag(0x0000, 0x0800), VR::US, PrimitiveValue::U16(vec![NO_DATA_SET])),
+        (Tag(0x0000, 0x0900), VR::US, PrimitiveValue::U16(vec![status])),
+    ])
+}
+
+/// Send a C-ECHO request through the association and wait for its
+/// response, returning the response status.
+///
+/// The association must have an accepted presentation context for the
+/// verification SOP class.
+pub fn echo(association: &mut Association, message_id: u16) -> Result<u16> {
+    let presentation_context_id = association
+        .presentation_contexts()
+        .iter()
+        .find(|pc| pc.abstract_syntax == VERIFICATION_SOP_CLASS)
+        .map(|pc| pc.id)
+        .ok_or_else(|| Error::NoPresentationContext(VERIFICATION_SOP_CLASS.to_owned()))?;
+
+    send_message(
+        association,
+        &Message {
+            presentation_context_id,
+            command: echo_rq(message_id),
+            data: None,
+        },
+    )?;
+
+    let response = receive_message(association)?
+        .ok_or_else(|| Error::UnexpectedPdu(Box::new(Pdu::ReleaseRQ)))?;
+    if command_field(&response.command)? != C_ECHO_RSP
+        || command_u16(&response.command, Tag(0x0000, 0x0120))? != message_id
+    {
+        return Err(Error::InvalidCommand("expected a C-ECHO-RSP"));
+    }
+    command_u16(&response.command, Tag(0x0000, 0x0900))
+}
+
+/// Respond to a C-ECHO request with a success status.
+pub fn respond(association: &mut Association, request: &Message) -> Result<()> {
+    let message_id = command_u16(&request.command, Tag(0x0000, 0x0110))?;
+    send_message(
+        association,
+        &Message {
+            presentation_context_id: request.presentation_context_id,
+            command: echo_rsp(message_id, status::SUCCESS),
+            data: None,
+        },
+    )
+}
@@ -0,0 +1,214 @@
+//! This module contains the DICOM message service element (DIMSE), as
+//! specified in PS3.7: the encoding of command sets and the exchange of
+//! messages over an association.
+//!
+//! The verification service (C-ECHO) is found in the
+//! [`echo`](echo/index.html) module, and the storage service (C-STORE) in
+//! the [`store`](store/index.html) module.
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::Cursor;
+
+use association::Association;
+use dicom_core::data::encode::Encode;
+use dicom_core::data::{DataElement, DataElementHeader, Header, Length, Tag, VR};
+use dicom_core::transfer_syntax::implicit_le::ImplicitVRLittleEndianEncoder;
+use dicom_core::transfer_syntax::ImplicitVRLittleEndian;
+use dicom_core::{DataDictionary, InMemDicomObject, PrimitiveValue, StandardDataDictionary};
+use error::{Error, Result};
+use pdu::{PDataValueType, Pdu};
+
+pub mod echo;
+pub mod store;
+
+/// A DIMSE command set.
+pub type Command = InMemDicomObject<StandardDataDictionary>;
+
+/// Command Field of a C-STORE-RQ.
+pub const C_STORE_RQ: u16 = 0x0001;
+/// Command Field of a C-STORE-RSP.
+pub const C_STORE_RSP: u16 = 0x8001;
+/// Command Field of a C-ECHO-RQ.
+pub const C_ECHO_RQ: u16 = 0x0030;
+/// Command Field of a C-ECHO-RSP.
+pub const C_ECHO_RSP: u16 = 0x8030;
+
+/// Command Data Set Type of a message without a data set.
+pub const NO_DATA_SET: u16 = 0x0101;
+/// Command Data Set Type of a message with a data set.
+pub const DATA_SET_PRESENT: u16 = 0x0000;
+
+/// Priority of a request with a medium priority.
+pub const PRIORITY_MEDIUM: u16 = 0x0000;
+
+/// Status codes of DIMSE responses.
+pub mod status {
+    /// The operation was successful.
+    pub const SUCCESS: u16 = 0x0000;
+    /// The operation failed for lack of resources.
+    pub const OUT_OF_RESOURCES: u16 = 0xA700;
+    /// The data set does not match the SOP class.
+    pub const DATA_SET_DOES_NOT_MATCH_SOP_CLASS: u16 = 0xA900;
+    /// The data set could not be understood.
+    pub const CANNOT_UNDERSTAND: u16 = 0xC000;
+    /// The operation failed while being processed.
+    pub const PROCESSING_FAILURE: u16 = 0x0110;
+    /// The SOP class is not supported.
+    pub const SOP_CLASS_NOT_SUPPORTED: u16 = 0x0122;
+    /// The operation requested by the command is not supported.
+    pub const UNRECOGNIZED_OPERATION: u16 = 0x0211;
+}
+
+/// A DIMSE message: a command set, with an optional data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// The presentation context ID in which the message is conveyed.
+    pub presentation_context_id: u8,
+    /// The command set.
+    pub command: Command,
+    /// The data set, encoded in the transfer syntax of the presentation
+    /// context.
+    pub data: Option<Vec<u8>>,
+}
+
+/// Create a command set from a list of elements, which are all in group
+/// 0000. The Command Group Length is added when the command is encoded.
+pub fn command<I>(elements: I) -> Command
+where
+    I: IntoIterator<Item = (Tag, VR, PrimitiveValue)>,
+{
+    let mut command = Command::create_empty();
+    for (tag, vr, value) in elements {
+        command.put(DataElement::new(tag, vr, value.into()));
+    }
+    command
+}
+
+/// Encode a command set in implicit VR little endian, starting with its
+/// Command Group Length (0000,0000).
+pub fn write_command<D>(command: &InMemDicomObject<D>) -> Result<Vec<u8>>
+where
+    D: DataDictionary + Clone,
+{
+    let elements = command
+        .into_iter()
+        .filter(|e| e.tag() != Tag(0x0000, 0x0000))
+        .cloned()
+        .map(Ok);
+    let command = InMemDicomObject::from_iter_with_dict(elements, command.dictionary().clone())?;
+    let mut group = Vec::new();
+    command.write_dataset(&mut group, &ImplicitVRLittleEndian)?;
+
+    let encoder = ImplicitVRLittleEndianEncoder::default();
+    let mut out = Vec::with_capacity(group.len() + 12);
+    let header = DataElementHeader::new(Tag(0x0000, 0x0000), VR::UL, Length(4));
+    encoder.encode_element_header(&mut out, header)?;
+    let mut len = [0u8; 4];
+    LittleEndian::write_u32(&mut len, group.len() as u32);
+    out.extend_from_slice(&len);
+    out.extend_from_slice(&group);
+    Ok(out)
+}
+
+/// Decode a command set in implicit VR little endian.
+pub fn read_command(data: Vec<u8>) -> Result<Command> {
+    Ok(Command::read_dataset(Cursor::new(data), &ImplicitVRLittleEndian)?)
+}
+
+/// Retrieve an unsigned short from a command set.
+pub fn command_u16(command: &Command, tag: Tag) -> Result<u16> {
+    command
+        .element(tag)
+        .ok()
+        .and_then(|e| e.value().primitive())
+        .and_then(PrimitiveValue::uint16)
+        .ok_or(Error::InvalidCommand("missing or invalid unsigned short element"))
+}
+
+/// Retrieve a UID or other text from a command set, without padding.
+pub fn command_str(command: &Command, tag: Tag) -> Result<String> {
+    command
+        .element(tag)
+        .ok()
+        .and_then(|e| e.value().primitive())
+        .and_then(PrimitiveValue::string)
+        .map(|s| s.trim_end_matches(&['\0', ' '][..]).to_owned())
+        .ok_or(Error::InvalidCommand("missing or invalid text element"))
+}
+
+/// Retrieve the Command Field (0000,0100) of a command set.
+pub fn command_field(command: &Command) -> Result<u16> {
+    command_u16(command, Tag(0x0000, 0x0100))
+}
+
+/// Send a message through the association, splitting the command set and
+/// the data set into P-DATA-TF PDUs.
+pub fn send_message(association: &mut Association, message: &Message) -> Result<()> {
+    let pc_id = message.presentation_context_id;
+    let command = write_command(&message.command)?;
+    association.send_pdata(pc_id, PDataValueType::Command, &command)?;
+    if let Some(ref data) = message.data {
+        association.send_pdata(pc_id, PDataValueType::Data, data)?;
+    }
+    Ok(())
+}
+
+/// Receive the next message from the association. If the other node
+/// requests the association to be released, the release is confirmed and
+/// `None` is returned.
+pub fn receive_message(association: &mut Association) -> Result<Option<Message>> {
+    let value = match association.receive_pdata()? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    if value.value_type != PDataValueType::Command {
+        return Err(Error::InvalidCommand("expected a command set"));
+    }
+    let presentation_context_id = value.presentation_context_id;
+    let command = read_command(value.data)?;
+
+    let data = if command_u16(&command, Tag(0x0000, 0x0800))? == NO_DATA_SET {
+        None
+    } else {
+        match association.receive_pdata()? {
+            Some(ref value) if value.presentation_context_id != presentation_context_id => {
+                return Err(Error::InvalidCommand("data set in another presentation context"));
+            }
+            Some(value) if value.value_type == PDataValueType::Data => Some(value.data),
+            Some(_) => return Err(Error::InvalidCommand("expected a data set")),
+            None => return Err(Error::UnexpectedPdu(Box::new(Pdu::ReleaseRQ))),
+        }
+    };
+
+    Ok(Some(Message {
+        presentation_context_id,
+        command,
+        data,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_encoding() {
+        let rq = echo::echo_rq(7);
+        let bytes = write_command(&rq).unwrap();
+        // (0000,0000) UL 4, followed by the length of the rest of the group
+        assert_eq!(&bytes[..8], &[0, 0, 0, 0, 4, 0, 0, 0]);
+        assert_eq!(LittleEndian::read_u32(&bytes[8..12]) as usize, bytes.len() - 12);
+        // (0000,0002) UI, "1.2.840.10008.1.1" padded with a null byte
+        assert_eq!(&bytes[12..20], &[0, 0, 2, 0, 18, 0, 0, 0]);
+        assert_eq!(&bytes[20..38], b"1.2.840.10008.1.1\0");
+
+        let read = read_command(bytes).unwrap();
+        assert_eq!(command_field(&read).unwrap(), C_ECHO_RQ);
+        assert_eq!(command_u16(&read, Tag(0x0000, 0x0110)).unwrap(), 7);
+        assert_eq!(command_u16(&read, Tag(0x0000, 0x0800)).unwrap(), NO_DATA_SET);
+        assert_eq!(
+            command_str(&read, Tag(0x0000, 0x0002)).unwrap(),
+            echo::VERIFICATION_SOP_CLASS
+        );
+        assert!(read.element(Tag(0x0000, 0x0000)).is_ok());
+    }
+}
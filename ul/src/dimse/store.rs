@@ -0,0 +1,609 @@
+//! This module contains the storage service (C-STORE), through which DICOM
+//! objects are sent to another node.
+//!
+//! [`StoreScu`](struct.StoreScu.html) sends objects through an established
+//! association, while [`StoreScp`](struct.StoreScp.html) receives them and
+//! hands them over to a user callback, such as the one created by
+//! [`save_to_directory`](fn.save_to_directory.html).
+use std::fs::File;
+use std::io::{BufWriter, Cursor};
+use std::path::{Path, PathBuf};
+
+use association::{Association, PresentationContext};
+use dicom_core::data::{Tag, VR};
+use dicom_core::meta::DicomMetaTableBuilder;
+use dicom_core::transfer_syntax::codec::get_registry;
+use dicom_core::{
+    open_file, to_file, DataDictionary, InMemDicomObject, PrimitiveValue, RootDicomObject,
+    StandardDataDictionary,
+};
+use dimse::echo::{self, echo};
+use dimse::{
+    command, command_field, command_str, command_u16, receive_message, send_message, status,
+    Command, Message, C_ECHO_RQ, C_STORE_RQ, C_STORE_RSP, DATA_SET_PRESENT, NO_DATA_SET,
+    PRIORITY_MEDIUM,
+};
+use error::{Error, Result};
+use pdu::Pdu;
+
+/// A DICOM object received by a storage service provider, along with its
+/// file meta group.
+pub type StoredObject = RootDicomObject<InMemDicomObject<StandardDataDictionary>>;
+
+/// Create a C-STORE-RQ command set.
+pub fn store_rq(message_id: u16, sop_class_uid: &str, sop_instance_uid: &str) -> Command {
+    command(vec![
+        (Tag(0x0000, 0x0002), VR::UI, PrimitiveValue::Str(sop_class_uid.to_owned())),
+        (Tag(0x0000, 0x0100), VR::US, PrimitiveValue::U16(vec![C_STORE_RQ])),
+        (Tag(0x0000, 0x0110), VR::US, PrimitiveValue::U16(vec![message_id])),
+        (Tag(0x0000, 0x0700), VR::US, PrimitiveValue::U16(vec![PRIORITY_MEDIUM])),
+        (Tag(0x0000, 0x0800), VR::US, PrimitiveValue::U16(vec![DATA_SET_PRESENT])),
+        (Tag(0x0000, 0x1000), VR::UI, PrimitiveValue::Str(sop_instance_uid.to_owned())),
+    ])
+}
+
+/// Create a C-STORE-RSP command set in response to the request with the
+/// given message ID.
+pub fn store_rsp(
+    message_id: u16,
+    sop_class_uid: &str,
+    sop_instance_uid: &str,
+    status: u16,
+) -> Command {
+    command(vec![
+        (Tag(0x0000, 0x0002), VR::UI, PrimitiveValue::Str(sop_class_uid.to_owned())),
+        (Tag(0x0000, 0x0100), VR::US, PrimitiveValue::U16(vec![C_STORE_RSP])),
+        (Tag(0x0000, 0x0120), VR::US, PrimitiveValue::U16(vec![message_id])),
+        (Tag(0x0000, 0x0800), VR::US, PrimitiveValue::U16(vec![NO_DATA_SET])),
+        (Tag(0x0000, 0x0900), VR::US, PrimitiveValue::U16(vec![status])),
+        (Tag(0x0000, 0x1000), VR::UI, PrimitiveValue::Str(sop_instance_uid.to_owned())),
+    ])
+}
+
+/// Create a response command set to a request which could not be carried
+/// out. The command field, message ID and SOP class UID of the request are
+/// included where present.
+fn failure_rsp(request: &Command, status: u16) -> Command {
+    let mut elements = Vec::new();
+    if let Ok(sop_class_uid) = command_str(request, Tag(0x0000, 0x0002)) {
+        elements.push((Tag(0x0000, 0x0002), VR::UI, PrimitiveValue::Str(sop_class_uid)));
+    }
+    if let Ok(field) = command_field(request) {
+        elements.push((Tag(0x0000, 0x0100), VR::US, PrimitiveValue::U16(vec![field | 0x8000])));
+    }
+    if let Ok(message_id) = command_u16(request, Tag(0x0000, 0x0110)) {
+        elements.push((Tag(0x0000, 0x0120), VR::US, PrimitiveValue::U16(vec![message_id])));
+    }
+    elements.push((Tag(0x0000, 0x0800), VR::US, PrimitiveValue::U16(vec![NO_DATA_SET])));
+    elements.push((Tag(0x0000, 0x0900), VR::US, PrimitiveValue::U16(vec![status])));
+    command(elements)
+}
+
+/// A storage service class user, which sends DICOM objects through an
+/// association.
+///
+/// # Example
+///
+/// ```no_run
+/// use dicom_ul::association::ClientAssociationOptions;
+/// use dicom_ul::dimse::store::StoreScu;
+/// # fn run() -> dicom_ul::Result<()> {
+/// let association = ClientAssociationOptions::new()
+///     .called_ae_title("STORE-SCP".to_owned())
+///     .with_abstract_syntax("1.2.840.10008.5.1.4.1.1.7".to_owned())
+///     .establish("127.0.0.1:104")?;
+/// let mut scu = StoreScu::new(association);
+/// let status = scu.store_file("image.dcm")?;
+/// scu.release()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StoreScu {
+    association: Association,
+    message_id: u16,
+}
+
+impl StoreScu {
+    /// Create a storage service class user over the given association.
+    pub fn new(association: Association) -> StoreScu {
+        StoreScu {
+            association,
+            message_id: 0,
+        }
+    }
+
+    /// Retrieve the underlying association.
+    pub fn association(&self) -> &Association {
+        &self.association
+    }
+
+    /// Send a C-ECHO request, returning the response status.
+    pub fn echo(&mut self) -> Result<u16> {
+        let message_id = self.next_message_id();
+        echo(&mut self.association, message_id)
+    }
+
+    /// Send a DICOM object with a C-STORE request, returning the response
+    /// status.
+    ///
+    /// The object is sent in a presentation context of its SOP class, as
+    /// declared in the file meta group. A context agreed with the transfer
+    /// syntax of the file is preferred. Otherwise, the object is encoded in
+    /// the transfer syntax of the first context for its SOP class, which
+    /// is only possible for native pixel data: encapsulated pixel data
+    /// requires its transfer syntax to be proposed with
+    /// `ClientAssociationOptions::with_presentation_context`.
+    pub fn store<D>(&mut self, obj: &RootDicomObject<InMemDicomObject<D>>) -> Result<u16>
+    where
+        D: DataDictionary + Clone,
+    {
+        let sop_class_uid = trim_uid(&obj.meta().media_storage_sop_class_uid);
+        let sop_instance_uid = trim_uid(&obj.meta().media_storage_sop_instance_uid);
+        let transfer_syntax = trim_uid(&obj.meta().transfer_syntax);
+        let contexts: Vec<_> = self
+            .association
+            .presentation_contexts()
+            .iter()
+            .filter(|pc| pc.abstract_syntax == sop_class_uid)
+            .collect();
+        if contexts.is_empty() {
+            return Err(Error::NoPresentationContext(sop_class_uid.to_owned()));
+        }
+        let encapsulated = obj
+            .element(Tag(0x7FE0, 0x0010))
+            .map(|e| e.value().fragments().is_some())
+            .unwrap_or(false);
+        let pc = contexts
+            .iter()
+            .find(|pc| pc.transfer_syntax == transfer_syntax)
+            .or_else(|| if encapsulated { None } else { contexts.first() })
+            .map(|&pc| pc.clone())
+            .ok_or_else(|| Error::TransferSyntaxNotAccepted(transfer_syntax.to_owned()))?;
+        let ts = get_registry()
+            .get(&pc.transfer_syntax)
+            .ok_or(dicom_core::Error::UnsupportedTransferSyntax)?;
+        let mut data = Vec::new();
+        obj.write_dataset(&mut data, ts)?;
+
+        let message_id = self.next_message_id();
+        send_message(
+            &mut self.association,
+            &Message {
+                presentation_context_id: pc.id,
+                command: store_rq(message_id, sop_class_uid, sop_instance_uid),
+                data: Some(data),
+            },
+        )?;
+
+        let response = receive_message(&mut self.association)?
+            .ok_or_else(|| Error::UnexpectedPdu(Box::new(Pdu::ReleaseRQ)))?;
+        if command_field(&response.command)? != C_STORE_RSP
+            || command_u16(&response.command, Tag(0x0000, 0x0120))? != message_id
+        {
+            return Err(Error::InvalidCommand("expected a C-STORE-RSP"));
+        }
+        command_u16(&response.command, Tag(0x0000, 0x0900))
+    }
+
+    /// Read a DICOM file and send it with a C-STORE request, returning the
+    /// response status.
+    pub fn store_file<P: AsRef<Path>>(&mut self, path: P) -> Result<u16> {
+        let obj = open_file(path)?;
+        self.store(&obj)
+    }
+
+    /// Release the association.
+    pub fn release(self) -> Result<()> {
+        self.association.release()
+    }
+
+    fn next_message_id(&mut self) -> u16 {
+        self.message_id = self.message_id.wrapping_add(1);
+        self.message_id
+    }
+}
+
+/// A storage service class provider, which receives DICOM objects through
+/// an association and passes them to a callback. The status returned by
+/// the callback is sent back in the C-STORE response.
+///
+/// C-ECHO requests are answered as well.
+///
+/// # Example
+///
+/// ```no_run
+/// use dicom_ul::association::ServerAssociationOptions;
+/// use dicom_ul::dimse::store::{save_to_directory, StoreScp};
+/// use std::net::TcpListener;
+/// # fn run() -> dicom_ul::Result<()> {
+/// let options = ServerAssociationOptions::new().ae_title("STORE-SCP".to_owned());
+/// let mut scp = StoreScp::new(save_to_directory("incoming"));
+/// let listener = TcpListener::bind("0.0.0.0:104")?;
+/// for socket in listener.incoming() {
+///     scp.serve(options.establish(socket?)?)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StoreScp<F> {
+    handler: F,
+}
+
+impl<F> StoreScp<F>
+where
+    F: FnMut(StoredObject) -> u16,
+{
+    /// Create a storage service class provider with the given callback.
+    pub fn new(handler: F) -> StoreScp<F> {
+        StoreScp { handler }
+    }
+
+    /// Serve the requests of the other node until it releases the
+    /// association.
+    ///
+    /// Requests for other operations are answered with an
+    /// `UNRECOGNIZED_OPERATION` status, and requests missing their message
+    /// ID or UIDs with a `CANNOT_UNDERSTAND` status.
+    pub fn serve(&mut self, mut association: Association) -> Result<()> {
+        while let Some(request) = receive_message(&mut association)? {
+            let presentation_context_id = request.presentation_context_id;
+            let message_id = command_u16(&request.command, Tag(0x0000, 0x0110));
+            let response = match (command_field(&request.command), message_id) {
+                (Ok(C_ECHO_RQ), Ok(_)) => {
+                    echo::respond(&mut association, &request)?;
+                    continue;
+                }
+                (Ok(C_STORE_RQ), Ok(message_id)) => {
+                    self.respond_to_store(&association, request, message_id)
+                }
+                (Ok(C_ECHO_RQ), Err(_)) | (Ok(C_STORE_RQ), Err(_)) | (Err(_), _) => {
+                    failure_rsp(&request.command, status::CANNOT_UNDERSTAND)
+                }
+                (Ok(_), _) => failure_rsp(&request.command, status::UNRECOGNIZED_OPERATION),
+            };
+            send_message(
+                &mut association,
+                &Message {
+                    presentation_context_id,
+                    command: response,
+                    data: None,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Handle a C-STORE request, returning the response command set.
+    fn respond_to_store(
+        &mut self,
+        association: &Association,
+        request: Message,
+        message_id: u16,
+    ) -> Command {
+        let pc = association
+            .presentation_contexts()
+            .iter()
+            .find(|pc| pc.id == request.presentation_context_id);
+        let sop_class_uid = command_str(&request.command, Tag(0x0000, 0x0002));
+        let sop_instance_uid = command_str(&request.command, Tag(0x0000, 0x1000));
+        match (pc, sop_class_uid, sop_instance_uid) {
+            (Some(pc), Ok(sop_class_uid), Ok(sop_instance_uid)) => {
+                let status = self.store(pc, &sop_class_uid, &sop_instance_uid, request.data);
+                store_rsp(message_id, &sop_class_uid, &sop_instance_uid, status)
+            }
+            _ => failure_rsp(&request.command, status::CANNOT_UNDERSTAND),
+        }
+    }
+
+    /// Decode a received data set and pass it to the callback, returning
+    /// the status of the operation.
+    fn store(
+        &mut self,
+        pc: &PresentationContext,
+        sop_class_uid: &str,
+        sop_instance_uid: &str,
+        data: Option<Vec<u8>>,
+    ) -> u16 {
+        if pc.abstract_syntax != sop_class_uid {
+            return status::DATA_SET_DOES_NOT_MATCH_SOP_CLASS;
+        }
+        let ts = match get_registry().get(&pc.transfer_syntax) {
+            Some(ts) => ts,
+            None => return status::CANNOT_UNDERSTAND,
+        };
+        let obj = match data.map(|data| InMemDicomObject::read_dataset(Cursor::new(data), ts)) {
+            Some(Ok(obj)) => obj,
+            _ => return status::CANNOT_UNDERSTAND,
+        };
+        let meta = DicomMetaTableBuilder::new()
+            .information_version([0, 1])
+            .media_storage_sop_class_uid(sop_class_uid.to_owned())
+            .media_storage_sop_instance_uid(sop_instance_uid.to_owned())
+            .transfer_syntax(pc.transfer_syntax.clone())
+            .implementation_class_uid(::association::IMPLEMENTATION_CLASS_UID.to_owned())
+            .build();
+        match meta {
+            Ok(meta) => (self.handler)(RootDicomObject::new(meta, obj)),
+            Err(_) => status::PROCESSING_FAILURE,
+        }
+    }
+}
+
+/// Create a storage callback which writes each received object to a DICOM
+/// file in the given directory, named after its SOP instance UID.
+///
+/// Objects whose SOP instance UID is not a valid UID are not written, and
+/// are answered with a `CANNOT_UNDERSTAND` status, since the UID could
+/// otherwise lead to a file outside of the directory.
+pub fn save_to_directory<P>(directory: P) -> impl FnMut(StoredObject) -> u16
+where
+    P: Into<PathBuf>,
+{
+    let directory = directory.into();
+    move |obj| {
+        let uid = trim_uid(&obj.meta().media_storage_sop_instance_uid);
+        if !is_valid_uid(uid) {
+            return status::CANNOT_UNDERSTAND;
+        }
+        let file_name = format!("{}.dcm", uid);
+        let written = File::create(directory.join(file_name))
+            .map_err(dicom_core::Error::from)
+            .and_then(|file| to_file(&obj, BufWriter::new(file)));
+        match written {
+            Ok(()) => status::SUCCESS,
+            Err(_) => status::OUT_OF_RESOURCES,
+        }
+    }
+}
+
+/// Remove the padding of a UID.
+fn trim_uid(uid: &str) -> &str {
+    uid.trim_end_matches(&['\0', ' '][..])
+}
+
+/// Check that a UID, without padding, is made of at most 64 digits and
+/// periods.
+fn is_valid_uid(uid: &str) -> bool {
+    !uid.is_empty() && uid.len() <= 64 && uid.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use association::{ClientAssociationOptions, ServerAssociationOptions};
+    use dicom_core::object::capture::{SecondaryCaptureBuilder, SECONDARY_CAPTURE_IMAGE_STORAGE};
+    use dicom_core::object::pixeldata::{InMemoryPixelData, NativePixelData};
+    use dicom_core::data::value::Value;
+    use dicom_core::data::DataElement;
+    use dimse::echo::VERIFICATION_SOP_CLASS;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn capture(patient_name: &str) -> StoredObject {
+        let pixels = (0..128 * 128).collect::<Vec<u16>>();
+        let pixels = InMemoryPixelData::new(pixels, 128, 128, 16, 1).unwrap();
+        SecondaryCaptureBuilder::new()
+            .pixel_data(NativePixelData::U16(pixels))
+            .patient_name(patient_name.to_owned())
+            .build()
+            .unwrap()
+    }
+
+    fn spawn_scp<F>(handler: F) -> (String, thread::JoinHandle<()>)
+    where
+        F: FnMut(StoredObject) -> u16 + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            let association = ServerAssociationOptions::new()
+                .ae_title("STORE-SCP".to_owned())
+                .with_abstract_syntax(VERIFICATION_SOP_CLASS.to_owned())
+                .with_abstract_syntax(SECONDARY_CAPTURE_IMAGE_STORAGE.to_owned())
+                .establish(socket)
+                .unwrap();
+            StoreScp::new(handler).serve(association).unwrap();
+        });
+        (addr, handle)
+    }
+
+    fn connect(addr: &str) -> StoreScu {
+        let association = ClientAssociationOptions::new()
+            .calling_ae_title("STORE-SCU".to_owned())
+            .called_ae_title("STORE-SCP".to_owned())
+            .with_abstract_syntax(VERIFICATION_SOP_CLASS.to_owned())
+            .with_abstract_syntax(SECONDARY_CAPTURE_IMAGE_STORAGE.to_owned())
+            .establish(addr)
+            .unwrap();
+        StoreScu::new(association)
+    }
+
+    #[test]
+    fn echo_and_store_to_callback() {
+        let (tx, rx) = mpsc::channel();
+        let (addr, scp) = spawn_scp(move |obj| {
+            tx.send(obj).unwrap();
+            status::SUCCESS
+        });
+
+        let mut scu = connect(&addr);
+        assert_eq!(scu.echo().unwrap(), status::SUCCESS);
+        let sent = vec![capture("Doe^John"), capture("Doe^Jane")];
+        for obj in &sent {
+            assert_eq!(scu.store(obj).unwrap(), status::SUCCESS);
+        }
+        scu.release().unwrap();
+        scp.join().unwrap();
+
+        let received: Vec<_> = rx.iter().collect();
+        assert_eq!(received.len(), 2);
+        for (obj, sent) in received.iter().zip(&sent) {
+            assert_eq!(
+                trim_uid(&obj.meta().media_storage_sop_instance_uid),
+                trim_uid(&sent.meta().media_storage_sop_instance_uid)
+            );
+            assert_eq!(obj.meta().transfer_syntax, "1.2.840.10008.1.2.1");
+            assert_eq!(
+                obj.element(Tag(0x0010, 0x0010)).unwrap().value().as_string().unwrap(),
+                sent.element(Tag(0x0010, 0x0010)).unwrap().value().as_string().unwrap()
+            );
+            assert_eq!(
+                obj.element(Tag(0x7FE0, 0x0010)).unwrap(),
+                sent.element(Tag(0x7FE0, 0x0010)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn store_to_directory() {
+        let directory =
+            ::std::env::temp_dir().join(format!("dicom-ul-store-{}", ::std::process::id()));
+        ::std::fs::create_dir_all(&directory).unwrap();
+        let (addr, scp) = spawn_scp(save_to_directory(directory.clone()));
+
+        let obj = capture("Doe^John");
+        let mut scu = connect(&addr);
+        assert_eq!(scu.store(&obj).unwrap(), status::SUCCESS);
+        scu.release().unwrap();
+        scp.join().unwrap();
+
+        let uid = trim_uid(&obj.meta().media_storage_sop_instance_uid);
+        let path = directory.join(format!("{}.dcm", uid));
+        let read = open_file(&path).unwrap();
+        assert_eq!(
+            read.element(Tag(0x0010, 0x0010)).unwrap().value().as_string().unwrap(),
+            "Doe^John"
+        );
+        ::std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn store_to_directory_refuses_hostile_uids() {
+        let directory =
+            ::std::env::temp_dir().join(format!("dicom-ul-hostile-{}", ::std::process::id()));
+        let incoming = directory.join("incoming");
+        ::std::fs::create_dir_all(&incoming).unwrap();
+        let mut save = save_to_directory(incoming.clone());
+        let long_uid = "1.".repeat(32) + "1";
+        for &uid in &["../escaped", "/tmp/absolute", "", "1.2.3/4", &long_uid[..]] {
+            let obj = capture("Doe^John");
+            let mut meta = obj.meta().clone();
+            meta.media_storage_sop_instance_uid = uid.to_owned();
+            let obj = RootDicomObject::new(meta, obj.into_inner());
+            assert_eq!(save(obj), status::CANNOT_UNDERSTAND, "UID {:?}", uid);
+        }
+        // nothing was written, neither inside nor next to the directory
+        assert_eq!(::std::fs::read_dir(&incoming).unwrap().count(), 0);
+        assert_eq!(::std::fs::read_dir(&directory).unwrap().count(), 1);
+        ::std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn answer_unsupported_and_malformed_commands() {
+        let (addr, scp) = spawn_scp(|_| status::SUCCESS);
+        let mut scu = connect(&addr);
+        let pc_id = scu.association().presentation_contexts()[0].id;
+        let requests = vec![
+            // C-FIND-RQ
+            (
+                command(vec![
+                    (Tag(0x0000, 0x0100), VR::US, PrimitiveValue::U16(vec![0x0020])),
+                    (Tag(0x0000, 0x0110), VR::US, PrimitiveValue::U16(vec![7])),
+                    (Tag(0x0000, 0x0800), VR::US, PrimitiveValue::U16(vec![NO_DATA_SET])),
+                ]),
+                status::UNRECOGNIZED_OPERATION,
+            ),
+            // C-STORE-RQ without its SOP instance UID
+            (
+                command(vec![
+                    (Tag(0x0000, 0x0100), VR::US, PrimitiveValue::U16(vec![C_STORE_RQ])),
+                    (Tag(0x0000, 0x0110), VR::US, PrimitiveValue::U16(vec![8])),
+                    (Tag(0x0000, 0x0800), VR::US, PrimitiveValue::U16(vec![NO_DATA_SET])),
+                ]),
+                status::CANNOT_UNDERSTAND,
+            ),
+        ];
+        for (request, expected) in requests {
+            let message_id = command_u16(&request, Tag(0x0000, 0x0110)).unwrap();
+            let field = command_field(&request).unwrap();
+            let message = Message {
+                presentation_context_id: pc_id,
+                command: request,
+                data: None,
+            };
+            send_message(&mut scu.association, &message).unwrap();
+            let response = receive_message(&mut scu.association).unwrap().unwrap();
+            assert_eq!(command_field(&response.command).unwrap(), field | 0x8000);
+            assert_eq!(command_u16(&response.command, Tag(0x0000, 0x0120)).unwrap(), message_id);
+            assert_eq!(command_u16(&response.command, Tag(0x0000, 0x0900)).unwrap(), expected);
+        }
+
+        // the association is still being served
+        assert_eq!(scu.store(&capture("Doe^John")).unwrap(), status::SUCCESS);
+        scu.release().unwrap();
+        scp.join().unwrap();
+    }
+
+    #[test]
+    fn keep_the_transfer_syntax_of_encapsulated_data() {
+        let (addr, scp) = spawn_scp(|_| status::SUCCESS);
+        let mut scu = connect(&addr);
+        let obj = capture("Doe^John");
+        let mut meta = obj.meta().clone();
+        meta.transfer_syntax = "1.2.840.10008.1.2.4.50".to_owned();
+        let mut obj = obj.into_inner();
+        obj.put(DataElement::new(
+            Tag(0x7FE0, 0x0010),
+            VR::OB,
+            Value::PixelSequence {
+                offset_table: vec![],
+                fragments: vec![vec![0xFF, 0xD8, 0xFF, 0xD9]],
+            },
+        ));
+        let obj = RootDicomObject::new(meta, obj);
+        // only explicit and implicit VR little endian were proposed
+        match scu.store(&obj) {
+            Err(Error::TransferSyntaxNotAccepted(ref uid)) => {
+                assert_eq!(uid, "1.2.840.10008.1.2.4.50")
+            }
+            other => panic!("unexpected outcome {:?}", other),
+        }
+        scu.release().unwrap();
+        scp.join().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let (addr, scp) = spawn_scp(move |obj| {
+            tx.send(obj).unwrap();
+            status::SUCCESS
+        });
+        let association = ClientAssociationOptions::new()
+            .called_ae_title("STORE-SCP".to_owned())
+            .with_presentation_context(
+                SECONDARY_CAPTURE_IMAGE_STORAGE.to_owned(),
+                vec!["1.2.840.10008.1.2.4.50".to_owned()],
+            )
+            .establish(&addr[..])
+            .unwrap();
+        let mut scu = StoreScu::new(association);
+        assert_eq!(scu.store(&obj).unwrap(), status::SUCCESS);
+        scu.release().unwrap();
+        scp.join().unwrap();
+        let received = rx.recv().unwrap();
+        assert_eq!(received.meta().transfer_syntax, "1.2.840.10008.1.2.4.50");
+        let pixels = received.element(Tag(0x7FE0, 0x0010)).unwrap();
+        assert_eq!(pixels.value().fragments(), Some(&[vec![0xFF, 0xD8, 0xFF, 0xD9]][..]));
+    }
+
+    #[test]
+    fn store_failure_status() {
+        let (addr, scp) = spawn_scp(|_| status::OUT_OF_RESOURCES);
+        let mut scu = connect(&addr);
+        assert_eq!(scu.store(&capture("Doe^John")).unwrap(), status::OUT_OF_RESOURCES);
+        scu.release().unwrap();
+        scp.join().unwrap();
+    }
+}
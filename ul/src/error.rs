@@ -0,0 +1,89 @@
+//! This module aggregates errors that may emerge from the upper layer
+//! protocol.
+use std::error::Error as BaseError;
+use std::io;
+use std::result;
+
+use dicom_core;
+use pdu::{AssociationRJResult, AssociationRJSource, Pdu};
+
+quick_error! {
+    /// The main data type for errors in the library.
+    #[derive(Debug)]
+    pub enum Error {
+        /// Raised when a PDU could not be decoded because its content does
+        /// not follow the protocol.
+        InvalidPdu(message: &'static str) {
+            description("Invalid PDU")
+            display(self_) -> ("{}: {}", self_.description(), message)
+        }
+        /// Raised when an incoming PDU is longer than the maximum length
+        /// accepted by this node.
+        PduTooLarge(length: u32, max: u32) {
+            description("PDU is too large")
+            display(self_) -> ("{}: {} bytes, but at most {} are accepted",
+                               self_.description(), length, max)
+        }
+        /// Raised when the peer sent a PDU which is not allowed in the
+        /// current state of the association.
+        UnexpectedPdu(pdu: Box<Pdu>) {
+            description("Unexpected PDU")
+            display(self_) -> ("{}: {:?}", self_.description(), pdu)
+        }
+        /// Raised when the association request was rejected, either by the
+        /// peer or by this node.
+        Rejected(result: AssociationRJResult, source: AssociationRJSource) {
+            description("Association rejected")
+            display(self_) -> ("{} ({:?}): {:?}", self_.description(), result, source)
+        }
+        /// Raised when the association was aborted by the peer.
+        Aborted {
+            description("Association aborted")
+        }
+        /// Raised when none of the proposed presentation contexts were
+        /// accepted.
+        NoAcceptedPresentationContexts {
+            description("No presentation contexts were accepted")
+        }
+        /// Raised when no presentation context was accepted for the abstract
+        /// syntax of a message.
+        NoPresentationContext(abstract_syntax: String) {
+            description("No presentation context for the abstract syntax")
+            display(self_) -> ("{}: {}", self_.description(), abstract_syntax)
+        }
+        /// Raised when an object with encapsulated pixel data would have to
+        /// be sent in a transfer syntax other than its own.
+        TransferSyntaxNotAccepted(transfer_syntax: String) {
+            description("No presentation context for the transfer syntax of encapsulated data")
+            display(self_) -> ("{}: {}", self_.description(), transfer_syntax)
+        }
+        /// Raised when a DIMSE command set is missing required elements or
+        /// is not supported.
+        InvalidCommand(message: &'static str) {
+            description("Invalid DIMSE command")
+            display(self_) -> ("{}: {}", self_.description(), message)
+        }
+        /// Raised when the association parameters are inconsistent.
+        InvalidOptions(message: &'static str) {
+            description("Invalid association options")
+            display(self_) -> ("{}: {}", self_.description(), message)
+        }
+        /// Errors from encoding or decoding DICOM content.
+        Dicom(err: dicom_core::Error) {
+            description("DICOM content error")
+            from()
+            cause(err)
+            display(self_) -> ("{}: {}", self_.description(), err)
+        }
+        /// Other I/O errors.
+        Io(err: io::Error) {
+            description("I/O error")
+            from()
+            cause(err)
+            display(self_) -> ("{}: {}", self_.description(), err)
+        }
+    }
+}
+
+/// Type alias for a result from this crate.
+pub type Result<T> = result::Result<T, Error>;
@@ -0,0 +1,25 @@
+#![crate_type = "lib"]
+#![deny(trivial_casts, trivial_numeric_casts, unsafe_code, unstable_features)]
+#![warn(missing_debug_implementations, missing_docs, unused_qualifications, unused_import_braces)]
+
+//! This is a library for the DICOM upper layer protocol, which enables
+//! DICOM nodes to communicate over TCP/IP.
+//!
+//! The [`pdu`](pdu/index.html) module encodes and decodes the protocol data
+//! units, while the [`association`](association/index.html) module
+//! negotiates associations and transfers messages through them. On top of
+//! these, the [`dimse`](dimse/index.html) module implements the
+//! verification (C-ECHO) and storage (C-STORE) services.
+
+extern crate byteorder;
+extern crate dicom_core;
+#[macro_use]
+extern crate quick_error;
+
+pub mod association;
+pub mod dimse;
+pub mod error;
+pub mod pdu;
+
+pub use association::{Association, ClientAssociationOptions, ServerAssociationOptions};
+pub use error::{Error, Result};
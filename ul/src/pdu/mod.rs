@@ -0,0 +1,521 @@
+//! This module contains the protocol data units (PDUs) of the DICOM upper
+//! layer, as specified in PS3.8, section 9.3, along with their encoding and
+//! decoding.
+pub mod reader;
+pub mod writer;
+
+pub use self::reader::read_pdu;
+pub use self::writer::write_pdu;
+
+/// The default maximum PDU length, in bytes.
+pub const DEFAULT_MAX_PDU: u32 = 16_384;
+
+/// The smallest maximum PDU length which a node may announce.
+pub const MINIMUM_PDU_SIZE: u32 = 4_096;
+
+/// The largest length of the PDUs other than P-DATA-TF accepted by this
+/// implementation.
+pub const MAXIMUM_PDU_SIZE: u32 = 131_072;
+
+/// The size of the header of each presentation data value item inside a
+/// P-DATA-TF PDU: the item length, the presentation context ID and the
+/// message control header.
+pub const PDV_HEADER_SIZE: u32 = 6;
+
+/// The application context name of DICOM, the only one in use.
+pub const DICOM_APPLICATION_CONTEXT_NAME: &str = "1.2.840.10008.3.1.1.1";
+
+/// A protocol data unit of the DICOM upper layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pdu {
+    /// An unrecognized PDU, kept with its raw content.
+    Unknown {
+        /// The PDU type code.
+        pdu_type: u8,
+        /// The content of the PDU, after its header.
+        data: Vec<u8>,
+    },
+    /// A-ASSOCIATE-RQ: a request to establish an association.
+    AssociationRQ {
+        /// The protocol version, a bit field where only version 1 is
+        /// defined.
+        protocol_version: u16,
+        /// The AE title of the calling node.
+        calling_ae_title: String,
+        /// The AE title of the called node.
+        called_ae_title: String,
+        /// The application context name.
+        application_context_name: String,
+        /// The proposed presentation contexts.
+        presentation_contexts: Vec<PresentationContextProposed>,
+        /// The user information sub-items.
+        user_variables: Vec<UserVariableItem>,
+    },
+    /// A-ASSOCIATE-AC: the acceptance of an association request.
+    AssociationAC {
+        /// The protocol version, a bit field where only version 1 is
+        /// defined.
+        protocol_version: u16,
+        /// The AE title of the calling node, as in the request.
+        calling_ae_title: String,
+        /// The AE title of the called node, as in the request.
+        called_ae_title: String,
+        /// The application context name.
+        application_context_name: String,
+        /// The outcome of each proposed presentation context.
+        presentation_contexts: Vec<PresentationContextResult>,
+        /// The user information sub-items.
+        user_variables: Vec<UserVariableItem>,
+    },
+    /// A-ASSOCIATE-RJ: the rejection of an association request.
+    AssociationRJ {
+        /// Whether the rejection is permanent or transient.
+        result: AssociationRJResult,
+        /// The source of the rejection and its reason.
+        source: AssociationRJSource,
+    },
+    /// P-DATA-TF: a transfer of message fragments.
+    PData {
+        /// The presentation data values.
+        data: Vec<PDataValue>,
+    },
+    /// A-RELEASE-RQ: a request to release the association.
+    ReleaseRQ,
+    /// A-RELEASE-RP: the confirmation of an association release.
+    ReleaseRP,
+    /// A-ABORT: the abortion of the association.
+    AbortRQ {
+        /// The source of the abort and its reason.
+        source: AbortRQSource,
+    },
+}
+
+/// A presentation context, as proposed in an association request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentationContextProposed {
+    /// The presentation context ID, an odd number between 1 and 255.
+    pub id: u8,
+    /// The abstract syntax UID, which identifies a SOP class or a meta SOP
+    /// class.
+    pub abstract_syntax: String,
+    /// The UIDs of the transfer syntaxes proposed for this context.
+    pub transfer_syntaxes: Vec<String>,
+}
+
+/// The outcome of a presentation context, as given in an association
+/// acceptance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentationContextResult {
+    /// The presentation context ID.
+    pub id: u8,
+    /// Whether the presentation context was accepted.
+    pub reason: PresentationContextResultReason,
+    /// The UID of the transfer syntax chosen for this context. It has no
+    /// meaning if the context was not accepted.
+    pub transfer_syntax: String,
+}
+
+/// The result of a presentation context negotiation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PresentationContextResultReason {
+    /// The presentation context was accepted.
+    Acceptance,
+    /// The presentation context was rejected by the service user.
+    UserRejection,
+    /// The presentation context was rejected by the service provider,
+    /// without a reason.
+    NoReason,
+    /// The abstract syntax is not supported.
+    AbstractSyntaxNotSupported,
+    /// None of the proposed transfer syntaxes are supported.
+    TransferSyntaxesNotSupported,
+}
+
+impl PresentationContextResultReason {
+    /// Obtain the reason from its code.
+    pub fn from_code(code: u8) -> Option<Self> {
+        use self::PresentationContextResultReason::*;
+        match code {
+            0 => Some(Acceptance),
+            1 => Some(UserRejection),
+            2 => Some(NoReason),
+            3 => Some(AbstractSyntaxNotSupported),
+            4 => Some(TransferSyntaxesNotSupported),
+            _ => None,
+        }
+    }
+
+    /// Obtain the code of the reason.
+    pub fn code(self) -> u8 {
+        use self::PresentationContextResultReason::*;
+        match self {
+            Acceptance => 0,
+            UserRejection => 1,
+            NoReason => 2,
+            AbstractSyntaxNotSupported => 3,
+            TransferSyntaxesNotSupported => 4,
+        }
+    }
+}
+
+/// Whether an association rejection is permanent or transient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssociationRJResult {
+    /// The rejection is permanent.
+    Permanent,
+    /// The rejection is transient, so the request may be tried again
+    /// later.
+    Transient,
+}
+
+/// The source of an association rejection, along with its reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssociationRJSource {
+    /// Rejected by the DICOM UL service user.
+    ServiceUser(AssociationRJServiceUserReason),
+    /// Rejected by the ACSE related function of the service provider.
+    ServiceProviderASCE(AssociationRJServiceProviderASCEReason),
+    /// Rejected by the presentation related function of the service
+    /// provider.
+    ServiceProviderPresentation(AssociationRJServiceProviderPresentationReason),
+}
+
+/// A reason for an association rejection by the service user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssociationRJServiceUserReason {
+    /// No reason was given.
+    NoReasonGiven,
+    /// The application context name is not supported.
+    ApplicationContextNameNotSupported,
+    /// The calling AE title is not recognized.
+    CallingAETitleNotRecognized,
+    /// The called AE title is not recognized.
+    CalledAETitleNotRecognized,
+    /// A reserved reason code.
+    Reserved(u8),
+}
+
+/// A reason for an association rejection by the ACSE related function of
+/// the service provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssociationRJServiceProviderASCEReason {
+    /// No reason was given.
+    NoReasonGiven,
+    /// The protocol version is not supported.
+    ProtocolVersionNotSupported,
+}
+
+/// A reason for an association rejection by the presentation related
+/// function of the service provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssociationRJServiceProviderPresentationReason {
+    /// The node is temporarily congested.
+    TemporaryCongestion,
+    /// A local limit was exceeded.
+    LocalLimitExceeded,
+    /// A reserved reason code.
+    Reserved(u8),
+}
+
+impl AssociationRJSource {
+    /// Obtain the source and reason from their codes.
+    pub fn from_codes(source: u8, reason: u8) -> Option<Self> {
+        use self::AssociationRJServiceProviderASCEReason as Asce;
+        use self::AssociationRJServiceProviderPresentationReason as Presentation;
+        use self::AssociationRJServiceUserReason as User;
+        match (source, reason) {
+            (1, 1) => Some(AssociationRJSource::ServiceUser(User::NoReasonGiven)),
+            (1, 2) => Some(AssociationRJSource::ServiceUser(
+                User::ApplicationContextNameNotSupported,
+            )),
+            (1, 3) => Some(AssociationRJSource::ServiceUser(User::CallingAETitleNotRecognized)),
+            (1, 7) => Some(AssociationRJSource::ServiceUser(User::CalledAETitleNotRecognized)),
+            (1, r) => Some(AssociationRJSource::ServiceUser(User::Reserved(r))),
+            (2, 1) => Some(AssociationRJSource::ServiceProviderASCE(Asce::NoReasonGiven)),
+            (2, 2) => Some(AssociationRJSource::ServiceProviderASCE(
+                Asce::ProtocolVersionNotSupported,
+            )),
+            (3, 1) => Some(AssociationRJSource::ServiceProviderPresentation(
+                Presentation::TemporaryCongestion,
+            )),
+            (3, 2) => Some(AssociationRJSource::ServiceProviderPresentation(
+                Presentation::LocalLimitExceeded,
+            )),
+            (3, r) => Some(AssociationRJSource::ServiceProviderPresentation(
+                Presentation::Reserved(r),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Obtain the codes of the source and reason.
+    pub fn codes(self) -> (u8, u8) {
+        use self::AssociationRJServiceProviderASCEReason as Asce;
+        use self::AssociationRJServiceProviderPresentationReason as Presentation;
+        use self::AssociationRJServiceUserReason as User;
+        match self {
+            AssociationRJSource::ServiceUser(reason) => (1, match reason {
+                User::NoReasonGiven => 1,
+                User::ApplicationContextNameNotSupported => 2,
+                User::CallingAETitleNotRecognized => 3,
+                User::CalledAETitleNotRecognized => 7,
+                User::Reserved(r) => r,
+            }),
+            AssociationRJSource::ServiceProviderASCE(reason) => (2, match reason {
+                Asce::NoReasonGiven => 1,
+                Asce::ProtocolVersionNotSupported => 2,
+            }),
+            AssociationRJSource::ServiceProviderPresentation(reason) => (3, match reason {
+                Presentation::TemporaryCongestion => 1,
+                Presentation::LocalLimitExceeded => 2,
+                Presentation::Reserved(r) => r,
+            }),
+        }
+    }
+}
+
+/// The source of an association abort, along with its reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbortRQSource {
+    /// Aborted by the DICOM UL service user.
+    ServiceUser,
+    /// Aborted by the DICOM UL service provider.
+    ServiceProvider(AbortRQServiceProviderReason),
+    /// A reserved source code.
+    Reserved,
+}
+
+/// A reason for an association abort by the service provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbortRQServiceProviderReason {
+    /// No reason was specified.
+    ReasonNotSpecified,
+    /// The PDU was not recognized.
+    UnrecognizedPdu,
+    /// The PDU was not expected.
+    UnexpectedPdu,
+    /// A PDU parameter was not recognized.
+    UnrecognizedPduParameter,
+    /// A PDU parameter was not expected.
+    UnexpectedPduParameter,
+    /// A PDU parameter has an invalid value.
+    InvalidPduParameter,
+    /// A reserved reason code.
+    Reserved(u8),
+}
+
+impl AbortRQSource {
+    /// Obtain the source and reason from their codes.
+    pub fn from_codes(source: u8, reason: u8) -> Self {
+        use self::AbortRQServiceProviderReason::*;
+        match source {
+            0 => AbortRQSource::ServiceUser,
+            2 => AbortRQSource::ServiceProvider(match reason {
+                0 => ReasonNotSpecified,
+                1 => UnrecognizedPdu,
+                2 => UnexpectedPdu,
+                4 => UnrecognizedPduParameter,
+                5 => UnexpectedPduParameter,
+                6 => InvalidPduParameter,
+                r => Reserved(r),
+            }),
+            _ => AbortRQSource::Reserved,
+        }
+    }
+
+    /// Obtain the codes of the source and reason.
+    pub fn codes(self) -> (u8, u8) {
+        use self::AbortRQServiceProviderReason::*;
+        match self {
+            AbortRQSource::ServiceUser => (0, 0),
+            AbortRQSource::ServiceProvider(reason) => (2, match reason {
+                ReasonNotSpecified => 0,
+                UnrecognizedPdu => 1,
+                UnexpectedPdu => 2,
+                UnrecognizedPduParameter => 4,
+                UnexpectedPduParameter => 5,
+                InvalidPduParameter => 6,
+                Reserved(r) => r,
+            }),
+            AbortRQSource::Reserved => (1, 0),
+        }
+    }
+}
+
+/// A presentation data value: a fragment of a DIMSE message, sent in a
+/// P-DATA-TF PDU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PDataValue {
+    /// The presentation context ID of the message.
+    pub presentation_context_id: u8,
+    /// Whether the fragment belongs to the command set or to the data set.
+    pub value_type: PDataValueType,
+    /// Whether this is the last fragment of the command set or data set.
+    pub is_last: bool,
+    /// The fragment content.
+    pub data: Vec<u8>,
+}
+
+/// The kind of message content in a presentation data value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PDataValueType {
+    /// A command set fragment.
+    Command,
+    /// A data set fragment.
+    Data,
+}
+
+/// A sub-item of the user information item of an association request or
+/// acceptance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserVariableItem {
+    /// The maximum length of P-DATA-TF PDUs which the node is able to
+    /// receive, where 0 stands for no limit.
+    MaxLength(u32),
+    /// The implementation class UID of the node.
+    ImplementationClassUID(String),
+    /// The implementation version name of the node.
+    ImplementationVersionName(String),
+    /// Any other sub-item, kept with its raw content.
+    Unknown(u8, Vec<u8>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn round_trip(pdu: Pdu) {
+        let mut bytes = Vec::new();
+        write_pdu(&mut bytes, &pdu).unwrap();
+        let read = read_pdu(&mut Cursor::new(&bytes), MAXIMUM_PDU_SIZE).unwrap();
+        assert_eq!(read, pdu);
+    }
+
+    #[test]
+    fn association_pdus_round_trip() {
+        round_trip(Pdu::AssociationRQ {
+            protocol_version: 1,
+            calling_ae_title: "STORE-SCU".to_owned(),
+            called_ae_title: "ANY-SCP".to_owned(),
+            application_context_name: DICOM_APPLICATION_CONTEXT_NAME.to_owned(),
+            presentation_contexts: vec![
+                PresentationContextProposed {
+                    id: 1,
+                    abstract_syntax: "1.2.840.10008.1.1".to_owned(),
+                    transfer_syntaxes: vec![
+                        "1.2.840.10008.1.2.1".to_owned(),
+                        "1.2.840.10008.1.2".to_owned(),
+                    ],
+                },
+                PresentationContextProposed {
+                    id: 3,
+                    abstract_syntax: "1.2.840.10008.5.1.4.1.1.2".to_owned(),
+                    transfer_syntaxes: vec!["1.2.840.10008.1.2".to_owned()],
+                },
+            ],
+            user_variables: vec![
+                UserVariableItem::MaxLength(DEFAULT_MAX_PDU),
+                UserVariableItem::ImplementationClassUID("1.2.3.4".to_owned()),
+                UserVariableItem::ImplementationVersionName("DICOM-RS 0.1".to_owned()),
+                UserVariableItem::Unknown(0x54, vec![0, 1, 2]),
+            ],
+        });
+        round_trip(Pdu::AssociationAC {
+            protocol_version: 1,
+            calling_ae_title: "STORE-SCU".to_owned(),
+            called_ae_title: "ANY-SCP".to_owned(),
+            application_context_name: DICOM_APPLICATION_CONTEXT_NAME.to_owned(),
+            presentation_contexts: vec![
+                PresentationContextResult {
+                    id: 1,
+                    reason: PresentationContextResultReason::Acceptance,
+                    transfer_syntax: "1.2.840.10008.1.2.1".to_owned(),
+                },
+                PresentationContextResult {
+                    id: 3,
+                    reason: PresentationContextResultReason::AbstractSyntaxNotSupported,
+                    transfer_syntax: "1.2.840.10008.1.2".to_owned(),
+                },
+            ],
+            user_variables: vec![UserVariableItem::MaxLength(0)],
+        });
+        round_trip(Pdu::AssociationRJ {
+            result: AssociationRJResult::Permanent,
+            source: AssociationRJSource::ServiceUser(
+                AssociationRJServiceUserReason::CalledAETitleNotRecognized,
+            ),
+        });
+        round_trip(Pdu::AssociationRJ {
+            result: AssociationRJResult::Transient,
+            source: AssociationRJSource::ServiceProviderPresentation(
+                AssociationRJServiceProviderPresentationReason::TemporaryCongestion,
+            ),
+        });
+    }
+
+    #[test]
+    fn other_pdus_round_trip() {
+        round_trip(Pdu::PData {
+            data: vec![
+                PDataValue {
+                    presentation_context_id: 1,
+                    value_type: PDataValueType::Command,
+                    is_last: true,
+                    data: vec![1, 2, 3, 4],
+                },
+                PDataValue {
+                    presentation_context_id: 1,
+                    value_type: PDataValueType::Data,
+                    is_last: false,
+                    data: vec![5; 100],
+                },
+            ],
+        });
+        round_trip(Pdu::ReleaseRQ);
+        round_trip(Pdu::ReleaseRP);
+        round_trip(Pdu::AbortRQ {
+            source: AbortRQSource::ServiceProvider(AbortRQServiceProviderReason::UnexpectedPdu),
+        });
+        round_trip(Pdu::AbortRQ {
+            source: AbortRQSource::ServiceUser,
+        });
+        round_trip(Pdu::Unknown {
+            pdu_type: 0x42,
+            data: vec![9, 8, 7],
+        });
+    }
+
+    #[test]
+    fn known_encoding() {
+        let mut bytes = Vec::new();
+        write_pdu(&mut bytes, &Pdu::ReleaseRQ).unwrap();
+        assert_eq!(bytes, [0x05, 0, 0, 0, 0, 4, 0, 0, 0, 0]);
+
+        let mut bytes = Vec::new();
+        let pdu = Pdu::PData {
+            data: vec![PDataValue {
+                presentation_context_id: 3,
+                value_type: PDataValueType::Command,
+                is_last: true,
+                data: vec![0xAA, 0xBB],
+            }],
+        };
+        write_pdu(&mut bytes, &pdu).unwrap();
+        assert_eq!(bytes, [0x04, 0, 0, 0, 0, 8, 0, 0, 0, 4, 3, 0x03, 0xAA, 0xBB]);
+
+        // P-DATA-TF PDUs longer than the maximum are refused
+        let mut bytes = Vec::new();
+        let pdu = Pdu::PData {
+            data: vec![PDataValue {
+                presentation_context_id: 1,
+                value_type: PDataValueType::Data,
+                is_last: true,
+                data: vec![0; 5000],
+            }],
+        };
+        write_pdu(&mut bytes, &pdu).unwrap();
+        assert!(read_pdu(&mut Cursor::new(&bytes), MINIMUM_PDU_SIZE).is_err());
+        assert!(read_pdu(&mut Cursor::new(&bytes), DEFAULT_MAX_PDU).is_ok());
+    }
+}
@@ -0,0 +1,229 @@
+//! This module contains the decoding of PDUs.
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt};
+use std::io::Read;
+
+use error::{Error, Result};
+use pdu::{
+    AbortRQSource, AssociationRJResult, AssociationRJSource, PDataValue, PDataValueType, Pdu,
+    PresentationContextProposed, PresentationContextResult, PresentationContextResultReason,
+    UserVariableItem, MAXIMUM_PDU_SIZE,
+};
+
+/// Read a single PDU from the given source.
+///
+/// P-DATA-TF PDUs longer than `max_pdu_length` are refused, as are other
+/// PDUs longer than `MAXIMUM_PDU_SIZE`.
+pub fn read_pdu<R>(reader: &mut R, max_pdu_length: u32) -> Result<Pdu>
+where
+    R: ?Sized + Read,
+{
+    let pdu_type = reader.read_u8()?;
+    reader.read_u8()?;
+    let length = reader.read_u32::<BigEndian>()?;
+    let max = if pdu_type == 0x04 {
+        max_pdu_length
+    } else {
+        MAXIMUM_PDU_SIZE
+    };
+    if length > max {
+        return Err(Error::PduTooLarge(length, max));
+    }
+    let mut body = vec![0; length as usize];
+    reader.read_exact(&mut body)?;
+
+    match pdu_type {
+        0x01 | 0x02 => read_association(pdu_type, &body),
+        0x03 => {
+            if body.len() != 4 {
+                return Err(Error::InvalidPdu("invalid A-ASSOCIATE-RJ length"));
+            }
+            let result = match body[1] {
+                1 => AssociationRJResult::Permanent,
+                2 => AssociationRJResult::Transient,
+                _ => return Err(Error::InvalidPdu("invalid association rejection result")),
+            };
+            let source = AssociationRJSource::from_codes(body[2], body[3])
+                .ok_or(Error::InvalidPdu("invalid association rejection source"))?;
+            Ok(Pdu::AssociationRJ { result, source })
+        }
+        0x04 => Ok(Pdu::PData {
+            data: read_pdata_values(&body)?,
+        }),
+        0x05 => Ok(Pdu::ReleaseRQ),
+        0x06 => Ok(Pdu::ReleaseRP),
+        0x07 => {
+            if body.len() != 4 {
+                return Err(Error::InvalidPdu("invalid A-ABORT length"));
+            }
+            Ok(Pdu::AbortRQ {
+                source: AbortRQSource::from_codes(body[2], body[3]),
+            })
+        }
+        _ => Ok(Pdu::Unknown {
+            pdu_type,
+            data: body,
+        }),
+    }
+}
+
+/// Split the first item off a sequence of items with a 16-bit length,
+/// returning its type, its content and the remaining items.
+fn split_item(buf: &[u8]) -> Result<(u8, &[u8], &[u8])> {
+    if buf.len() < 4 {
+        return Err(Error::InvalidPdu("truncated item header"));
+    }
+    let length = BigEndian::read_u16(&buf[2..4]) as usize;
+    if buf.len() < 4 + length {
+        return Err(Error::InvalidPdu("truncated item"));
+    }
+    Ok((buf[0], &buf[4..4 + length], &buf[4 + length..]))
+}
+
+/// Read a text field, such as a UID or an AE title, without its padding.
+fn read_text(bytes: &[u8]) -> Result<String> {
+    let text = ::std::str::from_utf8(bytes).map_err(|_| Error::InvalidPdu("invalid text field"))?;
+    Ok(text.trim_matches(|c| c == ' ' || c == '\0').to_owned())
+}
+
+fn read_association(pdu_type: u8, body: &[u8]) -> Result<Pdu> {
+    if body.len() < 68 {
+        return Err(Error::InvalidPdu("truncated association PDU"));
+    }
+    let protocol_version = BigEndian::read_u16(&body[0..2]);
+    let called_ae_title = read_text(&body[4..20])?;
+    let calling_ae_title = read_text(&body[20..36])?;
+
+    let mut application_context_name = None;
+    let mut proposed = vec![];
+    let mut results = vec![];
+    let mut user_variables = vec![];
+    let mut items = &body[68..];
+    while !items.is_empty() {
+        let (item_type, content, rest) = split_item(items)?;
+        items = rest;
+        match item_type {
+            0x10 => application_context_name = Some(read_text(content)?),
+            0x20 => proposed.push(read_proposed_context(content)?),
+            0x21 => results.push(read_context_result(content)?),
+            0x50 => user_variables = read_user_variables(content)?,
+            // other items are not defined and are ignored
+            _ => {}
+        }
+    }
+    let application_context_name =
+        application_context_name.ok_or(Error::InvalidPdu("missing application context"))?;
+
+    if pdu_type == 0x01 {
+        Ok(Pdu::AssociationRQ {
+            protocol_version,
+            calling_ae_title,
+            called_ae_title,
+            application_context_name,
+            presentation_contexts: proposed,
+            user_variables,
+        })
+    } else {
+        Ok(Pdu::AssociationAC {
+            protocol_version,
+            calling_ae_title,
+            called_ae_title,
+            application_context_name,
+            presentation_contexts: results,
+            user_variables,
+        })
+    }
+}
+
+fn read_proposed_context(content: &[u8]) -> Result<PresentationContextProposed> {
+    if content.len() < 4 {
+        return Err(Error::InvalidPdu("truncated presentation context"));
+    }
+    let mut abstract_syntax = None;
+    let mut transfer_syntaxes = vec![];
+    let mut items = &content[4..];
+    while !items.is_empty() {
+        let (item_type, content, rest) = split_item(items)?;
+        items = rest;
+        match item_type {
+            0x30 => abstract_syntax = Some(read_text(content)?),
+            0x40 => transfer_syntaxes.push(read_text(content)?),
+            _ => return Err(Error::InvalidPdu("unexpected presentation context sub-item")),
+        }
+    }
+    Ok(PresentationContextProposed {
+        id: content[0],
+        abstract_syntax: abstract_syntax.ok_or(Error::InvalidPdu("missing abstract syntax"))?,
+        transfer_syntaxes,
+    })
+}
+
+fn read_context_result(content: &[u8]) -> Result<PresentationContextResult> {
+    if content.len() < 4 {
+        return Err(Error::InvalidPdu("truncated presentation context"));
+    }
+    let reason = PresentationContextResultReason::from_code(content[2])
+        .ok_or(Error::InvalidPdu("invalid presentation context result"))?;
+    let mut transfer_syntax = String::new();
+    let mut items = &content[4..];
+    while !items.is_empty() {
+        let (item_type, content, rest) = split_item(items)?;
+        items = rest;
+        match item_type {
+            0x40 => transfer_syntax = read_text(content)?,
+            _ => return Err(Error::InvalidPdu("unexpected presentation context sub-item")),
+        }
+    }
+    Ok(PresentationContextResult {
+        id: content[0],
+        reason,
+        transfer_syntax,
+    })
+}
+
+fn read_user_variables(content: &[u8]) -> Result<Vec<UserVariableItem>> {
+    let mut variables = vec![];
+    let mut items = content;
+    while !items.is_empty() {
+        let (item_type, content, rest) = split_item(items)?;
+        items = rest;
+        variables.push(match item_type {
+            0x51 => {
+                if content.len() != 4 {
+                    return Err(Error::InvalidPdu("invalid maximum length sub-item"));
+                }
+                UserVariableItem::MaxLength(BigEndian::read_u32(content))
+            }
+            0x52 => UserVariableItem::ImplementationClassUID(read_text(content)?),
+            0x55 => UserVariableItem::ImplementationVersionName(read_text(content)?),
+            _ => UserVariableItem::Unknown(item_type, content.to_vec()),
+        });
+    }
+    Ok(variables)
+}
+
+fn read_pdata_values(body: &[u8]) -> Result<Vec<PDataValue>> {
+    let mut values = vec![];
+    let mut items = body;
+    while !items.is_empty() {
+        if items.len() < 4 {
+            return Err(Error::InvalidPdu("truncated presentation data value"));
+        }
+        let length = BigEndian::read_u32(&items[0..4]) as usize;
+        if length < 2 || items.len() < 4 + length {
+            return Err(Error::InvalidPdu("invalid presentation data value length"));
+        }
+        let header = items[5];
+        values.push(PDataValue {
+            presentation_context_id: items[4],
+            value_type: if header & 0x01 != 0 {
+                PDataValueType::Command
+            } else {
+                PDataValueType::Data
+            },
+            is_last: header & 0x02 != 0,
+            data: items[6..4 + length].to_vec(),
+        });
+        items = &items[4 + length..];
+    }
+    Ok(values)
+}
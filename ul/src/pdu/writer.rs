@@ -0,0 +1,174 @@
+//! This module contains the encoding of PDUs.
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use std::io::Write;
+
+use error::{Error, Result};
+use pdu::{PDataValueType, Pdu, UserVariableItem};
+
+/// Write a single PDU to the given destination.
+pub fn write_pdu<W>(writer: &mut W, pdu: &Pdu) -> Result<()>
+where
+    W: ?Sized + Write,
+{
+    let mut body = vec![];
+    let pdu_type = match *pdu {
+        Pdu::Unknown { pdu_type, ref data } => {
+            body.extend_from_slice(data);
+            pdu_type
+        }
+        Pdu::AssociationRQ {
+            protocol_version,
+            ref calling_ae_title,
+            ref called_ae_title,
+            ref application_context_name,
+            ref presentation_contexts,
+            ref user_variables,
+        } => {
+            write_association_header(
+                &mut body,
+                protocol_version,
+                called_ae_title,
+                calling_ae_title,
+                application_context_name,
+            )?;
+            for pc in presentation_contexts {
+                let mut content = vec![pc.id, 0, 0, 0];
+                write_item(&mut content, 0x30, pc.abstract_syntax.as_bytes())?;
+                for ts in &pc.transfer_syntaxes {
+                    write_item(&mut content, 0x40, ts.as_bytes())?;
+                }
+                write_item(&mut body, 0x20, &content)?;
+            }
+            write_user_variables(&mut body, user_variables)?;
+            0x01
+        }
+        Pdu::AssociationAC {
+            protocol_version,
+            ref calling_ae_title,
+            ref called_ae_title,
+            ref application_context_name,
+            ref presentation_contexts,
+            ref user_variables,
+        } => {
+            write_association_header(
+                &mut body,
+                protocol_version,
+                called_ae_title,
+                calling_ae_title,
+                application_context_name,
+            )?;
+            for pc in presentation_contexts {
+                let mut content = vec![pc.id, 0, pc.reason.code(), 0];
+                write_item(&mut content, 0x40, pc.transfer_syntax.as_bytes())?;
+                write_item(&mut body, 0x21, &content)?;
+            }
+            write_user_variables(&mut body, user_variables)?;
+            0x02
+        }
+        Pdu::AssociationRJ { result, source } => {
+            let result = match result {
+                ::pdu::AssociationRJResult::Permanent => 1,
+                ::pdu::AssociationRJResult::Transient => 2,
+            };
+            let (source, reason) = source.codes();
+            body.extend_from_slice(&[0, result, source, reason]);
+            0x03
+        }
+        Pdu::PData { ref data } => {
+            for value in data {
+                let mut header = 0;
+                if value.value_type == PDataValueType::Command {
+                    header |= 0x01;
+                }
+                if value.is_last {
+                    header |= 0x02;
+                }
+                body.write_u32::<BigEndian>(value.data.len() as u32 + 2)?;
+                body.extend_from_slice(&[value.presentation_context_id, header]);
+                body.extend_from_slice(&value.data);
+            }
+            0x04
+        }
+        Pdu::ReleaseRQ => {
+            body.extend_from_slice(&[0; 4]);
+            0x05
+        }
+        Pdu::ReleaseRP => {
+            body.extend_from_slice(&[0; 4]);
+            0x06
+        }
+        Pdu::AbortRQ { source } => {
+            let (source, reason) = source.codes();
+            body.extend_from_slice(&[0, 0, source, reason]);
+            0x07
+        }
+    };
+
+    writer.write_u8(pdu_type)?;
+    writer.write_u8(0)?;
+    writer.write_u32::<BigEndian>(body.len() as u32)?;
+    writer.write_all(&body)?;
+    Ok(())
+}
+
+/// Write an item with a 16-bit length.
+fn write_item(buf: &mut Vec<u8>, item_type: u8, content: &[u8]) -> Result<()> {
+    if content.len() > 0xFFFF {
+        return Err(Error::InvalidPdu("item is too long"));
+    }
+    let mut length = [0; 2];
+    BigEndian::write_u16(&mut length, content.len() as u16);
+    buf.extend_from_slice(&[item_type, 0]);
+    buf.extend_from_slice(&length);
+    buf.extend_from_slice(content);
+    Ok(())
+}
+
+/// Write an AE title in its fixed field of 16 characters.
+fn write_ae_title(buf: &mut Vec<u8>, ae_title: &str) -> Result<()> {
+    if ae_title.len() > 16 {
+        return Err(Error::InvalidPdu("AE title is longer than 16 characters"));
+    }
+    let end = buf.len() + 16;
+    buf.extend_from_slice(ae_title.as_bytes());
+    buf.resize(end, b' ');
+    Ok(())
+}
+
+fn write_association_header(
+    buf: &mut Vec<u8>,
+    protocol_version: u16,
+    called_ae_title: &str,
+    calling_ae_title: &str,
+    application_context_name: &str,
+) -> Result<()> {
+    buf.write_u16::<BigEndian>(protocol_version)?;
+    buf.extend_from_slice(&[0; 2]);
+    write_ae_title(buf, called_ae_title)?;
+    write_ae_title(buf, calling_ae_title)?;
+    buf.extend_from_slice(&[0; 32]);
+    write_item(buf, 0x10, application_context_name.as_bytes())
+}
+
+fn write_user_variables(buf: &mut Vec<u8>, variables: &[UserVariableItem]) -> Result<()> {
+    let mut content = vec![];
+    for variable in variables {
+        match *variable {
+            UserVariableItem::MaxLength(length) => {
+                let mut bytes = [0; 4];
+                BigEndian::write_u32(&mut bytes, length);
+                write_item(&mut content, 0x51, &bytes)?;
+            }
+            UserVariableItem::ImplementationClassUID(ref uid) => {
+                write_item(&mut content, 0x52, uid.as_bytes())?;
+            }
+            UserVariableItem::ImplementationVersionName(ref name) => {
+                write_item(&mut content, 0x55, name.as_bytes())?;
+            }
+            UserVariableItem::Unknown(item_type, ref data) => {
+                write_item(&mut content, item_type, data)?;
+            }
+        }
+    }
+    write_item(buf, 0x50, &content)
+}